@@ -50,7 +50,13 @@ fn compile_irfiles() {
             println!("cargo:rerun-if-changed={}", entry.path().to_str().unwrap());
 
             let code = match kind {
-                GenKind::IrFile => ir_file::generate(&name, &src),
+                GenKind::IrFile => {
+                    let filename = entry.path().to_str().unwrap();
+                    ir_file::generate(&name, filename, &src, &[]).unwrap_or_else(|errors| {
+                        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+                        panic!("failed to lower {}:\n{}", name, errors.join("\n"))
+                    })
+                }
                 GenKind::ParseNodes => grammar_notation_helper::generate(&src),
             };
 
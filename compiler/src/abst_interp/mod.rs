@@ -11,7 +11,7 @@ use jssat_ir::value_snapshot::{ValueSnapshotArena, SnapshotValue, SnapshotList};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::lifted::{FunctionId, LiftedProgram};
-use crate::types::{List, Record, Type, TypeCtx, TypeCtxMut, TypeDuplication, TypeCtxImmut};
+use crate::types::{List, Record, Type, TypeCtx, TypeCtxMut, TypeDuplication, TypeCtxImmut, Union};
 
 /// Collects statistics during abstract interpretation.
 pub trait AbsIntCollector<T: Tag> {
@@ -229,6 +229,7 @@ pub struct AbsIntEngine<'program, C> {
     program: &'program LiftedProgram,
     record_id: Counter<UniqueRecordId<LiftedCtx>>,
     list_id: Counter<UniqueListId<LiftedCtx>>,
+    union_id: Counter<UnionId<LiftedCtx>>,
     recursion_detector: RecursionDetector,
     function_cache: FunctionCache,
     pub collector: C,
@@ -260,6 +261,12 @@ pub enum AbsIntError {
     TypeError,
     #[error("Invalid program: key of record does not exist")]
     RegKeyDNE,
+    #[error("Invalid program: no function is named {0}")]
+    FnDNE(String),
+    #[error("Invalid program: popped from an empty list")]
+    ListEmpty,
+    #[error("Invalid program: function called with the wrong number of arguments")]
+    ArgumentCount,
 }
 
 impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
@@ -268,6 +275,7 @@ impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
             program,
             record_id: Default::default(),
             list_id: Default::default(),
+            union_id: Default::default(),
             recursion_detector: Default::default(),
             function_cache: Default::default(),
             collector,
@@ -488,9 +496,245 @@ impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
                     GetFnPtr(i) => {
                         insert!(state, i.result, Type::FnPtr(i.item));
                     }
+                    GetFnPtrDynamic(i) => {
+                        let res_typ = match state.rget(i.name)? {
+                            Type::Byts(name) => {
+                                let name = String::from_utf8_lossy(&name.to_vec()).into_owned();
+                                let function = (self.program.function_names.get(&name).copied())
+                                    .ok_or(AbsIntError::FnDNE(name))?;
+                                Type::FnPtr(function)
+                            }
+                            // without knowing the name, any function could be pointed to
+                            Type::Bytes => Type::Any,
+                            _ => Err(AbsIntError::TypeError)?,
+                        };
+
+                        insert!(state, i.result, res_typ);
+                    }
+                    RecordClone(i) => {
+                        let source = state.rget(i.record)?;
+                        let source = source.try_into_record().ok_or(AbsIntError::TypeError)?;
+
+                        // the clone has the same fields, but is a different record
+                        let mut record = Record::new(self.record_id.next());
+                        record.extend(source.borrow().iter().map(|(k, v)| (*k, *v)));
+                        insert!(state, i.result, state.make_type_record(record));
+                    }
+                    RecordEntries(i) => {
+                        let record = state.rget(i.record)?;
+                        let record = record.try_into_record().ok_or(AbsIntError::TypeError)?;
+
+                        // internal slots are keyed by atoms, and aren't entries
+                        let entries = (record.borrow().iter())
+                            .filter(|(k, _)| !matches!(k, Type::Atom(_)))
+                            .map(|(k, v)| (*k, *v))
+                            .collect::<Vec<_>>();
+
+                        let mut list = List::new(self.list_id.next());
+                        for (key, value) in entries {
+                            let mut pair = Record::new(self.record_id.next());
+                            pair.insert(Type::Int(0), key);
+                            pair.insert(Type::Int(1), value);
+                            list.push(state.make_type_record(pair));
+                        }
+
+                        insert!(state, i.result, state.make_type_list(list));
+                    }
+                    RecordGetInherited(i) => {
+                        use jssat_ir::isa::RecordKey::*;
+                        let key = match i.key {
+                            DynAtom(r) | Prop(r) => state.rget(r)?,
+                            Atom(a) => Type::Atom(a),
+                        };
+
+                        let prototype = Type::Atom(jssat_ir::isa::InternalSlot::Prototype.atom());
+                        let mut record = state.rget(i.record)?;
+
+                        let value = loop {
+                            let current = record.try_into_record().ok_or(AbsIntError::TypeError)?;
+                            let current = current.borrow();
+
+                            if let Some(value) = current.get(&key) {
+                                break *value;
+                            }
+
+                            record = match current.get(&prototype) {
+                                // a prototype that isn't a record, such as `null`, ends the chain
+                                Some(parent @ Type::Record(_)) => *parent,
+                                _ => Err(AbsIntError::RegKeyDNE)?,
+                            };
+                        };
+
+                        insert!(state, i.result, value);
+                    }
+                    RecordGetOr(i) => {
+                        let record = state.rget(i.record)?;
+                        let record = record.try_into_record().ok_or(AbsIntError::TypeError)?;
+                        let default = state.rget(i.default)?;
+
+                        use jssat_ir::isa::RecordKey::*;
+                        let key = match i.key {
+                            DynAtom(r) | Prop(r) => state.rget(r)?,
+                            Atom(a) => Type::Atom(a),
+                        };
+
+                        let res_typ = match key {
+                            // a key we don't know exactly may name any field, or none of them
+                            Type::Any | Type::Bytes | Type::Number | Type::Boolean | Type::Union(_) => {
+                                let fields = record.borrow().values().copied().collect::<Vec<_>>();
+                                self.union_of(&mut state, fields.into_iter().chain([default]))
+                            }
+                            key => record.borrow().get(&key).copied().unwrap_or(default),
+                        };
+
+                        insert!(state, i.result, res_typ);
+                    }
+                    TypeOf(i) => {
+                        // if we know the kind of the value, the name of the type is
+                        // known too. otherwise, we only know it'll be some string
+                        let res_typ = match state.rget(i.value)?.value_type() {
+                            Some(kind) => state.make_type_byts(kind.type_name().as_bytes()),
+                            None => Type::Bytes,
+                        };
+
+                        insert!(state, i.result, res_typ);
+                    }
+                    AssumeType(i) => {
+                        use jssat_ir::isa::ValueType;
+
+                        let variants = match state.rget(i.value)? {
+                            Type::Union(union) => union.borrow().to_vec(),
+                            typ => vec![typ],
+                        };
+
+                        // the frontend vouches for the kind, so variants of any other kind are dropped
+                        let narrowed = (variants.into_iter())
+                            .filter_map(|variant| match variant {
+                                Type::Any => Some(match i.kind {
+                                    ValueType::Bytes => Type::Bytes,
+                                    ValueType::Number => Type::Number,
+                                    ValueType::Boolean => Type::Boolean,
+                                    // there is no type describing every value of the other kinds
+                                    _ => Type::Any,
+                                }),
+                                variant => (variant.value_type() == Some(i.kind)).then(|| variant),
+                            })
+                            .collect::<Vec<_>>();
+
+                        if narrowed.is_empty() {
+                            Err(AbsIntError::TypeError)?;
+                        }
+
+                        insert!(state, i.result, self.union_of(&mut state, narrowed));
+                    }
+                    BytesConcat(i) => {
+                        let lhs = state.rget(i.lhs)?;
+                        let rhs = state.rget(i.rhs)?;
+
+                        let res_typ = match (lhs, rhs) {
+                            (Type::Byts(a), Type::Byts(b)) => {
+                                let mut bytes = a.to_vec();
+                                bytes.extend_from_slice(&b.to_vec());
+                                state.make_type_byts(&bytes)
+                            },
+                            (Type::Bytes | Type::Byts(_), Type::Bytes | Type::Byts(_)) => Type::Bytes,
+                            _ => panic!("cannot concatenate {:?} and {:?}", lhs, rhs),
+                        };
+
+                        insert!(state, i.result, res_typ);
+                    },
+                    BytesSlice(i) => {
+                        let bytes = state.rget(i.bytes)?;
+                        let start = state.rget(i.start)?;
+                        let end = state.rget(i.end)?;
+
+                        let res_typ = match (bytes, start, end) {
+                            (Type::Byts(payload), Type::Int(start), Type::Int(end)) => {
+                                let payload = payload.to_vec();
+                                let slice = (usize::try_from(start).ok())
+                                    .zip(usize::try_from(end).ok())
+                                    .and_then(|(start, end)| payload.get(start..end))
+                                    .unwrap_or_else(|| panic!("slice {}..{} is out of range", start, end))
+                                    .to_vec();
+
+                                state.make_type_byts(&slice)
+                            },
+                            (Type::Bytes | Type::Byts(_), Type::Number | Type::Int(_), Type::Number | Type::Int(_)) => Type::Bytes,
+                            _ => panic!("cannot slice {:?} from {:?} to {:?}", bytes, start, end),
+                        };
+
+                        insert!(state, i.result, res_typ);
+                    },
+                    ListPush(i) => {
+                        let list_typ = state.rget(i.list)?;
+                        let value = state.rget(i.value)?;
+
+                        let mut list = list_typ.try_into_list().ok_or(AbsIntError::TypeError)?.borrow_mut();
+                        list.push(value);
+
+                        drop(list);
+                        self.collector.record(i.list, list_typ);
+                    }
+                    ListPop(i) => {
+                        let list_typ = state.rget(i.list)?;
+
+                        let mut list = list_typ.try_into_list().ok_or(AbsIntError::TypeError)?.borrow_mut();
+                        let value = list.pop().ok_or(AbsIntError::ListEmpty)?;
+
+                        drop(list);
+                        self.collector.record(i.list, list_typ);
+                        insert!(state, i.result, value);
+                    }
+                    ListMap(i) => {
+                        let function = state.rget(i.function)?.try_into_fnptr().ok_or(AbsIntError::TypeError)?;
+                        let list_typ = state.rget(i.list)?;
+                        let elements = list_typ.try_into_list().ok_or(AbsIntError::TypeError)?.borrow().to_vec();
+
+                        let mut mapped = List::new(self.list_id.next());
+
+                        for element in elements {
+                            let mut calling_typs = (i.args.iter())
+                                .map(|register| state.rget(*register))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            calling_typs.push(element);
+
+                            let (_, ret_typ, new_typs) = self.call_with_types(&mut state, function, &calling_typs)?;
+
+                            // the arguments are threaded through every call, the element isn't
+                            let (new_typs, _) = new_typs.split_at(i.args.len());
+                            for (reg, typ) in i.args.iter().copied().strict_zip(new_typs.iter().copied()) {
+                                state.overwrite(reg, typ);
+                            }
+
+                            mapped.push(ret_typ.unwrap_or(Type::Any));
+                        }
+
+                        for register in i.args.iter().copied() {
+                            let typ = state.rget(register)?;
+                            self.collector.record(register, typ);
+                        }
+
+                        if let Some(result) = i.result {
+                            insert!(state, result, state.make_type_list(mapped));
+                        }
+                    }
                     CallStatic(i) => {
                         self.call_fn(&mut state, i.result, i.calling, &i.args)?;
                     }
+                    CallSpread(i) => {
+                        let list_typ = state.rget(i.args)?;
+                        let calling_typs = list_typ.try_into_list().ok_or(AbsIntError::TypeError)?.borrow().to_vec();
+
+                        // records and lists in the spread list are updated in place, and the
+                        // other elements can't be changed by the call
+                        let (_, ret_typ, _) = self.call_with_types(&mut state, i.calling, &calling_typs)?;
+
+                        if let Some(result) = i.result {
+                            insert!(state, result, ret_typ.unwrap_or(Type::Any));
+                        }
+
+                        self.collector.record(i.args, list_typ);
+                    }
                     CallExtern(_) => todo!(),
                     CallVirt(i) => {
                         let fnptr = state.get(&i.calling).unwrap();
@@ -527,6 +771,16 @@ impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
                                     _ => todo!("2op add: {:?}, {:?}", lhs, rhs),
                                 }
                             },
+                            Multiply => {
+                                match (lhs, rhs) {
+                                    // if the result overflows, we no longer know the exact value
+                                    (Int(a), Int(b)) => a.checked_mul(b).map_or(Number, Int),
+                                    (Int(_), Number) |
+                                    (Number, Int(_)) |
+                                    (Number, Number) => Number,
+                                    _ => todo!("2op multiply: {:?}, {:?}", lhs, rhs),
+                                }
+                            },
                             And => {
                                 match (lhs, rhs) {
                                     (Boolean, Boolean) |
@@ -717,12 +971,20 @@ impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
                             _ => panic!("invalid program"),
                         }
                     }
-                    Return(i) => match i.0 {
-                        Some(ret_reg) => {
-                            let typ = state.rget(ret_reg)?;
+                    Return(i) => match i.0.as_slice() {
+                        [] => TypeCtx::new(),
+                        [ret_reg] => {
+                            let typ = state.rget(*ret_reg)?;
                             TypeCtx::new_initial(None, typ)
                         }
-                        None => TypeCtx::new(),
+                        // like symbolic execution, a caller can only discard
+                        // more than one returned value, so they're only checked
+                        ret_regs => {
+                            for ret_reg in ret_regs {
+                                state.rget(*ret_reg)?;
+                            }
+                            TypeCtx::new()
+                        }
                     },
                 }
             }
@@ -777,20 +1039,71 @@ impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
         }
         self.collector.commit_changes();
 
-        // 2. prepare for calling function
-        let mut args = TypeCtx::new();
+        // 2. call function
+        let calling_typs = (calling_args.iter())
+            .map(|register| state.rget(*register))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (result, ret_typ, new_typs) = self.call_with_types(state, function, &calling_typs)?;
+
+        // 3. copy state back
+        for (reg, typ) in calling_args.iter().copied().strict_zip(new_typs) {
+            state.overwrite(reg, typ);
+        }
 
+        match (reg_result, ret_typ) {
+            (Some(reg), Some(typ)) => {
+                state.insert(reg, typ);
+            },
+            (Some(_), None) => panic!("invalid program"),
+            (None, _) => {}
+        };
+
+        // 4. record registers after call
+        if let Some(reg_result) = reg_result {
+            self.collector.record(reg_result, state.rget(reg_result)?);
+        }
+
+        for register in calling_args.iter().copied() {
+            let typ = state.rget(register)?;
+            self.collector.record(register, typ);
+        }
+        self.collector.commit_changes();
+
+        Ok(result)
+    }
+
+    /// Calls a function with arguments of the given types, rather than the
+    /// types in some registers. Produces the type returned by the function,
+    /// and the types of the arguments after the call.
+    fn call_with_types<'arena>(
+        &mut self,
+        state: &mut TypeCtxMut<'_, 'arena, LiftedCtx, RegisterId<LiftedCtx>>,
+        function: FunctionId,
+        calling_typs: &[Type<'arena, LiftedCtx>],
+    ) -> Result<(Arc<EvalResultInner>, Option<Type<'arena, LiftedCtx>>, Vec<Type<'arena, LiftedCtx>>), AbsIntError> {
         let target_fn_regs = &self.program.functions.get(&function).unwrap().parameters;
 
-        debug_assert_eq!(calling_args.len(), target_fn_regs.len());
+        if calling_typs.len() != target_fn_regs.len() {
+            return Err(AbsIntError::ArgumentCount);
+        }
 
-        let mut target_fn_args_iter = target_fn_regs.iter().cloned();
+        // 1. prepare for calling function
+        let mut args = TypeCtx::new();
 
-        let src_regs = calling_args.iter().copied();
-        let map_reg = |_| target_fn_args_iter.next().unwrap();
-        args.copy_from_map(state, src_regs, map_reg);
+        args.borrow_mut(|mut args| {
+            let mut dup = TypeDuplication::new(&mut args);
 
-        // 3. call function
+            let typs = (calling_typs.iter())
+                .map(|typ| dup.duplicate_type(*typ))
+                .collect::<Vec<_>>();
+
+            for (reg, typ) in target_fn_regs.iter().copied().strict_zip(typs) {
+                args.insert(reg, typ);
+            }
+        });
+
+        // 2. call function
         let result = self.call(function, args)?;
 
         let result = match result {
@@ -800,53 +1113,51 @@ impl<'p, C: AbsIntCollector<LiftedCtx>> AbsIntEngine<'p, C> {
             EvalResult::Present(result) => result,
         };
 
-        // 4. copy state back
-        let dest_arg_to_src = (target_fn_regs.iter().cloned())
-            .strict_zip(calling_args.iter().cloned())
-            .collect::<FxHashMap<_, _>>();
-
+        // 3. copy state back
         let (ret_typ, new_typs) = result.ctx.borrow(|result| {
             let mut dup = TypeDuplication::new(state);
 
             let ret_typ = result.get(&None).map(|t| dup.duplicate_type(t));
 
-            let new_typs = result
-                .iter()
-                .filter_map(|(k, v)| k.map(|reg| (reg, *v)))
-                .map(|(k, typ)| {
-                    let src_reg = *dest_arg_to_src.get(&k).unwrap();
-                    let typ = dup.duplicate_type(typ);
-                    (src_reg, typ)
-                })
+            let new_typs = (target_fn_regs.iter())
+                .map(|reg| dup.duplicate_type(result.get(&Some(*reg)).unwrap()))
                 .collect::<Vec<_>>();
 
             (ret_typ, new_typs)
         });
 
-        for (reg, typ) in new_typs {
-            state.overwrite(reg, typ);
-        }
+        Ok((result, ret_typ, new_typs))
+    }
 
-        match (reg_result, ret_typ) {
-            (Some(reg), Some(typ)) => {
-                state.insert(reg, typ);
-            },
-            (Some(_), None) => panic!("invalid program"),
-            (None, _) => {}
-        };
+    /// Produces a type that may be any of `types`. Nested unions are flattened
+    /// and duplicate variants removed, and a single variant is left as is.
+    fn union_of<'arena>(
+        &self,
+        state: &mut TypeCtxMut<'_, 'arena, LiftedCtx, RegisterId<LiftedCtx>>,
+        types: impl IntoIterator<Item = Type<'arena, LiftedCtx>>,
+    ) -> Type<'arena, LiftedCtx> {
+        let mut variants = Vec::new();
+
+        for typ in types {
+            let flattened = match typ {
+                Type::Union(union) => union.borrow().to_vec(),
+                typ => vec![typ],
+            };
 
-        // 5. record registers after call
-        if let Some(reg_result) = reg_result {
-            self.collector.record(reg_result, state.rget(reg_result)?);
+            for typ in flattened {
+                if !variants.contains(&typ) {
+                    variants.push(typ);
+                }
+            }
         }
 
-        for register in calling_args.iter().copied() {
-            let typ = state.rget(register)?;
-            self.collector.record(register, typ);
+        if let [typ] = variants.as_slice() {
+            return *typ;
         }
-        self.collector.commit_changes();
 
-        Ok(result)
+        let mut union = Union::new(self.union_id.next());
+        union.extend(variants);
+        state.make_type_union(union)
     }
 }
 
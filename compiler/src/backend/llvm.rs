@@ -2,6 +2,8 @@ use rustc_hash::FxHashMap;
 use std::hash::Hash;
 
 use super::BuildArtifact;
+use crate::frontend::ir::Target;
+use crate::symbolic_execution::types::RegisterType;
 
 type BlockId = crate::id::BlockId<crate::id::LlvmCtx>;
 type FunctionId = crate::id::FunctionId<crate::id::LlvmCtx>;
@@ -35,6 +37,7 @@ pub struct FunctionValue<'a>(PhantomData<&'a ()>);
 
 #[derive(Debug, Clone)]
 pub struct BackendIR<'name> {
+    pub target: Target,
     pub constants: FxHashMap<ConstantId, Constant<'name>>,
     pub opaque_structs: FxHashMap<OpaqueStructId, OpaqueStruct<'name>>,
     pub structs: FxHashMap<StructId, Struct>,
@@ -147,8 +150,9 @@ pub enum Instruction {
     /// # [`Instruction::Return`]
     ///
     /// Returns the value in the register to the caller, or returns nothing if
-    /// no register is given.
-    Return(Option<RegisterId>),
+    /// no register is given. Multiple registers are returned as a struct by
+    /// value.
+    Return(Vec<RegisterId>),
     /// # [`Instruction::Jump`]
     ///
     /// Jumps to the given block register.
@@ -190,7 +194,34 @@ pub enum Callable {
     // Virtual(RegisterId),
 }
 
-pub type ReturnType = crate::frontend::ir::Returns<ValueType>;
+#[derive(Debug, Clone)]
+pub enum ReturnType {
+    Void,
+    Value(ValueType),
+    /// Multi-value returns are lowered into an anonymous struct, returned by
+    /// value.
+    Values(Vec<ValueType>),
+}
+
+impl ReturnType {
+    /// Lowers the type a function was found to return, lowering each value it
+    /// returns with `lower_value`. A function that never returns has nothing
+    /// to return.
+    pub fn lower(
+        return_type: &crate::symbolic_execution::types::ReturnType,
+        mut lower_value: impl FnMut(RegisterType) -> ValueType,
+    ) -> Self {
+        use crate::symbolic_execution::types::ReturnType as Returns;
+
+        match return_type {
+            Returns::Void | Returns::Never => ReturnType::Void,
+            Returns::Value(typ) => ReturnType::Value(lower_value(*typ)),
+            Returns::Tuple(types) => {
+                ReturnType::Values(types.iter().map(|typ| lower_value(*typ)).collect())
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ValueType {
@@ -212,6 +243,44 @@ impl ValueType {
     pub fn into_ptr(self) -> ValueType {
         ValueType::Pointer(box self)
     }
+
+    /// The number of bytes a value of this type occupies on the target,
+    /// including any padding needed to align the fields of a struct.
+    pub fn size_of(&self, target: Target, structs: &FxHashMap<StructId, Struct>) -> usize {
+        match self {
+            ValueType::WordSizeBitType | ValueType::Pointer(_) => target.word_size,
+            ValueType::BitType(bits) => (*bits as usize + 7) / 8,
+            ValueType::Opaque(_) => panic!("cannot know the size of an opaque struct"),
+            ValueType::Defined(id) => {
+                let fields = &structs.get(id).unwrap().fields;
+
+                let size = fields.iter().fold(0, |size, field| {
+                    align_to(size, field.align_of(target, structs)) + field.size_of(target, structs)
+                });
+
+                align_to(size, self.align_of(target, structs))
+            }
+        }
+    }
+
+    /// The alignment of this type in bytes. Scalars are aligned to their size,
+    /// up to the size of a word, and structs to their most aligned field.
+    pub fn align_of(&self, target: Target, structs: &FxHashMap<StructId, Struct>) -> usize {
+        match self {
+            ValueType::Defined(id) => (structs.get(id).unwrap().fields.iter())
+                .map(|field| field.align_of(target, structs))
+                .max()
+                .unwrap_or(1),
+            scalar => scalar
+                .size_of(target, structs)
+                .next_power_of_two()
+                .min(target.word_size),
+        }
+    }
+}
+
+fn align_to(size: usize, align: usize) -> usize {
+    (size + align - 1) / align * align
 }
 
 #[derive(Debug, Clone)]
@@ -253,10 +322,7 @@ pub fn compile(ir: BackendIR) -> BuildArtifact {
     let builder = context.create_builder();
     let module = context.create_module("jssat");
 
-    #[cfg(target_pointer_width = "32")]
-    let word_size = context.i32_type();
-    #[cfg(target_pointer_width = "64")]
-    let word_size = context.i64_type();
+    let word_size = context.custom_width_int_type(8 * ir.target.word_size as u32);
 
     let compiler = BackendCompiler {
         context: &context,
@@ -517,6 +583,9 @@ impl<'c> BackendCompiler<'c, '_> {
             ReturnType::Value(v) => self
                 .llvm_typeify_value(v, &opaque_struct_resolver, &struct_resolver)
                 .fn_type(parameter_types, false),
+            ReturnType::Values(vs) => self
+                .llvm_typeify_values(vs, &opaque_struct_resolver, &struct_resolver)
+                .fn_type(parameter_types, false),
         };
 
         self.module.add_function(
@@ -550,6 +619,9 @@ impl<'c> BackendCompiler<'c, '_> {
             ReturnType::Value(v) => self
                 .llvm_typeify_value(v, &opaque_struct_resolver, &struct_resolver)
                 .fn_type(parameter_types, false),
+            ReturnType::Values(vs) => self
+                .llvm_typeify_values(vs, &opaque_struct_resolver, &struct_resolver)
+                .fn_type(parameter_types, false),
         };
 
         PartialFunction {
@@ -720,13 +792,21 @@ impl<'c> BackendCompiler<'c, '_> {
                     Instruction::Unreachable => {
                         self.builder.build_unreachable();
                     }
-                    Instruction::Return(None) => {
-                        self.builder.build_return(None);
-                    }
-                    Instruction::Return(Some(register)) => {
-                        let value = *register_values.get(&register).unwrap();
-                        self.builder.build_return(Some(&value));
-                    }
+                    Instruction::Return(registers) => match registers.as_slice() {
+                        [] => {
+                            self.builder.build_return(None);
+                        }
+                        [register] => {
+                            let value = *register_values.get(register).unwrap();
+                            self.builder.build_return(Some(&value));
+                        }
+                        registers => {
+                            let values = (registers.iter())
+                                .map(|r| *register_values.get(r).unwrap())
+                                .collect::<Vec<_>>();
+                            self.builder.build_aggregate_return(&values);
+                        }
+                    },
                     Instruction::Jump(block) => {
                         let block = *block_map.get(&block).unwrap();
                         self.builder.build_unconditional_branch(block);
@@ -881,4 +961,17 @@ impl<'c> BackendCompiler<'c, '_> {
             ValueType::Defined(id) => struct_resolver.resolve(&id).as_basic_type_enum(),
         }
     }
+
+    fn llvm_typeify_values(
+        &self,
+        value_types: Vec<ValueType>,
+        opaque_struct_resolver: &OpaqueStructResolver<'_, 'c>,
+        struct_resolver: &StructResolver<'_, 'c>,
+    ) -> StructType<'c> {
+        let field_types = (value_types.into_iter())
+            .map(|v| self.llvm_typeify_value(v, opaque_struct_resolver, struct_resolver))
+            .collect::<Vec<_>>();
+
+        self.context.struct_type(&field_types, false)
+    }
 }
@@ -1,6 +1,7 @@
 use crate::codegen::Program;
 
 pub mod llvm;
+pub mod wasm;
 
 pub struct BuildArtifact {
     pub llvm_ir: String,
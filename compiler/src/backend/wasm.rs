@@ -0,0 +1,1016 @@
+//! Emits a WebAssembly module directly from the assembler [`Program`], without
+//! going through LLVM.
+//!
+//! Every value is represented as an `i64`: integers are themselves, booleans
+//! are `0` or `1`, atoms are their number, and function pointers are the id of
+//! the function they point to. Bytes are the offset into linear memory of
+//! their length, which is followed by their payload. The blocks of a function
+//! become the arms of a `br_table` inside of a loop, and jumping to a block
+//! sets the label local and branches back to the loop.
+//!
+//! Records live in linear memory, which is bump allocated after the constants
+//! and never freed. A record is the offset of a word pointing to its first
+//! entry, and every entry is the kind of its key, the key, the value, and the
+//! offset of the next entry, with `0` ending the list. Deleting a field marks
+//! its entry's kind as [`DELETED_KIND`] so that it is never found again.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::codegen::{Block, EndInstruction, Function, Instruction, Program};
+use crate::frontend::ir::Returns;
+use crate::id::{AssemblerCtx, BlockId, FunctionId, IdCompat, LowerCtx, RegisterId};
+use crate::isa::{BinaryOperator, BlockJump, RecordKey, ValueType};
+
+type ExternalFunctionId = crate::id::ExternalFunctionId<AssemblerCtx>;
+type ConstantId = crate::id::ConstantId<AssemblerCtx>;
+
+const TYPE_SECTION: u8 = 1;
+const IMPORT_SECTION: u8 = 2;
+const FUNCTION_SECTION: u8 = 3;
+const MEMORY_SECTION: u8 = 5;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const CODE_SECTION: u8 = 10;
+const DATA_SECTION: u8 = 11;
+
+const I32: u8 = 0x7F;
+const I64: u8 = 0x7E;
+const EMPTY_BLOCK_TYPE: u8 = 0x40;
+const PAGE_SIZE: usize = 65536;
+const WORD_SIZE: usize = 8;
+
+/// The size of a record's entry, which is its key kind, key, value, and the
+/// offset of the next entry.
+const ENTRY_SIZE: i64 = 4 * WORD_SIZE as i64;
+const ENTRY_KIND: u32 = 0;
+const ENTRY_KEY: u32 = 8;
+const ENTRY_VALUE: u32 = 16;
+const ENTRY_NEXT: u32 = 24;
+/// The kind of an entry whose field was deleted, which no key ever has.
+const DELETED_KIND: i64 = -1;
+
+mod op {
+    pub const UNREACHABLE: u8 = 0x00;
+    pub const BLOCK: u8 = 0x02;
+    pub const LOOP: u8 = 0x03;
+    pub const IF: u8 = 0x04;
+    pub const ELSE: u8 = 0x05;
+    pub const END: u8 = 0x0B;
+    pub const BR: u8 = 0x0C;
+    pub const BR_TABLE: u8 = 0x0E;
+    pub const BR_IF: u8 = 0x0D;
+    pub const RETURN: u8 = 0x0F;
+    pub const CALL: u8 = 0x10;
+    pub const DROP: u8 = 0x1A;
+    pub const LOCAL_GET: u8 = 0x20;
+    pub const LOCAL_SET: u8 = 0x21;
+    pub const LOCAL_TEE: u8 = 0x22;
+    pub const GLOBAL_GET: u8 = 0x23;
+    pub const GLOBAL_SET: u8 = 0x24;
+    pub const I64_LOAD: u8 = 0x29;
+    pub const I64_LOAD8_U: u8 = 0x31;
+    pub const I64_STORE: u8 = 0x37;
+    pub const MEMORY_SIZE: u8 = 0x3F;
+    pub const MEMORY_GROW: u8 = 0x40;
+    pub const I32_CONST: u8 = 0x41;
+    pub const I64_CONST: u8 = 0x42;
+    pub const I64_EQZ: u8 = 0x50;
+    pub const I64_EQ: u8 = 0x51;
+    pub const I64_NE: u8 = 0x52;
+    pub const I64_LT_S: u8 = 0x53;
+    pub const I64_GT_S: u8 = 0x55;
+    pub const I64_ADD: u8 = 0x7C;
+    pub const I64_SUB: u8 = 0x7D;
+    pub const I64_MUL: u8 = 0x7E;
+    pub const I64_AND: u8 = 0x83;
+    pub const I64_OR: u8 = 0x84;
+    pub const I64_SHR_U: u8 = 0x88;
+    pub const I32_WRAP_I64: u8 = 0xA7;
+    pub const I64_EXTEND_I32_U: u8 = 0xAD;
+}
+
+/// Compiles the program into the bytes of a `.wasm` module. The entrypoint is
+/// exported as `main`, linear memory as `memory`, and external functions are
+/// imported from the `env` module by name.
+pub fn compile(program: &Program) -> Vec<u8> {
+    Module::new(program).emit()
+}
+
+/// The functions every module contains to support the program, which come
+/// after the functions of the program.
+#[derive(Clone, Copy)]
+enum Helper {
+    /// `alloc(size) -> offset` bump allocates `size` zeroed bytes, growing
+    /// linear memory if it's too small.
+    Alloc,
+    /// `bytes_equals(lhs, rhs) -> bool` compares the contents of two bytes.
+    BytesEquals,
+    /// `record_find(record, kind, key) -> entry` finds the entry of a field in
+    /// a record, or `0` if the record doesn't have the field.
+    RecordFind,
+}
+
+const HELPERS: [Helper; 3] = [Helper::Alloc, Helper::BytesEquals, Helper::RecordFind];
+
+impl Helper {
+    fn signature(self) -> (usize, usize) {
+        match self {
+            Helper::Alloc => (1, 1),
+            Helper::BytesEquals => (2, 1),
+            Helper::RecordFind => (3, 1),
+        }
+    }
+}
+
+struct Module<'p> {
+    program: &'p Program,
+    external_functions: Vec<ExternalFunctionId>,
+    functions: Vec<FunctionId<LowerCtx>>,
+    /// The offset of every constant into linear memory.
+    constants: Vec<(ConstantId, usize)>,
+    data: Vec<u8>,
+    /// The amount of values every function returns.
+    return_arity: FxHashMap<FunctionId<LowerCtx>, usize>,
+}
+
+impl<'p> Module<'p> {
+    fn new(program: &'p Program) -> Self {
+        let mut external_functions = program
+            .external_functions
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        external_functions.sort();
+
+        let mut functions = program.functions.keys().copied().collect::<Vec<_>>();
+        functions.sort();
+
+        let mut constant_ids = program.constants.keys().copied().collect::<Vec<_>>();
+        constant_ids.sort();
+
+        // every constant is prefixed with its length, and kept aligned so that
+        // the length can be loaded as a word
+        let mut constants = Vec::new();
+        let mut data = Vec::new();
+        for id in constant_ids {
+            let payload = &program.constants.get(&id).unwrap().payload;
+            constants.push((id, data.len()));
+            data.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+            data.extend_from_slice(payload);
+            data.resize((data.len() + WORD_SIZE - 1) / WORD_SIZE * WORD_SIZE, 0);
+        }
+
+        let return_arity = (program.functions.iter())
+            .map(|(id, function)| (*id, return_arity(function)))
+            .collect();
+
+        Module {
+            program,
+            external_functions,
+            functions,
+            constants,
+            data,
+            return_arity,
+        }
+    }
+
+    fn external_function_index(&self, id: ExternalFunctionId) -> u32 {
+        self.external_functions
+            .iter()
+            .position(|f| *f == id)
+            .unwrap() as u32
+    }
+
+    fn constant_offset(&self, id: ConstantId) -> usize {
+        self.constants.iter().find(|(c, _)| *c == id).unwrap().1
+    }
+
+    fn function_index(&self, id: FunctionId<LowerCtx>) -> u32 {
+        let position = self.functions.iter().position(|f| *f == id).unwrap();
+        (self.external_functions.len() + position) as u32
+    }
+
+    fn helper_index(&self, helper: Helper) -> u32 {
+        (self.external_functions.len() + self.functions.len() + helper as usize) as u32
+    }
+
+    fn external_function_signature(&self, id: ExternalFunctionId) -> (usize, usize) {
+        let ext_fn = self.program.external_functions.get(&id).unwrap();
+
+        let results = match ext_fn.return_type {
+            Returns::Value(_) => 1,
+            Returns::Void => 0,
+        };
+
+        (ext_fn.parameters.len(), results)
+    }
+
+    fn function_signature(&self, id: FunctionId<LowerCtx>) -> (usize, usize) {
+        let function = self.program.functions.get(&id).unwrap();
+        let entry = function.blocks.get(&function.entry).unwrap();
+        (entry.parameters.len(), *self.return_arity.get(&id).unwrap())
+    }
+
+    fn emit(&self) -> Vec<u8> {
+        let mut module = b"\0asm".to_vec();
+        module.extend_from_slice(&1u32.to_le_bytes());
+
+        // every import and function gets its own signature, so the index of
+        // a signature is the index of the function it belongs to
+        let signatures = (self.external_functions.iter())
+            .map(|id| self.external_function_signature(*id))
+            .chain(self.functions.iter().map(|id| self.function_signature(*id)))
+            .chain(HELPERS.iter().map(|helper| helper.signature()))
+            .collect::<Vec<_>>();
+
+        let mut types = Vec::new();
+        uleb(&mut types, signatures.len() as u64);
+        for (params, results) in signatures {
+            types.push(0x60);
+            uleb(&mut types, params as u64);
+            types.extend(std::iter::repeat(I64).take(params));
+            uleb(&mut types, results as u64);
+            types.extend(std::iter::repeat(I64).take(results));
+        }
+        section(&mut module, TYPE_SECTION, types);
+
+        let mut imports = Vec::new();
+        uleb(&mut imports, self.external_functions.len() as u64);
+        for (idx, id) in self.external_functions.iter().enumerate() {
+            let ext_fn = self.program.external_functions.get(id).unwrap();
+            name(&mut imports, "env");
+            name(&mut imports, &ext_fn.name);
+            imports.push(0x00);
+            uleb(&mut imports, idx as u64);
+        }
+        section(&mut module, IMPORT_SECTION, imports);
+
+        let defined = self.functions.len() + HELPERS.len();
+        let mut functions = Vec::new();
+        uleb(&mut functions, defined as u64);
+        for idx in 0..defined {
+            uleb(&mut functions, (self.external_functions.len() + idx) as u64);
+        }
+        section(&mut module, FUNCTION_SECTION, functions);
+
+        let pages = (self.data.len() + PAGE_SIZE - 1) / PAGE_SIZE;
+        let mut memory = Vec::new();
+        uleb(&mut memory, 1);
+        memory.push(0x00);
+        uleb(&mut memory, pages.max(1) as u64);
+        section(&mut module, MEMORY_SECTION, memory);
+
+        // the only global is the offset the next allocation is made at, which
+        // starts right after the constants
+        let mut globals = Vec::new();
+        uleb(&mut globals, 1);
+        globals.push(I64);
+        globals.push(0x01);
+        globals.push(op::I64_CONST);
+        sleb(&mut globals, self.data.len() as i64);
+        globals.push(op::END);
+        section(&mut module, GLOBAL_SECTION, globals);
+
+        let mut exports = Vec::new();
+        uleb(&mut exports, 2);
+        name(&mut exports, "main");
+        exports.push(0x00);
+        uleb(
+            &mut exports,
+            self.function_index(self.program.entrypoint) as u64,
+        );
+        name(&mut exports, "memory");
+        exports.push(0x02);
+        uleb(&mut exports, 0);
+        section(&mut module, EXPORT_SECTION, exports);
+
+        let mut code = Vec::new();
+        uleb(&mut code, defined as u64);
+        for id in self.functions.iter() {
+            let function = self.program.functions.get(id).unwrap();
+            let body = FnCompiler::new(self, function).compile();
+            uleb(&mut code, body.len() as u64);
+            code.extend(body);
+        }
+        for helper in HELPERS {
+            let body = self.helper_body(helper);
+            uleb(&mut code, body.len() as u64);
+            code.extend(body);
+        }
+        section(&mut module, CODE_SECTION, code);
+
+        let mut data = Vec::new();
+        uleb(&mut data, self.constants.len() as u64);
+        for (id, offset) in self.constants.iter() {
+            let payload = &self.program.constants.get(id).unwrap().payload;
+            data.push(0x00);
+            data.push(op::I32_CONST);
+            sleb(&mut data, *offset as i64);
+            data.push(op::END);
+            uleb(&mut data, (WORD_SIZE + payload.len()) as u64);
+            data.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+            data.extend_from_slice(payload);
+        }
+        section(&mut module, DATA_SECTION, data);
+
+        module
+    }
+
+    /// Produces the body of a helper function, including its local declarations.
+    fn helper_body(&self, helper: Helper) -> Vec<u8> {
+        let mut body = Vec::new();
+
+        match helper {
+            Helper::Alloc => {
+                // local 0 is the size, and local 1 is the allocation
+                uleb(&mut body, 1);
+                uleb(&mut body, 1);
+                body.push(I64);
+
+                body.push(op::GLOBAL_GET);
+                uleb(&mut body, 0);
+                local_tee(&mut body, 1);
+                local_get(&mut body, 0);
+                body.push(op::I64_ADD);
+                body.push(op::GLOBAL_SET);
+                uleb(&mut body, 0);
+
+                // grow memory by the amount of pages missing to fit the heap
+                body.push(op::GLOBAL_GET);
+                uleb(&mut body, 0);
+                i64_const(&mut body, PAGE_SIZE as i64 - 1);
+                body.push(op::I64_ADD);
+                i64_const(&mut body, PAGE_SIZE.trailing_zeros() as i64);
+                body.push(op::I64_SHR_U);
+                body.push(op::MEMORY_SIZE);
+                body.push(0x00);
+                body.push(op::I64_EXTEND_I32_U);
+                body.push(op::I64_SUB);
+                local_tee(&mut body, 0);
+                i64_const(&mut body, 0);
+                body.push(op::I64_GT_S);
+                body.push(op::IF);
+                body.push(EMPTY_BLOCK_TYPE);
+                local_get(&mut body, 0);
+                body.push(op::I32_WRAP_I64);
+                body.push(op::MEMORY_GROW);
+                body.push(0x00);
+                body.push(op::DROP);
+                body.push(op::END);
+
+                local_get(&mut body, 1);
+            }
+            Helper::BytesEquals => {
+                // locals 0 and 1 are the bytes, and local 2 counts down the
+                // bytes left to compare
+                uleb(&mut body, 1);
+                uleb(&mut body, 1);
+                body.push(I64);
+
+                local_get(&mut body, 0);
+                load(&mut body, op::I64_LOAD, 0);
+                local_tee(&mut body, 2);
+                local_get(&mut body, 1);
+                load(&mut body, op::I64_LOAD, 0);
+                body.push(op::I64_NE);
+                return_if(&mut body, 0);
+
+                body.push(op::BLOCK);
+                body.push(EMPTY_BLOCK_TYPE);
+                body.push(op::LOOP);
+                body.push(EMPTY_BLOCK_TYPE);
+                local_get(&mut body, 2);
+                body.push(op::I64_EQZ);
+                body.push(op::BR_IF);
+                uleb(&mut body, 1);
+
+                local_get(&mut body, 2);
+                i64_const(&mut body, 1);
+                body.push(op::I64_SUB);
+                body.push(op::LOCAL_SET);
+                uleb(&mut body, 2);
+
+                for bytes in [0, 1] {
+                    local_get(&mut body, bytes);
+                    local_get(&mut body, 2);
+                    body.push(op::I64_ADD);
+                    load(&mut body, op::I64_LOAD8_U, WORD_SIZE as u32);
+                }
+                body.push(op::I64_NE);
+                return_if(&mut body, 0);
+
+                body.push(op::BR);
+                uleb(&mut body, 0);
+                body.push(op::END);
+                body.push(op::END);
+
+                i64_const(&mut body, 1);
+            }
+            Helper::RecordFind => {
+                // local 0 is the record, and then the entry being looked at
+                uleb(&mut body, 0);
+
+                local_get(&mut body, 0);
+                load(&mut body, op::I64_LOAD, 0);
+                body.push(op::LOCAL_SET);
+                uleb(&mut body, 0);
+
+                body.push(op::BLOCK);
+                body.push(EMPTY_BLOCK_TYPE);
+                body.push(op::LOOP);
+                body.push(EMPTY_BLOCK_TYPE);
+                local_get(&mut body, 0);
+                body.push(op::I64_EQZ);
+                body.push(op::BR_IF);
+                uleb(&mut body, 1);
+
+                local_get(&mut body, 0);
+                load(&mut body, op::I64_LOAD, ENTRY_KIND);
+                local_get(&mut body, 1);
+                body.push(op::I64_EQ);
+                body.push(op::IF);
+                body.push(EMPTY_BLOCK_TYPE);
+
+                // keys which are bytes are equal if their contents are
+                local_get(&mut body, 1);
+                i64_const(&mut body, key_kind(ValueType::Bytes));
+                body.push(op::I64_EQ);
+                body.push(op::IF);
+                body.push(I64);
+                local_get(&mut body, 0);
+                load(&mut body, op::I64_LOAD, ENTRY_KEY);
+                local_get(&mut body, 2);
+                body.push(op::CALL);
+                uleb(&mut body, self.helper_index(Helper::BytesEquals) as u64);
+                body.push(op::ELSE);
+                local_get(&mut body, 0);
+                load(&mut body, op::I64_LOAD, ENTRY_KEY);
+                local_get(&mut body, 2);
+                body.push(op::I64_EQ);
+                body.push(op::I64_EXTEND_I32_U);
+                body.push(op::END);
+
+                body.push(op::I32_WRAP_I64);
+                body.push(op::IF);
+                body.push(EMPTY_BLOCK_TYPE);
+                local_get(&mut body, 0);
+                body.push(op::RETURN);
+                body.push(op::END);
+                body.push(op::END);
+
+                local_get(&mut body, 0);
+                load(&mut body, op::I64_LOAD, ENTRY_NEXT);
+                body.push(op::LOCAL_SET);
+                uleb(&mut body, 0);
+                body.push(op::BR);
+                uleb(&mut body, 0);
+                body.push(op::END);
+                body.push(op::END);
+
+                i64_const(&mut body, 0);
+            }
+        }
+
+        body.push(op::END);
+        body
+    }
+}
+
+struct FnCompiler<'m, 'p> {
+    module: &'m Module<'p>,
+    function: &'p Function<LowerCtx>,
+    /// The order the blocks are laid out in, with the entry block first.
+    blocks: Vec<BlockId<LowerCtx>>,
+    /// A local for instructions to hold onto intermediate values with, which
+    /// comes after the local of every register.
+    scratch: u32,
+    /// The local holding the index of the block to run next, which comes after
+    /// the scratch local.
+    label: u32,
+    locals: FxHashMap<RegisterId<LowerCtx>, u32>,
+    code: Vec<u8>,
+}
+
+impl<'m, 'p> FnCompiler<'m, 'p> {
+    fn new(module: &'m Module<'p>, function: &'p Function<LowerCtx>) -> Self {
+        let mut blocks = (function.blocks.keys().copied())
+            .filter(|id| *id != function.entry)
+            .collect::<Vec<_>>();
+        blocks.sort();
+        blocks.insert(0, function.entry);
+
+        // the parameters of the entry block are the parameters of the function,
+        // so they must be the first locals
+        let entry = function.blocks.get(&function.entry).unwrap();
+        let locals = (entry.parameters.iter().enumerate())
+            .map(|(idx, register)| (*register, idx as u32))
+            .collect();
+
+        let mut registers = FxHashSet::default();
+        for block in function.blocks.values() {
+            registers.extend(block.parameters.iter().copied());
+            registers.extend(block.instructions.iter().flat_map(declared_register));
+        }
+
+        FnCompiler {
+            module,
+            function,
+            blocks,
+            scratch: registers.len() as u32,
+            label: registers.len() as u32 + 1,
+            locals,
+            code: Vec::new(),
+        }
+    }
+
+    /// Produces the body of the function, including its local declarations.
+    fn compile(mut self) -> Vec<u8> {
+        let blocks = self.blocks.len();
+
+        self.code.push(op::LOOP);
+        self.code.push(EMPTY_BLOCK_TYPE);
+        for _ in 0..blocks {
+            self.code.push(op::BLOCK);
+            self.code.push(EMPTY_BLOCK_TYPE);
+        }
+
+        // the label starts at zero, which is the entry block
+        self.code.push(op::LOCAL_GET);
+        uleb(&mut self.code, self.label as u64);
+        self.code.push(op::BR_TABLE);
+        uleb(&mut self.code, (blocks - 1) as u64);
+        for depth in 0..blocks - 1 {
+            uleb(&mut self.code, depth as u64);
+        }
+        uleb(&mut self.code, (blocks - 1) as u64);
+
+        for idx in 0..blocks {
+            self.code.push(op::END);
+
+            let function = self.function;
+            let block = function.blocks.get(&self.blocks[idx]).unwrap();
+            let loop_depth = (blocks - 1 - idx) as u32;
+            self.compile_block(block, loop_depth);
+        }
+
+        self.code.push(op::END);
+        self.code.push(op::UNREACHABLE);
+        self.code.push(op::END);
+
+        let entry = self.function.blocks.get(&self.function.entry).unwrap();
+        let mut body = Vec::new();
+        uleb(&mut body, 2);
+        uleb(
+            &mut body,
+            (self.scratch as usize + 1 - entry.parameters.len()) as u64,
+        );
+        body.push(I64);
+        uleb(&mut body, 1);
+        body.push(I32);
+        body.extend(self.code);
+        body
+    }
+
+    fn local(&mut self, register: RegisterId<LowerCtx>) -> u32 {
+        let next = self.locals.len() as u32;
+        *self.locals.entry(register).or_insert(next)
+    }
+
+    fn get(&mut self, register: RegisterId<LowerCtx>) {
+        let local = self.local(register);
+        self.code.push(op::LOCAL_GET);
+        uleb(&mut self.code, local as u64);
+    }
+
+    fn set(&mut self, register: RegisterId<LowerCtx>) {
+        let local = self.local(register);
+        self.code.push(op::LOCAL_SET);
+        uleb(&mut self.code, local as u64);
+    }
+
+    fn compile_block(&mut self, block: &Block<LowerCtx, BlockId<LowerCtx>>, loop_depth: u32) {
+        for inst in block.instructions.iter() {
+            self.compile_instruction(block, inst);
+        }
+
+        match &block.end {
+            EndInstruction::Unreachable(_) => self.code.push(op::UNREACHABLE),
+            EndInstruction::Jump(jump) => self.jump(&jump.0, loop_depth),
+            EndInstruction::JumpIf(jump_if) => {
+                self.get(jump_if.condition);
+                self.code.push(op::I32_WRAP_I64);
+                self.code.push(op::IF);
+                self.code.push(EMPTY_BLOCK_TYPE);
+                self.jump(&jump_if.if_so, loop_depth + 1);
+                self.code.push(op::ELSE);
+                self.jump(&jump_if.other, loop_depth + 1);
+                self.code.push(op::END);
+                self.code.push(op::UNREACHABLE);
+            }
+            EndInstruction::Return(ret) => {
+                for register in ret.0.iter() {
+                    self.get(*register);
+                }
+                self.code.push(op::RETURN);
+            }
+        }
+    }
+
+    fn jump(&mut self, path: &BlockJump<BlockId<LowerCtx>, LowerCtx>, loop_depth: u32) {
+        let BlockJump(target, args) = path;
+        let function = self.function;
+        let parameters = &function.blocks.get(target).unwrap().parameters;
+
+        // every argument is read before any parameter is written to, as a block
+        // may jump to itself with its parameters in a different order
+        for arg in args.iter() {
+            self.get(*arg);
+        }
+        for parameter in parameters.iter().rev() {
+            self.set(*parameter);
+        }
+
+        let idx = self.blocks.iter().position(|b| b == target).unwrap();
+        self.code.push(op::I32_CONST);
+        sleb(&mut self.code, idx as i64);
+        self.code.push(op::LOCAL_SET);
+        uleb(&mut self.code, self.label as u64);
+        self.code.push(op::BR);
+        uleb(&mut self.code, loop_depth as u64);
+    }
+
+    /// The kind of value `register` holds, if every value it could hold is of
+    /// the same kind.
+    fn value_type(
+        &self,
+        block: &Block<LowerCtx, BlockId<LowerCtx>>,
+        register: RegisterId<LowerCtx>,
+    ) -> Option<ValueType> {
+        let type_info = &block.type_info;
+        let typ = type_info.try_get(register.convert())?;
+
+        let mut kinds = (type_info.variants_of(typ).into_iter()).map(|typ| typ.value_type());
+        let kind = kinds.next()??;
+        kinds.all(|k| k == Some(kind)).then(|| kind)
+    }
+
+    /// The kind of a record key, which keeps keys of different kinds apart even
+    /// if they're represented by the same `i64`.
+    fn record_key_kind(
+        &self,
+        block: &Block<LowerCtx, BlockId<LowerCtx>>,
+        key: &RecordKey<LowerCtx>,
+    ) -> i64 {
+        match key {
+            RecordKey::Prop(register) => match self.value_type(block, *register) {
+                Some(kind) => key_kind(kind),
+                None => panic!("record key %{} must have a known type", register),
+            },
+            RecordKey::Atom(_) | RecordKey::DynAtom(_) => key_kind(ValueType::Atom),
+        }
+    }
+
+    fn record_key(&mut self, key: &RecordKey<LowerCtx>) {
+        match key {
+            RecordKey::Prop(register) | RecordKey::DynAtom(register) => self.get(*register),
+            RecordKey::Atom(atom) => i64_const(&mut self.code, atom.0.get() as i64),
+        }
+    }
+
+    /// Finds the entry of a field in a record, leaving the offset of the entry
+    /// in the scratch local and on the stack, or `0` if there is no entry.
+    fn record_find(
+        &mut self,
+        block: &Block<LowerCtx, BlockId<LowerCtx>>,
+        record: RegisterId<LowerCtx>,
+        key: &RecordKey<LowerCtx>,
+    ) {
+        self.get(record);
+        let kind = self.record_key_kind(block, key);
+        i64_const(&mut self.code, kind);
+        self.record_key(key);
+        self.call_helper(Helper::RecordFind);
+        local_tee(&mut self.code, self.scratch);
+    }
+
+    fn compile_instruction(
+        &mut self,
+        block: &Block<LowerCtx, BlockId<LowerCtx>>,
+        inst: &Instruction<LowerCtx>,
+    ) {
+        match inst {
+            Instruction::Noop(_) | Instruction::Comment(_) => {}
+            Instruction::MakeInteger(i) => {
+                i64_const(&mut self.code, i.item);
+                self.set(i.result);
+            }
+            Instruction::MakeBoolean(i) => {
+                i64_const(&mut self.code, i.item as i64);
+                self.set(i.result);
+            }
+            Instruction::MakeAtom(i) => {
+                i64_const(&mut self.code, i.item.0.get() as i64);
+                self.set(i.result);
+            }
+            Instruction::GetFnPtr(i) => {
+                i64_const(&mut self.code, i.item.value() as i64);
+                self.set(i.result);
+            }
+            Instruction::MakeBytes(i) => {
+                let offset = self.module.constant_offset(i.item);
+                i64_const(&mut self.code, offset as i64);
+                self.set(i.result);
+            }
+            Instruction::BinOp(i) => {
+                self.get(i.lhs);
+                self.get(i.rhs);
+
+                let bytes = Some(ValueType::Bytes);
+                let compares_bytes = self.value_type(block, i.lhs) == bytes
+                    && self.value_type(block, i.rhs) == bytes;
+
+                match i.op {
+                    BinaryOperator::Add => self.code.push(op::I64_ADD),
+                    BinaryOperator::Multiply => self.code.push(op::I64_MUL),
+                    BinaryOperator::And => self.code.push(op::I64_AND),
+                    BinaryOperator::Or => self.code.push(op::I64_OR),
+                    BinaryOperator::Equals if compares_bytes => {
+                        self.call_helper(Helper::BytesEquals);
+                    }
+                    BinaryOperator::Equals => {
+                        self.code.push(op::I64_EQ);
+                        self.code.push(op::I64_EXTEND_I32_U);
+                    }
+                    BinaryOperator::LessThan => {
+                        self.code.push(op::I64_LT_S);
+                        self.code.push(op::I64_EXTEND_I32_U);
+                    }
+                }
+
+                self.set(i.result);
+            }
+            Instruction::Negate(i) => match self.value_type(block, i.operand) {
+                Some(ValueType::Boolean) => {
+                    self.get(i.operand);
+                    self.code.push(op::I64_EQZ);
+                    self.code.push(op::I64_EXTEND_I32_U);
+                    self.set(i.result);
+                }
+                Some(ValueType::Number) => {
+                    i64_const(&mut self.code, 0);
+                    self.get(i.operand);
+                    self.code.push(op::I64_SUB);
+                    self.set(i.result);
+                }
+                kind => panic!("cannot negate %{} of kind {:?}", i.operand, kind),
+            },
+            Instruction::CallStatic(i) => {
+                for arg in i.args.iter() {
+                    self.get(*arg);
+                }
+
+                let index = self.module.function_index(i.calling);
+                let results = *self.module.return_arity.get(&i.calling).unwrap();
+                self.call(index, results, i.result);
+            }
+            Instruction::CallExtern(i) => {
+                for arg in i.args.iter() {
+                    self.get(*arg);
+                }
+
+                let index = self.module.external_function_index(i.calling);
+                let (_, results) = self.module.external_function_signature(i.calling);
+                self.call(index, results, i.result);
+            }
+            Instruction::CallVirt(_) => panic!("virtual instructions not supported"),
+            Instruction::NewRecord(i) => {
+                // a record starts out without any entries, and allocated memory
+                // is zeroed, so there's nothing to initialize
+                i64_const(&mut self.code, WORD_SIZE as i64);
+                self.call_helper(Helper::Alloc);
+                self.set(i.result);
+            }
+            Instruction::RecordGet(i) => {
+                // reading a field the record doesn't have is a bug in the typing
+                // of the program
+                self.record_find(block, i.record, &i.key);
+                self.code.push(op::I64_EQZ);
+                self.code.push(op::IF);
+                self.code.push(EMPTY_BLOCK_TYPE);
+                self.code.push(op::UNREACHABLE);
+                self.code.push(op::END);
+
+                local_get(&mut self.code, self.scratch);
+                load(&mut self.code, op::I64_LOAD, ENTRY_VALUE);
+                self.set(i.result);
+            }
+            Instruction::RecordHasKey(i) => {
+                self.record_find(block, i.record, &i.key);
+                i64_const(&mut self.code, 0);
+                self.code.push(op::I64_NE);
+                self.code.push(op::I64_EXTEND_I32_U);
+                self.set(i.result);
+            }
+            Instruction::RecordSet(i) => match i.value {
+                Some(value) => {
+                    // fields the record doesn't have yet get a new entry at the
+                    // start of the record's entries
+                    self.record_find(block, i.record, &i.key);
+                    self.code.push(op::I64_EQZ);
+                    self.code.push(op::IF);
+                    self.code.push(EMPTY_BLOCK_TYPE);
+
+                    i64_const(&mut self.code, ENTRY_SIZE);
+                    self.call_helper(Helper::Alloc);
+                    self.code.push(op::LOCAL_SET);
+                    uleb(&mut self.code, self.scratch as u64);
+
+                    let kind = self.record_key_kind(block, &i.key);
+                    self.scratch_address();
+                    i64_const(&mut self.code, kind);
+                    store(&mut self.code, ENTRY_KIND);
+
+                    self.scratch_address();
+                    self.record_key(&i.key);
+                    store(&mut self.code, ENTRY_KEY);
+
+                    self.scratch_address();
+                    self.get(i.record);
+                    load(&mut self.code, op::I64_LOAD, 0);
+                    store(&mut self.code, ENTRY_NEXT);
+
+                    self.get(i.record);
+                    self.code.push(op::I32_WRAP_I64);
+                    local_get(&mut self.code, self.scratch);
+                    store(&mut self.code, 0);
+                    self.code.push(op::END);
+
+                    self.scratch_address();
+                    self.get(value);
+                    store(&mut self.code, ENTRY_VALUE);
+                }
+                None => {
+                    // deleted entries are kept around, but are never found again
+                    self.record_find(block, i.record, &i.key);
+                    i64_const(&mut self.code, 0);
+                    self.code.push(op::I64_NE);
+                    self.code.push(op::IF);
+                    self.code.push(EMPTY_BLOCK_TYPE);
+                    self.scratch_address();
+                    i64_const(&mut self.code, DELETED_KIND);
+                    store(&mut self.code, ENTRY_KIND);
+                    self.code.push(op::END);
+                }
+            },
+        }
+    }
+
+    fn call_helper(&mut self, helper: Helper) {
+        self.code.push(op::CALL);
+        uleb(&mut self.code, self.module.helper_index(helper) as u64);
+    }
+
+    /// Pushes the scratch local as an address into linear memory.
+    fn scratch_address(&mut self) {
+        local_get(&mut self.code, self.scratch);
+        self.code.push(op::I32_WRAP_I64);
+    }
+
+    fn call(&mut self, index: u32, results: usize, result: Option<RegisterId<LowerCtx>>) {
+        self.code.push(op::CALL);
+        uleb(&mut self.code, index as u64);
+
+        match result {
+            Some(register) => {
+                assert_eq!(results, 1, "the result of a call must be a single value");
+                self.set(register);
+            }
+            None => {
+                for _ in 0..results {
+                    self.code.push(op::DROP);
+                }
+            }
+        }
+    }
+}
+
+/// The amount of values a function returns, judging by its first `Return`.
+fn return_arity(function: &Function<LowerCtx>) -> usize {
+    (function.blocks.values())
+        .find_map(|block| match &block.end {
+            EndInstruction::Return(ret) => Some(ret.0.len()),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+fn declared_register(inst: &Instruction<LowerCtx>) -> Option<RegisterId<LowerCtx>> {
+    match inst {
+        Instruction::NewRecord(i) => Some(i.result),
+        Instruction::RecordGet(i) => Some(i.result),
+        Instruction::RecordHasKey(i) => Some(i.result),
+        Instruction::CallStatic(i) => i.result,
+        Instruction::CallExtern(i) => i.result,
+        Instruction::CallVirt(i) => i.result,
+        Instruction::GetFnPtr(i) => Some(i.result),
+        Instruction::MakeAtom(i) => Some(i.result),
+        Instruction::MakeBytes(i) => Some(i.result),
+        Instruction::MakeInteger(i) => Some(i.result),
+        Instruction::MakeBoolean(i) => Some(i.result),
+        Instruction::BinOp(i) => Some(i.result),
+        Instruction::Negate(i) => Some(i.result),
+        Instruction::Noop(_) | Instruction::Comment(_) | Instruction::RecordSet(_) => None,
+    }
+}
+
+/// Identifies the kind of a record key by the kind of value it is.
+fn key_kind(value_type: ValueType) -> i64 {
+    value_type as i64
+}
+
+fn local_get(buffer: &mut Vec<u8>, local: u32) {
+    buffer.push(op::LOCAL_GET);
+    uleb(buffer, local as u64);
+}
+
+fn local_tee(buffer: &mut Vec<u8>, local: u32) {
+    buffer.push(op::LOCAL_TEE);
+    uleb(buffer, local as u64);
+}
+
+fn i64_const(buffer: &mut Vec<u8>, value: i64) {
+    buffer.push(op::I64_CONST);
+    sleb(buffer, value);
+}
+
+/// Loads from the address on the stack, which is given as an `i64`.
+fn load(buffer: &mut Vec<u8>, opcode: u8, offset: u32) {
+    let align = match opcode {
+        op::I64_LOAD8_U => 0,
+        _ => 3,
+    };
+
+    buffer.push(op::I32_WRAP_I64);
+    buffer.push(opcode);
+    uleb(buffer, align);
+    uleb(buffer, offset as u64);
+}
+
+/// Stores a word at an address, which must already be on the stack as an `i32`
+/// below the word.
+fn store(buffer: &mut Vec<u8>, offset: u32) {
+    buffer.push(op::I64_STORE);
+    uleb(buffer, 3);
+    uleb(buffer, offset as u64);
+}
+
+/// Returns `value` if the `i32` on the stack is nonzero.
+fn return_if(buffer: &mut Vec<u8>, value: i64) {
+    buffer.push(op::IF);
+    buffer.push(EMPTY_BLOCK_TYPE);
+    i64_const(buffer, value);
+    buffer.push(op::RETURN);
+    buffer.push(op::END);
+}
+
+fn section(module: &mut Vec<u8>, id: u8, contents: Vec<u8>) {
+    module.push(id);
+    uleb(module, contents.len() as u64);
+    module.extend(contents);
+}
+
+fn name(buffer: &mut Vec<u8>, name: &str) {
+    uleb(buffer, name.len() as u64);
+    buffer.extend_from_slice(name.as_bytes());
+}
+
+/// Writes an unsigned LEB128 integer.
+fn uleb(buffer: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+
+        buffer.push(byte | 0x80);
+    }
+}
+
+/// Writes a signed LEB128 integer.
+fn sleb(buffer: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        let sign_bit_clear = byte & 0x40 == 0;
+        if (value == 0 && sign_bit_clear) || (value == -1 && !sign_bit_clear) {
+            buffer.push(byte);
+            return;
+        }
+
+        buffer.push(byte | 0x80);
+    }
+}
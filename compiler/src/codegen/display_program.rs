@@ -2,7 +2,7 @@ use crate::{
     codegen::EndInstruction,
     frontend::ir::FFIValueType,
     id::{BlockId, LowerCtx},
-    isa::{ISAInstruction, Unreachable},
+    isa::ISAInstruction,
     symbolic_execution::types::InstIdx,
 };
 
@@ -91,10 +91,7 @@ pub fn display_program(program: &Program) -> String {
                 EndInstruction::Jump(inst) => inst.display(&mut text),
                 EndInstruction::JumpIf(inst) => inst.display(&mut text),
                 EndInstruction::Return(inst) => inst.display(&mut text),
-                _ => todo!(),
-                /* EndInstruction::Unreachable(inst) => {
-                 *     <Unreachable as ISAInstruction<LowerCtx>>::display(inst, &mut text)
-                 * } */
+                EndInstruction::Unreachable(inst) => inst.display(&mut text),
             }
             .unwrap();
             iwl!(text);
@@ -1,8 +1,5 @@
 use crate::{
-    codegen::EndInstruction,
-    frontend::ir::FFIValueType,
-    id::AssemblerCtx,
-    isa::{ISAInstruction, Unreachable},
+    codegen::EndInstruction, frontend::ir::FFIValueType, id::AssemblerCtx, isa::ISAInstruction,
     symbolic_execution::types::InstIdx,
 };
 
@@ -85,10 +82,7 @@ pub fn display_typed(program: &TypedProgram) -> String {
             EndInstruction::Jump(inst) => inst.display(&mut text),
             EndInstruction::JumpIf(inst) => inst.display(&mut text),
             EndInstruction::Return(inst) => inst.display(&mut text),
-            _ => todo!(),
-            /* EndInstruction::Unreachable(inst) => {
-             *     <Unreachable as ISAInstruction<AssemblerCtx>>::display(inst, &mut text)
-             * } */
+            EndInstruction::Unreachable(inst) => inst.display(&mut text),
         }
         .unwrap();
         iwl!(text);
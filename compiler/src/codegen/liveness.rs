@@ -0,0 +1,117 @@
+//! Computes which registers are live at each point of a [`Function`], to
+//! estimate how many values a backend has to keep around at once.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use tinyvec::TinyVec;
+
+use crate::id::{BlockId, RegisterId, Tag};
+use crate::isa::{ISAInstruction, Jump, JumpIf};
+
+use super::{Block, EndInstruction, Function};
+
+/// The registers live within a single block.
+pub struct BlockLiveness<T: Tag> {
+    /// The registers live before each instruction of the block, followed by
+    /// the registers live before the end of the block.
+    pub live: Vec<FxHashSet<RegisterId<T>>>,
+    /// The most registers that are live at any one point in the block.
+    pub max_pressure: usize,
+}
+
+/// Computes the liveness of every register in every block of `function`.
+///
+/// A register is live from the point it is declared up until its last use,
+/// including uses in any blocks that may execute afterwards. The parameters of
+/// a block are declared when it is jumped to, so they are only live in the
+/// blocks jumping to it through the arguments of the jump.
+pub fn liveness<T: Tag>(function: &Function<T>) -> FxHashMap<BlockId<T>, BlockLiveness<T>> {
+    let mut live_in = FxHashMap::<BlockId<T>, FxHashSet<RegisterId<T>>>::default();
+
+    // the live registers of a block depend on those of the blocks it jumps to,
+    // which may jump back, so iterate until nothing changes
+    loop {
+        let mut changed = false;
+
+        for (id, block) in function.blocks.iter() {
+            let live = live_before(block, live_out(function, block, &live_in)).remove(0);
+
+            if live_in.get(id) != Some(&live) {
+                live_in.insert(*id, live);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    (function.blocks.iter())
+        .map(|(id, block)| {
+            let live = live_before(block, live_out(function, block, &live_in));
+            let max_pressure = live.iter().map(|live| live.len()).max().unwrap_or(0);
+
+            (*id, BlockLiveness { live, max_pressure })
+        })
+        .collect()
+}
+
+/// Finds the registers needed by the blocks that `block` may jump to.
+fn live_out<T: Tag>(
+    function: &Function<T>,
+    block: &Block<T, BlockId<T>>,
+    live_in: &FxHashMap<BlockId<T>, FxHashSet<RegisterId<T>>>,
+) -> FxHashSet<RegisterId<T>> {
+    let successors = match &block.end {
+        EndInstruction::Unreachable(_) | EndInstruction::Return(_) => vec![],
+        EndInstruction::Jump(Jump(target)) => vec![target.0],
+        EndInstruction::JumpIf(JumpIf { if_so, other, .. }) => vec![if_so.0, other.0],
+    };
+
+    let mut live = FxHashSet::default();
+
+    for successor in successors {
+        let parameters = &function.blocks.get(&successor).unwrap().parameters;
+
+        (live_in.get(&successor).into_iter().flatten())
+            .filter(|register| !parameters.contains(register))
+            .for_each(|register| {
+                live.insert(*register);
+            });
+    }
+
+    live
+}
+
+/// Walks `block` backwards from the registers live at its end, producing the
+/// registers live before each instruction and before the end of the block.
+fn live_before<T: Tag>(
+    block: &Block<T, BlockId<T>>,
+    live_out: FxHashSet<RegisterId<T>>,
+) -> Vec<FxHashSet<RegisterId<T>>> {
+    let mut live = live_out;
+    live.extend(end_used_registers(&block.end));
+
+    let mut points = vec![live.clone()];
+
+    for inst in block.instructions.iter().rev() {
+        if let Some(register) = inst.declared_register() {
+            live.remove(&register);
+        }
+
+        live.extend(inst.used_registers());
+        points.push(live.clone());
+    }
+
+    points.reverse();
+    points
+}
+
+fn end_used_registers<T: Tag>(end: &EndInstruction<T, BlockId<T>>) -> TinyVec<[RegisterId<T>; 3]> {
+    match end {
+        EndInstruction::Unreachable(i) => i.used_registers(),
+        EndInstruction::Jump(i) => i.used_registers(),
+        EndInstruction::JumpIf(i) => i.used_registers(),
+        EndInstruction::Return(i) => i.used_registers(),
+    }
+}
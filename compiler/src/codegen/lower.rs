@@ -3,7 +3,7 @@ use std::hash::Hash;
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::codegen::Block;
-use crate::id::{AssemblerCtx, BlockId, FunctionId, LowerCtx};
+use crate::id::{AssemblerCtx, BlockId, ExternalFunctionId, FunctionId, LowerCtx};
 use crate::isa::{self, BlockJump};
 use crate::retag::{
     BlkMapRetagger, BlkRetagger, CnstPassRetagger, ExtFnPassRetagger, FnMapRetagger,
@@ -20,18 +20,32 @@ pub fn lower(program: TypedProgram) -> Program {
     let functions = lowerer.lower();
     let entrypoint = lowerer.id_of_explored(program.entrypoint);
 
-    Program {
+    // only keep the external functions that are actually called, so that the
+    // backend doesn't emit imports for unused ones
+    let used_external_functions = lowerer.used_external_functions;
+    let mut external_functions = program.external_functions;
+    external_functions.retain(|id, _| used_external_functions.contains(id));
+
+    let mut program = Program {
         entrypoint,
-        external_functions: program.external_functions,
+        external_functions,
         constants: program.constants,
         functions,
-    }
+    };
+
+    // likewise, only keep the constants that are referenced so that the backend
+    // can emit a minimal data section
+    let reachable_constants = super::reachable_constants(&program);
+    (program.constants).retain(|id, _| reachable_constants.contains(id));
+
+    program
 }
 
 struct Lowerer<'a> {
     typed_program: &'a TypedProgram,
     fn_id_mapper: FnMapRetagger<AssemblerCtx, LowerCtx>,
     explore_queue: ExploreQueue<FunctionId<AssemblerCtx>>,
+    used_external_functions: FxHashSet<ExternalFunctionId<AssemblerCtx>>,
 }
 
 impl<'a> Lowerer<'a> {
@@ -40,6 +54,7 @@ impl<'a> Lowerer<'a> {
             typed_program,
             fn_id_mapper: Default::default(),
             explore_queue: Default::default(),
+            used_external_functions: Default::default(),
         }
     }
 
@@ -84,8 +99,12 @@ impl<'a> Lowerer<'a> {
             let block = self.typed_program.functions.get(&block_id).unwrap();
 
             for inst in block.instructions.iter() {
-                if let Instruction::CallStatic(inst) = inst {
-                    self.explore_queue.enqueue(inst.calling);
+                match inst {
+                    Instruction::CallStatic(inst) => self.explore_queue.enqueue(inst.calling),
+                    Instruction::CallExtern(inst) => {
+                        self.used_external_functions.insert(inst.calling);
+                    }
+                    _ => {}
                 }
             }
 
@@ -199,13 +218,12 @@ impl<'a> Lowerer<'a> {
                     CallVirt(_) => panic!("virtual instructions not supported"),
                     // CallVirt(i.retag(&mut reg_retagger)),
                     GetFnPtr(i) => GetFnPtr(i.retag(&mut reg_retagger, &fnptr_retagger)),
-                    // MakeAtom(i) => MakeAtom(i.retag(&mut reg_retagger)),
+                    MakeAtom(i) => MakeAtom(i.retag(&mut reg_retagger)),
                     MakeBytes(i) => MakeBytes(i.retag(&mut reg_retagger, &const_retagger)),
                     MakeInteger(i) => MakeInteger(i.retag(&mut reg_retagger)),
                     MakeBoolean(i) => MakeBoolean(i.retag(&mut reg_retagger)),
                     BinOp(i) => BinOp(i.retag(&mut reg_retagger)),
                     Negate(i) => Negate(i.retag(&mut reg_retagger)),
-                    _ => todo!(),
                 };
                 instructions.push(new_inst);
             }
@@ -254,13 +272,25 @@ impl<'a> Lowerer<'a> {
                 }
             };
 
+            // the types of the block are known by the registers of the typed program,
+            // so they're looked up by the lowered registers instead for backends
+            let declared = (block.instructions.iter()).filter_map(|i| i.declared_register());
+            let mut type_info = block.type_info.clone();
+            type_info.registers = (block.parameters.iter().copied())
+                .chain(declared)
+                .filter_map(|register| {
+                    let typ = block.type_info.try_get(register.convert())?;
+                    Some((reg_retagger.retag_old(register).convert(), typ))
+                })
+                .collect();
+
             blocks.insert(
                 block_id_mapper.retag_old(block_id.convert()),
                 Block {
                     parameters,
                     instructions,
                     end,
-                    type_info: block.type_info.clone(),
+                    type_info,
                 },
             );
 
@@ -14,9 +14,12 @@ pub use display_typed::display_typed;
 mod display_program;
 pub use display_program::display_program;
 
+mod liveness;
+pub use liveness::{liveness, BlockLiveness};
+
 use std::fmt::Write;
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use tinyvec::TinyVec;
 
 use crate::frontend::ir::{Constant, ExternalFunction};
@@ -36,6 +39,19 @@ pub struct Program<T: Tag = LowerCtx> {
     pub functions: FxHashMap<FunctionId<T>, Function<T>>,
 }
 
+/// Finds every constant referenced by the functions of the program, which is
+/// the exact set of constants a backend needs to emit.
+pub fn reachable_constants<T: Tag>(program: &Program<T>) -> FxHashSet<ConstantId> {
+    let blocks = (program.functions.values()).flat_map(|function| function.blocks.values());
+
+    (blocks.flat_map(|block| block.instructions.iter()))
+        .filter_map(|inst| match inst {
+            Instruction::MakeBytes(inst) => Some(inst.item),
+            _ => None,
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct Function<T: Tag> {
     pub entry: BlockId<T>,
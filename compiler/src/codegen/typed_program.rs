@@ -6,7 +6,7 @@ use tinyvec::TinyVec;
 
 use crate::{
     id::{AssemblerCtx, FunctionId, LiftedCtx, RegisterId, SymbolicCtx},
-    isa::{BlockJump, Call, Comment, Jump, JumpIf, Make, Noop},
+    isa::{BlockJump, Call, Comment, Jump, JumpIf, Make, Noop, Unreachable},
     lifted::LiftedProgram,
     retag::{
         CnstPassRetagger, CnstRetagger, ExtFnPassRetagger, ExtFnRetagger, FnPassRetagger,
@@ -145,6 +145,10 @@ impl<'r> FnTyper<'r, '_> {
             .map(|r| self.reg_retagger.retag_new(*r))
             .collect();
 
+        if let Some(diverges_at) = self.results.diverges_at {
+            return self.process_trap_stub(parameters, diverges_at);
+        }
+
         let instructions = (self.lifted_fn.instructions.iter().enumerate())
             .map(|(idx, inst)| self.process_inst(inst, InstIdx::Inst(idx)))
             .collect();
@@ -161,110 +165,118 @@ impl<'r> FnTyper<'r, '_> {
         }
     }
 
+    /// Functions which never return because one of their instructions diverges
+    /// are emitted as a body which traps right after that instruction. The
+    /// instructions before it still run, and so does the instruction itself if
+    /// it's a call into a function that diverges, so their side effects are
+    /// kept. Nothing after it is lowered, so any function which refers to them
+    /// can still be compiled.
+    fn process_trap_stub(
+        &mut self,
+        parameters: Vec<RegisterId<AssemblerCtx>>,
+        diverges_at: usize,
+    ) -> Block<AssemblerCtx> {
+        use crate::frontend::ir::InstructionData;
+
+        let lifted_fn = self.lifted_fn;
+        let kept = match &lifted_fn.instructions[diverges_at].data {
+            InstructionData::CallStatic(_)
+            | InstructionData::CallSpread(_)
+            | InstructionData::CallExtern(_)
+            | InstructionData::CallVirt(_) => diverges_at + 1,
+            _ => diverges_at,
+        };
+
+        let instructions = (lifted_fn.instructions[..kept].iter().enumerate())
+            .map(|(idx, inst)| self.process_inst(inst, InstIdx::Inst(idx)))
+            .collect::<Vec<_>>();
+
+        // nothing after the kept instructions is lowered, so any register past
+        // the ones they declare is free
+        let declared = (instructions.iter()).filter_map(Instruction::declared_register);
+        let result = (parameters.iter().copied().chain(declared).max())
+            .map(|r| r.next())
+            .unwrap_or_else(RegisterId::new);
+
+        Block {
+            parameters,
+            instructions,
+            end: EndInstruction::Unreachable(Unreachable { result }),
+            type_info: self.results.types.clone(),
+        }
+    }
+
     fn process_inst(
         &mut self,
         instruction: &crate::frontend::ir::Instruction<LiftedCtx, LiftedCtx>,
         inst_idx: InstIdx,
     ) -> Instruction<AssemblerCtx> {
-        use crate::frontend::ir;
-
-        todo!()
-        // match instruction {
-        //     ir::Instruction::Comment(i) => Instruction::Comment(*i),
-        //     ir::Instruction::NewRecord(i) => {
-        //         Instruction::NewRecord(i.retag(&mut self.reg_retagger))
-        //     }
-        //     ir::Instruction::RecordGet(i) => {
-        //         Instruction::RecordGet(i.retag(&mut self.reg_retagger))
-        //     }
-        //     ir::Instruction::RecordSet(i) => {
-        //         Instruction::RecordSet(i.retag(&mut self.reg_retagger))
-        //     }
-        //     ir::Instruction::RecordHasKey(i) => {
-        //         Instruction::RecordHasKey(i.retag(&mut self.reg_retagger))
-        //     }
-        //     ir::Instruction::GetFnPtr(i) => Instruction::GetFnPtr(Make {
-        //         result: self.reg_retagger.retag_new(i.result),
-        //         item: i.item,
-        //     }),
-        //     ir::Instruction::CallStatic(i) => {
-        //         // TODO: abstract out SymbWorker::exec_types and use their
-        // same logic         // TODO: this logic is also present in
-        // mapping a BlockJump, need to consolidaate         // the
-        // logic         let prev = inst_idx.back().unwrap();
-        //         let target_id =
-        //             self.lookup_symbolic_fn_id_from_invocation(i.calling,
-        // &i.args, prev);
-
-        //         Instruction::CallStatic(Call {
-        //             result: i.result.map(|r| self.reg_retagger.retag_new(r)),
-        //             calling: self.fn_id_mapper.retag_old(target_id),
-        //             args: i
-        //                 .args
-        //                 .iter()
-        //                 .map(|r| self.reg_retagger.retag_old(*r))
-        //                 .collect(),
-        //         })
-        //     }
-        //     ir::Instruction::CallExtern(i) => Instruction::CallExtern(Call {
-        //         result: i.result.map(|r| self.reg_retagger.retag_new(r)),
-        //         calling: self.ext_fn_id_mapper.retag_old(i.calling),
-        //         args: i
-        //             .args
-        //             .iter()
-        //             .map(|r| self.reg_retagger.retag_old(*r))
-        //             .collect(),
-        //     }),
-        //     ir::Instruction::CallVirt(i) => {
-        //         // TODO: no support for unions of fnptrs yet
-        //         // SANITY: this is fine as SSA form prevents registers from
-        // being set and we         // should only have fnpts stored in
-        // the register id         let fn_id =
-        // self.results.types.get_fnptr(i.calling);         let prev =
-        // inst_idx.back().unwrap();         let target_id =
-        // self.lookup_symbolic_fn_id_from_invocation(fn_id, &i.args, prev);
-
-        //         // TODO: once callvirt supports calling unions,
-        //         // replace this instruction with a different one maybe? maybe
-        // some kind of         // callvirt instruction? one that would
-        // handle the idea of "here         // are the unions of
-        // functions we may call bla bla bla"         Instruction::
-        // CallStatic(Call {             result: i.result.map(|r|
-        // self.reg_retagger.retag_new(r)),             calling:
-        // self.fn_id_mapper.retag_old(target_id),             args: i
-        //                 .args
-        //                 .iter()
-        //                 .map(|r| self.reg_retagger.retag_old(*r))
-        //                 .collect(),
-        //         })
-        //     }
-        //     // ir::Instruction::MakeAtom(i) => {
-        //     //     Instruction::MakeAtom(i.retag(&mut self.reg_retagger))
-        //     // }
-        //     ir::Instruction::MakeBytes(i) => {
-        //         let const_retagger = self.constant_id_mapper;
-        //         let reg_retagger = &mut self.reg_retagger;
-        //         Instruction::MakeBytes(i.retag(reg_retagger, const_retagger))
-        //     }
-        //     ir::Instruction::MakeInteger(i) => {
-        //         Instruction::MakeInteger(i.retag(&mut self.reg_retagger))
-        //     }
-        //     ir::Instruction::MakeBoolean(i) => {
-        //         Instruction::MakeBoolean(i.retag(&mut self.reg_retagger))
-        //     }
-        //     ir::Instruction::BinOp(i) => Instruction::BinOp(i.retag(&mut
-        // self.reg_retagger)),     ir::Instruction::Negate(i) =>
-        // Instruction::Negate(i.retag(&mut self.reg_retagger)),
-        //     ir::Instruction::Generalize(_) => todo!(),
-        //     ir::Instruction::Assert(_) => todo!(),
-        //     ir::Instruction::IsType(_) => todo!(),
-        //     ir::Instruction::NewList(_) => todo!(),
-        //     ir::Instruction::ListGet(_) => todo!(),
-        //     ir::Instruction::ListSet(_) => todo!(),
-        //     ir::Instruction::ListHasKey(_) => todo!(),
-        //     ir::Instruction::ListLen(_) => todo!(),
-        //     _ => todo!(),
-        // }
+        use crate::frontend::ir::InstructionData;
+
+        match instruction.data.clone() {
+            InstructionData::Comment(i) => Instruction::Comment(i),
+            InstructionData::NewRecord(i) => {
+                Instruction::NewRecord(i.retag(&mut self.reg_retagger))
+            }
+            InstructionData::RecordGet(i) => {
+                Instruction::RecordGet(i.retag(&mut self.reg_retagger))
+            }
+            InstructionData::RecordSet(i) => {
+                Instruction::RecordSet(i.retag(&mut self.reg_retagger))
+            }
+            InstructionData::RecordHasKey(i) => {
+                Instruction::RecordHasKey(i.retag(&mut self.reg_retagger))
+            }
+            InstructionData::GetFnPtr(i) => Instruction::GetFnPtr(Make {
+                result: self.reg_retagger.retag_new(i.result),
+                item: i.item,
+            }),
+            InstructionData::CallStatic(i) => {
+                let target_id =
+                    self.lookup_symbolic_fn_id_from_invocation(i.calling, &i.args, inst_idx);
+                self.process_call(target_id, i.result, &i.args)
+            }
+            InstructionData::CallVirt(i) => {
+                // SANITY: registers are only ever assigned once, so the register
+                // holds a pointer to exactly one function
+                let fn_id = self.results.types.get_fnptr(i.calling);
+                let target_id =
+                    self.lookup_symbolic_fn_id_from_invocation(fn_id, &i.args, inst_idx);
+                self.process_call(target_id, i.result, &i.args)
+            }
+            InstructionData::CallExtern(i) => Instruction::CallExtern(
+                i.retag(&mut self.reg_retagger, &*self.factory.ext_fn_id_mapper),
+            ),
+            InstructionData::MakeAtom(i) => Instruction::MakeAtom(i.retag(&mut self.reg_retagger)),
+            InstructionData::MakeBytes(i) => Instruction::MakeBytes(
+                i.retag(&mut self.reg_retagger, self.factory.constant_id_mapper),
+            ),
+            InstructionData::MakeInteger(i) => {
+                Instruction::MakeInteger(i.retag(&mut self.reg_retagger))
+            }
+            InstructionData::MakeBoolean(i) => {
+                Instruction::MakeBoolean(i.retag(&mut self.reg_retagger))
+            }
+            InstructionData::BinOp(i) => Instruction::BinOp(i.retag(&mut self.reg_retagger)),
+            InstructionData::Negate(i) => Instruction::Negate(i.retag(&mut self.reg_retagger)),
+            _ => panic!("instruction at {:?} can't be typed yet", inst_idx),
+        }
+    }
+
+    fn process_call(
+        &mut self,
+        target_id: FunctionId<SymbolicCtx>,
+        result: Option<RegisterId<LiftedCtx>>,
+        args: &[RegisterId<LiftedCtx>],
+    ) -> Instruction<AssemblerCtx> {
+        Instruction::CallStatic(Call {
+            result: result.map(|r| self.reg_retagger.retag_new(r)),
+            calling: self.factory.fn_id_mapper.retag_new(target_id),
+            args: args
+                .iter()
+                .map(|r| self.reg_retagger.retag_old(*r))
+                .collect(),
+        })
     }
 
     fn process_end(
@@ -283,7 +295,7 @@ impl<'r> FnTyper<'r, '_> {
                 }
             }
             crate::lifted::EndInstruction::Return(i) => {
-                EndInstruction::Return(i.retag(&self.reg_retagger))
+                EndInstruction::Return(i.clone().retag(&self.reg_retagger))
             }
         }
     }
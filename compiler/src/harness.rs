@@ -0,0 +1,57 @@
+//! Compiles and runs programs written as IR files from start to finish, for
+//! tests that would rather write out IR than build it through the builder APIs.
+
+use crate::{
+    frontend::builder::ProgramBuilder,
+    interpreter::{ExternRegistry, Interpreter, Value},
+    lifted,
+    stages::{self, Stage},
+};
+
+/// Compiles and runs an IR file, returning the value its entrypoint returns.
+/// The entrypoint is the first function in the file, which must not take any
+/// parameters.
+///
+/// The file is parsed and lowered, then the program is annotated by symbolic
+/// execution and assembled, to check that it would compile, before its
+/// entrypoint is interpreted.
+///
+/// Panics if the file has errors, if the program doesn't compile, or if the
+/// program calls an external function missing from `externs`, fails while
+/// executing, or doesn't return a value.
+pub fn run_ir_file(source: &str, externs: ExternRegistry) -> Value {
+    let ast = ir_file::parse(source, &[]);
+    let entrypoint = (ast.sections.first())
+        .expect("the IR file should have an entrypoint")
+        .header
+        .method_name
+        .clone();
+
+    let mut program = ProgramBuilder::new();
+    let functions = ir_file::lower_ast(ast, &mut program).unwrap_or_else(|errors| {
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        panic!("failed to lower the IR file:\n{}", errors.join("\n"))
+    });
+
+    let mut main = program.start_function_main();
+    let mut block = main.start_block_main();
+    let result = block.call_dynargs_with_result(functions[&entrypoint], vec![]);
+    main.end_block(block.ret(&[result]));
+    program.end_function(main);
+
+    let ir = program.finish();
+
+    if let Err(error) = stages::compile_to(Stage::Assembler, ir.clone()) {
+        panic!("failed to compile the program: {}", error);
+    }
+
+    let program = lifted::lift(ir);
+    let ext_fns = externs.bind(&program);
+
+    let mut interpreter = Interpreter::new(&program, &ext_fns);
+    let result = interpreter
+        .execute_fn_id(program.entrypoint, vec![])
+        .unwrap_or_else(|err| panic!("failed to run the program: {:?}", err));
+
+    result.expect("the entrypoint should return a value")
+}
@@ -33,6 +33,7 @@ pub mod backend;
 pub mod codegen;
 pub use jssat_ir::collections;
 pub mod frontend;
+pub mod harness;
 pub use jssat_ir::id;
 pub mod interner;
 pub use jssat_interpreter as interpreter;
@@ -41,6 +42,7 @@ pub use jssat_ir::lifted;
 pub mod my_tests;
 pub mod opt;
 pub use jssat_ir::retag;
+pub mod stages;
 use jssat_ir::{frontend::source_map::SourceMap, isa::AtomDealer, lifted::LiftedProgram};
 use symbolic_execution::SystemRun;
 pub mod symbolic_execution;
@@ -184,16 +186,28 @@ f(print);
         .expect("should parse js");
     let source_map = frontend.ecma_methods.source_map;
 
-    f.end_block(b.ret(Some(result)));
+    f.end_block(b.ret(&[result]));
     builder.end_function(f);
-    let ir = builder.finish();
+    let mut ir = builder.finish();
 
     let dealer = ir.dealer.clone();
 
     // println!("{}", crate::frontend::display_jssatir::display(&ir));
 
+    println!("eliminating dead functions");
+    time(|| frontend::ir::dead_code_elimination(&mut ir));
+
     println!("lifting program");
-    let program = time(move || lifted::lift(ir));
+    let mut program = time(move || lifted::lift(ir));
+
+    println!("simplifying control flow");
+    time(|| lifted::simplify_cfg(&mut program));
+
+    #[cfg(feature = "constexpr")]
+    {
+        println!("evaluating constant calls");
+        time(|| opt::constexpr(&mut program));
+    }
 
     println!("executing program");
     // interpret(&program, dealer, source_map);
@@ -2,15 +2,67 @@
 
 #[cfg(test)]
 use crate::{
-    frontend::builder::ProgramBuilder,
-    isa::{BlockJump, Jump},
+    codegen::{self, TypedProgram},
+    collections::StrictZip,
+    frontend::{
+        builder::{self, DynBlockBuilder, ProgramBuilder},
+        ir::{
+            used_external_functions, Constant, ExternalFunction, FFIValueType, InstructionData,
+            Returns, IR,
+        },
+    },
+    harness::run_ir_file,
+    id::{
+        AssemblerCtx, ConstantId, ExternalFunctionId, FunctionId, LiftedCtx, RegisterId,
+        SymbolicCtx,
+    },
+    interpreter::{ExternRegistry, Value},
+    isa::{BinOp, BinaryOperator, BlockJump, Call, Jump, Make, Return, ValueType},
     lifted::EndInstruction,
     symbolic_execution::{
         self,
-        types::{RegisterType, ReturnType},
+        escape::Escapes,
+        types::{InstIdx, RegisterType, ReturnType, TypeBag},
+        worker::WorkerResults,
         SystemRun,
     },
 };
+#[cfg(test)]
+use serial_test::serial;
+
+/// Lifts the program and symbolically executes it from its entrypoint,
+/// producing the results of the entrypoint.
+#[cfg(test)]
+fn run_entrypoint(ir: IR) -> WorkerResults {
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn,
+        mut results,
+        ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    results.remove(&entry_fn).unwrap()
+}
+
+/// Symbolically executes a program whose entrypoint is a single block that
+/// returns the register produced by `build`. Tests checking several values
+/// run one program per value, rather than returning them all at once.
+#[cfg(test)]
+fn run_returning(
+    build: impl FnOnce(&mut ProgramBuilder, &mut DynBlockBuilder) -> builder::RegisterId,
+) -> WorkerResults {
+    let mut program = ProgramBuilder::new();
+    let mut main = program.start_function_main();
+    let mut block = main.start_block_main();
+
+    let value = build(&mut program, &mut block);
+
+    main.end_block(block.ret(&[value]));
+    program.end_function(main);
+
+    run_entrypoint(program.finish())
+}
 
 /// Confirm that registers are passed one block away properly
 #[test]
@@ -25,7 +77,7 @@ pub fn can_lift_registers_in_near_blocks() {
     let undefined = program.dealer.deal("undefined");
     let undefined = block1.make_atom(undefined);
     main.end_block(block1.jmp(block2.signature(), []));
-    main.end_block(block2.ret(Some(undefined)));
+    main.end_block(block2.ret(&[undefined]));
 
     program.end_function(main);
 
@@ -70,7 +122,7 @@ pub fn can_lift_registers_in_far_blocks() {
     main.end_block(block2.jmp(block3.signature(), []));
     main.end_block(block3.jmp(block4.signature(), []));
     main.end_block(block4.jmp(block5.signature(), []));
-    main.end_block(block5.ret(Some(undefined)));
+    main.end_block(block5.ret(&[undefined]));
 
     program.end_function(main);
 
@@ -128,7 +180,7 @@ pub fn mutations_in_function_propagate_to_caller() {
         let null = program.dealer.deal("null");
         let null = block.make_atom(null);
         block.record_set_atom(record, base, null);
-        mutate.end_block(block.ret(None));
+        mutate.end_block(block.ret(&[]));
         program.end_function(mutate)
     };
 
@@ -140,7 +192,7 @@ pub fn mutations_in_function_propagate_to_caller() {
         block.call(mutate, [record]);
         let has_key = block.record_has_atom(record, base);
 
-        main.end_block(block.ret(Some(has_key)));
+        main.end_block(block.ret(&[has_key]));
 
         program.end_function(main)
     };
@@ -206,7 +258,7 @@ pub fn shape_weirdness_does_not_happen() {
         let one = block.make_number_decimal(1);
         block.record_set_atom(record, base, one);
 
-        mutate.end_block(block.ret(None));
+        mutate.end_block(block.ret(&[]));
         program.end_function(mutate)
     };
 
@@ -227,7 +279,7 @@ pub fn shape_weirdness_does_not_happen() {
         block.call(mutate, [big]);
         block.call(mutate, [small]);
 
-        main.end_block(block.ret(None));
+        main.end_block(block.ret(&[]));
         program.end_function(main)
     };
 
@@ -244,3 +296,3163 @@ pub fn shape_weirdness_does_not_happen() {
     // - mutate(small)
     assert_eq!(results.len(), 3);
 }
+
+/// Confirms that a function returning multiple values has the type of each
+/// returned value annotated
+#[test]
+#[serial]
+pub fn multi_value_returns_are_annotated() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let number = block.make_number_decimal(1);
+        let boolean = block.make_bool(true);
+
+        main.end_block(block.ret(&[number, boolean]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let entrypoint = lifted.functions.get(&lifted.entrypoint).unwrap();
+    if let EndInstruction::Return(Return(values)) = &entrypoint.end {
+        assert_eq!(values.len(), 2);
+    } else {
+        panic!("main should end in a return")
+    }
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Tuple(vec![RegisterType::Int(1), RegisterType::Bool(true)])
+    );
+}
+
+/// Confirms that an equality chain (as lowered from a `match` statement) on a
+/// known constant only takes the path of the matching case
+#[test]
+#[serial]
+pub fn constant_equality_chain_takes_matching_case() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+
+        let mut block = main.start_block_main();
+        let (mut case1_test, []) = main.start_block();
+        let (mut case1_body, []) = main.start_block();
+        let (mut case2_test, []) = main.start_block();
+        let (mut case2_body, []) = main.start_block();
+        let (mut otherwise, []) = main.start_block();
+
+        let discriminant = block.make_number_decimal(2);
+        main.end_block(block.jmp(case1_test.signature(), []));
+
+        let one = case1_test.make_number_decimal(1);
+        let is_one = case1_test.compare_equal(discriminant, one);
+        main.end_block(case1_test.jmpif(
+            is_one,
+            case1_body.signature(),
+            [],
+            case2_test.signature(),
+            [],
+        ));
+
+        let ten = case1_body.make_number_decimal(10);
+        main.end_block(case1_body.ret(&[ten]));
+
+        let two = case2_test.make_number_decimal(2);
+        let is_two = case2_test.compare_equal(discriminant, two);
+        main.end_block(case2_test.jmpif(
+            is_two,
+            case2_body.signature(),
+            [],
+            otherwise.signature(),
+            [],
+        ));
+
+        let twenty = case2_body.make_number_decimal(20);
+        main.end_block(case2_body.ret(&[twenty]));
+
+        let thirty = otherwise.make_number_decimal(30);
+        main.end_block(otherwise.ret(&[thirty]));
+
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Int(20))
+    );
+}
+
+/// Confirms that external functions which are declared but never called are
+/// not considered reachable, and are pruned when lowering the program
+#[test]
+pub fn unused_external_functions_are_pruned() {
+    let mut program = ProgramBuilder::new();
+    let used = program.external_function("used", [], Returns::Void);
+    let unused = program.external_function("unused", [], Returns::Void);
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        block.call_external_function(used, []);
+
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let reachable = used_external_functions(&ir);
+
+    assert_eq!(reachable.len(), 1);
+    assert!(reachable.contains(&used.0));
+    assert!(!reachable.contains(&unused.0));
+
+    let entrypoint = FunctionId::<AssemblerCtx>::new();
+    let used = ExternalFunctionId::<AssemblerCtx>::new_with_value_const(0);
+    let unused = ExternalFunctionId::<AssemblerCtx>::new_with_value_const(1);
+
+    let ext_fn = |name: &str| ExternalFunction {
+        name: name.to_string(),
+        parameters: Vec::new(),
+        return_type: Returns::Void,
+    };
+
+    let main = codegen::Block {
+        parameters: Vec::new(),
+        instructions: vec![codegen::Instruction::CallExtern(Call {
+            result: None,
+            calling: used,
+            args: Vec::new(),
+        })],
+        end: codegen::EndInstruction::Return(Return(Vec::new())),
+        type_info: TypeBag::default(),
+    };
+
+    let program = codegen::lower(TypedProgram {
+        entrypoint,
+        external_functions: vec![(used, ext_fn("used")), (unused, ext_fn("unused"))]
+            .into_iter()
+            .collect(),
+        constants: Default::default(),
+        functions: vec![(entrypoint, main)].into_iter().collect(),
+    });
+
+    assert!(program.external_functions.contains_key(&used));
+    assert!(!program.external_functions.contains_key(&unused));
+}
+
+/// Confirms that a function pointer obtained by name at runtime is typed as a
+/// pointer to an unknown function, and that calls through it are not
+/// devirtualized
+#[test]
+#[serial]
+pub fn dynamic_fnptr_calls_are_not_devirtualized() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut target, []) = program.start_function();
+        target.with_name("target".into());
+        let block = target.start_block_main();
+        target.end_block(block.ret(&[]));
+        program.end_function(target)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let name = program.constant_str("target");
+        let name = block.make_string(name);
+        let fn_ptr = block.make_fnptr_dynamic(name);
+        block.call_virt(fn_ptr, []);
+
+        main.end_block(block.ret(&[fn_ptr]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+
+    // only `main` should have been executed, as the call to `target` cannot
+    // be resolved statically
+    assert_eq!(results.len(), 1);
+
+    let results = results.get(&entry_fn).unwrap();
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::AnyFnPtr)
+    );
+}
+
+/// Ensures that cloning a record produces a record with a new identity, but
+/// with the same fields as the original
+#[test]
+#[serial]
+pub fn record_clone_copies_fields_with_new_identity() {
+    let returned = |value: usize| {
+        let results = run_returning(|_, block| {
+            let record = block.record_new();
+            let key = block.make_number_decimal(1);
+            let value = block.make_number_decimal(2);
+            block.record_set_prop(record, key, value);
+
+            let clone = block.record_clone(record);
+            let is_same = block.compare_equal(clone, record);
+            let record_field = block.record_get_prop(record, key);
+            let clone_field = block.record_get_prop(clone, key);
+
+            [is_same, record_field, clone_field][value]
+        });
+        results.return_type
+    };
+
+    let [is_same, record_field, clone_field] = [0, 1, 2].map(returned);
+    assert_eq!(is_same, ReturnType::Value(RegisterType::Bool(false)));
+    assert_eq!(record_field, ReturnType::Value(RegisterType::Int(2)));
+    assert_eq!(clone_field, ReturnType::Value(RegisterType::Int(2)));
+}
+
+/// Ensures that calls to an opaque function are given the declared return type
+/// of the function, without its body ever being explored
+#[test]
+#[serial]
+pub fn opaque_functions_are_not_explored() {
+    let mut program = ProgramBuilder::new();
+
+    let inner = {
+        let (mut inner, []) = program.start_function();
+        let mut block = inner.start_block_main();
+        let value = block.make_number_decimal(1);
+        inner.end_block(block.ret(&[value]));
+        program.end_function(inner)
+    };
+
+    let helper = {
+        let (mut helper, []) = program.start_function();
+        helper.with_name("helper".into());
+        let mut block = helper.start_block_main();
+        let value = block.call_with_result(inner, []);
+        helper.end_block(block.ret(&[value]));
+        program.end_function(helper)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let value = block.call_with_result(helper, []);
+
+        main.end_block(block.ret(&[value]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let helper = *lifted.function_names.get("helper").unwrap();
+    let mut opaque_functions = rustc_hash::FxHashMap::default();
+    opaque_functions.insert(helper, ReturnType::Value(RegisterType::Number));
+
+    let engine = symbolic_execution::make_system_with_opaque(&lifted, opaque_functions);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+
+    // neither `helper` nor `inner` should have been spawned
+    assert_eq!(results.len(), 1);
+
+    let results = results.get(&entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Number));
+}
+
+/// Ensures that `type-of` on a value of a known type produces the exact name of
+/// that type
+#[test]
+#[serial]
+pub fn type_of_known_number_is_folded() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let number = block.make_number_decimal(1);
+        let name = block.type_of(number);
+
+        main.end_block(block.ret(&[name]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    match results.return_type {
+        ReturnType::Value(RegisterType::Byts(name)) => {
+            assert_eq!(&*results.types.constant_bytes(name), b"number")
+        }
+        ref r => panic!("expected an exact string, got {:?}", r),
+    }
+}
+
+/// Ensures that `type-of` on a value of an unknown type produces some string
+#[test]
+#[serial]
+pub fn type_of_any_is_string() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [value]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+        let name = block.type_of(value);
+        f.end_block(block.ret(&[name]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Bytes));
+}
+
+/// Ensures that an inherited lookup walks the chain of prototypes to find a
+/// property, stopping at a prototype that isn't a record
+#[test]
+#[serial]
+pub fn inherited_property_is_resolved_through_prototype_chain() {
+    let mut program = ProgramBuilder::new();
+    let null = program.dealer.deal("null");
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let key = block.make_number_decimal(1);
+        let value = block.make_number_decimal(5);
+        let null = block.make_atom(null);
+
+        let root = block.record_new();
+        block.record_set_prop(root, key, value);
+        block.record_set_prototype(root, null);
+
+        let parent = block.record_new();
+        block.record_set_prototype(parent, root);
+
+        let child = block.record_new();
+        block.record_set_prototype(child, parent);
+
+        let inherited = block.record_get_inherited(child, key);
+
+        main.end_block(block.ret(&[inherited]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Int(5)));
+}
+
+/// Ensures that a function which never returns is compiled to a stub that
+/// traps once it diverges, keeping the instructions that run before then
+#[test]
+#[serial]
+pub fn diverging_function_compiles_to_trapping_stub() {
+    let mut program = ProgramBuilder::new();
+    let hello = program.constant_str("hello");
+
+    let halt = {
+        let (mut halt, []) = program.start_function();
+        let mut block = halt.start_block_main();
+
+        let hello = block.make_string(hello);
+        let start = block.make_number_decimal(2);
+        let end = block.make_number_decimal(10);
+        let slice = block.bytes_slice(hello, start, end);
+
+        halt.end_block(block.ret(&[slice]));
+        program.end_function(halt)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let value = block.make_number_decimal(1);
+        block.call(halt, []);
+
+        main.end_block(block.ret(&[value]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    assert_eq!(
+        run.results.get(&run.entry_fn).unwrap().return_type,
+        ReturnType::Never
+    );
+
+    let typed = codegen::type_program(run);
+    let entrypoint = typed.functions.get(&typed.entrypoint).unwrap();
+
+    // the call into the diverging function is kept, and traps after it
+    let halt = match entrypoint.instructions.as_slice() {
+        [codegen::Instruction::MakeInteger(_), codegen::Instruction::CallStatic(call)] => {
+            call.calling
+        }
+        _ => panic!("expected the number and the call to be kept"),
+    };
+    assert!(matches!(
+        entrypoint.end,
+        codegen::EndInstruction::Unreachable(_)
+    ));
+
+    // everything up until the slice which diverges is kept
+    let halt = typed.functions.get(&halt).unwrap();
+    assert!(matches!(
+        halt.instructions.as_slice(),
+        [
+            codegen::Instruction::MakeBytes(_),
+            codegen::Instruction::MakeInteger(_),
+            codegen::Instruction::MakeInteger(_),
+        ]
+    ));
+    assert!(matches!(halt.end, codegen::EndInstruction::Unreachable(_)));
+}
+
+/// Ensures that pushing onto a list of a known length produces a list one
+/// element longer
+#[test]
+#[serial]
+pub fn list_push_grows_length() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let list = block.list_new();
+        let value = block.make_number_decimal(1);
+        block.list_push(list, value);
+        block.list_push(list, value);
+        let len = block.list_len(list);
+
+        main.end_block(block.ret(&[len]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Int(2)));
+}
+
+/// Ensures that popping from a list which is known to be empty is reported as
+/// an error
+#[test]
+#[serial]
+pub fn list_pop_on_empty_list_errors() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let list = block.list_new();
+        let value = block.list_pop(list);
+
+        main.end_block(block.ret(&[value]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = run.results.get(&run.entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Never);
+
+    let diagnostics = run.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].1.message.contains("always empty"));
+}
+
+/// Ensures that a list pushed onto by a function it's passed to is seen by the
+/// caller, rather than keeping the length it had before the call
+#[test]
+#[serial]
+pub fn list_pushed_by_callee_is_seen_by_caller() {
+    let mut program = ProgramBuilder::new();
+
+    let push = {
+        let (mut push, [list]) = program.start_function();
+        let mut block = push.start_block_main();
+        let value = block.make_number_decimal(1);
+        block.list_push(list, value);
+        push.end_block(block.ret(&[]));
+        program.end_function(push)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let list = block.list_new();
+        block.call(push, [list]);
+        let len = block.list_len(list);
+
+        main.end_block(block.ret(&[len]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = run.results.get(&run.entry_fn).unwrap();
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Number));
+}
+
+/// Ensures that a trivial value, such as `undefined`, can be passed to an
+/// external function which accepts `Any`
+#[test]
+#[serial]
+pub fn trivial_value_coerces_to_any_in_extern_call() {
+    let mut program = ProgramBuilder::new();
+    let undefined = program.dealer.deal("undefined");
+    let print = program.external_function("print", [FFIValueType::Any], Returns::Void);
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let value = block.make_atom(undefined);
+        block.call_external_function(print, [value]);
+
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = run.results.get(&run.entry_fn).unwrap();
+
+    assert_eq!(results.return_type, ReturnType::Void);
+    assert!(run.diagnostics().is_empty());
+    assert!(RegisterType::Atom(undefined).can_coerce_to(&FFIValueType::Any));
+    assert!(!RegisterType::Atom(undefined).can_coerce_to(&FFIValueType::String));
+}
+
+/// Ensures that register types are displayed with the names used in
+/// diagnostics, rather than their `Debug` representation
+#[test]
+pub fn register_types_display_spec_like_names() {
+    use crate::id::{AllocationId, LiftedCtx};
+
+    assert_eq!(RegisterType::Any.to_string(), "Any");
+    assert_eq!(RegisterType::Number.to_string(), "Number");
+    assert_eq!(RegisterType::Int(5).to_string(), "5: Number");
+    assert_eq!(RegisterType::Bool(true).to_string(), "true: Boolean");
+
+    let record = AllocationId::<LiftedCtx>::new_with_value_const(3);
+    assert_eq!(RegisterType::Record(record).to_string(), "Record#3");
+}
+
+/// Ensures that calling a pure function with constant arguments produces a
+/// constant, which the caller can then use as a constant
+#[test]
+#[serial]
+pub fn constant_arguments_fold_across_calls() {
+    let returned = |value: usize| {
+        let results = run_returning(|program, block| {
+            let square = {
+                let (mut square, [x]) = program.start_function();
+                let mut block = square.start_block_main();
+                let squared = block.multiply(x, x);
+                square.end_block(block.ret(&[squared]));
+                program.end_function(square)
+            };
+
+            let four = block.make_number_decimal(4);
+            let sixteen = block.call_with_result(square, [four]);
+            let one = block.make_number_decimal(1);
+            let seventeen = block.add(sixteen, one);
+
+            [sixteen, seventeen][value]
+        });
+        results.return_type
+    };
+
+    let [sixteen, seventeen] = [0, 1].map(returned);
+    assert_eq!(sixteen, ReturnType::Value(RegisterType::Int(16)));
+    assert_eq!(seventeen, ReturnType::Value(RegisterType::Int(17)));
+}
+
+/// Ensures that the pipeline can be stopped after lifting into pure blocks, and
+/// that the output displays the lifted program
+#[test]
+pub fn compiles_to_pure_blocks_stage() {
+    use crate::stages::{compile_to, Artifact, Stage};
+
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let number = block.make_number_decimal(1);
+
+        main.end_block(block.ret(&[number]));
+        program.end_function(main)
+    };
+
+    let output = compile_to(Stage::PureBlocks, program.finish()).unwrap();
+
+    let lifted = match &output.artifact {
+        Artifact::PureBlocks(lifted) => lifted,
+        _ => panic!("expected the pure blocks stage"),
+    };
+
+    let main = lifted.functions.get(&lifted.entrypoint).unwrap();
+    assert_eq!(lifted.functions.len(), 1);
+    assert_eq!(
+        output.display,
+        crate::lifted::display(lifted.entrypoint, main)
+    );
+}
+
+/// Ensures that the pipeline can be stopped after annotating the program with
+/// types, and that stages which aren't implemented are reported as errors
+#[test]
+#[serial]
+pub fn compiles_to_annotated_stage() {
+    use crate::stages::{compile_to, Artifact, Stage, StageError};
+
+    let ir = || {
+        let mut program = ProgramBuilder::new();
+
+        {
+            let mut main = program.start_function_main();
+            let mut block = main.start_block_main();
+
+            let number = block.make_number_decimal(1);
+
+            main.end_block(block.ret(&[number]));
+            program.end_function(main)
+        };
+
+        program.finish()
+    };
+
+    let output = compile_to(Stage::Annotated, ir()).unwrap();
+    let typed = match &output.artifact {
+        Artifact::Annotated(typed) => typed,
+        _ => panic!("expected the annotated stage"),
+    };
+    assert_eq!(output.display, codegen::display_typed(typed));
+
+    assert!(matches!(
+        compile_to(Stage::Llvm, ir()),
+        Err(StageError::Unimplemented(Stage::Llvm))
+    ));
+}
+
+/// Ensures that `is-type-as` on two records compares the shape of the records
+#[test]
+#[serial]
+pub fn is_type_as_compares_record_shapes() {
+    let returned = |value: usize| {
+        let results = run_returning(|_, block| {
+            let x = block.make_number_decimal(1);
+            let y = block.make_number_decimal(2);
+
+            let a = block.record_new();
+            block.record_set_prop(a, x, x);
+            let b = block.record_new();
+            block.record_set_prop(b, x, y);
+            let c = block.record_new();
+            block.record_set_prop(c, y, y);
+
+            let same = block.is_type_as(a, b);
+            let different = block.is_type_as(a, c);
+
+            [same, different][value]
+        });
+        results.return_type
+    };
+
+    let [same, different] = [0, 1].map(returned);
+    assert_eq!(same, ReturnType::Value(RegisterType::Bool(true)));
+    assert_eq!(different, ReturnType::Value(RegisterType::Bool(false)));
+}
+
+/// Ensures that `is-type-as` on a value of an unknown type could be either
+/// true or false
+#[test]
+#[serial]
+pub fn is_type_as_any_is_unknown() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [value]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+        let record = block.record_new();
+        let is_type = block.is_type_as(value, record);
+        f.end_block(block.ret(&[is_type]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Boolean)
+    );
+}
+
+/// Ensures that a comment emitted by the builder shows up when displaying the
+/// IR, and has no effect on the types of the program
+#[test]
+#[serial]
+pub fn comments_are_displayed_and_ignored() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        block.comment("the answer");
+        let number = block.make_number_decimal(42);
+
+        main.end_block(block.ret(&[number]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let display = crate::frontend::display_jssatir::display(&ir);
+    assert!(display.contains("; the answer"));
+
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Int(42))
+    );
+}
+
+/// Ensures that running symbolic execution on the same program twice assigns
+/// the same function ids to the same functions
+#[test]
+#[serial]
+pub fn symbolic_function_ids_are_deterministic() {
+    fn run() -> Vec<(FunctionId<SymbolicCtx>, FunctionId<LiftedCtx>, ReturnType)> {
+        let mut program = ProgramBuilder::new();
+
+        let identity = {
+            let (mut identity, [x]) = program.start_function();
+            let block = identity.start_block_main();
+            identity.end_block(block.ret(&[x]));
+            program.end_function(identity)
+        };
+
+        let double = {
+            let (mut double, [x]) = program.start_function();
+            let mut block = double.start_block_main();
+            let x = block.call_with_result(identity, [x]);
+            let doubled = block.add(x, x);
+            double.end_block(block.ret(&[doubled]));
+            program.end_function(double)
+        };
+
+        {
+            let mut main = program.start_function_main();
+            let mut block = main.start_block_main();
+
+            let one = block.make_number_decimal(1);
+            let yes = block.make_bool(true);
+            let two = block.call_with_result(double, [one]);
+            let yes = block.call_with_result(identity, [yes]);
+            let four = block.call_with_result(double, [two]);
+
+            main.end_block(block.ret(&[four, yes]));
+            program.end_function(main)
+        };
+
+        let ir = program.finish();
+        let lifted = crate::lifted::lift(ir);
+
+        let engine = symbolic_execution::make_system(&lifted);
+        let SystemRun { results, .. } =
+            symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+
+        let mut ids = results
+            .into_iter()
+            .map(|(id, results)| (id, results.lifted_id, results.return_type))
+            .collect::<Vec<_>>();
+        ids.sort_by_key(|(id, _, _)| *id);
+        ids
+    }
+
+    let first = run();
+    assert!(first.len() > 1);
+    assert_eq!(first, run());
+}
+
+/// Ensures that concatenating and slicing exact strings produces exact strings
+#[test]
+#[serial]
+pub fn bytes_concat_and_slice_fold_exact_strings() {
+    let returned = |value: usize| {
+        let results = run_returning(|program, block| {
+            let hello = program.constant_str("hello");
+            let world = program.constant_str(", world");
+
+            let hello = block.make_string(hello);
+            let world = block.make_string(world);
+            let greeting = block.bytes_concat(hello, world);
+
+            let start = block.make_number_decimal(1);
+            let end = block.make_number_decimal(4);
+            let slice = block.bytes_slice(greeting, start, end);
+
+            [greeting, slice][value]
+        });
+
+        match results.return_type {
+            ReturnType::Value(RegisterType::Byts(bytes)) => {
+                results.types.constant_bytes(bytes).to_vec()
+            }
+            r => panic!("expected an exact string, got {:?}", r),
+        }
+    };
+
+    assert_eq!(returned(0), b"hello, world");
+    assert_eq!(returned(1), b"ell");
+}
+
+/// Ensures that slicing an exact string with constant bounds that are out of
+/// range is reported as a diagnostic, and never returns
+#[test]
+#[serial]
+pub fn bytes_slice_out_of_range_errors() {
+    let mut program = ProgramBuilder::new();
+    let hello = program.constant_str("hello");
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let hello = block.make_string(hello);
+        let start = block.make_number_decimal(2);
+        let end = block.make_number_decimal(10);
+        let slice = block.bytes_slice(hello, start, end);
+
+        main.end_block(block.ret(&[slice]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = run.results.get(&run.entry_fn).unwrap();
+    assert!(matches!(results.return_type, ReturnType::Never));
+
+    let diagnostics = run.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].1.message.contains("out of range"));
+}
+
+/// Ensures that `assume-type` narrows a value of an unknown type, without
+/// emitting any instructions to check the type at runtime
+#[test]
+#[serial]
+pub fn assume_type_narrows_any_without_check() {
+    use crate::frontend::ir::InstructionData;
+    use crate::isa::ValueType;
+
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [value]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+        let number = block.assume_type(value, ValueType::Number);
+        f.end_block(block.ret(&[number]));
+        program.end_function(f)
+    };
+
+    program.create_blank_entrypoint();
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let instructions = &lifted.functions.get(&f).unwrap().instructions;
+    let emits_check = instructions.iter().any(|inst| {
+        matches!(
+            inst.data,
+            InstructionData::Assert(_) | InstructionData::IsType(_)
+        )
+    });
+    assert!(!emits_check);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Number));
+}
+
+/// Ensures that `assume-type` keeps what is known about records and atoms, and
+/// leaves records, lists and atoms of an unknown type unknown
+#[test]
+#[serial]
+pub fn assume_type_keeps_records_lists_and_atoms() {
+    let assumed_of_any = |kind| {
+        let mut program = ProgramBuilder::new();
+
+        {
+            let (mut f, [value]) = program.start_function();
+            f.with_name("f".into());
+            let mut block = f.start_block_main();
+            let assumed = block.assume_type(value, kind);
+            f.end_block(block.ret(&[assumed]));
+            program.end_function(f)
+        };
+
+        program.create_blank_entrypoint();
+
+        let lifted = crate::lifted::lift(program.finish());
+        let f = *lifted.function_names.get("f").unwrap();
+
+        let engine = symbolic_execution::make_system(&lifted);
+        let SystemRun {
+            entry_fn,
+            mut results,
+            ..
+        } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+        results.remove(&entry_fn).unwrap().return_type
+    };
+
+    for kind in [ValueType::Atom, ValueType::Record, ValueType::List] {
+        assert_eq!(assumed_of_any(kind), ReturnType::Value(RegisterType::Any));
+    }
+
+    let record = run_returning(|_, block| {
+        let record = block.record_new();
+        block.assume_type(record, ValueType::Record)
+    });
+    assert!(matches!(
+        record.return_type,
+        ReturnType::Value(RegisterType::Record(_))
+    ));
+
+    let mut dealt = None;
+    let atom = run_returning(|program, block| {
+        let atom = program.dealer.deal("atom");
+        dealt = Some(atom);
+        let atom = block.make_atom(atom);
+        block.assume_type(atom, ValueType::Atom)
+    });
+    assert_eq!(
+        atom.return_type,
+        ReturnType::Value(RegisterType::Atom(dealt.unwrap()))
+    );
+}
+
+/// Ensures that a block which only jumps to another block is removed, with the
+/// blocks jumping to it jumping to the other block with the same arguments
+#[test]
+#[serial]
+pub fn forwarding_blocks_are_simplified_away() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut a = main.start_block_main();
+        let (b, [b_x, b_y]) = main.start_block();
+        let (c, [c_y, c_x]) = main.start_block();
+
+        let x = a.make_number_decimal(5);
+        let y = a.make_number_decimal(6);
+        let (b_sig, c_sig) = (b.signature(), c.signature());
+
+        main.end_block(a.jmp(b_sig, [x, y]));
+        main.end_block(b.jmp(c_sig, [b_y, b_x]));
+        main.end_block(c.ret(&[c_x, c_y]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let mut lifted = crate::lifted::lift(ir);
+    assert_eq!(lifted.functions.len(), 3);
+
+    crate::lifted::simplify_cfg(&mut lifted);
+    assert_eq!(lifted.functions.len(), 2);
+
+    let a = lifted.functions.get(&lifted.entrypoint).unwrap();
+    let x = a.instructions[0].assigned_to().unwrap();
+    let y = a.instructions[1].assigned_to().unwrap();
+    let c = *(lifted.functions.iter())
+        .find(|(_, f)| matches!(f.end, EndInstruction::Return(_)))
+        .unwrap()
+        .0;
+
+    assert_eq!(a.end, EndInstruction::Jump(Jump(BlockJump(c, vec![y, x]))));
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Tuple(vec![RegisterType::Int(5), RegisterType::Int(6)])
+    );
+}
+
+/// Ensures that emitting the parse nodes of a script produces the kind of the
+/// top-level parse node
+#[test]
+pub fn emitting_script_produces_script_node() {
+    use crate::frontend::js::{
+        ast::{
+            self,
+            parse_nodes::{Dealer, ParseNodeKind, Visitor},
+        },
+        ecmascript::ECMA262Methods,
+    };
+
+    let mut program = ProgramBuilder::new();
+    let ecma_methods = ECMA262Methods::new(&mut program);
+    let dealer = Dealer::new(&mut program);
+
+    let mut main = program.start_function_main();
+    let mut block = main.start_block_main();
+
+    let script = ast::parse_script("1;").expect("should parse js");
+    let node = ast::emit_nodes(
+        &mut program,
+        &mut block,
+        &ecma_methods,
+        &dealer,
+        &Default::default(),
+        |v| v.visit_script(&script),
+    );
+
+    main.end_block(block.ret(&[node.register]));
+    program.end_function(main);
+
+    assert_eq!(node.kind, ParseNodeKind::Script);
+}
+
+/// Ensures that spreading a list of a known length into a call is the same as
+/// calling the function with each element as an argument
+#[test]
+#[serial]
+pub fn call_spread_with_known_length_is_precise() {
+    let mut program = ProgramBuilder::new();
+
+    let multiply = {
+        let (mut multiply, [x, y]) = program.start_function();
+        let mut block = multiply.start_block_main();
+        let product = block.multiply(x, y);
+        multiply.end_block(block.ret(&[product]));
+        program.end_function(multiply)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let args = block.list_new();
+        let four = block.make_number_decimal(4);
+        block.list_push(args, four);
+        block.list_push(args, four);
+        let sixteen = block.call_spread_with_result(multiply.id, args);
+
+        main.end_block(block.ret(&[sixteen]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Int(16))
+    );
+}
+
+/// Ensures that each element of a list spread into a call is passed as the
+/// argument at its position, rather than every argument being any element
+#[test]
+#[serial]
+pub fn call_spread_passes_elements_by_position() {
+    let mut program = ProgramBuilder::new();
+
+    let first = {
+        let (mut first, [x, _]) = program.start_function();
+        let block = first.start_block_main();
+        first.end_block(block.ret(&[x]));
+        program.end_function(first)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let args = block.list_new();
+        let four = block.make_number_decimal(4);
+        let yes = block.make_bool(true);
+        block.list_push(args, four);
+        block.list_push(args, yes);
+        let four = block.call_spread_with_result(first.id, args);
+
+        main.end_block(block.ret(&[four]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Int(4)));
+}
+
+/// Ensures that a record in a list spread into a call is seen to be changed by
+/// the caller if the callee changes it
+#[test]
+#[serial]
+pub fn call_spread_mutations_propagate_to_caller() {
+    let mut program = ProgramBuilder::new();
+    let base = program.dealer.deal("base");
+
+    let mutate = {
+        let (mut mutate, [record]) = program.start_function();
+        let mut block = mutate.start_block_main();
+        let value = block.make_number_decimal(1);
+        block.record_set_atom(record, base, value);
+        mutate.end_block(block.ret(&[]));
+        program.end_function(mutate)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let record = block.record_new();
+        let args = block.list_new();
+        block.list_push(args, record);
+        block.call_spread(mutate.id, args);
+        let has_key = block.record_has_atom(record, base);
+
+        main.end_block(block.ret(&[has_key]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Bool(true))
+    );
+}
+
+/// Ensures that spreading a list of the wrong length into a call is reported
+/// as an error
+#[test]
+#[serial]
+pub fn call_spread_with_wrong_length_errors() {
+    let mut program = ProgramBuilder::new();
+
+    let multiply = {
+        let (mut multiply, [x, y]) = program.start_function();
+        let mut block = multiply.start_block_main();
+        let product = block.multiply(x, y);
+        multiply.end_block(block.ret(&[product]));
+        program.end_function(multiply)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let args = block.list_new();
+        let four = block.make_number_decimal(4);
+        block.list_push(args, four);
+        let product = block.call_spread_with_result(multiply.id, args);
+
+        main.end_block(block.ret(&[product]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = run.results.get(&run.entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Never);
+
+    let diagnostics = run.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].1.message.contains("spread 1 arguments"));
+}
+
+/// Ensures that the sizes of value types match what the backend lays out for
+/// the target, rather than for the machine the compiler runs on
+#[test]
+pub fn value_type_sizes() {
+    use crate::backend::llvm::ValueType;
+    use crate::frontend::ir::Target;
+
+    let structs = Default::default();
+
+    for word_size in [4, 8] {
+        let target = Target { word_size };
+
+        assert_eq!(
+            ValueType::WordSizeBitType.size_of(target, &structs),
+            word_size
+        );
+        assert_eq!(ValueType::BitType(32).size_of(target, &structs), 4);
+        assert_eq!(FFIValueType::Any.size_of(target), 2 * word_size);
+    }
+}
+
+/// Ensures that a function returning multiple values is lowered to a backend
+/// function returning each of those values
+#[test]
+pub fn multi_value_returns_lower_to_values() {
+    use crate::backend::llvm::{self, ValueType};
+
+    let returns = ReturnType::Tuple(vec![RegisterType::Int(1), RegisterType::Boolean]);
+    let lowered = llvm::ReturnType::lower(&returns, |typ| match typ {
+        RegisterType::Int(_) => ValueType::BitType(64),
+        RegisterType::Boolean => ValueType::BitType(1),
+        typ => panic!("unexpected type {:?}", typ),
+    });
+
+    match lowered {
+        llvm::ReturnType::Values(values) => {
+            assert_eq!(values, vec![ValueType::BitType(64), ValueType::BitType(1)])
+        }
+        r => panic!("expected multiple values, got {:?}", r),
+    }
+
+    let never = llvm::ReturnType::lower(&ReturnType::Never, |_| unreachable!());
+    assert!(matches!(never, llvm::ReturnType::Void));
+}
+
+/// Ensures that every specialization of a function gets its own entry in the
+/// manifest, each of which refers back to the function in the IR
+#[test]
+#[serial]
+pub fn manifest_has_entry_per_specialization() {
+    use crate::symbolic_execution::manifest::manifest;
+
+    let mut program = ProgramBuilder::new();
+
+    let identity = {
+        let (mut identity, [x]) = program.start_function();
+        let mut block = identity.start_block_main();
+        identity.end_block(block.ret(&[x]));
+        program.end_function(identity)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let one = block.make_number_decimal(1);
+        let two = block.make_number_decimal(2);
+        block.call(identity, [one]);
+        block.call(identity, [two]);
+
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+
+    // entries are ordered by id, and functions are explored in call order
+    let specializations = (manifest(&run).into_iter())
+        .filter(|entry| entry.ir_fn_id == identity.id)
+        .map(|entry| (entry.parameters, entry.return_type))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        specializations,
+        vec![
+            (
+                vec![RegisterType::Int(1)],
+                ReturnType::Value(RegisterType::Int(1))
+            ),
+            (
+                vec![RegisterType::Int(2)],
+                ReturnType::Value(RegisterType::Int(2))
+            ),
+        ]
+    );
+}
+
+/// Ensures that deleting a field of a record is displayed differently from
+/// setting it, and that the field is gone afterwards
+#[test]
+#[serial]
+pub fn record_deletion_displays_distinctly() {
+    let mut program = ProgramBuilder::new();
+    let slot = program.dealer.deal("slot");
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let record = block.record_new();
+        let value = block.make_number_decimal(1);
+        block.record_set_atom(record, slot, value);
+        block.record_del_atom(record, slot);
+        let has_slot = block.record_has_atom(record, slot);
+
+        main.end_block(block.ret(&[has_slot]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let display = crate::frontend::display_jssatir::display(&ir);
+    assert!(display.contains("RecordSet "));
+    assert!(display.contains("RecordDelSlot "));
+
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Bool(false))
+    );
+}
+
+/// Ensures that checking a populated record for a key that isn't known until
+/// runtime produces some boolean, as the key could be any of its properties
+#[test]
+#[serial]
+pub fn record_has_prop_with_runtime_key_is_boolean() {
+    let mut program = ProgramBuilder::new();
+    let name = program.constant_str("name");
+
+    {
+        let (mut f, [key]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+
+        let record = block.record_new();
+        let name = block.make_string(name);
+        let value = block.make_number_decimal(1);
+        block.record_set_prop(record, name, value);
+        let has_key = block.record_has_prop(record, key);
+
+        f.end_block(block.ret(&[has_key]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Bytes]);
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Boolean)
+    );
+}
+
+/// Ensures that type bags are compared by what they contain, rather than by
+/// the ids of what they've interned
+#[test]
+pub fn type_bags_are_compared_structurally() {
+    use crate::id::{IdCompat, RegisterId};
+
+    let (greeting, number) = (
+        RegisterId::<LiftedCtx>::new_with_value(0),
+        RegisterId::<LiftedCtx>::new_with_value(1),
+    );
+
+    let mut bag = TypeBag::default();
+    let hello = bag.intern_constant(b"hello");
+    bag.assign_type(greeting, RegisterType::Byts(hello));
+    bag.assign_type(number, RegisterType::Int(1));
+
+    assert!(bag.structurally_eq(&bag.clone()));
+
+    // the same types, with the constant interned under a different id
+    let mut renamed = TypeBag::default();
+    renamed.intern_constant(b"unused");
+    let hello = renamed.intern_constant(b"hello");
+    renamed.assign_type(greeting, RegisterType::Byts(hello));
+    renamed.assign_type(number, RegisterType::Int(1));
+
+    assert!(bag.structurally_eq(&renamed));
+
+    let mut modified = TypeBag::default();
+    let hello = modified.intern_constant(b"hello");
+    modified.assign_type(greeting, RegisterType::Byts(hello));
+    modified.assign_type(number, RegisterType::Int(2));
+
+    assert!(
+        !bag.structurally_eq(&modified),
+        "{:?} == {:?}",
+        bag,
+        modified
+    );
+}
+
+/// Confirms that a program adding two numbers compiles to a valid WebAssembly
+/// module
+#[test]
+pub fn add_program_compiles_to_valid_wasm() {
+    let main_id = FunctionId::<AssemblerCtx>::new();
+    let add_id = FunctionId::<AssemblerCtx>::new();
+    let reg = RegisterId::<AssemblerCtx>::new_with_value_const;
+
+    let main = codegen::Block {
+        parameters: Vec::new(),
+        instructions: vec![
+            codegen::Instruction::MakeInteger(Make {
+                result: reg(0),
+                item: 1,
+            }),
+            codegen::Instruction::MakeInteger(Make {
+                result: reg(1),
+                item: 2,
+            }),
+            codegen::Instruction::CallStatic(Call {
+                result: Some(reg(2)),
+                calling: add_id,
+                args: vec![reg(0), reg(1)],
+            }),
+        ],
+        end: codegen::EndInstruction::Return(Return(vec![reg(2)])),
+        type_info: TypeBag::default(),
+    };
+
+    let add = codegen::Block {
+        parameters: vec![reg(0), reg(1)],
+        instructions: vec![codegen::Instruction::BinOp(BinOp {
+            result: reg(2),
+            op: BinaryOperator::Add,
+            lhs: reg(0),
+            rhs: reg(1),
+        })],
+        end: codegen::EndInstruction::Return(Return(vec![reg(2)])),
+        type_info: TypeBag::default(),
+    };
+
+    let program = codegen::lower(TypedProgram {
+        entrypoint: main_id,
+        external_functions: Default::default(),
+        constants: Default::default(),
+        functions: vec![(main_id, main), (add_id, add)].into_iter().collect(),
+    });
+
+    let wasm = crate::backend::wasm::compile(&program);
+
+    assert!(wasm.starts_with(b"\0asm"));
+    wasmparser::validate(&wasm).expect("module to be valid");
+}
+
+/// Confirms that records, comparing bytes, and negating a boolean parameter
+/// compile to a valid WebAssembly module
+#[test]
+pub fn records_compile_to_valid_wasm() {
+    use crate::isa::{Negate, NewRecord, RecordGet, RecordKey, RecordSet};
+
+    let main_id = FunctionId::<AssemblerCtx>::new();
+    let not_id = FunctionId::<AssemblerCtx>::new();
+    let hello = ConstantId::<AssemblerCtx>::new_with_value_const(0);
+    let reg = RegisterId::<AssemblerCtx>::new_with_value_const;
+    let typed = RegisterId::<LiftedCtx>::new_with_value_const;
+
+    let mut main_types = TypeBag::default();
+    main_types.assign_type(typed(1), RegisterType::Bytes);
+    main_types.assign_type(typed(4), RegisterType::Boolean);
+
+    let main = codegen::Block {
+        parameters: Vec::new(),
+        instructions: vec![
+            codegen::Instruction::NewRecord(NewRecord { result: reg(0) }),
+            codegen::Instruction::MakeBytes(Make {
+                result: reg(1),
+                item: hello,
+            }),
+            codegen::Instruction::RecordSet(RecordSet {
+                record: reg(0),
+                key: RecordKey::Prop(reg(1)),
+                value: Some(reg(0)),
+            }),
+            codegen::Instruction::RecordGet(RecordGet {
+                result: reg(3),
+                record: reg(0),
+                key: RecordKey::Prop(reg(1)),
+            }),
+            codegen::Instruction::BinOp(BinOp {
+                result: reg(4),
+                op: BinaryOperator::Equals,
+                lhs: reg(1),
+                rhs: reg(1),
+            }),
+            codegen::Instruction::CallStatic(Call {
+                result: Some(reg(5)),
+                calling: not_id,
+                args: vec![reg(4)],
+            }),
+        ],
+        end: codegen::EndInstruction::Return(Return(vec![reg(5)])),
+        type_info: main_types,
+    };
+
+    let mut not_types = TypeBag::default();
+    not_types.assign_type(typed(0), RegisterType::Boolean);
+
+    let not = codegen::Block {
+        parameters: vec![reg(0)],
+        instructions: vec![codegen::Instruction::Negate(Negate {
+            result: reg(1),
+            operand: reg(0),
+        })],
+        end: codegen::EndInstruction::Return(Return(vec![reg(1)])),
+        type_info: not_types,
+    };
+
+    let program = codegen::lower(TypedProgram {
+        entrypoint: main_id,
+        external_functions: Default::default(),
+        constants: vec![(
+            hello,
+            Constant {
+                payload: b"hello".to_vec(),
+            },
+        )]
+        .into_iter()
+        .collect(),
+        functions: vec![(main_id, main), (not_id, not)].into_iter().collect(),
+    });
+
+    let wasm = crate::backend::wasm::compile(&program);
+    wasmparser::validate(&wasm).expect("module to be valid");
+}
+
+/// Confirms that reading a dump of the IR and dumping it again reproduces the
+/// original dump
+#[test]
+pub fn ir_dump_round_trips() {
+    let mut program = ProgramBuilder::new();
+    let slot = program.dealer.deal("slot");
+    let message = program.constant_str("hello, \"world\"\n");
+    let print = program.external_function("print", [FFIValueType::Any], Returns::Void);
+
+    let double = {
+        let (mut double, [x]) = program.start_function();
+        double.with_name("double".into());
+        let mut block = double.start_block_main();
+        let result = block.add(x, x);
+        double.end_block(block.ret(&[result]));
+        let signature = double.signature();
+        program.end_function(double);
+        signature
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+        let (mut positive, [value]) = main.start_block();
+        let (negative, []) = main.start_block();
+
+        block.comment("the answer");
+        let string = block.make_string(message);
+        block.call_external_function(print, [string]);
+        let number = block.make_number_decimal(-21);
+        let number = block.call_with_result(double, [number]);
+        let record = block.record_new();
+        block.record_set_atom(record, slot, number);
+        block.record_del_atom(record, slot);
+        let list = block.list_new();
+        let atom = block.make_atom(slot);
+        block.list_push(list, atom);
+        let fnptr = block.make_fnptr(double.id);
+        let is_fnptr = block.is_type_of(fnptr, ValueType::FnPtr);
+        let is_not_fnptr = block.negate(is_fnptr);
+        main.end_block(block.jmpif(
+            is_not_fnptr,
+            negative.signature(),
+            [],
+            positive.signature(),
+            [number],
+        ));
+
+        let yes = positive.make_bool(true);
+        positive.assert(yes, "always true");
+        main.end_block(positive.ret(&[value]));
+        main.end_block(negative.ret(&[]));
+
+        program.end_function(main);
+    }
+
+    let ir = program.finish();
+    let display = crate::frontend::display_jssatir::display(&ir);
+    assert!(display.starts_with("; jssat-ir v1\n"));
+
+    let parsed = crate::frontend::parse_jssatir::parse(&display);
+    assert_eq!(crate::frontend::display_jssatir::display(&parsed), display);
+}
+
+/// Ensures that the same literal made in two different functions interns to the
+/// same constant, so that comparing them folds to `true`
+#[test]
+#[serial]
+pub fn same_literal_in_two_functions_interns_to_one_id() {
+    let mut program = ProgramBuilder::new();
+    let first_undefined = program.constant_str("undefined");
+    let second_undefined = program.constant_str("undefined");
+
+    let first = {
+        let (mut first, []) = program.start_function();
+        first.with_name("first".into());
+        let mut block = first.start_block_main();
+        let value = block.make_string(first_undefined);
+        first.end_block(block.ret(&[value]));
+        program.end_function(first)
+    };
+
+    let second = {
+        let (mut second, []) = program.start_function();
+        second.with_name("second".into());
+        let mut block = second.start_block_main();
+        let value = block.make_string(second_undefined);
+        second.end_block(block.ret(&[value]));
+        program.end_function(second)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let a = block.call_with_result(first, []);
+        let b = block.call_with_result(second, []);
+        let equal = block.compare_equal(a, b);
+
+        main.end_block(block.ret(&[equal]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let first = *lifted.function_names.get("first").unwrap();
+    let second = *lifted.function_names.get("second").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+
+    let returned_constant = |lifted_id| {
+        let results = (results.values())
+            .find(|results| results.lifted_id == lifted_id)
+            .unwrap();
+
+        match results.return_type {
+            ReturnType::Value(RegisterType::Byts(id)) => (id, &results.types),
+            ref r => panic!("expected an exact string, got {:?}", r),
+        }
+    };
+
+    let (first, types) = returned_constant(first);
+    let (second, _) = returned_constant(second);
+    assert_eq!(first, second);
+    assert_eq!(&*types.constant_bytes(first), b"undefined");
+
+    let results = results.get(&entry_fn).unwrap();
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Bool(true))
+    );
+}
+
+/// Ensures that the allocation site of a record points at the `NewRecord`
+/// instruction which allocated it
+#[test]
+#[serial]
+pub fn record_allocation_site_points_at_new_record() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let number = block.make_number_decimal(1);
+        let record = block.record_new();
+        block.record_set_prop(record, number, number);
+
+        main.end_block(block.ret(&[record]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = results.get(&entry_fn).unwrap();
+
+    let record = match results.return_type {
+        ReturnType::Value(RegisterType::Record(record)) => record,
+        ref r => panic!("expected a record, got {:?}", r),
+    };
+
+    let unique_id = results.types.record_unique_id(record);
+    let site = results.types.allocation_site(unique_id).unwrap();
+    assert_eq!(site.function, lifted.entrypoint);
+
+    let inst_idx = match site.inst_idx {
+        InstIdx::Inst(inst_idx) => inst_idx,
+        other => panic!("expected an instruction, got {}", other),
+    };
+
+    let function = lifted.functions.get(&site.function).unwrap();
+    assert!(matches!(
+        function.instructions[inst_idx].data,
+        InstructionData::NewRecord(_)
+    ));
+}
+
+/// Ensures that escape analysis tells apart a record which is returned from a
+/// record which is only used locally
+#[test]
+#[serial]
+pub fn escape_analysis_classifies_returned_and_local_records() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let key = block.make_number_decimal(1);
+
+        let local = block.record_new();
+        block.record_set_prop(local, key, key);
+        let value = block.record_get_prop(local, key);
+
+        let returned = block.record_new();
+        block.record_set_prop(returned, key, value);
+
+        main.end_block(block.ret(&[returned]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let escapes = symbolic_execution::escape::escape_analysis(&run);
+
+    let results = run.results.get(&run.entry_fn).unwrap();
+    let returned = match results.return_type {
+        ReturnType::Value(RegisterType::Record(record)) => results.types.record_unique_id(record),
+        ref r => panic!("expected a record, got {:?}", r),
+    };
+
+    assert_eq!(escapes.len(), 2);
+    assert_eq!(escapes.get(&returned), Some(&Escapes::Yes));
+
+    let (_, local) = (escapes.iter())
+        .find(|(record, _)| **record != returned)
+        .unwrap();
+    assert_eq!(*local, Escapes::No);
+}
+
+/// Ensures that the arms of an if-expression checking the kind of a value see
+/// the value narrowed to that kind, so the then-arm can do arithmetic on it
+#[test]
+#[serial]
+pub fn if_expression_arms_see_narrowed_types() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [x]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+        let (mut then, [then_x]) = f.start_block();
+        let (mut r#else, []) = f.start_block();
+        let (end, [value]) = f.start_block();
+
+        let is_number = block.is_type_of(x, ValueType::Number);
+        f.end_block(block.jmpif(is_number, then.signature(), [x], r#else.signature(), []));
+
+        let one = then.make_number_decimal(1);
+        let sum = then.add(then_x, one);
+        f.end_block(then.jmp(end.signature(), [sum]));
+
+        let zero = r#else.make_number_decimal(0);
+        f.end_block(r#else.jmp(end.signature(), [zero]));
+
+        f.end_block(end.ret(&[value]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+
+    // the then-arm produces a number rather than failing on `Any`
+    assert!((results.values())
+        .any(|results| results.return_type == ReturnType::Value(RegisterType::Number)));
+
+    let results = results.get(&entry_fn).unwrap();
+    assert!(matches!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Union(_))
+    ));
+}
+
+/// Ensures that a branch where one arm returns a value and the other returns
+/// nothing is widened to returning `Any`, rather than never returning
+#[test]
+#[serial]
+pub fn branch_returning_value_and_void_widens_to_any() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [x]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+        let (then, [then_x]) = f.start_block();
+        let (r#else, []) = f.start_block();
+
+        let is_number = block.is_type_of(x, ValueType::Number);
+        f.end_block(block.jmpif(is_number, then.signature(), [x], r#else.signature(), []));
+
+        f.end_block(then.ret(&[then_x]));
+        f.end_block(r#else.ret(&[]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+
+    let results = run.results.get(&run.entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Any));
+    assert!(run.diagnostics().is_empty());
+}
+
+/// Ensures that constants which are never referenced are pruned when lowering
+/// the program
+#[test]
+pub fn unused_constants_are_pruned() {
+    let entrypoint = FunctionId::<AssemblerCtx>::new();
+    let used = ConstantId::<AssemblerCtx>::new_with_value_const(0);
+    let unused = ConstantId::<AssemblerCtx>::new_with_value_const(1);
+
+    let constant = |payload: &[u8]| Constant {
+        payload: payload.to_vec(),
+    };
+
+    let main = codegen::Block {
+        parameters: Vec::new(),
+        instructions: vec![codegen::Instruction::MakeBytes(Make {
+            result: RegisterId::new(),
+            item: used,
+        })],
+        end: codegen::EndInstruction::Return(Return(Vec::new())),
+        type_info: TypeBag::default(),
+    };
+
+    let program = codegen::lower(TypedProgram {
+        entrypoint,
+        external_functions: Default::default(),
+        constants: vec![(used, constant(b"used")), (unused, constant(b"unused"))]
+            .into_iter()
+            .collect(),
+        functions: vec![(entrypoint, main)].into_iter().collect(),
+    });
+
+    let reachable = codegen::reachable_constants(&program);
+    assert_eq!(reachable.len(), 1);
+    assert!(reachable.contains(&used));
+
+    assert!(program.constants.contains_key(&used));
+    assert!(!program.constants.contains_key(&unused));
+}
+
+/// Ensures that ordering comparisons between exact integers are folded
+#[test]
+#[serial]
+pub fn integer_comparisons_fold_when_both_operands_are_known() {
+    let returned = |value: usize| {
+        let results = run_returning(|_, block| {
+            let five = block.make_number_decimal(5);
+            let three = block.make_number_decimal(3);
+            let greater_than = block.compare_greater_than(five, three);
+            let less_than = block.compare_less_than(five, three);
+            let less_than_or_equal = block.compare_less_than_or_equal(three, three);
+            let greater_than_or_equal = block.compare_greater_than_or_equal(three, five);
+
+            [
+                greater_than,
+                less_than,
+                less_than_or_equal,
+                greater_than_or_equal,
+            ][value]
+        });
+        results.return_type
+    };
+
+    let expected = [true, false, true, false];
+    for (value, expected) in expected.into_iter().enumerate() {
+        assert_eq!(
+            returned(value),
+            ReturnType::Value(RegisterType::Bool(expected))
+        );
+    }
+}
+
+/// Ensures that ordering comparisons between numbers not known until runtime
+/// produce an unknown boolean
+#[test]
+#[serial]
+pub fn integer_comparisons_on_runtime_numbers_are_unknown() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [x, y]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+
+        let greater_than_or_equal = block.compare_greater_than_or_equal(x, y);
+
+        f.end_block(block.ret(&[greater_than_or_equal]));
+        program.end_function(f)
+    };
+
+    program.create_blank_entrypoint();
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| {
+        vec![RegisterType::Number, RegisterType::Number]
+    });
+    let results = results.get(&entry_fn).unwrap();
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Boolean)
+    );
+}
+
+/// A function recursing with a constantly growing integer would be explored
+/// once per integer until the depth limit. Instead, the growing argument is
+/// widened to a `Number`, and the recursion is only explored once more.
+#[test]
+#[serial]
+pub fn growing_recursive_arguments_are_widened() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [n]) = program.start_function();
+        f.with_name("f".into());
+        let signature = f.signature();
+        let mut block = f.start_block_main();
+
+        let one = block.make_number_decimal(1);
+        let next = block.add(n, one);
+        let result = block.call_with_result(signature, [next]);
+
+        f.end_block(block.ret(&[result]));
+        program.end_function(f)
+    };
+
+    program.create_blank_entrypoint();
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+    let n = lifted.functions.get(&f).unwrap().parameters[0];
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Int(0)]);
+
+    let specializations = (results.values())
+        .filter(|results| results.lifted_id == f)
+        .map(|results| results.types.get(n))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        specializations
+            .iter()
+            .filter(|typ| **typ == RegisterType::Number)
+            .count(),
+        1
+    );
+    assert_eq!(specializations.len(), 5);
+    assert_eq!(
+        results.get(&entry_fn).unwrap().return_type,
+        ReturnType::Value(RegisterType::Any)
+    );
+}
+
+/// Once a recursive argument has been widened, the recursion may end at runtime,
+/// so the recursive call can't be assumed to never return. Otherwise, only the
+/// base case would be seen, and `f(0)` would fold to an exact integer.
+#[test]
+#[serial]
+pub fn widened_recursion_does_not_return_exactly() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [n]) = program.start_function();
+        f.with_name("f".into());
+        let signature = f.signature();
+        let mut block = f.start_block_main();
+        let (mut base, []) = f.start_block();
+        let (mut recurse, []) = f.start_block();
+
+        let ten = block.make_number_decimal(10);
+        let done = block.compare_less_than(ten, n);
+        f.end_block(block.jmpif(done, base.signature(), [], recurse.signature(), []));
+
+        let zero = base.make_number_decimal(0);
+        f.end_block(base.ret(&[zero]));
+
+        let one = recurse.make_number_decimal(1);
+        let next = recurse.add(n, one);
+        let rest = recurse.call_with_result(signature, [next]);
+        let result = recurse.add(rest, one);
+        f.end_block(recurse.ret(&[result]));
+
+        program.end_function(f)
+    };
+
+    program.create_blank_entrypoint();
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Int(0)]);
+
+    match results.get(&entry_fn).unwrap().return_type {
+        ReturnType::Value(typ) => assert!(!typ.is_exact(), "f(0) folded to {}", typ),
+        ref r => panic!("expected f to return a value, got {:?}", r),
+    }
+}
+
+/// Joining incompatible types produces a union of them, up until the union
+/// would grow past [`symbolic_execution::types::MAX_UNION_VARIANTS`]
+#[test]
+pub fn joining_too_many_types_degrades_to_any() {
+    use crate::symbolic_execution::types::MAX_UNION_VARIANTS;
+
+    let mut types = TypeBag::default();
+
+    let number_or_string = types.union_of(RegisterType::Number, RegisterType::Bytes);
+    assert!(matches!(number_or_string, RegisterType::Union(_)));
+    assert_eq!(
+        types.variants_of(number_or_string),
+        vec![RegisterType::Number, RegisterType::Bytes]
+    );
+
+    let max_variants = (0..MAX_UNION_VARIANTS as i64)
+        .map(RegisterType::Int)
+        .reduce(|a, b| types.union_of(a, b))
+        .unwrap();
+    assert_eq!(types.variants_of(max_variants).len(), MAX_UNION_VARIANTS);
+
+    let next = RegisterType::Int(MAX_UNION_VARIANTS as i64);
+    assert_eq!(types.union_of(max_variants, next), RegisterType::Any);
+}
+
+/// Ensures that getting a field of a value that may or may not be a record
+/// produces a type of unknown value rather than panicking
+#[test]
+pub fn record_get_field_of_union_with_non_record_is_any() {
+    use crate::{id::UniqueRecordId, isa::AtomDealer};
+
+    let mut types = TypeBag::default();
+    let mut dealer = AtomDealer::new();
+    let field = crate::isa::RecordKey::Atom(dealer.deal("Field"));
+
+    let record = RegisterId::<LiftedCtx>::new_with_value_const(0);
+    types.new_record(record, UniqueRecordId::new_with_value_const(0));
+    types.record_set_field(record, field, Some(RegisterType::Int(1)), InstIdx::Inst(0));
+
+    let maybe_record = RegisterId::<LiftedCtx>::new_with_value_const(1);
+    let typ = types.union_of(types.get(record), RegisterType::Number);
+    types.assign_type(maybe_record, typ);
+
+    assert_eq!(
+        types.record_get_field(maybe_record, field),
+        RegisterType::Any
+    );
+    assert_eq!(
+        types.record_get_field_or(maybe_record, field, RegisterType::Boolean),
+        RegisterType::Any
+    );
+}
+
+/// Ensures that mapping a list explores the function with the type of the
+/// list's elements, producing a list of what the function returns that is as
+/// long as the original list
+#[test]
+#[serial]
+pub fn list_map_produces_list_of_function_results() {
+    let returned = |value: usize| {
+        let results = run_returning(|program, block| {
+            let name = program.constant_str("number");
+
+            let to_string = {
+                let (mut f, [_]) = program.start_function();
+                let mut block = f.start_block_main();
+
+                let name = block.make_string(name);
+
+                f.end_block(block.ret(&[name]));
+                program.end_function(f)
+            };
+
+            let list = block.list_new();
+            let one = block.make_number_decimal(1);
+            let two = block.make_number_decimal(2);
+            block.list_push(list, one);
+            block.list_push(list, two);
+
+            let to_string = block.make_fnptr(to_string.id);
+            let strings = block.list_map(list, to_string);
+            let len = block.list_len(strings);
+            let string = block.list_pop(strings);
+
+            [len, string][value]
+        });
+        results.return_type
+    };
+
+    let [len, string] = [0, 1].map(returned);
+    assert_eq!(len, ReturnType::Value(RegisterType::Int(2)));
+    match string {
+        ReturnType::Value(typ) => assert_eq!(typ.value_type(), Some(ValueType::Bytes)),
+        other => panic!("expected a string, got {:?}", other),
+    }
+}
+
+/// Ensures that the arguments bound to a list mapping are passed before each
+/// element
+#[test]
+#[serial]
+pub fn list_map_passes_args_before_elements() {
+    let mut program = ProgramBuilder::new();
+
+    let first = {
+        let (mut f, [x, _]) = program.start_function();
+        let block = f.start_block_main();
+        f.end_block(block.ret(&[x]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let list = block.list_new();
+        let yes = block.make_bool(true);
+        block.list_push(list, yes);
+
+        let first = block.make_fnptr(first.id);
+        let four = block.make_number_decimal(4);
+        let fours = block.list_map_dynargs(list, first, vec![four]);
+        let four = block.list_pop(fours);
+
+        main.end_block(block.ret(&[four]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Int(4)));
+}
+
+/// Ensures that a record in a list is seen to be changed by the caller if the
+/// function called with each element changes it
+#[test]
+#[serial]
+pub fn list_for_each_mutations_propagate_to_caller() {
+    let mut program = ProgramBuilder::new();
+    let base = program.dealer.deal("base");
+
+    let mutate = {
+        let (mut mutate, [record]) = program.start_function();
+        let mut block = mutate.start_block_main();
+        let value = block.make_number_decimal(1);
+        block.record_set_atom(record, base, value);
+        mutate.end_block(block.ret(&[]));
+        program.end_function(mutate)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let record = block.record_new();
+        let list = block.list_new();
+        block.list_push(list, record);
+        let mutate = block.make_fnptr(mutate.id);
+        block.list_for_each(list, mutate);
+        let has_key = block.record_has_atom(record, base);
+
+        main.end_block(block.ret(&[has_key]));
+        program.end_function(main)
+    };
+
+    let results = run_entrypoint(program.finish());
+
+    assert_eq!(
+        results.return_type,
+        ReturnType::Value(RegisterType::Bool(true))
+    );
+}
+
+/// Ensures that mapping a list with a function that returns nothing is
+/// reported as an error
+#[test]
+#[serial]
+pub fn list_map_to_void_errors() {
+    let mut program = ProgramBuilder::new();
+
+    let nothing = {
+        let (mut f, [_]) = program.start_function();
+        let block = f.start_block_main();
+        f.end_block(block.ret(&[]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let list = block.list_new();
+        let one = block.make_number_decimal(1);
+        block.list_push(list, one);
+        let nothing = block.make_fnptr(nothing.id);
+        let mapped = block.list_map(list, nothing);
+
+        main.end_block(block.ret(&[mapped]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, lifted.entrypoint, |_| Vec::new());
+    let results = run.results.get(&run.entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Never);
+
+    let diagnostics = run.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].1.message.contains("Void"));
+}
+
+/// Ensures that the types a parameter was observed to be are collected from
+/// every specialization of a function
+#[test]
+#[serial]
+pub fn observed_types_span_every_specialization() {
+    let mut program = ProgramBuilder::new();
+
+    let name = program.constant_str("name");
+
+    let f = {
+        let (mut f, [x]) = program.start_function();
+        f.with_name("f".into());
+        let block = f.start_block_main();
+        f.end_block(block.ret(&[x]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let one = block.make_number_decimal(1);
+        block.call(f, [one]);
+        let name = block.make_string(name);
+        block.call(f, [name]);
+
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let run = symbolic_execution::execute(&lifted);
+    let observed = run.observed_types(f, 0);
+
+    assert_eq!(observed.len(), 2);
+    assert_eq!(observed[0], RegisterType::Int(1));
+    assert_eq!(observed[1].value_type(), Some(ValueType::Bytes));
+}
+
+/// Ensures that interning byte-equal payloads yields a single constant, and
+/// that the constant can be resolved back into its payload
+#[test]
+pub fn interning_same_bytes_twice_yields_one_id() {
+    let bag = TypeBag::default();
+
+    let first = bag.intern_constant(b"hello");
+    let second = bag.intern_constant(&b"hello".to_vec());
+    let other = bag.intern_constant(b"world");
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+    assert_eq!(&*bag.constant_bytes(first), b"hello");
+    assert_eq!(&*bag.constant_bytes(other), b"world");
+
+    // payloads are bytes, which needn't be valid utf-8
+    let invalid = bag.intern_constant(&[0x80, 0xff]);
+    assert_eq!(&*bag.constant_bytes(invalid), &[0x80, 0xff]);
+
+    // bags sharing program state intern into the same constants
+    let sibling = TypeBag::sharing_program_state(&bag);
+    assert_eq!(sibling.intern_constant(b"hello"), first);
+}
+
+/// Parameters marked with `specialize_on` are never widened, so a function
+/// counting up to a limit keeps a specialization for every exact count while
+/// its other growing arguments are still generalized
+#[test]
+#[serial]
+pub fn exact_parameters_keep_their_specializations() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [n, m]) = program.start_function();
+        f.with_name("f".into());
+        f.specialize_on(0);
+        let signature = f.signature();
+        let mut block = f.start_block_main();
+        let (mut recurse, []) = f.start_block();
+        let (done, []) = f.start_block();
+
+        let limit = block.make_number_decimal(6);
+        let below_limit = block.compare_less_than(n, limit);
+        f.end_block(block.jmpif(below_limit, recurse.signature(), [], done.signature(), []));
+
+        let one = recurse.make_number_decimal(1);
+        let next_n = recurse.add(n, one);
+        let next_m = recurse.add(m, one);
+        let result = recurse.call_with_result(signature, [next_n, next_m]);
+        f.end_block(recurse.ret(&[result]));
+
+        f.end_block(done.ret(&[m]));
+        program.end_function(f)
+    };
+
+    program.create_blank_entrypoint();
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+    let f_fn = lifted.functions.get(&f).unwrap();
+    let (n, m) = (f_fn.parameters[0], f_fn.parameters[1]);
+    assert!(f_fn.exact_parameters.contains(&n));
+    assert!(!f_fn.exact_parameters.contains(&m));
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun { results, .. } = symbolic_execution::system_run(engine, f, |_| {
+        vec![RegisterType::Int(0), RegisterType::Int(0)]
+    });
+
+    let mut specializations = (results.values())
+        .filter(|results| results.lifted_id == f)
+        .map(|results| (results.types.get(n), results.types.get(m)))
+        .collect::<Vec<_>>();
+    specializations.sort_by_key(|(n, _)| match n {
+        RegisterType::Int(n) => *n,
+        other => panic!("exact parameter was generalized to {:?}", other),
+    });
+
+    assert_eq!(
+        specializations.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+        (0..=6).map(RegisterType::Int).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        specializations
+            .iter()
+            .filter(|(_, m)| *m == RegisterType::Number)
+            .count(),
+        3
+    );
+}
+
+/// Function pointers to known functions are equal exactly when they point to
+/// the same function, while comparing against an unknown function pointer
+/// could go either way
+#[test]
+#[serial]
+pub fn function_pointers_are_compared_by_function() {
+    let returned = |value: usize| {
+        let mut program = ProgramBuilder::new();
+
+        let empty_function = |program: &mut ProgramBuilder| {
+            let (mut f, []) = program.start_function();
+            let block = f.start_block_main();
+            f.end_block(block.ret(&[]));
+            program.end_function(f)
+        };
+
+        let a = empty_function(&mut program);
+        let b = empty_function(&mut program);
+
+        {
+            let (mut compare, [unknown]) = program.start_function();
+            compare.with_name("compare".into());
+            let mut block = compare.start_block_main();
+
+            let a_ptr = block.make_fnptr(a.id);
+            let a_ptr_again = block.make_fnptr(a.id);
+            let b_ptr = block.make_fnptr(b.id);
+
+            let same = block.compare_equal(a_ptr, a_ptr_again);
+            let different = block.compare_equal(a_ptr, b_ptr);
+            let maybe = block.compare_equal(unknown, a_ptr);
+
+            let value = [same, different, maybe][value];
+            compare.end_block(block.ret(&[value]));
+            program.end_function(compare)
+        };
+
+        program.create_blank_entrypoint();
+
+        let ir = program.finish();
+        let lifted = crate::lifted::lift(ir);
+        let compare = *lifted.function_names.get("compare").unwrap();
+
+        let engine = symbolic_execution::make_system(&lifted);
+        let SystemRun {
+            entry_fn,
+            mut results,
+            ..
+        } = symbolic_execution::system_run(engine, compare, |_| vec![RegisterType::AnyFnPtr]);
+        results.remove(&entry_fn).unwrap().return_type
+    };
+
+    let [same, different, maybe] = [0, 1, 2].map(returned);
+    assert_eq!(same, ReturnType::Value(RegisterType::Bool(true)));
+    assert_eq!(different, ReturnType::Value(RegisterType::Bool(false)));
+    assert_eq!(maybe, ReturnType::Value(RegisterType::Boolean));
+}
+
+/// Ensures that the liveness of a straight-line block tracks each register
+/// from its declaration up until its last use, including the parameters of the
+/// block which are used at its end
+#[test]
+pub fn liveness_of_straight_line_block() {
+    use crate::id::BlockId;
+
+    let reg = RegisterId::<AssemblerCtx>::new_with_value_const;
+    let entry = BlockId::<AssemblerCtx>::new();
+
+    let block = codegen::Block {
+        parameters: vec![reg(4)],
+        instructions: vec![
+            codegen::Instruction::MakeInteger(Make {
+                result: reg(0),
+                item: 1,
+            }),
+            codegen::Instruction::MakeInteger(Make {
+                result: reg(1),
+                item: 2,
+            }),
+            codegen::Instruction::BinOp(BinOp {
+                result: reg(2),
+                op: BinaryOperator::Add,
+                lhs: reg(0),
+                rhs: reg(1),
+            }),
+            codegen::Instruction::BinOp(BinOp {
+                result: reg(3),
+                op: BinaryOperator::Multiply,
+                lhs: reg(2),
+                rhs: reg(2),
+            }),
+        ],
+        end: codegen::EndInstruction::Return(Return(vec![reg(3), reg(4)])),
+        type_info: TypeBag::default(),
+    };
+
+    let function = codegen::Function {
+        entry,
+        blocks: vec![(entry, block)].into_iter().collect(),
+    };
+
+    let liveness = codegen::liveness(&function);
+    let liveness = liveness.get(&entry).unwrap();
+
+    let expected = [
+        vec![reg(4)],
+        vec![reg(0), reg(4)],
+        vec![reg(0), reg(1), reg(4)],
+        vec![reg(2), reg(4)],
+        vec![reg(3), reg(4)],
+    ];
+
+    for (live, expected) in liveness.live.iter().strict_zip(expected) {
+        assert_eq!(*live, expected.into_iter().collect());
+    }
+
+    assert_eq!(liveness.max_pressure, 3);
+}
+
+/// Ensures that the entries of a record with a known layout are a list with a
+/// `[key, value]` pair for each of its fields
+#[test]
+#[serial]
+pub fn record_entries_of_known_record_are_pairs() {
+    let mut program = ProgramBuilder::new();
+    let a = program.constant_str("a");
+    let b = program.constant_str("b");
+    let hello = program.constant_str("hello");
+
+    for name in ["len", "value"] {
+        let (mut f, []) = program.start_function();
+        f.with_name(name.into());
+        let mut block = f.start_block_main();
+
+        let record = block.record_new();
+        let a = block.make_string(a);
+        let one = block.make_number_decimal(1);
+        block.record_set_prop(record, a, one);
+        let b = block.make_string(b);
+        let hello = block.make_string(hello);
+        block.record_set_prop(record, b, hello);
+
+        let entries = block.record_entries(record);
+        let result = match name {
+            "len" => block.list_len(entries),
+            _ => {
+                let pair = block.list_pop(entries);
+                block.record_get_prop(pair, one)
+            }
+        };
+
+        f.end_block(block.ret(&[result]));
+        program.end_function(f);
+    }
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+
+    let run = |name: &str| {
+        let f = *lifted.function_names.get(name).unwrap();
+
+        let engine = symbolic_execution::make_system(&lifted);
+        let SystemRun {
+            entry_fn, results, ..
+        } = symbolic_execution::system_run(engine, f, |_| Vec::new());
+        results.get(&entry_fn).unwrap().clone()
+    };
+
+    let len = run("len");
+    assert_eq!(len.return_type, ReturnType::Value(RegisterType::Int(2)));
+
+    let value = run("value");
+    let typ = match value.return_type {
+        ReturnType::Value(typ) => typ,
+        r => panic!("expected a value, got {:?}", r),
+    };
+
+    match value.types.variants_of(typ).as_slice() {
+        [RegisterType::Int(1), RegisterType::Byts(hello)] => {
+            assert_eq!(&*value.types.constant_bytes(*hello), b"hello");
+        }
+        typs => panic!("expected a number and a string, got {:?}", typs),
+    }
+}
+
+/// Ensures that strict mode reports where a register could only be typed as
+/// `Any`, rather than silently typing it as such
+#[test]
+#[serial]
+pub fn strict_mode_reports_any_sites() {
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [name]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+
+        let one = block.make_number_decimal(1);
+        let fn_ptr = block.make_fnptr_dynamic(name);
+        let result = block.call_virt_with_result(fn_ptr, [one]);
+
+        f.end_block(block.ret(&[result]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let call_idx = (lifted.functions.get(&f).unwrap().instructions.iter())
+        .position(|inst| matches!(inst.data, InstructionData::CallVirt(_)))
+        .unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let lenient = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Bytes]);
+    let results = lenient.results.get(&lenient.entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Value(RegisterType::Any));
+    assert!(lenient.any_sites().is_empty());
+
+    let engine = symbolic_execution::make_system_strict(&lifted);
+    let strict = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Bytes]);
+
+    let sites = strict.any_sites();
+    assert_eq!(sites.len(), 1);
+
+    let (function, site) = sites[0];
+    assert_eq!(function, f);
+    assert_eq!(site.inst_idx, InstIdx::Inst(call_idx));
+
+    let operand_types = (site.operands.iter())
+        .map(|(_, typ)| *typ)
+        .collect::<Vec<_>>();
+    assert!(operand_types.contains(&RegisterType::AnyFnPtr));
+    assert!(operand_types.contains(&RegisterType::Int(1)));
+}
+
+/// Ensures that adding integers which overflow an `i64` still produces their
+/// exact sum
+#[test]
+#[serial]
+pub fn adding_big_integers_does_not_overflow() {
+    use num_bigint::BigInt;
+
+    let mut program = ProgramBuilder::new();
+
+    {
+        let (mut f, [a, b]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+
+        let sum = block.add(a, b);
+
+        f.end_block(block.ret(&[sum]));
+        program.end_function(f)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let block = main.start_block_main();
+        main.end_block(block.ret(&[]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let two_pow_63 = BigInt::from(1) << 63usize;
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let SystemRun {
+        entry_fn, results, ..
+    } = symbolic_execution::system_run(engine, f, |types| {
+        let typ = types.make_integer(two_pow_63.clone());
+        vec![typ, typ]
+    });
+    let results = results.get(&entry_fn).unwrap();
+
+    let sum = match results.return_type {
+        ReturnType::Value(typ @ RegisterType::ExactBigInt(_)) => typ,
+        ref r => panic!("expected an exact big integer, got {:?}", r),
+    };
+
+    let expected = "18446744073709551616".parse::<BigInt>().unwrap();
+    assert_eq!(results.types.integer_value(sum), Some(expected));
+}
+
+/// Ensures that integers too big for an i64 are interned exactly, even though
+/// their bytes aren't valid utf-8
+#[test]
+pub fn big_integers_round_trip_through_the_constant_interner() {
+    use num_bigint::BigInt;
+
+    let types = TypeBag::default();
+    let two_pow_63 = BigInt::from(1) << 63usize;
+
+    let values = [
+        two_pow_63.clone(),
+        -two_pow_63 - 1,
+        BigInt::from(u64::MAX) * 3,
+    ];
+    for value in values {
+        let typ = types.make_integer(value.clone());
+        assert!(matches!(typ, RegisterType::ExactBigInt(_)));
+        assert_eq!(types.integer_value(typ), Some(value));
+    }
+}
+
+/// Ensures that getting a slot with a default from a record which may not have
+/// the slot is typed as a union of the slot and the default
+#[test]
+pub fn record_get_slot_or_of_maybe_absent_slot_is_a_union() {
+    use crate::{id::UniqueRecordId, isa::AtomDealer};
+
+    let mut types = TypeBag::default();
+    let slot = crate::isa::RecordKey::Atom(AtomDealer::new().deal("Slot"));
+
+    let with = RegisterId::<LiftedCtx>::new_with_value_const(0);
+    let without = RegisterId::<LiftedCtx>::new_with_value_const(1);
+    let either = RegisterId::<LiftedCtx>::new_with_value_const(2);
+
+    types.new_record(with, UniqueRecordId::new_with_value_const(0));
+    types.record_set_field(with, slot, Some(RegisterType::Int(1)), InstIdx::Inst(0));
+    types.new_record(without, UniqueRecordId::new_with_value_const(1));
+
+    let (with_typ, without_typ) = (types.get(with), types.get(without));
+    let either_typ = types.union_of(with_typ, without_typ);
+    types.assign_type(either, either_typ);
+
+    let typ = types.record_get_field_or(either, slot, RegisterType::Boolean);
+    assert_eq!(
+        types.variants_of(typ),
+        vec![RegisterType::Int(1), RegisterType::Boolean]
+    );
+
+    // a record that definitively has the slot never produces the default
+    assert_eq!(
+        types.record_get_field_or(with, slot, RegisterType::Boolean),
+        RegisterType::Int(1)
+    );
+}
+
+/// Ensures that a call to a pure function with constant arguments is evaluated
+/// at compile time, and replaced with the constant it returns
+#[test]
+pub fn constexpr_replaces_pure_call_with_constant() {
+    let mut program = ProgramBuilder::new();
+
+    let factorial = {
+        let (mut factorial, [n]) = program.start_function();
+        let mut block = factorial.start_block_main();
+        let (mut base, []) = factorial.start_block();
+        let (mut recur, []) = factorial.start_block();
+
+        let one = block.make_number_decimal(1);
+        let done = block.compare_less_than(n, one);
+        factorial.end_block(block.jmpif(done, base.signature(), [], recur.signature(), []));
+
+        let one = base.make_number_decimal(1);
+        factorial.end_block(base.ret(&[one]));
+
+        let minus_one = recur.make_number_decimal(-1);
+        let previous = recur.add(n, minus_one);
+        let rest = recur.call_with_result(factorial.signature(), [previous]);
+        let product = recur.multiply(n, rest);
+        factorial.end_block(recur.ret(&[product]));
+
+        program.end_function(factorial)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let five = block.make_number_decimal(5);
+        let result = block.call_with_result(factorial, [five]);
+
+        main.end_block(block.ret(&[result]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let mut lifted = crate::lifted::lift(ir);
+    crate::opt::constexpr(&mut lifted);
+
+    let entrypoint = lifted.functions.get(&lifted.entrypoint).unwrap();
+    let result = match &entrypoint.end {
+        EndInstruction::Return(Return(values)) => values[0],
+        _ => panic!("the entrypoint should return"),
+    };
+
+    let instructions = &entrypoint.instructions;
+    assert!(!instructions
+        .iter()
+        .any(|inst| matches!(inst.data, InstructionData::CallStatic(_))));
+    assert!(instructions.iter().any(|inst| matches!(
+        inst.data,
+        InstructionData::MakeInteger(Make { result: r, item: 120 }) if r == result
+    )));
+}
+
+/// Ensures that a pure call whose arithmetic overflows while being evaluated at
+/// compile time is left as a call, rather than wrapping or panicking
+#[test]
+pub fn constexpr_leaves_overflowing_call() {
+    let mut program = ProgramBuilder::new();
+
+    let factorial = {
+        let (mut factorial, [n]) = program.start_function();
+        let mut block = factorial.start_block_main();
+        let (mut base, []) = factorial.start_block();
+        let (mut recur, []) = factorial.start_block();
+
+        let one = block.make_number_decimal(1);
+        let done = block.compare_less_than(n, one);
+        factorial.end_block(block.jmpif(done, base.signature(), [], recur.signature(), []));
+
+        let one = base.make_number_decimal(1);
+        factorial.end_block(base.ret(&[one]));
+
+        let minus_one = recur.make_number_decimal(-1);
+        let previous = recur.add(n, minus_one);
+        let rest = recur.call_with_result(factorial.signature(), [previous]);
+        let product = recur.multiply(n, rest);
+        factorial.end_block(recur.ret(&[product]));
+
+        program.end_function(factorial)
+    };
+
+    {
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+
+        let twenty_five = block.make_number_decimal(25);
+        let result = block.call_with_result(factorial, [twenty_five]);
+
+        main.end_block(block.ret(&[result]));
+        program.end_function(main)
+    };
+
+    let ir = program.finish();
+    let mut lifted = crate::lifted::lift(ir);
+    crate::opt::constexpr(&mut lifted);
+
+    let entrypoint = lifted.functions.get(&lifted.entrypoint).unwrap();
+    assert!(entrypoint
+        .instructions
+        .iter()
+        .any(|inst| matches!(inst.data, InstructionData::CallStatic(_))));
+}
+
+/// Ensures that the facts both arms of a branch record about a record are
+/// merged, rather than the facts of one arm overwriting those of the other
+#[test]
+pub fn facts_from_both_arms_of_a_branch_are_merged() {
+    use crate::{id::UniqueRecordId, isa::AtomDealer};
+
+    let mut types = TypeBag::default();
+    let mut dealer = AtomDealer::new();
+    let kept = crate::isa::RecordKey::Atom(dealer.deal("Kept"));
+    let both = crate::isa::RecordKey::Atom(dealer.deal("Both"));
+    let only_if_so = crate::isa::RecordKey::Atom(dealer.deal("OnlyIfSo"));
+
+    let record = RegisterId::<LiftedCtx>::new_with_value_const(0);
+    types.new_record(record, UniqueRecordId::new_with_value_const(0));
+    types.record_set_field(record, kept, Some(RegisterType::Int(1)), InstIdx::Inst(0));
+
+    let checkpoint = types.checkpoint_facts();
+
+    types.record_set_field(record, both, Some(RegisterType::Int(1)), InstIdx::Epilogue);
+    types.record_set_field(
+        record,
+        only_if_so,
+        Some(RegisterType::Int(2)),
+        InstIdx::Epilogue,
+    );
+    let if_so = types.take_facts_since(&checkpoint);
+
+    types.record_set_field(
+        record,
+        both,
+        Some(RegisterType::Bool(true)),
+        InstIdx::Epilogue,
+    );
+    let other = types.take_facts_since(&checkpoint);
+
+    types.merge_branch_facts(if_so, other, InstIdx::Epilogue);
+
+    assert_eq!(types.record_has_field(record, kept), Some(true));
+    assert_eq!(types.record_get_field(record, kept), RegisterType::Int(1));
+
+    assert_eq!(types.record_has_field(record, both), Some(true));
+    let typ = types.record_get_field(record, both);
+    assert_eq!(
+        types.variants_of(typ),
+        vec![RegisterType::Int(1), RegisterType::Bool(true)]
+    );
+
+    assert_eq!(types.record_has_field(record, only_if_so), None);
+    let typ = types.record_get_field_or(record, only_if_so, RegisterType::Boolean);
+    assert_eq!(
+        types.variants_of(typ),
+        vec![RegisterType::Int(2), RegisterType::Boolean]
+    );
+}
+
+/// Ensures that looking up a field a record only may have produces either its
+/// own field or the one inherited from its prototype
+#[test]
+pub fn inherited_field_of_record_that_may_have_it_is_unioned() {
+    use crate::{
+        id::UniqueRecordId,
+        isa::{AtomDealer, InternalSlot},
+    };
+
+    let mut types = TypeBag::default();
+    let mut dealer = AtomDealer::new();
+    let field = crate::isa::RecordKey::Atom(dealer.deal("Field"));
+    let prototype = crate::isa::RecordKey::Atom(InternalSlot::Prototype.atom());
+
+    let parent = RegisterId::<LiftedCtx>::new_with_value_const(0);
+    types.new_record(parent, UniqueRecordId::new_with_value_const(0));
+    types.record_set_field(parent, field, Some(RegisterType::Int(1)), InstIdx::Inst(0));
+
+    let child = RegisterId::<LiftedCtx>::new_with_value_const(1);
+    types.new_record(child, UniqueRecordId::new_with_value_const(1));
+    let parent_typ = types.get(parent);
+    types.record_set_field(child, prototype, Some(parent_typ), InstIdx::Inst(1));
+
+    let checkpoint = types.checkpoint_facts();
+    types.record_set_field(
+        child,
+        field,
+        Some(RegisterType::Bool(true)),
+        InstIdx::Epilogue,
+    );
+    let if_so = types.take_facts_since(&checkpoint);
+    let other = types.take_facts_since(&checkpoint);
+    types.merge_branch_facts(if_so, other, InstIdx::Epilogue);
+
+    assert_eq!(types.record_has_field(child, field), None);
+    let typ = types.record_get_field_inherited(child, field);
+    assert_eq!(
+        types.variants_of(typ),
+        vec![RegisterType::Bool(true), RegisterType::Int(1)]
+    );
+}
+
+/// Ensures that looking up a record by an atom whose value isn't known gives
+/// a type of unknown value rather than panicking
+#[test]
+pub fn record_get_field_by_unknown_atom_is_any() {
+    use crate::id::UniqueRecordId;
+
+    let mut types = TypeBag::default();
+
+    let record = RegisterId::<LiftedCtx>::new_with_value_const(0);
+    types.new_record(record, UniqueRecordId::new_with_value_const(0));
+
+    let atom = RegisterId::<LiftedCtx>::new_with_value_const(1);
+    types.assign_type(atom, RegisterType::Any);
+
+    let field = crate::isa::RecordKey::DynAtom(atom);
+    assert_eq!(types.record_get_field(record, field), RegisterType::Any);
+}
+
+/// Ensures that a value which may be bytes can be passed to an external
+/// function accepting a string, while one which can't be is reported rather
+/// than panicking
+#[test]
+#[serial]
+pub fn extern_call_coercions_are_checked() {
+    let mut program = ProgramBuilder::new();
+    let print = program.external_function("print", [FFIValueType::String], Returns::Void);
+
+    {
+        let (mut f, [value]) = program.start_function();
+        f.with_name("f".into());
+        let mut block = f.start_block_main();
+
+        block.call_external_function(print, [value]);
+        let number = block.make_number_decimal(5);
+        block.call_external_function(print, [number]);
+
+        f.end_block(block.ret(&[]));
+        program.end_function(f)
+    };
+
+    program.create_blank_entrypoint();
+
+    let ir = program.finish();
+    let lifted = crate::lifted::lift(ir);
+    let f = *lifted.function_names.get("f").unwrap();
+
+    let engine = symbolic_execution::make_system(&lifted);
+    let run = symbolic_execution::system_run(engine, f, |_| vec![RegisterType::Any]);
+    let results = run.results.get(&run.entry_fn).unwrap();
+    assert_eq!(results.return_type, ReturnType::Never);
+
+    let diagnostics = run.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].1.message.contains("cannot coerce 5: Number"));
+}
+
+#[test]
+#[serial]
+fn runs_ir_file_end_to_end() {
+    let source = r#"
+(extern log (any) void)
+
+((:1.1 Main ())
+ ((sum = (call Add 2 3))
+  (call-extern log :sum)
+  (return :sum)))
+
+((:1.2 Add (a b))
+ ((return (:a + :b))))
+"#;
+
+    let externs = ExternRegistry::new().with("log", |args| match args.as_slice() {
+        [Value::Number(5)] => Ok(None),
+        _ => panic!("expected the sum to be logged"),
+    });
+
+    let result = run_ir_file(source, externs);
+    assert!(matches!(result, Value::Number(5)));
+}
@@ -0,0 +1,152 @@
+//! Evaluates calls to pure functions with constant arguments at compile time,
+//! replacing the call with the constant it produces.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    frontend::ir::InstructionData,
+    interpreter::{Interpreter, Value},
+    isa::Make,
+    lifted::{FunctionId, LiftedProgram, RegisterId},
+};
+
+/// The most instructions a single call is interpreted for before giving up on
+/// evaluating it, so that calls which loop forever or recurse too deeply are
+/// left as calls rather than hanging the compiler.
+const FUEL: usize = 100_000;
+
+/// Replaces every call to a pure function whose arguments are all constants
+/// with the result of interpreting the call. Only calls producing an integer,
+/// boolean, or atom are replaced, and calls that fail while being interpreted,
+/// overflow, or run out of [`FUEL`] are left for the program to evaluate at
+/// runtime.
+pub fn constexpr(program: &mut LiftedProgram) {
+    let pure = pure_functions(program);
+    let no_ext_fns = FxHashMap::default();
+
+    let mut replacements = Vec::new();
+    for (fn_id, function) in program.functions.iter() {
+        let mut constants = FxHashMap::<RegisterId, Value>::default();
+
+        for (idx, inst) in function.instructions.iter().enumerate() {
+            let (result, calling, args) = match &inst.data {
+                InstructionData::MakeInteger(Make { result, item }) => {
+                    constants.insert(*result, Value::Number(*item));
+                    continue;
+                }
+                InstructionData::MakeBoolean(Make { result, item }) => {
+                    constants.insert(*result, Value::Boolean(*item));
+                    continue;
+                }
+                InstructionData::MakeAtom(Make { result, item }) => {
+                    constants.insert(*result, Value::Atom(*item));
+                    continue;
+                }
+                InstructionData::MakeBytes(Make { result, item }) => {
+                    let payload = program.constants.get(item).unwrap().payload.clone();
+                    constants.insert(*result, Value::Bytes(payload));
+                    continue;
+                }
+                InstructionData::CallStatic(call) => match call.result {
+                    Some(result) if pure.contains(&call.calling) => {
+                        (result, call.calling, &call.args)
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            let args = args.iter().map(|arg| constants.get(arg).cloned());
+            let args = match args.collect::<Option<Vec<_>>>() {
+                Some(args) => args,
+                None => continue,
+            };
+
+            let mut interpreter = Interpreter::new(program, &no_ext_fns).with_fuel(FUEL);
+            let data = match interpreter.execute_fn_id(calling, args) {
+                Ok(Some(Value::Number(item))) => {
+                    constants.insert(result, Value::Number(item));
+                    InstructionData::MakeInteger(Make { result, item })
+                }
+                Ok(Some(Value::Boolean(item))) => {
+                    constants.insert(result, Value::Boolean(item));
+                    InstructionData::MakeBoolean(Make { result, item })
+                }
+                Ok(Some(Value::Atom(item))) => {
+                    constants.insert(result, Value::Atom(item));
+                    InstructionData::MakeAtom(Make { result, item })
+                }
+                _ => continue,
+            };
+
+            replacements.push((*fn_id, idx, data));
+        }
+    }
+
+    for (fn_id, idx, data) in replacements {
+        let function = program.functions.get_mut(&fn_id).unwrap();
+        function.instructions[idx].data = data;
+    }
+}
+
+/// Determines which functions are pure: they, and every function they may call
+/// or jump to, only compute values from their arguments and constants. Calling
+/// external functions, taking function pointers, or making records or lists
+/// could have side effects or let values escape, so functions doing so aren't
+/// pure. Functions which generalize their values ask not to be specialized on
+/// them, so they aren't considered pure either.
+fn pure_functions(program: &LiftedProgram) -> FxHashSet<FunctionId> {
+    let mut pure = (program.functions.iter())
+        .filter(|(_, function)| {
+            function.instructions.iter().all(|inst| {
+                matches!(
+                    inst.data,
+                    InstructionData::Comment(_)
+                        | InstructionData::CallStatic(_)
+                        | InstructionData::MakeAtom(_)
+                        | InstructionData::MakeBytes(_)
+                        | InstructionData::MakeInteger(_)
+                        | InstructionData::MakeBoolean(_)
+                        | InstructionData::BinOp(_)
+                        | InstructionData::Negate(_)
+                        | InstructionData::Assert(_)
+                        | InstructionData::IsType(_)
+                        | InstructionData::AssumeType(_)
+                        | InstructionData::TypeOf(_)
+                        | InstructionData::BytesConcat(_)
+                        | InstructionData::BytesSlice(_)
+                        | InstructionData::Unreachable(_)
+                )
+            })
+        })
+        .map(|(id, _)| *id)
+        .collect::<FxHashSet<_>>();
+
+    // functions may call or jump to each other recursively, so keep removing
+    // functions depending on impure ones until nothing changes
+    loop {
+        let impure = (pure.iter().copied())
+            .filter(|id| {
+                let function = program.functions.get(id).unwrap();
+
+                let calls = (function.instructions.iter()).filter_map(|inst| match &inst.data {
+                    InstructionData::CallStatic(call) => Some(call.calling),
+                    _ => None,
+                });
+                let jumps = function.end.paths().into_iter().map(|path| path.0);
+
+                calls.chain(jumps).any(|callee| !pure.contains(&callee))
+            })
+            .collect::<Vec<_>>();
+
+        if impure.is_empty() {
+            break;
+        }
+
+        for id in impure {
+            pure.remove(&id);
+        }
+    }
+
+    pure
+}
@@ -8,6 +8,9 @@
 
 use crate::codegen::TypedProgram;
 
+mod constexpr;
+pub use constexpr::constexpr;
+
 pub fn opt(program: TypedProgram) -> TypedProgram {
     program
 }
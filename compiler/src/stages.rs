@@ -0,0 +1,93 @@
+//! Runs the compilation pipeline up to a given stage, so that the output of any
+//! stage can be inspected. This is what an `--emit` style flag would use.
+
+use thiserror::Error;
+
+use crate::{
+    codegen::{self, Program, TypedProgram},
+    frontend::ir::{dead_code_elimination, IR},
+    lifted::{self, LiftedProgram},
+    opt, symbolic_execution,
+};
+
+/// A step of the compilation pipeline, in the order they are run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// The IR lifted into pure blocks, where every block is a function.
+    PureBlocks,
+    /// The program after symbolic execution has annotated every register with
+    /// its type, and the typed program has been optimized.
+    Annotated,
+    /// The typed program lowered into a form ready for the backend.
+    Assembler,
+    /// The LLVM IR produced by the backend. The backend is not implemented
+    /// yet, so compiling to this stage is an error.
+    Llvm,
+}
+
+#[derive(Error, Debug)]
+pub enum StageError {
+    #[error("The stage {0:?} is not implemented yet.")]
+    Unimplemented(Stage),
+}
+
+/// The artifact produced by a [`Stage`].
+pub enum Artifact {
+    PureBlocks(LiftedProgram),
+    Annotated(TypedProgram),
+    Assembler(Program),
+}
+
+pub struct StageOutput {
+    pub artifact: Artifact,
+    /// The artifact, displayed as text.
+    pub display: String,
+}
+
+/// Compiles the IR up until (and including) the given stage.
+pub fn compile_to(stage: Stage, mut ir: IR) -> Result<StageOutput, StageError> {
+    if stage == Stage::Llvm {
+        return Err(StageError::Unimplemented(stage));
+    }
+
+    dead_code_elimination(&mut ir);
+
+    let mut program = lifted::lift(ir);
+    lifted::simplify_cfg(&mut program);
+    #[cfg(feature = "constexpr")]
+    opt::constexpr(&mut program);
+
+    if stage == Stage::PureBlocks {
+        return Ok(StageOutput {
+            display: display_lifted(&program),
+            artifact: Artifact::PureBlocks(program),
+        });
+    }
+
+    let system_run = symbolic_execution::execute(&program);
+    let program = opt::opt(codegen::type_program(system_run));
+
+    if stage == Stage::Annotated {
+        return Ok(StageOutput {
+            display: codegen::display_typed(&program),
+            artifact: Artifact::Annotated(program),
+        });
+    }
+
+    let program = codegen::lower(program);
+
+    Ok(StageOutput {
+        display: codegen::display_program(&program),
+        artifact: Artifact::Assembler(program),
+    })
+}
+
+fn display_lifted(program: &LiftedProgram) -> String {
+    let mut fns = program.functions.iter().collect::<Vec<_>>();
+    fns.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    fns.into_iter()
+        .map(|(id, function)| lifted::display(*id, function))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
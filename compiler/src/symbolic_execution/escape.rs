@@ -0,0 +1,161 @@
+//! Determines which records may be observed outside of the function that
+//! allocated them. Records which never escape may be stack allocated by a
+//! backend.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::frontend::ir::InstructionData;
+use crate::id::*;
+use crate::isa::RecordKey;
+use crate::lifted::EndInstruction;
+
+use super::{types::TypeBag, worker::WorkerResults, SystemRun};
+
+/// Whether or not a record may be observed outside of the function that
+/// allocated it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Escapes {
+    /// The record is only ever used locally.
+    No,
+    /// The record is returned, stored into a record that escapes, or used in
+    /// some way that can't be tracked, such as being passed to an external
+    /// function.
+    Yes,
+}
+
+/// Classifies every record allocated during symbolic execution.
+///
+/// This is conservative: any use of a record which isn't known to keep it
+/// local is assumed to make it escape.
+pub fn escape_analysis(run: &SystemRun) -> FxHashMap<UniqueRecordId<SymbolicCtx>, Escapes> {
+    let mut analysis = EscapeAnalysis::default();
+
+    let explored = (run.results.values())
+        .map(|results| results.lifted_id)
+        .collect::<FxHashSet<_>>();
+
+    for results in run.results.values() {
+        analysis.visit(run, &explored, results);
+    }
+
+    analysis.finish()
+}
+
+#[derive(Default)]
+struct EscapeAnalysis {
+    allocated: FxHashSet<UniqueRecordId<SymbolicCtx>>,
+    escaping: Vec<UniqueRecordId<SymbolicCtx>>,
+    /// { record |-> records stored inside of it }
+    stored_in: FxHashMap<UniqueRecordId<SymbolicCtx>, Vec<UniqueRecordId<SymbolicCtx>>>,
+}
+
+impl EscapeAnalysis {
+    fn visit(
+        &mut self,
+        run: &SystemRun,
+        explored: &FxHashSet<FunctionId<LiftedCtx>>,
+        results: &WorkerResults,
+    ) {
+        let function = run.program.functions.get(&results.lifted_id).unwrap();
+        let types = &results.types;
+
+        for inst in function.instructions.iter() {
+            match &inst.data {
+                InstructionData::NewRecord(i) => {
+                    self.allocated.extend(records(types, i.result));
+                }
+                InstructionData::RecordClone(i) => {
+                    self.allocated.extend(records(types, i.result));
+                }
+                InstructionData::RecordSet(i) => {
+                    let mut stored = Vec::new();
+                    if let RecordKey::Prop(key) = i.key {
+                        stored.extend(records(types, key));
+                    }
+                    if let Some(value) = i.value {
+                        stored.extend(records(types, value));
+                    }
+
+                    for container in records(types, i.record) {
+                        (self.stored_in.entry(container).or_default()).extend(stored.iter());
+                    }
+                }
+                InstructionData::CallStatic(i) if explored.contains(&i.calling) => {}
+                // reading from or inspecting a record doesn't let it escape
+                InstructionData::Comment(_)
+                | InstructionData::RecordGet(_)
+                | InstructionData::RecordGetInherited(_)
+                | InstructionData::RecordHasKey(_)
+                | InstructionData::BinOp(_)
+                | InstructionData::IsType(_)
+                | InstructionData::TypeOf(_) => {}
+                _ => {
+                    for register in inst.used_registers() {
+                        self.escaping.extend(records(types, register));
+                    }
+                }
+            }
+        }
+
+        match &function.end {
+            EndInstruction::Jump(i) => self.jump(types, explored, i.0 .0, &i.0 .1),
+            EndInstruction::JumpIf(i) => {
+                self.jump(types, explored, i.if_so.0, &i.if_so.1);
+                self.jump(types, explored, i.other.0, &i.other.1);
+            }
+            EndInstruction::Return(i) => {
+                for register in i.0.iter() {
+                    self.escaping.extend(records(types, *register));
+                }
+            }
+        }
+    }
+
+    fn jump(
+        &mut self,
+        types: &TypeBag,
+        explored: &FxHashSet<FunctionId<LiftedCtx>>,
+        target: FunctionId<LiftedCtx>,
+        args: &[RegisterId<LiftedCtx>],
+    ) {
+        // the block being jumped to is analyzed on its own, so passing records
+        // to it doesn't make them escape
+        if explored.contains(&target) {
+            return;
+        }
+
+        for register in args {
+            self.escaping.extend(records(types, *register));
+        }
+    }
+
+    fn finish(mut self) -> FxHashMap<UniqueRecordId<SymbolicCtx>, Escapes> {
+        let mut escaped = FxHashSet::default();
+
+        while let Some(record) = self.escaping.pop() {
+            if !escaped.insert(record) {
+                continue;
+            }
+
+            if let Some(stored) = self.stored_in.get(&record) {
+                self.escaping.extend(stored.iter());
+            }
+        }
+
+        (self.allocated.into_iter())
+            .map(|record| match escaped.contains(&record) {
+                true => (record, Escapes::Yes),
+                false => (record, Escapes::No),
+            })
+            .collect()
+    }
+}
+
+fn records(types: &TypeBag, register: RegisterId<LiftedCtx>) -> Vec<UniqueRecordId<SymbolicCtx>> {
+    match types.try_get(register) {
+        Some(typ) => (types.possible_records(typ).into_iter())
+            .map(|record| types.record_unique_id(record))
+            .collect(),
+        None => Vec::new(),
+    }
+}
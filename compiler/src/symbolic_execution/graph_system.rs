@@ -47,6 +47,10 @@ where
     /// Given the ID of a worker, this function will execute that worker and
     /// produce the value that the worker executed.
     fn spawn(&self, id: W::Id) -> Arc<W::Result>;
+
+    /// Produces the IDs of every worker that is still working on the way to
+    /// the current worker, starting with the current worker.
+    fn callstack(&self) -> Vec<W::Id>;
 }
 
 /// The return type of a system, with the existence of a "bogus" state.
@@ -124,6 +128,31 @@ where
         }
         Ok(results)
     }
+
+    /// Produces the results of every worker that has completed, skipping any
+    /// workers that are still working. Unlike
+    /// [`ComputeGraphSys::try_into_results`], this may be used after the
+    /// system has aborted (e.g. from a panic) in order to inspect the progress
+    /// made up until that point.
+    pub fn into_partial_results(self) -> FxHashMap<W::Id, W::Result>
+    where
+        W::Result: Clone,
+    {
+        // a panic may have happened while the lock was held, but we only read
+        // completed results which are never left in an inconsistent state
+        let workers = match self.inner.workers.lock() {
+            Ok(workers) => workers,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        workers
+            .iter()
+            .filter_map(|(id, state)| match state {
+                WorkStatus::Working => None,
+                WorkStatus::Completed(r) => Some((*id, (**r).clone())),
+            })
+            .collect()
+    }
 }
 
 impl<W, F> ComputeGraphSys<W, F>
@@ -225,6 +254,21 @@ where
     fn spawn(&self, id: W::Id) -> Arc<W::Result> {
         GraphSystemInner::spawn(&self.inner, id, &self.callstack, &self.global_callstack)
     }
+
+    fn callstack(&self) -> Vec<W::Id> {
+        let mut frames = Vec::new();
+        let mut current_frame = &*self.callstack;
+
+        while let CallStack::Child {
+            previous, frame, ..
+        } = current_frame
+        {
+            frames.push(*frame);
+            current_frame = &**previous;
+        }
+
+        frames
+    }
 }
 
 impl<W, F> GraphSystemInner<W, F>
@@ -318,3 +362,65 @@ where
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Worker `n` spawns `n - 2` and `n - 1` (in that order), and panics if
+    /// `n` is the poisoned id.
+    struct CountdownWorker {
+        id: u32,
+        poisoned: u32,
+    }
+
+    impl Worker for CountdownWorker {
+        type Id = u32;
+        type Result = u32;
+
+        fn work(&mut self, system: &impl System<Self>) -> Computation<Self::Result> {
+            if self.id == self.poisoned {
+                panic!("worker {} aborted", self.id);
+            }
+
+            if self.id >= 2 {
+                system.spawn(self.id - 2);
+                system.spawn(self.id - 1);
+            }
+
+            Computation::Result(self.id)
+        }
+    }
+
+    struct CountdownFactory {
+        poisoned: u32,
+    }
+
+    impl WorkerFactory for CountdownFactory {
+        type Worker = CountdownWorker;
+
+        fn make(&mut self, id: u32) -> CountdownWorker {
+            CountdownWorker {
+                id,
+                poisoned: self.poisoned,
+            }
+        }
+    }
+
+    #[test]
+    pub fn partial_results_are_retrievable_after_abort() {
+        let system = ComputeGraphSys::new(CountdownFactory { poisoned: 3 });
+
+        // 4 spawns 2 (which completes after spawning 0 and 1), then spawns 3,
+        // which aborts the run while 4 is still working
+        let run = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| system.spawn(4)));
+        assert!(run.is_err());
+
+        let mut completed = system
+            .into_partial_results()
+            .into_keys()
+            .collect::<Vec<_>>();
+        completed.sort_unstable();
+        assert_eq!(completed, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,48 @@
+//! Summarizes every function produced by symbolic execution, which is useful
+//! for generating FFI headers or documentation.
+
+use crate::id::*;
+
+use super::{
+    types::{RegisterType, ReturnType},
+    SystemRun,
+};
+
+/// A single specialization of a function, produced by symbolic execution.
+///
+/// Types which refer to records, lists, or unions only have meaning within the
+/// type bag of the worker that produced them, found in [`SystemRun::results`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FnManifestEntry {
+    pub id: FunctionId<SymbolicCtx>,
+    /// The function in the lifted program that was specialized.
+    pub lifted_id: FunctionId<LiftedCtx>,
+    /// The function in the IR that the lifted function originates from.
+    pub ir_fn_id: FunctionId<IrCtx>,
+    pub name: Option<String>,
+    pub parameters: Vec<RegisterType>,
+    pub return_type: ReturnType,
+}
+
+/// Produces an entry for every specialized function, ordered by their ids.
+pub fn manifest(run: &SystemRun) -> Vec<FnManifestEntry> {
+    let mut entries = (run.results.values())
+        .map(|results| {
+            let function = run.program.functions.get(&results.lifted_id).unwrap();
+
+            FnManifestEntry {
+                id: results.id,
+                lifted_id: results.lifted_id,
+                ir_fn_id: function.ir_fn_id,
+                name: function.name.clone(),
+                parameters: (function.parameters.iter())
+                    .map(|param| results.types.get(*param))
+                    .collect(),
+                return_type: results.return_type.clone(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by_key(|entry| entry.id);
+    entries
+}
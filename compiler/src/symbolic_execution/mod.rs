@@ -18,11 +18,15 @@ use self::types::RegisterType;
 use self::types::ReturnType;
 use self::types::TypeBag;
 use self::unique_id::{UniqueFnId, UniqueFnIdShared};
+use self::worker::AnySite;
 use self::worker::CurrentInstruction;
+use self::worker::Diagnostic;
 use self::worker::SymbWorker;
 use self::worker::WorkerResults;
 
+pub mod escape;
 pub mod graph_system;
+pub mod manifest;
 pub mod type_computations;
 pub mod types;
 pub mod unique_id;
@@ -40,6 +44,35 @@ pub fn execute(program: &LiftedProgram) -> SystemRun {
 }
 
 pub fn make_system(program: &LiftedProgram) -> Engine {
+    make_system_with_opaque(program, FxHashMap::default())
+}
+
+/// Creates a system which records every place a register could only be typed
+/// as [`RegisterType::Any`], so that places where symbolic execution couldn't
+/// be precise can be reported with [`SystemRun::any_sites`].
+pub fn make_system_strict(program: &LiftedProgram) -> Engine {
+    make_system_with(program, FxHashMap::default(), true)
+}
+
+/// Creates a system where calls to any of the `opaque_functions` are not
+/// explored. Instead, the result of the call is assigned the declared return
+/// type of the function. This is useful for trusted functions whose signature
+/// is known, as it can drastically cut down on the time spent in analysis.
+///
+/// The declared return types must not refer to any records, as opaque
+/// functions never allocate.
+pub fn make_system_with_opaque(
+    program: &LiftedProgram,
+    opaque_functions: FxHashMap<FunctionId<LiftedCtx>, ReturnType>,
+) -> Engine {
+    make_system_with(program, opaque_functions, false)
+}
+
+fn make_system_with(
+    program: &LiftedProgram,
+    opaque_functions: FxHashMap<FunctionId<LiftedCtx>, ReturnType>,
+    strict: bool,
+) -> Engine {
     let mut asm_ext_map = ExtFnPassRetagger::default();
     for (id, _) in program.external_functions.iter() {
         asm_ext_map.retag_new(*id);
@@ -54,6 +87,8 @@ pub fn make_system(program: &LiftedProgram) -> Engine {
         fn_ids: fn_ids_shared.clone(),
         asm_ext_map: Arc::new(asm_ext_map),
         unique_allocation_id,
+        opaque_functions: Arc::new(opaque_functions),
+        strict,
     };
 
     let system = ComputeGraphSys::new(factory);
@@ -73,6 +108,62 @@ pub struct SystemRun<'a> {
     pub results: FxHashMap<FunctionId<SymbolicCtx>, WorkerResults>,
 }
 
+impl SystemRun<'_> {
+    /// Lists every type the parameter at `param_idx` of the given function was
+    /// observed to be, across every way the function was specialized. Types
+    /// are listed in the order their specializations were explored.
+    pub fn observed_types(
+        &self,
+        fn_id: FunctionId<LiftedCtx>,
+        param_idx: usize,
+    ) -> Vec<RegisterType> {
+        let parameter = self.program.functions.get(&fn_id).unwrap().parameters[param_idx];
+
+        let mut specializations = (self.results.values())
+            .filter(|results| results.lifted_id == fn_id)
+            .collect::<Vec<_>>();
+        specializations.sort_unstable_by_key(|results| results.id);
+
+        let mut types = Vec::new();
+        for results in specializations {
+            let typ = results.types.get(parameter);
+
+            if !types.contains(&typ) {
+                types.push(typ);
+            }
+        }
+
+        types
+    }
+
+    /// Lists every place a register could only be typed as `Any`, along with
+    /// the function it is in. This is only populated for systems made with
+    /// [`make_system_strict`].
+    pub fn any_sites(&self) -> Vec<(FunctionId<LiftedCtx>, &AnySite)> {
+        let mut results = self.results.values().collect::<Vec<_>>();
+        results.sort_unstable_by_key(|results| results.id);
+
+        (results.into_iter())
+            .flat_map(|results| {
+                (results.any_sites.iter()).map(move |site| (results.lifted_id, site))
+            })
+            .collect()
+    }
+
+    /// Lists every instruction that was proven to fail at runtime, along with
+    /// the function it is in.
+    pub fn diagnostics(&self) -> Vec<(FunctionId<LiftedCtx>, &Diagnostic)> {
+        let mut results = self.results.values().collect::<Vec<_>>();
+        results.sort_unstable_by_key(|results| results.id);
+
+        (results.into_iter())
+            .flat_map(|results| {
+                (results.diagnostics.iter()).map(move |diagnostic| (results.lifted_id, diagnostic))
+            })
+            .collect()
+    }
+}
+
 pub fn system_run(
     engine: Engine,
     fn_id: FunctionId<LiftedCtx>,
@@ -262,6 +353,8 @@ struct SymbFactory<'program> {
     program: &'program LiftedProgram,
     fn_ids: UniqueFnIdShared,
     asm_ext_map: Arc<ExtFnPassRetagger<LiftedCtx, AssemblerCtx>>,
+    opaque_functions: Arc<FxHashMap<FunctionId<LiftedCtx>, ReturnType>>,
+    strict: bool,
 }
 
 impl<'p> WorkerFactory for SymbFactory<'p> {
@@ -284,6 +377,10 @@ impl<'p> WorkerFactory for SymbFactory<'p> {
             asm_ext_map: self.asm_ext_map.clone(),
             never_infected: false,
             unique_allocation_id: self.unique_allocation_id.clone(),
+            opaque_functions: self.opaque_functions.clone(),
+            strict: self.strict,
+            any_sites: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 }
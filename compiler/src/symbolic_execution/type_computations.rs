@@ -1,13 +1,14 @@
 use crate::isa::BinaryOperator;
+use num_bigint::BigInt;
 use thiserror::Error;
 
 use super::types::{RegisterType, TypeBag};
 
-// impl BinaryOperator {
-//     pub fn make_executor<'t>(&self, types: &'t mut TypeBag) ->
-// BinOpExecutor<'t> {         BinOpExecutor { types, op: *self }
-//     }
-// }
+impl BinaryOperator {
+    pub fn make_executor<'t>(&self, types: &'t mut TypeBag) -> BinOpExecutor<'t> {
+        BinOpExecutor { types, op: *self }
+    }
+}
 
 pub struct BinOpExecutor<'a> {
     types: &'a mut TypeBag,
@@ -33,12 +34,44 @@ impl BinOpExecutor<'_> {
         lhs: RegisterType,
         rhs: RegisterType,
     ) -> Result<RegisterType, BinOpExecErr> {
+        // an operation on a union is the join of the operation on its variants
+        if let (RegisterType::Union(_), _) | (_, RegisterType::Union(_)) = (lhs, rhs) {
+            let mut result = None;
+
+            for lhs in self.types.variants_of(lhs) {
+                for rhs in self.types.variants_of(rhs) {
+                    let typ = self.execute(lhs, rhs)?;
+
+                    result = Some(match result {
+                        Some(result) => self.types.union_of(result, typ),
+                        None => typ,
+                    });
+                }
+            }
+
+            return Ok(result.expect("unions have variants"));
+        }
+
+        // nothing is known about a value of any type, so only the kind of value
+        // the operator always produces is known
+        if let (RegisterType::Any, _) | (_, RegisterType::Any) = (lhs, rhs) {
+            return Ok(match self.op {
+                BinaryOperator::Add => RegisterType::Any,
+                BinaryOperator::Multiply => RegisterType::Number,
+                BinaryOperator::And
+                | BinaryOperator::Or
+                | BinaryOperator::Equals
+                | BinaryOperator::LessThan => RegisterType::Boolean,
+            });
+        }
+
         let result = match self.op {
             BinaryOperator::Add => self.add(lhs, rhs),
+            BinaryOperator::Multiply => self.multiply(lhs, rhs),
             BinaryOperator::And => self.and(lhs, rhs),
             BinaryOperator::Or => self.or(lhs, rhs),
             BinaryOperator::Equals => self.equals(lhs, rhs),
-            BinaryOperator::LessThan => todo!(),
+            BinaryOperator::LessThan => self.less_than(lhs, rhs),
             // _ => Err(BinaryOperatorExecutionError::Unimplemented),
         };
 
@@ -53,6 +86,12 @@ impl BinOpExecutor<'_> {
         }
     }
 
+    /// Gets the values of two exact integers.
+    fn integers(&self, lhs: RegisterType, rhs: RegisterType) -> (BigInt, BigInt) {
+        let value = |typ| self.types.integer_value(typ).expect("exact integer");
+        (value(lhs), value(rhs))
+    }
+
     fn add(
         &mut self,
         lhs: RegisterType,
@@ -63,11 +102,60 @@ impl BinOpExecutor<'_> {
         Ok(match (lhs, rhs) {
             (Bytes, Bytes) | (Byts(_), Bytes) | (Bytes, Byts(_)) => Bytes,
             (Byts(a), Byts(b)) => {
-                let mut new = self.types.unintern_const(a).to_owned();
-                new.extend(self.types.unintern_const(b));
+                let mut new = self.types.constant_bytes(a).to_vec();
+                new.extend_from_slice(&self.types.constant_bytes(b));
                 let id = self.types.intern_constant(&new);
                 Byts(id)
             }
+            (Number, Number)
+            | (Int(_) | ExactBigInt(_), Number)
+            | (Number, Int(_) | ExactBigInt(_)) => Number,
+            (Int(a), Int(b)) if a.checked_add(b).is_some() => Int(a + b),
+            // integers are unbounded, so the result is exact even if it overflows
+            (Int(_) | ExactBigInt(_), Int(_) | ExactBigInt(_)) => {
+                let (a, b) = self.integers(lhs, rhs);
+                self.types.make_integer(a + b)
+            }
+            _ => return Err(BinaryOperatorExecutionError::Unimplemented),
+        })
+    }
+
+    fn multiply(
+        &self,
+        lhs: RegisterType,
+        rhs: RegisterType,
+    ) -> Result<RegisterType, BinaryOperatorExecutionError> {
+        use RegisterType::*;
+
+        Ok(match (lhs, rhs) {
+            (Number, Number)
+            | (Int(_) | ExactBigInt(_), Number)
+            | (Number, Int(_) | ExactBigInt(_)) => Number,
+            (Int(a), Int(b)) if a.checked_mul(b).is_some() => Int(a * b),
+            (Int(_) | ExactBigInt(_), Int(_) | ExactBigInt(_)) => {
+                let (a, b) = self.integers(lhs, rhs);
+                self.types.make_integer(a * b)
+            }
+            _ => return Err(BinaryOperatorExecutionError::Unimplemented),
+        })
+    }
+
+    fn less_than(
+        &self,
+        lhs: RegisterType,
+        rhs: RegisterType,
+    ) -> Result<RegisterType, BinaryOperatorExecutionError> {
+        use RegisterType::*;
+
+        Ok(match (lhs, rhs) {
+            (Number, Number)
+            | (Int(_) | ExactBigInt(_), Number)
+            | (Number, Int(_) | ExactBigInt(_)) => Boolean,
+            (Int(a), Int(b)) => Bool(a < b),
+            (Int(_) | ExactBigInt(_), Int(_) | ExactBigInt(_)) => {
+                let (a, b) = self.integers(lhs, rhs);
+                Bool(a < b)
+            }
             _ => return Err(BinaryOperatorExecutionError::Unimplemented),
         })
     }
@@ -108,16 +196,26 @@ impl BinOpExecutor<'_> {
         use RegisterType::*;
 
         Ok(match (lhs, rhs) {
-            (Number, Number) | (Int(_), Number) | (Number, Int(_)) => Boolean,
+            (Number, Number)
+            | (Int(_) | ExactBigInt(_), Number)
+            | (Number, Int(_) | ExactBigInt(_)) => Boolean,
             (Int(a), Int(b)) => Bool(a == b),
+            // integers only have one exact type, and constants are interned
+            // program-wide, so equal integers share an id
+            (ExactBigInt(a), ExactBigInt(b)) => Bool(a == b),
+            (Int(_), ExactBigInt(_)) | (ExactBigInt(_), Int(_)) => Bool(false),
             (Bytes, Bytes) | (Byts(_), Bytes) | (Bytes, Byts(_)) => Boolean,
-            (Byts(a), Byts(b)) => {
-                Bool(self.types.unintern_const(a) == self.types.unintern_const(b))
-            }
+            // constants are interned program-wide, so equal payloads share an id
+            (Byts(a), Byts(b)) => Bool(a == b),
             (Boolean, Boolean) | (Boolean, Bool(_)) | (Bool(_), Boolean) => Boolean,
             (Bool(a), Bool(b)) => Bool(a == b),
             (Atom(a), Atom(b)) => Bool(a == b),
+            // records are compared by identity
+            (Record(a), Record(b)) => Bool(a == b),
             (Atom(_), Record(_)) | (Record(_), Atom(_)) => Bool(false),
+            // function pointers are compared by the function they point to
+            (FnPtr(a), FnPtr(b)) => Bool(a == b),
+            (AnyFnPtr, AnyFnPtr) | (AnyFnPtr, FnPtr(_)) | (FnPtr(_), AnyFnPtr) => Boolean,
             _ => return Err(BinaryOperatorExecutionError::Unimplemented),
         })
     }
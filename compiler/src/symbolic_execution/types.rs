@@ -4,15 +4,16 @@
 //! [blog post]: https://sirjosh3917.com/posts/jssat-typing-objects-in-ssa-form/
 
 use derive_more::{Deref, DerefMut, Display};
-use lasso::{Key, Rodeo};
+use num_bigint::BigInt;
 use std::ops::Deref;
 use std::sync::{Arc, Mutex};
 
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::collections::{FxBiHashMap, StrictZip};
+use crate::frontend::ir::FFIValueType;
 use crate::id::{IdCompat, LiftedCtx, SymbolicCtx, UniqueRecordId};
-use crate::isa::Atom;
+use crate::isa::{Atom, InternalSlot, ValueType};
 use crate::UnwrapNone;
 
 type AllocationId = crate::id::AllocationId<LiftedCtx>;
@@ -21,18 +22,20 @@ type RegisterId = crate::id::RegisterId<LiftedCtx>;
 /// The ID of a function whose argument types are not yet known.
 type DynFnId = crate::id::FunctionId<LiftedCtx>;
 type UnionId = crate::id::UnionId<LiftedCtx>;
+type ListId = crate::id::UniqueListId<LiftedCtx>;
 type WorkRecordKey = crate::isa::RecordKey<LiftedCtx>;
 
 #[derive(Clone, Copy, Hash, Debug)]
 enum RecordKey {
     Key(RegisterType),
+    Slot(Atom),
 }
 
 impl RecordKey {
     fn into_record_key_eq(self) -> RecordKeyEq {
         match self {
             RecordKey::Key(a) => RecordKeyEq::Key(a),
-            // RecordKey::Slot(a) => RecordKeyEq::Slot(a),
+            RecordKey::Slot(a) => RecordKeyEq::Slot(a),
         }
     }
 }
@@ -47,51 +50,126 @@ impl RecordKey {
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 enum RecordKeyEq {
     Key(RegisterType),
+    Slot(Atom),
 }
 
 impl RecordKeyEq {
     fn into_record_key(self) -> RecordKey {
         match self {
             RecordKeyEq::Key(a) => RecordKey::Key(a),
-            // RecordKeyEq::Slot(a) => RecordKey::Slot(a),
+            RecordKeyEq::Slot(a) => RecordKey::Slot(a),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ReturnType {
     Void,
     Value(RegisterType),
+    /// Produced by functions which return more than one value at once.
+    Tuple(Vec<RegisterType>),
     Never,
 }
 
 impl ReturnType {
-    pub fn map<F>(self, map: F) -> ReturnType
+    pub fn map<F>(self, mut map: F) -> ReturnType
     where
-        F: FnOnce(RegisterType) -> RegisterType,
+        F: FnMut(RegisterType) -> RegisterType,
     {
         match self {
             ReturnType::Value(v) => ReturnType::Value(map(v)),
+            ReturnType::Tuple(vs) => ReturnType::Tuple(vs.into_iter().map(map).collect()),
             other => other,
         }
     }
 }
 
+/// The most types a [`RegisterType::Union`] may be made of. Joining any more
+/// types than this produces [`RegisterType::Any`] instead.
+pub const MAX_UNION_VARIANTS: usize = 4;
+
+/// The type of a register. When displayed, exact values are shown alongside
+/// the type they are of (`5: Number`), and types that refer to something by
+/// its id are shown with the id (`Record#1`).
 #[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Hash)]
 pub enum RegisterType {
     Any,
+    #[display(fmt = "{}: Atom", _0)]
     Atom(Atom),
     Bytes,
+    #[display(fmt = "Bytes#{}", _0)]
     Byts(ConstantId),
     Number,
+    #[display(fmt = "{}: Number", _0)]
     Int(i64),
+    /// An integer too large to fit in an [`RegisterType::Int`]. The integer is
+    /// interned as a constant of its signed little endian bytes.
+    #[display(fmt = "BigInt#{}: Number", _0)]
+    ExactBigInt(ConstantId),
     Boolean,
+    #[display(fmt = "{}: Boolean", _0)]
     Bool(bool),
+    #[display(fmt = "FnPtr#{}", _0)]
     FnPtr(DynFnId),
+    AnyFnPtr,
+    #[display(fmt = "Record#{}", _0)]
     Record(AllocationId),
+    #[display(fmt = "List#{}", _0)]
+    List(ListId),
+    #[display(fmt = "Union#{}", _0)]
     Union(UnionId),
 }
 
+impl RegisterType {
+    /// The kind of value this type is, if it is known.
+    pub fn value_type(&self) -> Option<ValueType> {
+        match self {
+            RegisterType::Atom(_) => Some(ValueType::Atom),
+            RegisterType::Bytes | RegisterType::Byts(_) => Some(ValueType::Bytes),
+            RegisterType::Number | RegisterType::Int(_) | RegisterType::ExactBigInt(_) => {
+                Some(ValueType::Number)
+            }
+            RegisterType::Boolean | RegisterType::Bool(_) => Some(ValueType::Boolean),
+            RegisterType::FnPtr(_) | RegisterType::AnyFnPtr => Some(ValueType::FnPtr),
+            RegisterType::Record(_) => Some(ValueType::Record),
+            RegisterType::List(_) => Some(ValueType::List),
+            RegisterType::Any | RegisterType::Union(_) => None,
+        }
+    }
+
+    /// Determines if this type describes exactly one value, such as `5: Number`
+    /// rather than `Number`.
+    pub fn is_exact(&self) -> bool {
+        matches!(
+            self,
+            RegisterType::Atom(_)
+                | RegisterType::Byts(_)
+                | RegisterType::Int(_)
+                | RegisterType::ExactBigInt(_)
+                | RegisterType::Bool(_)
+                | RegisterType::FnPtr(_)
+        )
+    }
+
+    /// Determines if a value of this type can be passed to an external function
+    /// as a parameter of the given type. Values passed as `Any` are boxed, so
+    /// every type coerces to it, including trivial ones such as an `undefined`
+    /// atom. A value of type `Any` may be bytes, so it's allowed to be passed
+    /// as a string. Unions are checked per variant by
+    /// [`TypeBag::can_coerce_to`].
+    pub fn can_coerce_to(&self, ffi_type: &FFIValueType) -> bool {
+        match ffi_type {
+            FFIValueType::Any => true,
+            FFIValueType::String => matches!(
+                self,
+                RegisterType::Any | RegisterType::Bytes | RegisterType::Byts(_)
+            ),
+            // the runtime isn't tracked during symbolic execution
+            FFIValueType::Runtime => true,
+        }
+    }
+}
+
 impl PartialOrd for RegisterType {
     /// Order's the type of a register based on whether a register type is a
     /// subtype of another. The following subtypes are defined:
@@ -101,7 +179,9 @@ impl PartialOrd for RegisterType {
     /// forall t . Any :> t
     /// Bytes :> Byts
     /// Number :> Int
+    /// Number :> ExactBigInt
     /// Boolean :> Bool
+    /// AnyFnPtr :> FnPtr
     /// ```
     ///
     /// Any undefined relation produces `None`.
@@ -131,8 +211,9 @@ impl PartialOrd for RegisterType {
             }
 
             // Number :> Int
+            // Number :> ExactBigInt
             if let Number = lhs {
-                if let Int(_) = rhs {
+                if let Int(_) | ExactBigInt(_) = rhs {
                     return Some(Greater);
                 }
             }
@@ -144,6 +225,13 @@ impl PartialOrd for RegisterType {
                 }
             }
 
+            // AnyFnPtr :> FnPtr
+            if let AnyFnPtr = lhs {
+                if let FnPtr(_) = rhs {
+                    return Some(Greater);
+                }
+            }
+
             None
         }
 
@@ -269,6 +357,20 @@ impl RecordBag {
         self.records.get(&record).unwrap().unique_allocation_id
     }
 
+    /// Creates a new record with the same facts as `record`, but with a
+    /// different identity.
+    pub fn clone_record(
+        &mut self,
+        record: AllocationId,
+        unique_allocation_id: UniqueRecordId<SymbolicCtx>,
+    ) -> AllocationId {
+        let fact_paths = self.records.get(&record).unwrap().fact_paths.clone();
+
+        let id = self.new_record(unique_allocation_id);
+        self.records.get_mut(&id).unwrap().fact_paths = fact_paths;
+        id
+    }
+
     pub fn try_all_facts(&self, record: AllocationId) -> Option<&Union<Facts<Fact>>> {
         self.records.get(&record).map(|r| &r.fact_paths)
     }
@@ -425,6 +527,55 @@ impl RecordBag {
         keys
     }
 
+    /// Computes the keys a record has, if the record only has one possible
+    /// shape and all of its keys are constants.
+    fn record_shape(&self, id: AllocationId) -> Option<FxHashSet<RecordKeyEq>> {
+        let fields = self.record_fields(id)?;
+        Some(fields.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// Computes the fields a record has along with their values, in the order
+    /// they were first set, if the record only has one possible shape and all
+    /// of its keys are constants.
+    fn record_fields(&self, id: AllocationId) -> Option<Vec<(RecordKeyEq, RegisterType)>> {
+        let record = self.records.get(&id).unwrap();
+
+        if record.fact_paths.len() != 1 {
+            return None;
+        }
+
+        let mut fields = Vec::<(RecordKeyEq, RegisterType)>::new();
+        for fact in record.fact_paths[0].iter() {
+            let key = fact.key().into_record_key_eq();
+
+            // a key which isn't a constant could be any key
+            if let RecordKeyEq::Key(typ) = key {
+                if !matches!(
+                    typ,
+                    RegisterType::Atom(_)
+                        | RegisterType::Byts(_)
+                        | RegisterType::Int(_)
+                        | RegisterType::ExactBigInt(_)
+                        | RegisterType::Bool(_)
+                ) {
+                    return None;
+                }
+            }
+
+            let existing = fields.iter().position(|(k, _)| *k == key);
+            match (fact, existing) {
+                (Fact::Set { value, .. }, Some(idx)) => fields[idx].1 = *value,
+                (Fact::Set { value, .. }, None) => fields.push((key, *value)),
+                (Fact::Remove { .. }, Some(idx)) => {
+                    fields.remove(idx);
+                }
+                (Fact::Remove { .. }, None) => {}
+            };
+        }
+
+        Some(fields)
+    }
+
     pub fn only_record_keys(&self, id: AllocationId) -> FxHashSet<RecordKeyEq> {
         self.record_keys(id)
     }
@@ -450,6 +601,81 @@ impl RecordBag {
 
         new_facts
     }
+
+    fn fact_counts(&self) -> FactsCheckpoint {
+        let counts = (self.records.iter())
+            .map(|(id, record)| (*id, record.fact_paths.iter().map(|f| f.len()).collect()))
+            .collect();
+
+        FactsCheckpoint(counts)
+    }
+
+    fn take_facts_since(&mut self, checkpoint: &FactsCheckpoint) -> BranchFacts {
+        let mut taken = FxHashMap::default();
+
+        for (id, counts) in checkpoint.0.iter() {
+            let record = self.records.get_mut(id).unwrap();
+
+            let facts = (record.fact_paths.iter_mut().strict_zip(counts))
+                .map(|(facts, count)| facts.split_off(*count))
+                .collect::<Vec<_>>();
+
+            if facts.iter().any(|facts| !facts.is_empty()) {
+                taken.insert(*id, facts);
+            }
+        }
+
+        BranchFacts(taken)
+    }
+}
+
+/// The amount of facts known about every record at some point in time, so
+/// that the facts recorded after it can be taken back out.
+pub struct FactsCheckpoint(FxHashMap<AllocationId, Vec<usize>>);
+
+/// The facts recorded about records since a [`FactsCheckpoint`], for every
+/// line of facts of each record.
+pub struct BranchFacts(FxHashMap<AllocationId, Vec<Vec<Fact>>>);
+
+#[derive(Clone, Default)]
+struct ListBag {
+    counter: ListId,
+    lists: FxHashMap<ListId, List>,
+}
+
+/// Unlike records, facts are not kept about lists. Only the type of each of
+/// its elements and the type of its elements as a whole are tracked.
+#[derive(Clone)]
+struct List {
+    /// The type of every element of the list in order, or `None` if the length
+    /// of the list is not statically known.
+    items: Option<Vec<RegisterType>>,
+    /// The union of the types of every element that has been in the list, or
+    /// `None` if nothing has ever been put into the list.
+    element: Option<RegisterType>,
+}
+
+impl List {
+    /// `None` if the length of the list is not statically known.
+    fn len(&self) -> Option<usize> {
+        self.items.as_ref().map(Vec::len)
+    }
+}
+
+impl ListBag {
+    pub fn new_list(&mut self, list: List) -> ListId {
+        let id = self.counter.next_and_mut();
+        self.lists.insert(id, list).expect_free();
+        id
+    }
+
+    pub fn get(&self, id: ListId) -> List {
+        self.lists.get(&id).unwrap().clone()
+    }
+
+    pub fn get_mut(&mut self, id: ListId) -> &mut List {
+        self.lists.get_mut(&id).unwrap()
+    }
 }
 
 /// we need a number to determine when a fact was added so that optimization
@@ -506,6 +732,11 @@ trait SyncResolver {
     fn save_record_id(&mut self, src_id: AllocationId, gen_id: AllocationId);
 
     fn save_sync_stats(&mut self, id: AllocationId, stats: SyncStats);
+
+    /// list id in src -> list id in dest, if the list has been synced before
+    fn map_list_id(&mut self, id: ListId) -> Option<ListId>;
+
+    fn save_list_id(&mut self, src_id: ListId, gen_id: ListId);
 }
 
 // TODO: the resolvers need to be aware of src_fact_init and dest_fact_init
@@ -522,10 +753,12 @@ trait SyncResolver {
 struct ResolveLeft<'a>(
     &'a mut FxBiHashMap<AllocationId, AllocationId>,
     &'a mut FxHashMap<AllocationId, SyncStats>,
+    &'a mut FxBiHashMap<ListId, ListId>,
 );
 struct ResolveRight<'a>(
     &'a mut FxBiHashMap<AllocationId, AllocationId>,
     &'a mut FxHashMap<AllocationId, SyncStats>,
+    &'a mut FxBiHashMap<ListId, ListId>,
 );
 
 impl<'a> SyncResolver for ResolveLeft<'a> {
@@ -541,6 +774,12 @@ impl<'a> SyncResolver for ResolveLeft<'a> {
     fn save_sync_stats(&mut self, id: AllocationId, stats: SyncStats) {
         self.1.insert(id, stats);
     }
+    fn map_list_id(&mut self, id: ListId) -> Option<ListId> {
+        self.2.get_by_left(&id).cloned()
+    }
+    fn save_list_id(&mut self, src_id: ListId, gen_id: ListId) {
+        self.2.insert(src_id, gen_id).expect_free();
+    }
 }
 impl<'a> SyncResolver for ResolveRight<'a> {
     fn map_record_id(&mut self, id: AllocationId) -> Option<AllocationId> {
@@ -555,6 +794,12 @@ impl<'a> SyncResolver for ResolveRight<'a> {
     fn save_sync_stats(&mut self, id: AllocationId, stats: SyncStats) {
         self.1.insert(id, stats);
     }
+    fn map_list_id(&mut self, id: ListId) -> Option<ListId> {
+        self.2.get_by_right(&id).cloned()
+    }
+    fn save_list_id(&mut self, src_id: ListId, gen_id: ListId) {
+        self.2.insert(gen_id, src_id).expect_free();
+    }
 }
 
 impl<'a, R: SyncResolver> Syncer<'a, R> {
@@ -567,12 +812,18 @@ impl<'a, R: SyncResolver> Syncer<'a, R> {
             | RegisterType::Int(_)
             | RegisterType::Boolean
             | RegisterType::Bool(_)
-            | RegisterType::FnPtr(_) => typ,
+            | RegisterType::FnPtr(_)
+            | RegisterType::AnyFnPtr => typ,
             RegisterType::Byts(id) => {
-                let payload = self.src.unintern_const(id);
-                let id = self.dest.intern_constant(payload);
+                let payload = self.src.constant_bytes(id);
+                let id = self.dest.intern_constant(&payload);
                 RegisterType::Byts(id)
             }
+            RegisterType::ExactBigInt(id) => {
+                let payload = self.src.constant_bytes(id);
+                let id = self.dest.intern_constant(&payload);
+                RegisterType::ExactBigInt(id)
+            }
             RegisterType::Union(u) => {
                 let union = self.src.unions.unintern(u);
                 let id = self.dest.unions.intern(union.clone());
@@ -655,6 +906,44 @@ impl<'a, R: SyncResolver> Syncer<'a, R> {
 
                 RegisterType::Record(dest_id)
             }
+            RegisterType::List(id) => {
+                let list = self.src.lists.get(id);
+                let element = list.element.map(|t| self.sync_type(t));
+                let items = (list.items).map(|items| {
+                    (items.into_iter())
+                        .map(|t| self.sync_type(t))
+                        .collect::<Vec<_>>()
+                });
+
+                let dest_id = match self.resolve.map_list_id(id) {
+                    Some(dest_id) => dest_id,
+                    None => {
+                        let dest_id = self.dest.lists.new_list(List { items, element });
+                        self.resolve.save_list_id(id, dest_id);
+                        return RegisterType::List(dest_id);
+                    }
+                };
+
+                // the list is shared with the other bag, so anything done to
+                // it there, such as pushing onto it, is seen here too. if the
+                // lengths differ, it could be either
+                let dest = self.dest.lists.get(dest_id);
+                let element = match (dest.element, element) {
+                    (Some(a), Some(b)) => Some(self.dest.union_of(a, b)),
+                    (a, b) => a.or(b),
+                };
+                let items = match (dest.items, items) {
+                    (Some(a), Some(b)) if a.len() == b.len() => Some(
+                        (a.into_iter().strict_zip(b))
+                            .map(|(a, b)| self.dest.union_of(a, b))
+                            .collect(),
+                    ),
+                    _ => None,
+                };
+
+                *self.dest.lists.get_mut(dest_id) = List { items, element };
+                RegisterType::List(dest_id)
+            }
         }
     }
 }
@@ -696,6 +985,8 @@ pub struct Subset<'a> {
     // reg_map: FxBiHashMap<RegisterId, RegisterId>,
     // { original record id |-> child record id }
     rec_map: FxBiHashMap<AllocationId, AllocationId>,
+    // { original list id |-> child list id }
+    list_map: FxBiHashMap<ListId, ListId>,
 
     // { record id in original |-> number of facts present at subset creation }
     src_fact_init: FxHashMap<AllocationId, SyncStats>,
@@ -719,7 +1010,11 @@ impl<'a> Subset<'a> {
             up_until,
             src: &self.original,
             dest: &mut self.child,
-            resolve: ResolveLeft(&mut self.rec_map, &mut self.src_fact_init),
+            resolve: ResolveLeft(
+                &mut self.rec_map,
+                &mut self.src_fact_init,
+                &mut self.list_map,
+            ),
         }
     }
 
@@ -735,7 +1030,11 @@ impl<'a> Subset<'a> {
             up_until,
             src: child,
             dest,
-            resolve: ResolveRight(&mut self.rec_map, &mut self.dest_fact_init),
+            resolve: ResolveRight(
+                &mut self.rec_map,
+                &mut self.dest_fact_init,
+                &mut self.list_map,
+            ),
         }
     }
 
@@ -748,7 +1047,16 @@ impl<'a> Subset<'a> {
         up_until: InstIdx,
     ) {
         let typ = self.original.get(src_reg);
+        self.sync_typ_to_child(typ, child_reg, inst_idx, up_until);
+    }
 
+    fn sync_typ_to_child(
+        &mut self,
+        typ: RegisterType,
+        child_reg: RegisterId,
+        inst_idx: InstIdx,
+        up_until: InstIdx,
+    ) {
         let res_typ = self.self_to_child_syncer(inst_idx, up_until).sync_type(typ);
 
         self.child.assign_type(child_reg, res_typ);
@@ -821,35 +1129,65 @@ impl UnionInterner {
     }
 }
 
-#[derive(Deref, DerefMut)]
-struct CloneRodeo<T>(Rodeo<T>);
+/// Interns constant payloads for an entire program. Every type bag derived
+/// from the same root bag shares one interner, so the same payload gets the
+/// same id no matter which function it was made in.
+///
+/// Payloads are arbitrary bytes, such as slices of strings or the bytes of big
+/// integers, so they're interned as bytes rather than as strings.
+#[derive(Clone, Default)]
+struct ConstantInterner(Arc<Mutex<ConstantPayloads>>);
 
-impl<T: Key> Default for CloneRodeo<T> {
-    fn default() -> Self {
-        CloneRodeo(Rodeo::new())
-    }
+#[derive(Default)]
+struct ConstantPayloads {
+    ids: FxHashMap<Arc<[u8]>, ConstantId>,
+    payloads: Vec<Arc<[u8]>>,
 }
 
-impl<T: Key + Default> Clone for CloneRodeo<T> {
-    fn clone(&self) -> Self {
-        let mut new = Rodeo::new();
+impl ConstantInterner {
+    fn intern(&self, payload: &[u8]) -> ConstantId {
+        let mut constants = self.0.lock().unwrap();
 
-        for (k, v) in self.iter() {
-            let k_new = new.get_or_intern(v);
-            assert!(k == k_new, "need keys on rodeos to match");
+        if let Some(id) = constants.ids.get(payload) {
+            return *id;
         }
 
-        CloneRodeo(new)
+        let id = ConstantId::new_with_value(constants.payloads.len());
+        let payload = Arc::<[u8]>::from(payload);
+        constants.payloads.push(payload.clone());
+        constants.ids.insert(payload, id);
+        id
+    }
+
+    fn resolve(&self, id: ConstantId) -> Option<Arc<[u8]>> {
+        let constants = self.0.lock().unwrap();
+        constants.payloads.get(id.value()).cloned()
     }
 }
 
+/// The place in the program where a record was allocated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AllocationSite {
+    /// The function containing the allocating instruction.
+    pub function: DynFnId,
+    /// The `NewRecord` or `RecordClone` instruction within that function.
+    pub inst_idx: InstIdx,
+}
+
+/// Maps every record allocated by the program back to its allocation site.
+/// Like [`ConstantInterner`], this is shared by every bag derived from the
+/// same root bag, as records freely flow between functions.
+#[derive(Clone, Default)]
+struct AllocationSites(Arc<Mutex<FxHashMap<UniqueRecordId<SymbolicCtx>, AllocationSite>>>);
+
 #[derive(Clone)]
 pub struct TypeBag {
     pub(crate) registers: FxHashMap<RegisterId, RegisterType>,
     records: RecordBag,
+    lists: ListBag,
     unions: UnionInterner,
-    // constants: CloneRodeo<ConstantId>,
-    constants: bool,
+    constants: ConstantInterner,
+    allocation_sites: AllocationSites,
     status: LookingUp,
 }
 
@@ -877,24 +1215,201 @@ impl TypeBag {
         self.records.record_unique_id(record)
     }
 
+    pub fn record_allocation_site(
+        &mut self,
+        unique_record_id: UniqueRecordId<SymbolicCtx>,
+        site: AllocationSite,
+    ) {
+        let mut sites = self.allocation_sites.0.lock().unwrap();
+        sites.insert(unique_record_id, site);
+    }
+
+    /// Finds where the record with the given unique id was allocated. This is
+    /// known for any record allocated by a bag that shares program-wide state
+    /// with this one, not only records that this bag knows about.
+    pub fn allocation_site(
+        &self,
+        unique_record_id: UniqueRecordId<SymbolicCtx>,
+    ) -> Option<AllocationSite> {
+        let sites = self.allocation_sites.0.lock().unwrap();
+        sites.get(&unique_record_id).copied()
+    }
+
+    pub fn record_clone(
+        &mut self,
+        register: RegisterId,
+        source: RegisterId,
+        unique_record_id: UniqueRecordId<SymbolicCtx>,
+    ) {
+        let source = match self.get(source) {
+            RegisterType::Record(id) => id,
+            _ => panic!("not of type record"),
+        };
+
+        let id = self.records.clone_record(source, unique_record_id);
+        self.registers
+            .insert(register, RegisterType::Record(id))
+            .expect_free();
+    }
+
     pub fn record_get_field(&mut self, record: RegisterId, field: WorkRecordKey) -> RegisterType {
         let typ = self.get(record);
 
-        let record = match typ {
+        // a field of a union of records is any of the fields of its records
+        let records = match self.records_of(typ) {
+            Some(records) => records,
+            None => return RegisterType::Any,
+        };
+
+        let field = self.record_key(field);
+        let mut field_typ = None;
+        for record in records {
+            let typ = self.record_get_field_of(record, field);
+            field_typ = Some(match field_typ {
+                Some(field_typ) => self.union_of(field_typ, typ),
+                None => typ,
+            });
+        }
+
+        field_typ.unwrap()
+    }
+
+    /// Gets the type of a field on a record, or `default` if the record doesn't
+    /// have the field. If the record may or may not have the field, the type is
+    /// a union of both.
+    pub fn record_get_field_or(
+        &mut self,
+        record: RegisterId,
+        field: WorkRecordKey,
+        default: RegisterType,
+    ) -> RegisterType {
+        let typ = self.get(record);
+
+        let records = match self.records_of(typ) {
+            Some(records) => records,
+            None => return RegisterType::Any,
+        };
+
+        let field = self.record_key(field);
+        let mut field_typ = None;
+        for record in records {
+            let typ = match self.record_has_field_of(record, field) {
+                Some(true) => self.record_get_field_of(record, field),
+                Some(false) => default,
+                None => {
+                    let typ = self.record_get_field_of(record, field);
+                    self.union_of(typ, default)
+                }
+            };
+
+            field_typ = Some(match field_typ {
+                Some(field_typ) => self.union_of(field_typ, typ),
+                None => typ,
+            });
+        }
+
+        field_typ.unwrap()
+    }
+
+    /// Lists the records a value of `typ` could be. A union that may be
+    /// something other than a record has no records to list, as the field of
+    /// such a value could be anything.
+    fn records_of(&self, typ: RegisterType) -> Option<Vec<AllocationId>> {
+        match typ {
+            RegisterType::Record(id) => Some(vec![id]),
+            RegisterType::Union(_) => (self.variants_of(typ).into_iter())
+                .map(|typ| match typ {
+                    RegisterType::Record(id) => Some(id),
+                    _ => None,
+                })
+                .collect(),
+            _ => panic!("not of type record"),
+        }
+    }
+
+    /// Gets the type of a field on a record, walking up the `[[Prototype]]`
+    /// chain of the record if the record does not definitively have the field.
+    /// The chain terminates upon reaching a prototype that isn't a record.
+    pub fn record_get_field_inherited(
+        &mut self,
+        record: RegisterId,
+        field: WorkRecordKey,
+    ) -> RegisterType {
+        let typ = self.get(record);
+
+        let mut record = match typ {
             RegisterType::Record(id) => id,
             _ => panic!("not of type record"),
         };
 
-        let field = match field {
+        let field = self.record_key(field);
+        let prototype = RecordKey::Slot(InternalSlot::Prototype.atom());
+
+        // a record which may or may not have the field either produces its own
+        // field or the inherited one, so the lookup carries on to its prototype
+        let mut own_types = Vec::new();
+        let inherited = loop {
+            match self.record_has_field_of(record, field) {
+                Some(true) => break self.record_get_field_of(record, field),
+                Some(false) => {}
+                None => {
+                    let typ = self.record_get_field_of(record, field);
+                    own_types.push(typ);
+                }
+            }
+
+            if self.record_has_field_of(record, prototype) != Some(true) {
+                break self.record_get_field_of(record, field);
+            }
+
+            match self.record_get_field_of(record, prototype) {
+                RegisterType::Record(parent) => record = parent,
+                // a non-record prototype, such as `null`, is the end of the chain
+                _ => break self.record_get_field_of(record, field),
+            };
+        };
+
+        (own_types.into_iter()).fold(inherited, |typ, own| self.union_of(own, typ))
+    }
+
+    fn record_key(&self, field: WorkRecordKey) -> RecordKey {
+        match field {
             WorkRecordKey::Prop(register) => RecordKey::Key(self.get(register)),
-            // WorkRecordKey::Slot(slot) => RecordKey::Slot(slot),
-            _ => todo!(),
+            WorkRecordKey::Atom(atom) => RecordKey::Slot(atom),
+            WorkRecordKey::DynAtom(register) => match self.get(register) {
+                RegisterType::Atom(atom) => RecordKey::Slot(atom),
+                // an atom whose value isn't known could be any of the slots of
+                // the record, which is what a key of unknown value means too
+                _ => RecordKey::Key(RegisterType::Any),
+            },
+        }
+    }
+
+    fn record_has_field_of(&self, record: AllocationId, field: RecordKey) -> Option<bool> {
+        // a key whose value isn't known could be any of the keys of the record
+        if let RecordKey::Key(key) = field {
+            if !key.is_exact() {
+                return None;
+            }
+        }
+
+        let keys_eq = |a, b| {
+            use RecordKey::*;
+            match (a, b) {
+                (Slot(a), Slot(b)) => a == b,
+                (Key(a), Key(b)) => self.typ_eq(a, b),
+                _ => false,
+            }
         };
 
+        self.records.record_has_field(record, field, keys_eq)
+    }
+
+    fn record_get_field_of(&mut self, record: AllocationId, field: RecordKey) -> RegisterType {
         let keys_eq = |a, b| {
             use RecordKey::*;
             match (a, b) {
-                // (Slot(a), Slot(b)) => a == b,
+                (Slot(a), Slot(b)) => a == b,
                 (Key(a), Key(b)) => self.typ_eq(a, b),
                 _ => false,
             }
@@ -932,16 +1447,13 @@ impl TypeBag {
                 Fact::Remove { .. } => {
                     panic!("attempted to get field of record that does not exist")
                 }
+                // lines of facts sharing a fact agree on the value
+                Fact::Set { value, .. } if types.contains(value) => {}
                 Fact::Set { value, .. } => types.push(*value),
             };
         }
 
-        if types.len() == 1 {
-            types[0]
-        } else {
-            let union_id = self.unions.intern(Union(types));
-            RegisterType::Union(union_id)
-        }
+        self.make_union(types)
     }
 
     pub fn record_set_field(
@@ -958,11 +1470,7 @@ impl TypeBag {
             _ => panic!("not of type record"),
         };
 
-        let field = match field {
-            WorkRecordKey::Prop(register) => RecordKey::Key(self.get(register)),
-            // WorkRecordKey::Slot(slot) => RecordKey::Slot(slot),
-            _ => todo!(),
-        };
+        let field = self.record_key(field);
 
         match value {
             Some(value) => self.records.record_fact_set(record, field, value, inst_idx),
@@ -970,6 +1478,74 @@ impl TypeBag {
         };
     }
 
+    /// Computes the `[key, value]` pairs of every field of `record`, in the
+    /// order the fields were inserted. Internal slots are not included.
+    ///
+    /// The boolean is `true` if the record has exactly these fields. Otherwise,
+    /// the record may have any number of the fields, each of which could be any
+    /// of the values it was ever set to.
+    pub fn record_entries(
+        &mut self,
+        record: RegisterId,
+    ) -> (Vec<(RegisterType, RegisterType)>, bool) {
+        let record = match self.get(record) {
+            RegisterType::Record(id) => id,
+            _ => panic!("not of type record"),
+        };
+
+        if let Some(fields) = self.records.record_fields(record) {
+            let entries = (fields.into_iter())
+                .filter_map(|(key, value)| match key {
+                    RecordKeyEq::Key(key) => Some((key, value)),
+                    RecordKeyEq::Slot(_) => None,
+                })
+                .collect();
+
+            return (entries, true);
+        }
+
+        let values = (self.records.records.get(&record).unwrap().fact_paths.iter())
+            .flat_map(|facts| facts.iter())
+            .filter_map(|fact| match fact {
+                Fact::Set {
+                    key: RecordKey::Key(key),
+                    value,
+                    ..
+                } => Some((*key, *value)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut entries = Vec::<(RegisterType, RegisterType)>::new();
+        for (key, value) in values {
+            match entries.iter().position(|(k, _)| self.typ_eq(*k, key)) {
+                Some(idx) => entries[idx].1 = self.union_of(entries[idx].1, value),
+                None => entries.push((key, value)),
+            }
+        }
+
+        (entries, false)
+    }
+
+    /// Creates a record holding `key` at `0` and `value` at `1`.
+    pub fn new_pair(
+        &mut self,
+        key: RegisterType,
+        value: RegisterType,
+        unique_record_id: UniqueRecordId<SymbolicCtx>,
+        inst_idx: InstIdx,
+    ) -> RegisterType {
+        let id = self.records.new_record(unique_record_id);
+
+        let fields = [(0, key), (1, value)];
+        for (idx, value) in fields {
+            let key = RecordKey::Key(RegisterType::Int(idx));
+            self.records.record_fact_set(id, key, value, inst_idx);
+        }
+
+        RegisterType::Record(id)
+    }
+
     pub fn record_has_field(&self, record: RegisterId, field: WorkRecordKey) -> Option<bool> {
         let typ = self.get(record);
 
@@ -978,56 +1554,357 @@ impl TypeBag {
             _ => panic!("not of type record"),
         };
 
-        let field = match field {
-            WorkRecordKey::Prop(register) => RecordKey::Key(self.get(register)),
-            // WorkRecordKey::Slot(slot) => RecordKey::Slot(slot),
-            _ => todo!(),
+        let field = self.record_key(field);
+        self.record_has_field_of(record, field)
+    }
+
+    pub fn new_list(&mut self, register: RegisterId) {
+        let id = self.lists.new_list(List {
+            items: Some(Vec::new()),
+            element: None,
+        });
+
+        self.registers
+            .insert(register, RegisterType::List(id))
+            .expect_free();
+    }
+
+    fn list_id(&self, list: RegisterId) -> ListId {
+        match self.get(list) {
+            RegisterType::List(id) => id,
+            _ => panic!("not of type list"),
+        }
+    }
+
+    pub fn list_len(&self, list: RegisterId) -> RegisterType {
+        match self.lists.get(self.list_id(list)).len() {
+            Some(len) => RegisterType::Int(len as i64),
+            None => RegisterType::Number,
+        }
+    }
+
+    pub fn list_push(&mut self, list: RegisterId, value: RegisterType) {
+        let id = self.list_id(list);
+
+        let element = match self.lists.get(id).element {
+            Some(element) => self.union_of(element, value),
+            None => value,
         };
 
-        let keys_eq = |a, b| {
-            use RecordKey::*;
-            match (a, b) {
-                // (Slot(a), Slot(b)) => a == b,
-                (Key(a), Key(b)) => self.typ_eq(a, b),
-                _ => false,
+        let list = self.lists.get_mut(id);
+        if let Some(items) = &mut list.items {
+            items.push(value);
+        }
+        list.element = Some(element);
+    }
+
+    /// Removes the last element of `list`, producing its type. Produces `None`
+    /// if the list is statically known to be empty, as popping from it would
+    /// fail.
+    pub fn list_pop(&mut self, list: RegisterId) -> Option<RegisterType> {
+        let id = self.list_id(list);
+        let list = self.lists.get_mut(id);
+
+        match &mut list.items {
+            Some(items) => items.pop(),
+            None => Some(list.element.unwrap_or(RegisterType::Any)),
+        }
+    }
+
+    /// Marks the length of `list` as no longer statically known.
+    pub fn list_forget_len(&mut self, list: RegisterId) {
+        let id = self.list_id(list);
+        self.lists.get_mut(id).items = None;
+    }
+
+    /// The union of the types of every element that has been in the list, or
+    /// `None` if nothing has ever been put into the list.
+    pub fn list_element(&self, list: RegisterId) -> Option<RegisterType> {
+        self.lists.get(self.list_id(list)).element
+    }
+
+    /// Creates a list in `register` of the same length as `list`, whose
+    /// elements are of the type `element`.
+    pub fn list_map(
+        &mut self,
+        register: RegisterId,
+        list: RegisterId,
+        element: Option<RegisterType>,
+    ) {
+        let items = (self.lists.get(self.list_id(list)).items)
+            .map(|items| vec![element.unwrap_or(RegisterType::Any); items.len()]);
+        let id = self.lists.new_list(List { items, element });
+
+        self.registers
+            .insert(register, RegisterType::List(id))
+            .expect_free();
+    }
+
+    /// Marks the facts currently known about records, so that the facts recorded
+    /// by an arm of a branch can be taken back out with
+    /// [`TypeBag::take_facts_since`].
+    pub fn checkpoint_facts(&self) -> FactsCheckpoint {
+        self.records.fact_counts()
+    }
+
+    /// Removes every fact recorded about records since `checkpoint`, producing
+    /// them.
+    pub fn take_facts_since(&mut self, checkpoint: &FactsCheckpoint) -> BranchFacts {
+        self.records.take_facts_since(checkpoint)
+    }
+
+    /// Records facts that were taken out with [`TypeBag::take_facts_since`]
+    /// back onto their records, as they were.
+    pub fn restore_facts(&mut self, facts: BranchFacts) {
+        for (id, facts) in facts.0 {
+            let record = self.records.records.get_mut(&id).unwrap();
+
+            for (path, facts) in record.fact_paths.iter_mut().strict_zip(facts) {
+                path.extend(facts);
             }
+        }
+    }
+
+    /// Records the facts from both arms of a branch where only one of the arms
+    /// is taken at runtime. A field that both arms leave on a record has either
+    /// of their types, while a field only one arm leaves on it splits the
+    /// record's facts into one line of facts per arm, so that the record may or
+    /// may not have the field.
+    pub fn merge_branch_facts(
+        &mut self,
+        mut a: BranchFacts,
+        mut b: BranchFacts,
+        inst_idx: InstIdx,
+    ) {
+        let mut records = a.0.keys().chain(b.0.keys()).copied().collect::<Vec<_>>();
+        records.sort_unstable();
+        records.dedup();
+
+        for id in records {
+            let record = self.records.records.get(&id).unwrap();
+            let paths = record.fact_paths.0.clone();
+            let (a, b) = (a.0.remove(&id), b.0.remove(&id));
+
+            let mut merged = Vec::new();
+            for (idx, path) in paths.into_iter().enumerate() {
+                let a = a.as_ref().map(|a| a[idx].as_slice()).unwrap_or_default();
+                let b = b.as_ref().map(|b| b[idx].as_slice()).unwrap_or_default();
+                merged.extend(self.merge_path(path, a, b, inst_idx));
+            }
+
+            self.records.records.get_mut(&id).unwrap().fact_paths = Union(merged);
+        }
+    }
+
+    fn merge_path(
+        &mut self,
+        path: Facts<Fact>,
+        a: &[Fact],
+        b: &[Fact],
+        inst_idx: InstIdx,
+    ) -> Vec<Facts<Fact>> {
+        if a.is_empty() && b.is_empty() {
+            return vec![path];
+        }
+
+        let latest = |facts: &[Fact], key: RecordKeyEq| {
+            (facts.iter().rev())
+                .find(|fact| fact.key().into_record_key_eq() == key)
+                .cloned()
+        };
+        let value_of = |fact: &Option<Fact>| match fact {
+            Some(Fact::Set { value, .. }) => Some(*value),
+            _ => None,
         };
 
-        self.records.record_has_field(record, field, keys_eq)
+        let mut keys = Vec::new();
+        for key in a
+            .iter()
+            .chain(b)
+            .map(|fact| fact.key().into_record_key_eq())
+        {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        let (mut common, mut only_a, mut only_b) = (path.clone(), Vec::new(), Vec::new());
+        for key in keys {
+            let before = latest(path.as_slice(), key);
+            let had_key = value_of(&before).is_some();
+            let in_a = value_of(&latest(a, key).or_else(|| before.clone()));
+            let in_b = value_of(&latest(b, key).or(before));
+
+            let key = key.into_record_key();
+            let set = |value| Fact::Set {
+                key,
+                value,
+                inst_idx,
+            };
+            let remove = Fact::Remove { key, inst_idx };
+
+            match (in_a, in_b) {
+                (Some(a), Some(b)) => common.push(set(self.union_of(a, b))),
+                (None, None) if had_key => common.push(remove),
+                (None, None) => {}
+                (Some(value), None) => {
+                    only_a.push(set(value));
+                    if had_key {
+                        only_b.push(remove);
+                    }
+                }
+                (None, Some(value)) => {
+                    only_b.push(set(value));
+                    if had_key {
+                        only_a.push(remove);
+                    }
+                }
+            }
+        }
+
+        if only_a.is_empty() && only_b.is_empty() {
+            return vec![common];
+        }
+
+        let mut with_a = common.clone();
+        with_a.extend(only_a);
+        let mut with_b = common;
+        with_b.extend(only_b);
+        vec![with_a, with_b]
+    }
+
+    /// Produces a type that is either `a` or `b`, flattening any unions.
+    pub fn union_of(&mut self, a: RegisterType, b: RegisterType) -> RegisterType {
+        let is_boolean = |t: &RegisterType| t.value_type() == Some(ValueType::Boolean);
+
+        let mut types = self.variants_of(a);
+        for typ in self.variants_of(b) {
+            if types.iter().any(|t| self.typ_eq(*t, typ)) {
+                continue;
+            }
+
+            // `true` and `false` together are every boolean
+            match types.iter_mut().find(|t| is_boolean(t) && is_boolean(&typ)) {
+                Some(t) => *t = RegisterType::Boolean,
+                None => types.push(typ),
+            }
+        }
+
+        self.make_union(types)
+    }
+
+    /// Lists every type a value of `typ` could be, which is the types a union
+    /// is made of, or only `typ` itself for any other type.
+    pub fn variants_of(&self, typ: RegisterType) -> Vec<RegisterType> {
+        match typ {
+            RegisterType::Union(id) => self.unions.unintern(id).0.clone(),
+            typ => vec![typ],
+        }
+    }
+
+    /// Determines if a value of `typ` can be passed to an external function as
+    /// a parameter of the given type, which for a union means every type it is
+    /// made of can be.
+    pub fn can_coerce_to(&self, typ: RegisterType, ffi_type: &FFIValueType) -> bool {
+        (self.variants_of(typ).iter()).all(|variant| variant.can_coerce_to(ffi_type))
+    }
+
+    fn make_union(&mut self, types: Vec<RegisterType>) -> RegisterType {
+        match types.len() {
+            1 => types[0],
+            len if len > MAX_UNION_VARIANTS => RegisterType::Any,
+            _ => RegisterType::Union(self.unions.intern(Union(types))),
+        }
+    }
+
+    /// Splits `typ` by whether or not a value of it is of the given kind,
+    /// producing the type for when it is and the type for when it isn't. A
+    /// side is `None` if no value of `typ` could end up there.
+    pub fn narrow_to_kind(
+        &mut self,
+        typ: RegisterType,
+        kind: ValueType,
+    ) -> (Option<RegisterType>, Option<RegisterType>) {
+        let variants = match typ {
+            RegisterType::Any => {
+                let is_kind = match kind {
+                    ValueType::Bytes => RegisterType::Bytes,
+                    ValueType::Number => RegisterType::Number,
+                    ValueType::Boolean => RegisterType::Boolean,
+                    ValueType::FnPtr => RegisterType::AnyFnPtr,
+                    // there is no type describing every record or list
+                    _ => RegisterType::Any,
+                };
+
+                return (Some(is_kind), Some(RegisterType::Any));
+            }
+            typ => self.variants_of(typ),
+        };
+
+        let mut is_kind = None;
+        let mut isnt_kind = None;
+
+        for variant in variants {
+            let side = match variant.value_type() == Some(kind) {
+                true => &mut is_kind,
+                false => &mut isnt_kind,
+            };
+
+            *side = Some(match *side {
+                Some(typ) => self.union_of(typ, variant),
+                None => variant,
+            });
+        }
+
+        (is_kind, isnt_kind)
     }
 
     pub fn assign_type(&mut self, register: RegisterId, typ: RegisterType) {
         self.registers.insert(register, typ).expect_free();
     }
 
-    pub fn intern_constant(&mut self, payload: &[u8]) -> ConstantId {
-        // we don't really want or care about strigns specifically
-        // but the API wants strings
-        // TODO: is this safe?
-        let str = unsafe { std::str::from_utf8_unchecked(payload) };
-        // self.constants.get_or_intern(str)
-        todo!()
+    /// Interns a constant into the interner shared by every bag in the program.
+    /// Identical payloads always produce the same id, so two [`RegisterType::Byts`]
+    /// with equal ids are known to hold equal bytes.
+    /// Only needs a shared borrow, so it may be called while other bags holding
+    /// the same interner are alive.
+    pub fn intern_constant(&self, payload: &[u8]) -> ConstantId {
+        self.constants.intern(payload)
     }
 
     // TODO: this function shouldn't exist,
-    // there is a bug somewhere that causes the assertion in `unintern_const` to
+    // there is a bug somewhere that causes the assertion in `constant_bytes` to
     // trigger but at the time of writing i'm not here to fix that
-    pub fn mayb_unintern_const(&self, id: ConstantId) -> Option<&[u8]> {
-        todo!()
-        // if id.into_usize() >= self.constants.len() {
-        // return None;
-        // }
+    pub fn mayb_unintern_const(&self, id: ConstantId) -> Option<Arc<[u8]>> {
+        self.constants.resolve(id)
+    }
 
-        // Some(self.constants.resolve(&id).as_bytes())
+    /// Looks up the payload a [`ConstantId`] was interned from.
+    pub fn constant_bytes(&self, id: ConstantId) -> Arc<[u8]> {
+        match self.constants.resolve(id) {
+            Some(payload) => payload,
+            None => panic!("this will be terrible for the economy"),
+        }
     }
 
-    pub fn unintern_const(&self, id: ConstantId) -> &[u8] {
-        todo!()
-        // if id.into_usize() >= self.constants.len() {
-        // panic!("this will be terrible for the economy");
-        // }
+    /// Produces the type of exactly `value`. Integers which fit are kept as an
+    /// [`RegisterType::Int`], so that every integer has only one exact type.
+    pub fn make_integer(&self, value: BigInt) -> RegisterType {
+        match i64::try_from(&value) {
+            Ok(value) => RegisterType::Int(value),
+            Err(_) => RegisterType::ExactBigInt(self.intern_constant(&value.to_signed_bytes_le())),
+        }
+    }
 
-        // self.constants.resolve(&id).as_bytes()
+    /// The value of `typ`, if it is an exact integer.
+    pub fn integer_value(&self, typ: RegisterType) -> Option<BigInt> {
+        match typ {
+            RegisterType::Int(value) => Some(BigInt::from(value)),
+            RegisterType::ExactBigInt(id) => {
+                Some(BigInt::from_signed_bytes_le(&self.constant_bytes(id)))
+            }
+            _ => None,
+        }
     }
 
     pub fn try_get(&self, register: RegisterId) -> Option<RegisterType> {
@@ -1042,6 +1919,17 @@ impl TypeBag {
         typ
     }
 
+    /// Lists every record that a value of the given type could be.
+    pub fn possible_records(&self, typ: RegisterType) -> Vec<AllocationId> {
+        match typ {
+            RegisterType::Record(id) => vec![id],
+            RegisterType::Union(id) => (self.unions.unintern(id).iter())
+                .flat_map(|typ| self.possible_records(*typ))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn get_fnptr(&self, register: RegisterId) -> DynFnId {
         if let RegisterType::FnPtr(f) = self.get(register) {
             f
@@ -1077,6 +1965,29 @@ impl TypeBag {
         TypeBag::subset_impl(MaybeMut::Mut(self), src_args, target_args, up_until)
     }
 
+    /// Like [`TypeBag::subset`], but any of the `src_args` present in
+    /// `narrowed` are given the narrowed type rather than their own.
+    pub fn subset_narrowed(
+        &mut self,
+        src_args: &[RegisterId],
+        target_args: &[RegisterId],
+        narrowed: &[(RegisterId, RegisterType)],
+        up_until: InstIdx,
+    ) -> Subset {
+        let mut subset = TypeBag::subset_impl(MaybeMut::Mut(self), &[], &[], up_until);
+
+        for (src_reg, child_reg) in src_args.iter().strict_zip(target_args.iter()) {
+            let typ = match narrowed.iter().find(|(reg, _)| reg == src_reg) {
+                Some((_, typ)) => *typ,
+                None => subset.original.get(*src_reg),
+            };
+
+            subset.sync_typ_to_child(typ, *child_reg, InstIdx::Prologue, up_until);
+        }
+
+        subset
+    }
+
     pub fn subset_immut(
         &self,
         src_args: &[RegisterId],
@@ -1086,6 +1997,50 @@ impl TypeBag {
         TypeBag::subset_impl(MaybeMut::Imm(self), src_args, target_args, up_until)
     }
 
+    /// Like [`TypeBag::subset`], but each of the `target_args` is given the
+    /// type of the element of `list` at the same position. Produces `None` if
+    /// the length of the list is not statically known, as then we don't know
+    /// which arguments are passed. Otherwise, the list must be exactly as long
+    /// as `target_args`.
+    pub fn subset_spread(
+        &mut self,
+        list: RegisterId,
+        target_args: &[RegisterId],
+        up_until: InstIdx,
+    ) -> Option<Subset> {
+        let items = self.lists.get(self.list_id(list)).items?;
+
+        let mut subset = TypeBag::subset_impl(MaybeMut::Mut(self), &[], &[], up_until);
+
+        for (item, child_reg) in items.into_iter().strict_zip(target_args.iter()) {
+            subset.sync_typ_to_child(item, *child_reg, InstIdx::Prologue, up_until);
+        }
+
+        Some(subset)
+    }
+
+    /// Like [`TypeBag::subset`], but the last of the `target_args` is given
+    /// the type of the elements of `list`, no matter how long the list is. The
+    /// rest of the `target_args` are given the types of the `src_args`.
+    pub fn subset_elements(
+        &mut self,
+        src_args: &[RegisterId],
+        list: RegisterId,
+        target_args: &[RegisterId],
+        up_until: InstIdx,
+    ) -> Subset {
+        let element = self.list_element(list);
+        let (element_reg, target_args) = target_args.split_last().unwrap();
+
+        let mut subset = TypeBag::subset_impl(MaybeMut::Mut(self), src_args, target_args, up_until);
+
+        if let Some(element) = element {
+            subset.sync_typ_to_child(element, *element_reg, InstIdx::Prologue, up_until);
+        }
+
+        subset
+    }
+
     fn subset_impl<'a>(
         original: MaybeMut<'a, TypeBag>,
         src_args: &[RegisterId],
@@ -1094,10 +2049,13 @@ impl TypeBag {
     ) -> Subset<'a> {
         debug_assert_eq!(src_args.len(), target_args.len());
 
+        let child = TypeBag::sharing_program_state(&original);
+
         let mut subset = Subset {
             original,
-            child: Default::default(),
+            child,
             rec_map: Default::default(),
+            list_map: Default::default(),
             src_fact_init: Default::default(),
             dest_fact_init: Default::default(),
         };
@@ -1141,6 +2099,22 @@ impl TypeBag {
         self.solve_constraints(other, &mut record_constraints, &mut union_constraints)
     }
 
+    /// Determines if two types are the same kind of value. Records are only
+    /// the same if they have the same shape, i.e. the same keys. Produces
+    /// `None` if it can't be known statically.
+    pub fn is_same_type(&self, lhs: RegisterType, rhs: RegisterType) -> Option<bool> {
+        if let (RegisterType::Record(lhs), RegisterType::Record(rhs)) = (lhs, rhs) {
+            let lhs = self.records.record_shape(lhs)?;
+            let rhs = self.records.record_shape(rhs)?;
+            return Some(lhs == rhs);
+        }
+
+        match (lhs.value_type(), rhs.value_type()) {
+            (Some(lhs), Some(rhs)) => Some(lhs == rhs),
+            _ => None,
+        }
+    }
+
     pub fn is_subtype(&self, subset: RegisterType, superset: RegisterType) -> bool {
         subset <= superset
     }
@@ -1171,11 +2145,36 @@ impl TypeBag {
                     true
                 }
             }
+            (List(a), List(b)) => {
+                let (a, b) = (self.lists.get(a), other.lists.get(b));
+
+                let items_equal = match (a.items, b.items) {
+                    (Some(a), Some(b)) => {
+                        a.len() == b.len()
+                            && (a.into_iter().strict_zip(b)).all(|(a, b)| {
+                                self.maybe_equal(other, a, b, record_constraints, union_constraints)
+                            })
+                    }
+                    (None, None) => true,
+                    _ => false,
+                };
+
+                items_equal
+                    && match (a.element, b.element) {
+                        (Some(a), Some(b)) => {
+                            self.maybe_equal(other, a, b, record_constraints, union_constraints)
+                        }
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
             (Union(a), Union(b)) => {
                 union_constraints.push((a, b));
                 true
             }
-            (Byts(a), Byts(b)) => self.unintern_const(a) == other.unintern_const(b),
+            (Byts(a), Byts(b)) | (ExactBigInt(a), ExactBigInt(b)) => {
+                self.constant_bytes(a) == other.constant_bytes(b)
+            }
             (a, b) => a == b,
         };
 
@@ -1301,7 +2300,7 @@ impl TypeBag {
 
             use RecordKey::*;
             match (a, b) {
-                // (Slot(a), Slot(b)) => a == b,
+                (Slot(a), Slot(b)) => a == b,
                 // a <: b
                 (Key(a), Key(b)) => self.is_subtype(a, b),
                 _ => false,
@@ -1375,6 +2374,15 @@ impl TypeBag {
             })
             .collect::<Vec<_>>();
 
+        // lines of facts that share a fact agree on the type of the field
+        let mut unique_types = Vec::new();
+        for typ in field_types {
+            if !unique_types.contains(&typ) {
+                unique_types.push(typ);
+            }
+        }
+        let field_types = unique_types;
+
         match field_types.len() {
             // no need to set a field if it doesn't have any type
             // ^ in that case, facts : [remove a] => facts : []
@@ -1416,15 +2424,30 @@ impl Default for TypeBag {
             registers: Default::default(),
             unions: Default::default(),
             records: Default::default(),
+            lists: Default::default(),
             constants: Default::default(),
+            allocation_sites: Default::default(),
             status: Default::default(),
         }
     }
 }
 
-impl PartialEq for TypeBag {
+impl TypeBag {
+    /// Creates an empty type bag which shares the program-wide state of
+    /// `other`, which is its interned constants and allocation sites.
+    pub fn sharing_program_state(other: &TypeBag) -> Self {
+        TypeBag {
+            constants: other.constants.clone(),
+            allocation_sites: other.allocation_sites.clone(),
+            ..Default::default()
+        }
+    }
+
     /// Makes sure that every register pairing of two type bags are the same.
-    fn eq(&self, other: &Self) -> bool {
+    /// Records and unions are compared by their contents rather than by their
+    /// ids, and constants are compared by their payloads, so two bags built
+    /// up in the same way are equal even if they interned things differently.
+    pub fn structurally_eq(&self, other: &TypeBag) -> bool {
         if self.registers.len() != other.registers.len() {
             return false;
         }
@@ -1455,6 +2478,35 @@ impl PartialEq for TypeBag {
     }
 }
 
+impl PartialEq for TypeBag {
+    fn eq(&self, other: &Self) -> bool {
+        self.structurally_eq(other)
+    }
+}
+
+impl std::fmt::Debug for TypeBag {
+    /// Dumps the type of every register, in order of the registers.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut registers = self.all_registers();
+        registers.sort();
+
+        let mut display = DisplayContext {
+            types: self,
+            records_shown: Default::default(),
+            inst_idx: InstIdx::Epilogue,
+        };
+
+        let mut dump = f.debug_map();
+        for register in registers {
+            dump.entry(
+                &format_args!("%{}", register),
+                &format_args!("{}", display.display(register)),
+            );
+        }
+        dump.finish()
+    }
+}
+
 pub struct DisplayContext<'types> {
     types: &'types TypeBag,
     records_shown: FxHashSet<AllocationId>,
@@ -1487,7 +2539,8 @@ impl DisplayContext<'_> {
             RegisterType::Any
             | RegisterType::Bytes
             | RegisterType::Number
-            | RegisterType::Boolean => write!(w, "{:?}", reg_typ)?,
+            | RegisterType::Boolean
+            | RegisterType::AnyFnPtr => write!(w, "{:?}", reg_typ)?,
             RegisterType::Atom(t) => write!(w, "{:?}", t)?,
             RegisterType::Byts(p) => {
                 w.push_str("Bytes(");
@@ -1495,10 +2548,27 @@ impl DisplayContext<'_> {
                 w.push(')');
             }
             RegisterType::Int(v) => write!(w, "Int({})", v)?,
+            RegisterType::ExactBigInt(_) => {
+                let value = self.types.integer_value(*reg_typ).unwrap();
+                write!(w, "Int({})", value)?
+            }
             RegisterType::Bool(v) => write!(w, "Boolean({})", v)?,
             RegisterType::FnPtr(f) => write!(w, "FnPtr(@{})", f)?,
             // TODO: display records and unions
             RegisterType::Union(u) => write!(w, "TODO: union {}", u)?,
+            RegisterType::List(l) => {
+                let list = self.types.lists.get(l);
+
+                match list.len() {
+                    Some(len) => write!(w, "List({})", len)?,
+                    None => write!(w, "List(?)")?,
+                };
+
+                if let Some(element) = list.element {
+                    w.push_str(" of ");
+                    self.display_typ(w, &element)?;
+                }
+            }
             RegisterType::Record(r) => {
                 let unique_id = self.types.record_unique_id(r);
 
@@ -1583,7 +2653,7 @@ impl DisplayContext<'_> {
 
         match key {
             RecordKey::Key(k) => self.display_typ(w, k),
-            // RecordKey::Slot(s) => write!(w, "[[{}]]", s),
+            RecordKey::Slot(s) => write!(w, "[[{}]]", s),
         }
     }
 
@@ -1598,7 +2668,7 @@ impl DisplayContext<'_> {
             }
         };
 
-        if let Ok(str) = std::str::from_utf8(payload) {
+        if let Ok(str) = std::str::from_utf8(&payload) {
             write!(w, "{:?}", str)?;
             return Ok(());
         }
@@ -8,6 +8,13 @@ use crate::{collections::PoorMap, id::*, symbolic_execution::types::InstIdx};
 
 use super::types::TypeBag;
 
+/// Hands out a unique ID for every combination of function and argument types.
+///
+/// IDs are handed out in the order functions are explored. This is
+/// deterministic, as the graph system runs workers one at a time, depth-first,
+/// in the order that calls appear within a function. Should exploration ever
+/// become concurrent, IDs will need to be reassigned after exploration using a
+/// stable ordering.
 #[derive(Default)]
 pub struct UniqueFnId {
     id_gen: FunctionId<SymbolicCtx>,
@@ -2,23 +2,33 @@
 
 use std::sync::Arc;
 
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::collections::StrictZip;
 use crate::frontend::ir;
 use crate::frontend::ir::Returns;
 use crate::id::*;
-use crate::isa::Make;
+use crate::isa::{BlockJump, CompareType, Make};
 use crate::lifted;
 use crate::lifted::{Function, LiftedProgram};
 use crate::retag::ExtFnPassRetagger;
 use crate::symbolic_execution::types::{RegisterType, ReturnType};
 
 use super::graph_system::Computation;
-use super::types::InstIdx;
+use super::types::{AllocationSite, InstIdx};
 use super::{
     graph_system::{System, Worker},
     types::TypeBag,
     unique_id::UniqueFnIdShared,
 };
 
+/// Registers paired with the type they are narrowed to.
+type Narrowed = Vec<(RegisterId<LiftedCtx>, RegisterType)>;
+
+/// The amount of recursive calls in a row that must pass a strictly growing
+/// integer as an argument before that argument is widened to a `Number`.
+const GROWTH_LIMIT: usize = 4;
+
 #[derive(Clone, Copy, Debug)]
 pub enum CurrentInstruction<'program> {
     None,
@@ -42,6 +52,14 @@ pub struct SymbWorker<'program> {
     pub asm_ext_map: Arc<ExtFnPassRetagger<LiftedCtx, AssemblerCtx>>,
     pub never_infected: bool,
     pub unique_allocation_id: Arc<Counter<UniqueRecordId<SymbolicCtx>>>,
+    /// Functions which are not explored when called, along with the return
+    /// type to assign to the result of calling them.
+    pub opaque_functions: Arc<FxHashMap<FunctionId<LiftedCtx>, ReturnType>>,
+    /// If set, every register that could only be typed as `Any` is recorded
+    /// into `any_sites`.
+    pub strict: bool,
+    pub any_sites: Vec<AnySite>,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Clone)]
@@ -51,7 +69,34 @@ pub struct WorkerResults {
     pub lifted_id: FunctionId<LiftedCtx>,
     pub is_entry_fn: bool,
     pub return_type: ReturnType,
+    /// The instruction the function never returns past, if the function never
+    /// returns because of one of its instructions rather than where it jumps.
+    pub diverges_at: Option<usize>,
     pub types: TypeBag,
+    /// Only populated when running in strict mode.
+    pub any_sites: Vec<AnySite>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A place where symbolic execution gave up on precisely typing a register,
+/// and typed it as [`RegisterType::Any`] instead.
+#[derive(Clone, Debug)]
+pub struct AnySite {
+    /// The instruction the register was typed at, or [`InstIdx::Epilogue`] if
+    /// the register was narrowed upon branching.
+    pub inst_idx: InstIdx,
+    pub register: RegisterId<LiftedCtx>,
+    /// The registers that were used to produce the register, and their types.
+    pub operands: Vec<(RegisterId<LiftedCtx>, RegisterType)>,
+}
+
+/// An instruction that symbolic execution proved will fail at runtime, such as
+/// slicing a constant out of range. The function is typed as never returning
+/// past it, rather than aborting the analysis of the whole program.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub inst_idx: InstIdx,
+    pub message: String,
 }
 
 impl<'p> Worker for SymbWorker<'p> {
@@ -63,11 +108,18 @@ impl<'p> Worker for SymbWorker<'p> {
     type Result = WorkerResults;
 
     fn work(&mut self, system: &impl System<Self>) -> Computation<Self::Result> {
+        let mut diverges_at = None;
+
         for (inst_idx, inst) in self.func.instructions.iter().enumerate() {
             self.inst_on = CurrentInstruction::Sequential(inst);
             self.exec_inst(inst, InstIdx::Inst(inst_idx), system);
 
+            if self.strict {
+                self.record_any_site(inst, InstIdx::Inst(inst_idx));
+            }
+
             if self.never_infected {
+                diverges_at = Some(inst_idx);
                 break;
             }
         }
@@ -75,7 +127,7 @@ impl<'p> Worker for SymbWorker<'p> {
         self.inst_on = CurrentInstruction::ControlFlow(&self.func.end);
 
         let return_type = if self.never_infected {
-            // TODO: write "unreachable" instruction
+            // the backend emits functions which never return as a trapping stub
             ReturnType::Never
         } else {
             let last_inst = Some(InstIdx::from_inst_len(self.func.instructions.len()));
@@ -91,33 +143,116 @@ impl<'p> Worker for SymbWorker<'p> {
                     RegisterType::Bool(false) => {
                         self.exec_types(system, i.other.0, &i.other.1, inst_idx, last_inst)
                     }
-                    RegisterType::Boolean => todo!("cannot handle divergence atm"),
-                    r => unimplemented!("cannot use non-boolean register as conditional {:?}", r),
+                    RegisterType::Boolean => {
+                        let (if_so, other) = self.narrow_condition(i.condition);
+
+                        let exec_arm = |worker: &mut Self,
+                                        jump: &BlockJump<FunctionId<LiftedCtx>, LiftedCtx>,
+                                        narrowed: Narrowed| {
+                            let (fn_id, args) = (jump.0, &jump.1);
+                            worker.exec_types_narrowed(
+                                system, fn_id, args, &narrowed, inst_idx, last_inst,
+                            )
+                        };
+
+                        match (if_so, other) {
+                            (Some(if_so), Some(other)) => {
+                                // only one arm is taken at runtime, so the facts
+                                // each arm records about records are merged
+                                // rather than applied one after another
+                                let checkpoint = self.types.checkpoint_facts();
+
+                                let if_so = exec_arm(self, &i.if_so, if_so);
+                                let if_so_facts = self.types.take_facts_since(&checkpoint);
+
+                                let other = exec_arm(self, &i.other, other);
+                                let other_facts = self.types.take_facts_since(&checkpoint);
+
+                                match (&if_so, &other) {
+                                    (ReturnType::Never, _) => self.types.restore_facts(other_facts),
+                                    (_, ReturnType::Never) => self.types.restore_facts(if_so_facts),
+                                    _ => (self.types).merge_branch_facts(
+                                        if_so_facts,
+                                        other_facts,
+                                        inst_idx,
+                                    ),
+                                }
+
+                                self.join_returns(if_so, other)
+                            }
+                            (Some(narrowed), None) => exec_arm(self, &i.if_so, narrowed),
+                            (None, Some(narrowed)) => exec_arm(self, &i.other, narrowed),
+                            (None, None) => unreachable!("a value is always of some kind"),
+                        }
+                    }
+                    r => unimplemented!("cannot use non-boolean register as conditional {}", r),
                 },
-                crate::lifted::EndInstruction::Return(i) => match i.0 {
-                    Some(r) => ReturnType::Value(self.types.get(r)),
-                    None => ReturnType::Void,
+                crate::lifted::EndInstruction::Return(i) => match i.0.as_slice() {
+                    [] => ReturnType::Void,
+                    [r] => ReturnType::Value(self.types.get(*r)),
+                    rs => ReturnType::Tuple(rs.iter().map(|r| self.types.get(*r)).collect()),
                 },
             }
         };
 
         self.inst_on = CurrentInstruction::Completed;
-        self.return_type = return_type;
-
-        // this is only here temporarily
-        assert_ne!(return_type, ReturnType::Never, "why is ret typ never");
+        self.return_type = return_type.clone();
 
         Computation::Result(WorkerResults {
             id: self.id,
             lifted_id: self.lifted_id,
             is_entry_fn: self.is_entry_fn,
             return_type,
+            diverges_at,
             types: self.types.clone(),
+            any_sites: self.any_sites.clone(),
+            diagnostics: self.diagnostics.clone(),
         })
     }
 }
 
 impl SymbWorker<'_> {
+    fn allocate_record_id(&mut self, inst_idx: InstIdx) -> UniqueRecordId<SymbolicCtx> {
+        let unique_id = self.unique_allocation_id.next();
+
+        let site = AllocationSite {
+            function: self.lifted_id,
+            inst_idx,
+        };
+        self.types.record_allocation_site(unique_id, site);
+
+        unique_id
+    }
+
+    /// Reports that the instruction at `inst_idx` always fails, so nothing
+    /// after it is ever executed.
+    fn diagnose(&mut self, inst_idx: InstIdx, message: String) {
+        self.diagnostics.push(Diagnostic { inst_idx, message });
+        self.never_infected = true;
+    }
+
+    /// Records an [`AnySite`] if `inst` produced a value typed as `Any`.
+    fn record_any_site(&mut self, inst: &ir::Instruction<LiftedCtx, LiftedCtx>, inst_idx: InstIdx) {
+        let register = match inst.assigned_to() {
+            Some(register) => register,
+            None => return,
+        };
+
+        if self.types.try_get(register) != Some(RegisterType::Any) {
+            return;
+        }
+
+        let operands = (inst.used_registers().into_iter())
+            .map(|register| (register, self.types.get(register)))
+            .collect();
+
+        self.any_sites.push(AnySite {
+            inst_idx,
+            register,
+            operands,
+        });
+    }
+
     fn exec_inst(
         &mut self,
         inst: &ir::Instruction<LiftedCtx, LiftedCtx>,
@@ -126,13 +261,41 @@ impl SymbWorker<'_> {
     ) {
         match &inst.data {
             ir::InstructionData::Comment(_) => {}
-            ir::InstructionData::NewRecord(i) => self
-                .types
-                .new_record(i.result, self.unique_allocation_id.next()),
+            ir::InstructionData::NewRecord(i) => {
+                let unique_id = self.allocate_record_id(inst_idx);
+                self.types.new_record(i.result, unique_id)
+            }
+            ir::InstructionData::RecordClone(i) => {
+                let unique_id = self.allocate_record_id(inst_idx);
+                self.types.record_clone(i.result, i.record, unique_id)
+            }
+            ir::InstructionData::RecordEntries(i) => {
+                let (entries, exact) = self.types.record_entries(i.record);
+
+                self.types.new_list(i.result);
+                for (key, value) in entries {
+                    let unique_id = self.allocate_record_id(inst_idx);
+                    let pair = self.types.new_pair(key, value, unique_id, inst_idx);
+                    self.types.list_push(i.result, pair);
+                }
+
+                if !exact {
+                    self.types.list_forget_len(i.result);
+                }
+            }
             ir::InstructionData::RecordGet(i) => {
                 let field_typ = self.types.record_get_field(i.record, i.key);
                 self.types.assign_type(i.result, field_typ);
             }
+            ir::InstructionData::RecordGetOr(i) => {
+                let default = self.types.get(i.default);
+                let field_typ = self.types.record_get_field_or(i.record, i.key, default);
+                self.types.assign_type(i.result, field_typ);
+            }
+            ir::InstructionData::RecordGetInherited(i) => {
+                let field_typ = self.types.record_get_field_inherited(i.record, i.key);
+                self.types.assign_type(i.result, field_typ);
+            }
             ir::InstructionData::RecordSet(i) => {
                 let value = i.value.map(|value| self.types.get(value));
 
@@ -158,7 +321,12 @@ impl SymbWorker<'_> {
                 };
             }
             &ir::InstructionData::GetFnPtr(i) => self.make(i, RegisterType::FnPtr),
-            &ir::InstructionData::MakeAtom(i) => todo!(),
+            ir::InstructionData::GetFnPtrDynamic(i) => {
+                // the function is looked up by name at runtime, so we can't know
+                // which function is being pointed to
+                self.types.assign_type(i.result, RegisterType::AnyFnPtr);
+            }
+            &ir::InstructionData::MakeAtom(i) => self.make(i, RegisterType::Atom),
             &ir::InstructionData::MakeInteger(i) => self.make(i, RegisterType::Int),
             &ir::InstructionData::MakeBoolean(i) => self.make(i, RegisterType::Bool),
             ir::InstructionData::MakeBytes(i) => {
@@ -169,13 +337,12 @@ impl SymbWorker<'_> {
             ir::InstructionData::BinOp(i) => {
                 let (lhs, rhs) = (self.types.get(i.lhs), self.types.get(i.rhs));
 
-                todo!()
-                // let res_typ =
-                //     i.op.make_executor(&mut self.types)
-                //         .execute(lhs, rhs)
-                //         .expect("expected binary operator to perform");
+                let res_typ =
+                    i.op.make_executor(&mut self.types)
+                        .execute(lhs, rhs)
+                        .expect("expected binary operator to perform");
 
-                // self.types.assign_type(i.result, res_typ);
+                self.types.assign_type(i.result, res_typ);
             }
             ir::InstructionData::Negate(i) => {
                 let o = self.types.get(i.operand);
@@ -183,22 +350,71 @@ impl SymbWorker<'_> {
                 let res_typ = match o {
                     RegisterType::Boolean => RegisterType::Boolean,
                     RegisterType::Bool(b) => RegisterType::Bool(!b),
-                    o => panic!("cannot negate for {:?}", o),
+                    o => panic!("cannot negate for {}", o),
                 };
 
                 self.types.assign_type(i.result, res_typ);
             }
-            ir::InstructionData::CallVirt(i) => {
-                let fn_id = self.types.get_fnptr(i.calling);
-                self.call_fn(system, i.result, fn_id, &i.args, inst_idx);
-            }
+            ir::InstructionData::CallVirt(i) => match self.types.get(i.calling) {
+                RegisterType::FnPtr(fn_id) => {
+                    self.call_fn(system, i.result, fn_id, &i.args, inst_idx);
+                }
+                // we can't devirtualize a call to an unknown function, so the
+                // result could be anything
+                RegisterType::AnyFnPtr => {
+                    if let Some(result) = i.result {
+                        self.types.assign_type(result, RegisterType::Any);
+                    }
+                }
+                r => panic!("cannot call non-fnptr register {}", r),
+            },
             ir::InstructionData::CallStatic(i) => {
                 self.call_fn(system, i.result, i.calling, &i.args, inst_idx)
             }
+            ir::InstructionData::CallSpread(i) => {
+                let return_type = match self.opaque_functions.get(&i.calling) {
+                    Some(return_type) => Some(return_type.clone()),
+                    None => self.exec_spread(system, i.calling, i.args, inst_idx),
+                };
+
+                match return_type {
+                    Some(return_type) => self.assign_return(i.result, return_type),
+                    // without knowing how many arguments are passed, we can't
+                    // explore the function, so the result could be anything
+                    None => {
+                        if let Some(result) = i.result {
+                            self.types.assign_type(result, RegisterType::Any);
+                        }
+                    }
+                }
+            }
             ir::InstructionData::CallExtern(i) => {
-                // TODO: ensure/make args are coercible into `fn_id`,
-                // although the `assembler` phase does this for us as of the time of writing
-                let ext_fn = self.program.external_functions.get(&i.calling).unwrap();
+                let program = self.program;
+                let ext_fn = program.external_functions.get(&i.calling).unwrap();
+
+                if i.args.len() != ext_fn.parameters.len() {
+                    let message = format!(
+                        "`{}` takes {} arguments but was called with {}",
+                        ext_fn.name,
+                        ext_fn.parameters.len(),
+                        i.args.len()
+                    );
+                    self.diagnose(inst_idx, message);
+                    return;
+                }
+
+                for (arg, parameter) in i.args.iter().strict_zip(ext_fn.parameters.iter()) {
+                    let typ = self.types.get(*arg);
+
+                    if !self.types.can_coerce_to(typ, parameter) {
+                        let message = format!(
+                            "cannot coerce {} into {:?} when calling `{}`",
+                            typ, parameter, ext_fn.name
+                        );
+                        self.diagnose(inst_idx, message);
+                        return;
+                    }
+                }
 
                 match (i.result, &ext_fn.return_type) {
                     (Some(_), Returns::Void) => panic!("cannot assign `void` to register"),
@@ -212,12 +428,165 @@ impl SymbWorker<'_> {
                 todo!("generalization algorithm");
             }
             ir::InstructionData::Assert(_) => todo!(),
-            ir::InstructionData::IsType(_) => todo!(),
-            ir::InstructionData::NewList(_) => todo!(),
+            ir::InstructionData::IsType(i) => {
+                let value = self.types.get(i.value);
+
+                let is_type = match i.kind {
+                    CompareType::Register(other) => {
+                        self.types.is_same_type(value, self.types.get(other))
+                    }
+                    CompareType::Kind(kind) => value.value_type().map(|value| value == kind),
+                };
+
+                let res_typ = match is_type {
+                    Some(is_type) => RegisterType::Bool(is_type),
+                    None => RegisterType::Boolean,
+                };
+
+                self.types.assign_type(i.result, res_typ);
+            }
+            ir::InstructionData::AssumeType(i) => {
+                let value = self.types.get(i.value);
+
+                // the frontend is trusted, so no check is performed. whatever
+                // is known about the value being of the type is kept
+                let res_typ = match self.types.narrow_to_kind(value, i.kind) {
+                    (Some(typ), _) => typ,
+                    (None, _) => {
+                        let message = format!("cannot assume {} is of type {}", value, i.kind);
+                        self.diagnose(inst_idx, message);
+                        return;
+                    }
+                };
+
+                self.types.assign_type(i.result, res_typ);
+            }
+            ir::InstructionData::TypeOf(i) => {
+                let kind = self.types.get(i.value).value_type();
+
+                // if we know the type of the value, the name of the type is
+                // known too. otherwise, we only know it'll be some string
+                let res_typ = match kind {
+                    Some(kind) => {
+                        let c = self.types.intern_constant(kind.type_name().as_bytes());
+                        RegisterType::Byts(c)
+                    }
+                    None => RegisterType::Bytes,
+                };
+
+                self.types.assign_type(i.result, res_typ);
+            }
+            ir::InstructionData::BytesConcat(i) => {
+                let res_typ = match (self.types.get(i.lhs), self.types.get(i.rhs)) {
+                    (RegisterType::Byts(lhs), RegisterType::Byts(rhs)) => {
+                        let mut bytes = self.types.constant_bytes(lhs).to_vec();
+                        bytes.extend_from_slice(&self.types.constant_bytes(rhs));
+                        RegisterType::Byts(self.types.intern_constant(&bytes))
+                    }
+                    (
+                        RegisterType::Bytes | RegisterType::Byts(_),
+                        RegisterType::Bytes | RegisterType::Byts(_),
+                    ) => RegisterType::Bytes,
+                    (lhs, rhs) => panic!("cannot concatenate {} and {}", lhs, rhs),
+                };
+
+                self.types.assign_type(i.result, res_typ);
+            }
+            ir::InstructionData::BytesSlice(i) => {
+                let bytes = self.types.get(i.bytes);
+                let (start, end) = (self.types.get(i.start), self.types.get(i.end));
+
+                let res_typ = match (bytes, start, end) {
+                    (RegisterType::Byts(c), RegisterType::Int(start), RegisterType::Int(end)) => {
+                        let payload = self.types.constant_bytes(c);
+
+                        let slice = (usize::try_from(start).ok())
+                            .zip(usize::try_from(end).ok())
+                            .and_then(|(start, end)| payload.get(start..end));
+
+                        match slice {
+                            Some(slice) => RegisterType::Byts(self.types.intern_constant(slice)),
+                            None => {
+                                let message = format!(
+                                    "slice {}..{} is out of range for {} of length {}",
+                                    start,
+                                    end,
+                                    bytes,
+                                    payload.len()
+                                );
+                                self.diagnose(inst_idx, message);
+                                return;
+                            }
+                        }
+                    }
+                    (
+                        RegisterType::Bytes | RegisterType::Byts(_),
+                        RegisterType::Number | RegisterType::Int(_),
+                        RegisterType::Number | RegisterType::Int(_),
+                    ) => RegisterType::Bytes,
+                    (bytes, start, end) => {
+                        panic!("cannot slice {} from {} to {}", bytes, start, end)
+                    }
+                };
+
+                self.types.assign_type(i.result, res_typ);
+            }
+            ir::InstructionData::NewList(i) => self.types.new_list(i.result),
             ir::InstructionData::ListGet(_) => todo!(),
             ir::InstructionData::ListSet(_) => todo!(),
             ir::InstructionData::ListHasKey(_) => todo!(),
-            ir::InstructionData::ListLen(_) => todo!(),
+            ir::InstructionData::ListLen(i) => {
+                let len = self.types.list_len(i.list);
+                self.types.assign_type(i.result, len);
+            }
+            ir::InstructionData::ListPush(i) => {
+                let value = self.types.get(i.value);
+                self.types.list_push(i.list, value);
+            }
+            ir::InstructionData::ListPop(i) => {
+                let element = match self.types.list_pop(i.list) {
+                    Some(element) => element,
+                    None => {
+                        let message = "popped from a list that is always empty".to_string();
+                        self.diagnose(inst_idx, message);
+                        return;
+                    }
+                };
+
+                self.types.assign_type(i.result, element);
+            }
+            ir::InstructionData::ListMap(i) => {
+                let fn_id = match self.types.get(i.function) {
+                    RegisterType::FnPtr(fn_id) => Some(fn_id),
+                    RegisterType::AnyFnPtr => None,
+                    r => panic!("cannot call non-fnptr register {}", r),
+                };
+
+                let return_type = match (fn_id, self.types.list_element(i.list)) {
+                    // nothing has ever been in the list, so nothing is called
+                    (_, None) => None,
+                    (Some(fn_id), Some(_)) => Some(match self.opaque_functions.get(&fn_id) {
+                        Some(return_type) => return_type.clone(),
+                        None => self.exec_elements(system, fn_id, &i.args, i.list, inst_idx),
+                    }),
+                    // we can't devirtualize a call to an unknown function, so
+                    // the results could be anything
+                    (None, Some(_)) => Some(ReturnType::Value(RegisterType::Any)),
+                };
+
+                match (i.result, return_type) {
+                    (_, Some(ReturnType::Never)) => self.never_infected = true,
+                    (None, _) => {}
+                    (Some(result), None) => self.types.list_map(result, i.list, None),
+                    (Some(result), Some(ReturnType::Value(element))) => {
+                        self.types.list_map(result, i.list, Some(element))
+                    }
+                    (Some(_), Some(return_type)) => {
+                        let message = format!("cannot map elements of a list to {:?}", return_type);
+                        self.diagnose(inst_idx, message);
+                    }
+                }
+            }
             _ => todo!(),
         };
     }
@@ -234,14 +603,84 @@ impl SymbWorker<'_> {
         args: &[RegisterId<LiftedCtx>],
         inst_idx: InstIdx,
     ) {
-        let return_type = self.exec_types(system, fn_id, args, inst_idx, None);
+        let return_type = match self.opaque_functions.get(&fn_id) {
+            Some(return_type) => return_type.clone(),
+            None => self.exec_types(system, fn_id, args, inst_idx, None),
+        };
+
+        self.assign_return(result, return_type);
+    }
+
+    /// If `condition` is the result of checking the kind of a value, the value
+    /// is narrowed for each arm of a branch on the condition. Produces the
+    /// narrowed registers for when the condition holds and when it doesn't,
+    /// where `None` means that arm can never be taken.
+    fn narrow_condition(
+        &mut self,
+        condition: RegisterId<LiftedCtx>,
+    ) -> (Option<Narrowed>, Option<Narrowed>) {
+        let is_type = (self.func.instructions.iter()).find_map(|inst| match &inst.data {
+            ir::InstructionData::IsType(i) if i.result == condition => Some(i),
+            _ => None,
+        });
+
+        let (value, kind) = match is_type.map(|i| (i.value, i.kind)) {
+            Some((value, CompareType::Kind(kind))) => (value, kind),
+            _ => return (Some(Vec::new()), Some(Vec::new())),
+        };
 
+        let typ = self.types.get(value);
+        let (is_kind, isnt_kind) = self.types.narrow_to_kind(typ, kind);
+
+        let narrowed_to_any = [is_kind, isnt_kind].contains(&Some(RegisterType::Any));
+        if self.strict && narrowed_to_any {
+            self.any_sites.push(AnySite {
+                inst_idx: InstIdx::Epilogue,
+                register: value,
+                operands: vec![(value, typ)],
+            });
+        }
+
+        let narrow = |typ| vec![(value, typ)];
+        (is_kind.map(narrow), isnt_kind.map(narrow))
+    }
+
+    /// Combines the return types of both arms of a branch.
+    fn join_returns(&mut self, a: ReturnType, b: ReturnType) -> ReturnType {
+        match (a, b) {
+            (ReturnType::Never, other) | (other, ReturnType::Never) => other,
+            (ReturnType::Void, ReturnType::Void) => ReturnType::Void,
+            (ReturnType::Value(a), ReturnType::Value(b)) => {
+                ReturnType::Value(self.types.union_of(a, b))
+            }
+            // the value is only present when one of the arms is taken, so nothing
+            // more is known about it than that it's some value
+            (ReturnType::Void, ReturnType::Value(_)) | (ReturnType::Value(_), ReturnType::Void) => {
+                ReturnType::Value(RegisterType::Any)
+            }
+            (ReturnType::Tuple(a), ReturnType::Tuple(b)) if a.len() == b.len() => {
+                ReturnType::Tuple(
+                    (a.into_iter().strict_zip(b))
+                        .map(|(a, b)| self.types.union_of(a, b))
+                        .collect(),
+                )
+            }
+            (a, b) => {
+                let message = format!("arms of a branch return incompatible types {:?} {:?}", a, b);
+                self.diagnose(InstIdx::Epilogue, message);
+                ReturnType::Never
+            }
+        }
+    }
+
+    fn assign_return(&mut self, result: Option<RegisterId<LiftedCtx>>, return_type: ReturnType) {
         match (result, return_type) {
             (_, ReturnType::Never) => {
                 self.never_infected = true;
             }
             (None, ReturnType::Void) => {}
             (None, ReturnType::Value(_)) => {}
+            (None, ReturnType::Tuple(_)) => {}
             (Some(r), ReturnType::Value(t)) => {
                 self.types.assign_type(r, t);
             }
@@ -259,6 +698,20 @@ impl SymbWorker<'_> {
         fn_args: &[RegisterId<LiftedCtx>],
         inst_idx: InstIdx,
         prev: Option<InstIdx>,
+    ) -> ReturnType {
+        self.exec_types_narrowed(system, fn_id, fn_args, &[], inst_idx, prev)
+    }
+
+    /// Like [`SymbWorker::exec_types`], but the registers in `narrowed` are
+    /// passed with the narrowed type rather than their own.
+    fn exec_types_narrowed(
+        &mut self,
+        system: &impl System<Self>,
+        fn_id: FunctionId<LiftedCtx>,
+        fn_args: &[RegisterId<LiftedCtx>],
+        narrowed: &[(RegisterId<LiftedCtx>, RegisterType)],
+        inst_idx: InstIdx,
+        prev: Option<InstIdx>,
     ) -> ReturnType {
         let target_fn = self.program.functions.get(&fn_id).unwrap();
         debug_assert_eq!(
@@ -269,11 +722,83 @@ impl SymbWorker<'_> {
 
         let prev = prev.unwrap_or(inst_idx);
 
-        let mut subset = self.types.subset(fn_args, &target_fn.parameters, prev);
+        let mut subset =
+            (self.types).subset_narrowed(fn_args, &target_fn.parameters, narrowed, prev);
+
+        let growing = growing_parameters(system, &self.fn_ids, fn_id, target_fn, &subset.child());
+        if !growing.is_empty() {
+            drop(subset);
+
+            let mut widened = narrowed.to_vec();
+            widened.extend(
+                growing
+                    .into_iter()
+                    .map(|idx| (fn_args[idx], RegisterType::Number)),
+            );
+
+            subset = (self.types).subset_narrowed(fn_args, &target_fn.parameters, &widened, prev);
+        }
+
+        let target_id = self.fn_ids.id_of(fn_id, subset.child(), false);
+
+        // calling a function with the exact same arguments as one that hasn't
+        // finished executing yet will only ever recurse forever. arguments that
+        // aren't exact, such as widened ones, may differ at runtime and let the
+        // recursion end, so nothing is known about what the call returns
+        if system.callstack().contains(&target_id) {
+            let child = subset.child();
+            let exact = (target_fn.parameters.iter()).all(|reg| child.get(*reg).is_exact());
+
+            return match exact {
+                true => ReturnType::Never,
+                false => unknown_return(self.program, fn_id),
+            };
+        }
+
+        let results = system.spawn(target_id);
+
+        target_fn
+            .parameters
+            .iter()
+            .for_each(|reg| subset.update_reg(&results.types, *reg, inst_idx, InstIdx::Epilogue));
+
+        results
+            .return_type
+            .clone()
+            .map(|v| subset.update_typ(&results.types, v, inst_idx, InstIdx::Epilogue))
+    }
+
+    /// Like [`SymbWorker::exec_types`], but with an element of the list `list`
+    /// passed after `fn_args`.
+    fn exec_elements(
+        &mut self,
+        system: &impl System<Self>,
+        fn_id: FunctionId<LiftedCtx>,
+        fn_args: &[RegisterId<LiftedCtx>],
+        list: RegisterId<LiftedCtx>,
+        inst_idx: InstIdx,
+    ) -> ReturnType {
+        let program = self.program;
+        let target_fn = program.functions.get(&fn_id).unwrap();
+
+        if target_fn.parameters.len() != fn_args.len() + 1 {
+            let message = format!(
+                "mapped over a list with a function taking {} arguments rather than {}",
+                target_fn.parameters.len(),
+                fn_args.len() + 1
+            );
+            self.diagnose(inst_idx, message);
+            return ReturnType::Never;
+        }
+
+        let mut subset =
+            (self.types).subset_elements(fn_args, list, &target_fn.parameters, inst_idx);
 
         let target_id = self.fn_ids.id_of(fn_id, subset.child(), false);
         let results = system.spawn(target_id);
 
+        // the element isn't a register in this function, but records it refers
+        // to may have been changed
         target_fn
             .parameters
             .iter()
@@ -281,6 +806,120 @@ impl SymbWorker<'_> {
 
         results
             .return_type
+            .clone()
             .map(|v| subset.update_typ(&results.types, v, inst_idx, InstIdx::Epilogue))
     }
+
+    /// Like [`SymbWorker::exec_types`], but with every element of the list
+    /// `fn_args` passed as an argument. Produces `None` if the length of the
+    /// list is not statically known.
+    fn exec_spread(
+        &mut self,
+        system: &impl System<Self>,
+        fn_id: FunctionId<LiftedCtx>,
+        fn_args: RegisterId<LiftedCtx>,
+        inst_idx: InstIdx,
+    ) -> Option<ReturnType> {
+        let program = self.program;
+        let target_fn = program.functions.get(&fn_id).unwrap();
+
+        if let RegisterType::Int(len) = self.types.list_len(fn_args) {
+            if len as usize != target_fn.parameters.len() {
+                let message = format!(
+                    "spread {} arguments into a function taking {}",
+                    len,
+                    target_fn.parameters.len()
+                );
+                self.diagnose(inst_idx, message);
+                return Some(ReturnType::Never);
+            }
+        }
+
+        let mut subset = (self.types).subset_spread(fn_args, &target_fn.parameters, inst_idx)?;
+
+        let target_id = self.fn_ids.id_of(fn_id, subset.child(), false);
+        let results = system.spawn(target_id);
+
+        // the elements of the list aren't registers in this function, but
+        // records they refer to may have been changed
+        target_fn
+            .parameters
+            .iter()
+            .for_each(|reg| subset.update_reg(&results.types, *reg, inst_idx, InstIdx::Epilogue));
+
+        let return_type = results
+            .return_type
+            .clone()
+            .map(|v| subset.update_typ(&results.types, v, inst_idx, InstIdx::Epilogue));
+
+        Some(return_type)
+    }
+}
+
+/// The most that is known about what a function returns without executing it:
+/// a value of any type for every value its first reachable `ret` returns.
+fn unknown_return(program: &LiftedProgram, fn_id: FunctionId<LiftedCtx>) -> ReturnType {
+    let mut visited = FxHashSet::default();
+    let mut to_visit = vec![fn_id];
+
+    while let Some(fn_id) = to_visit.pop() {
+        if !visited.insert(fn_id) {
+            continue;
+        }
+
+        match &program.functions.get(&fn_id).unwrap().end {
+            lifted::EndInstruction::Return(i) => {
+                return match i.0.len() {
+                    0 => ReturnType::Void,
+                    1 => ReturnType::Value(RegisterType::Any),
+                    n => ReturnType::Tuple(vec![RegisterType::Any; n]),
+                }
+            }
+            end => to_visit.extend(end.paths().into_iter().map(|path| path.0)),
+        }
+    }
+
+    ReturnType::Never
+}
+
+/// Finds the parameters of `target_fn` that were passed a strictly growing
+/// integer on each of the last [`GROWTH_LIMIT`] calls to it that are still in
+/// progress. Recursion like this is unlikely to stop at any one integer, so
+/// exploring it would only end at the depth limit. Parameters marked as exact
+/// are never considered to be growing.
+fn growing_parameters(
+    system: &impl System<SymbWorker<'_>>,
+    fn_ids: &UniqueFnIdShared,
+    fn_id: FunctionId<LiftedCtx>,
+    target_fn: &Function,
+    child: &TypeBag,
+) -> Vec<usize> {
+    let in_progress = (system.callstack().into_iter())
+        .map(|id| fn_ids.types_of(id))
+        .filter(|(lifted_id, _)| *lifted_id == fn_id)
+        .take(GROWTH_LIMIT)
+        .map(|(_, types)| types)
+        .collect::<Vec<_>>();
+
+    if in_progress.len() < GROWTH_LIMIT {
+        return Vec::new();
+    }
+
+    (target_fn.parameters.iter().enumerate())
+        .filter(|(_, parameter)| !target_fn.exact_parameters.contains(*parameter))
+        .filter(|(_, parameter)| {
+            let mut next = child.get(**parameter);
+
+            in_progress.iter().all(|types| {
+                let prev = types.get(**parameter);
+                let grew = match (prev, next) {
+                    (RegisterType::Int(prev), RegisterType::Int(next)) => prev < next,
+                    _ => false,
+                };
+                next = prev;
+                grew
+            })
+        })
+        .map(|(idx, _)| idx)
+        .collect()
 }
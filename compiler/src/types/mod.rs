@@ -19,7 +19,7 @@ use ordered_float::OrderedFloat;
 
 use crate::{
     id::{FunctionId, LiftedCtx, Tag, UnionId},
-    isa::Atom,
+    isa::{Atom, ValueType},
 };
 
 use derive_more::{Deref, DerefMut};
@@ -238,6 +238,20 @@ impl<'ctx1, 'ctx2, T: Tag> PartialEq<Type<'ctx2, T>> for Type<'ctx1, T> {
 }
 
 impl<'ctx, T: Tag> Type<'ctx, T> {
+    /// The kind of value this type is, if it is known.
+    pub fn value_type(&self) -> Option<ValueType> {
+        match self {
+            Type::Atom(_) => Some(ValueType::Atom),
+            Type::Bytes | Type::Byts(_) => Some(ValueType::Bytes),
+            Type::Number | Type::Int(_) | Type::Float(_) => Some(ValueType::Number),
+            Type::Boolean | Type::Bool(_) => Some(ValueType::Boolean),
+            Type::FnPtr(_) => Some(ValueType::FnPtr),
+            Type::Record(_) => Some(ValueType::Record),
+            Type::List(_) => Some(ValueType::List),
+            Type::Any | Type::Nothing | Type::Union(_) => None,
+        }
+    }
+
     pub fn try_into_fnptr(self) -> Option<FunctionId<LiftedCtx>> {
         match self {
             Type::FnPtr(f) => Some(f),
@@ -16,7 +16,7 @@ fn main() {
     e.comment("ok");
     let value = e.make_number_decimal(5);
     e.record_set_prop(a, b, value);
-    secondary.end_block(e.ret(Some(a)));
+    secondary.end_block(e.ret(&[a]));
 
     let secondary = builder.end_function(secondary);
 
@@ -29,7 +29,7 @@ fn main() {
     let f = e.make_fnptr(secondary.id);
     let c = e.call_virt_with_result(f, [a, b]);
     e.comment("lol");
-    main.end_block(e.ret(Some(c)));
+    main.end_block(e.ret(&[c]));
 
     builder.end_function(main);
 
@@ -8,10 +8,13 @@ fn criterion_benchmark(c: &mut Criterion) {
         b.iter(|| {
             ir_file::generate(
                 "ECMA262Methods",
+                "ECMA262Methods.lisp",
                 black_box(include_str!(
                     "../../jssat_frontend_js/src/ecmascript/ECMA262Methods.lisp"
                 )),
+                &[],
             )
+            .unwrap()
         })
     });
 }
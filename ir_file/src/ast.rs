@@ -1,11 +1,58 @@
-use lexpr::datum::Span;
+use lexpr::parse::Position;
+
+use crate::parser::grow_stack;
+
+/// A location in an IR file, along with the name of the file it's in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    filename: &'static str,
+    span: lexpr::datum::Span,
+}
+
+impl Span {
+    pub fn new(filename: &'static str, span: lexpr::datum::Span) -> Self {
+        Self { filename, span }
+    }
+
+    pub fn filename(&self) -> &'static str {
+        self.filename
+    }
+
+    pub fn start(&self) -> Position {
+        self.span.start()
+    }
+
+    pub fn end(&self) -> Position {
+        self.span.end()
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AST {
     pub source: String,
+    pub externs: Vec<ExternDecl>,
     pub sections: Vec<Section>,
 }
 
+/// A top-level `(extern name (param-types...) return-type)` form, declaring
+/// the signature of a function provided by the runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternDecl {
+    pub name: FnName,
+    pub parameters: Vec<FFIType>,
+    /// The return type, or `None` if the extern function returns `void`.
+    pub return_type: Option<FFIType>,
+    pub span: Span,
+}
+
+/// The type of a value passed across an extern boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FFIType {
+    Any,
+    Runtime,
+    String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Section {
     pub header: Header,
@@ -19,6 +66,12 @@ pub struct Header {
     pub document_index: String,
     pub method_name: FnName,
     pub parameters: Vec<Variable>,
+    /// Documentation attached to the section, written as either a leading
+    /// string or a `(doc "...")` form in the header.
+    pub doc: Option<String>,
+    /// The parameters named by a `(specialize-on ...)` form in the header,
+    /// which are kept exact rather than generalized during symbolic execution.
+    pub specialize_on: Vec<Variable>,
     pub span: Span,
 }
 
@@ -39,6 +92,15 @@ pub struct Statement {
     pub data: StatementData,
 }
 
+impl Drop for Statement {
+    fn drop(&mut self) {
+        // the drop glue recurses over how deeply statements are nested, so the
+        // stack may need to grow to drop them
+        let data = std::mem::replace(&mut self.data, StatementData::Continue);
+        grow_stack(move || drop(data));
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StatementData {
     Assign(Assign),
@@ -65,6 +127,17 @@ pub enum StatementData {
         prop: Expression,
         value: Option<Expression>,
     },
+    ListPush {
+        list: Expression,
+        value: Expression,
+    },
+    /// Calls `function` with every element of `list`, passing `args` before
+    /// the element.
+    ListForEach {
+        list: Expression,
+        function: Expression,
+        args: Vec<Expression>,
+    },
     Return {
         expr: Option<Expression>,
     },
@@ -79,8 +152,11 @@ pub enum StatementData {
         function_name: FnName,
         args: Vec<Expression>,
     },
-    // TODO(isa): implement calling external functions
-    // CallExternal {},
+    /// Calls a function declared with a top-level `extern` form.
+    CallExternal {
+        function_name: FnName,
+        args: Vec<Expression>,
+    },
     CallVirt {
         fn_ptr: Expression,
         args: Vec<Expression>,
@@ -95,6 +171,27 @@ pub enum StatementData {
         next: Vec<Assign>,
         body: Vec<Statement>,
     },
+    /// Exits the innermost loop. The value is only present, and must be
+    /// present, when breaking out of a loop used as an expression.
+    Break {
+        value: Option<Expression>,
+    },
+    /// Skips the rest of the innermost loop's body, moving on to its next
+    /// iteration.
+    Continue,
+    /// Executes the body of the first case whose value is equal to the
+    /// discriminant, or the `else` body if no case matches.
+    Match {
+        discriminant: Expression,
+        cases: Vec<MatchCase>,
+        r#else: Option<Vec<Statement>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchCase {
+    pub value: Expression,
+    pub body: Vec<Statement>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -103,6 +200,15 @@ pub struct Expression {
     pub data: ExpressionData,
 }
 
+impl Drop for Expression {
+    fn drop(&mut self) {
+        // the drop glue recurses over how deeply expressions are nested, so the
+        // stack may need to grow to drop them
+        let data = std::mem::replace(&mut self.data, ExpressionData::Unreachable);
+        grow_stack(move || drop(data));
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SlotOrExpr {
     Slot(Slot),
@@ -132,7 +238,25 @@ pub enum ExpressionData {
         r#in: (Vec<Statement>, Box<Expression>),
     },
     Unreachable,
+    /// A loop which produces a value. The value is carried by a `break` out of
+    /// the loop, or is `else` if the loop ends because `cond` is false.
+    Loop {
+        init: Vec<Assign>,
+        cond: Box<Expression>,
+        next: Vec<Assign>,
+        body: Vec<Statement>,
+        r#else: Box<Expression>,
+    },
     RecordNew,
+    /// Makes a shallow copy of a record, with a new identity.
+    RecordClone {
+        record: Box<Expression>,
+    },
+    /// A list of `[key, value]` pairs for every field of a record, in the order
+    /// the fields were inserted.
+    RecordEntries {
+        record: Box<Expression>,
+    },
     RecordGetProp {
         record: Box<Expression>,
         property: Box<Expression>,
@@ -141,6 +265,13 @@ pub enum ExpressionData {
         record: Box<Expression>,
         slot: Slot,
     },
+    /// Gets an internal slot of a record, or `default` if the record does not
+    /// have the slot.
+    RecordGetSlotOr {
+        record: Box<Expression>,
+        slot: Slot,
+        default: Box<Expression>,
+    },
     RecordHasProp {
         record: Box<Expression>,
         property: Box<Expression>,
@@ -161,17 +292,35 @@ pub enum ExpressionData {
     ListLen {
         list: Box<Expression>,
     },
+    ListPop {
+        list: Box<Expression>,
+    },
+    /// Produces a new list made of the result of calling `function` with
+    /// every element of `list`, passing `args` before the element.
+    ListMap {
+        list: Box<Expression>,
+        function: Box<Expression>,
+        args: Vec<Expression>,
+    },
     GetFnPtr {
         function_name: FnName,
     },
+    /// Looks up a function by the name held in `name` at runtime, rather than
+    /// at parse time.
+    GetFnPtrDynamic {
+        name: Box<Expression>,
+    },
     /// A call as an expression is different from a call as a statement, because
     /// an expression call expects a value whereas statement call does not.
     CallStatic {
         function_name: FnName,
         args: Vec<Expression>,
     },
-    // TODO(isa): implement calling external functions
-    // CallExternal {},
+    /// Calls a function declared with a top-level `extern` form.
+    CallExternal {
+        function_name: FnName,
+        args: Vec<Expression>,
+    },
     CallVirt {
         fn_ptr: Box<Expression>,
         args: Vec<Expression>,
@@ -204,6 +353,27 @@ pub enum ExpressionData {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
     },
+    /// Re-types `expr` as `kind` without a runtime check, trusting that the
+    /// frontend knows the type.
+    AssumeType {
+        expr: Box<Expression>,
+        kind: String,
+    },
+    /// Produces a string with the name of the type of `expr`.
+    TypeOf {
+        expr: Box<Expression>,
+    },
+    BytesConcat {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+    /// Produces the bytes of `bytes` from `start` (inclusive) up until `end`
+    /// (exclusive).
+    BytesSlice {
+        bytes: Box<Expression>,
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -264,7 +434,7 @@ pub trait Visitor {
     }
 
     fn visit_stmt_impl(&mut self, stmt: &mut Statement) {
-        match &mut stmt.data {
+        grow_stack(|| match &mut stmt.data {
             StatementData::Assign(assign) => self.visit_assign(assign),
             StatementData::If {
                 condition,
@@ -298,6 +468,19 @@ pub trait Visitor {
                 self.visit_expr(prop);
                 self.visit_maybe_expr(value.as_mut());
             }
+            StatementData::ListPush { list, value } => {
+                self.visit_expr(list);
+                self.visit_expr(value);
+            }
+            StatementData::ListForEach {
+                list,
+                function,
+                args,
+            } => {
+                self.visit_expr(list);
+                self.visit_expr(function);
+                self.visit_exprs(args);
+            }
             StatementData::Return { expr } => {
                 self.visit_maybe_expr(expr.as_mut());
             }
@@ -307,6 +490,12 @@ pub trait Visitor {
             } => {
                 self.visit_exprs(args);
             }
+            StatementData::CallExternal {
+                function_name: _,
+                args,
+            } => {
+                self.visit_exprs(args);
+            }
             StatementData::CallVirt { fn_ptr, args } => {
                 self.visit_expr(fn_ptr);
                 self.visit_exprs(args);
@@ -325,7 +514,23 @@ pub trait Visitor {
                 self.visit_assigns(next);
                 self.visit_stmts(body);
             }
-        }
+            StatementData::Match {
+                discriminant,
+                cases,
+                r#else,
+            } => {
+                self.visit_expr(discriminant);
+                for case in cases {
+                    self.visit_expr(&mut case.value);
+                    self.visit_stmts(&mut case.body);
+                }
+                self.visit_maybe_stmts(r#else.as_deref_mut());
+            }
+            StatementData::Break { value } => {
+                self.visit_maybe_expr(value.as_mut());
+            }
+            StatementData::Continue => {}
+        })
     }
 
     fn visit_assigns(&mut self, assigns: &mut [Assign]) {
@@ -366,7 +571,7 @@ pub trait Visitor {
     }
 
     fn visit_expr_impl(&mut self, expr: &mut Expression) {
-        match &mut expr.data {
+        grow_stack(|| match &mut expr.data {
             ExpressionData::If {
                 condition,
                 then: (then_stmts, then_expr),
@@ -387,6 +592,22 @@ pub trait Visitor {
                 self.visit_stmts(stmts);
                 self.visit_expr(expr);
             }
+            ExpressionData::Loop {
+                init,
+                cond,
+                next,
+                body,
+                r#else,
+            } => {
+                self.visit_assigns(init);
+                self.visit_expr(cond);
+                self.visit_assigns(next);
+                self.visit_stmts(body);
+                self.visit_expr(r#else);
+            }
+            ExpressionData::RecordClone { record } | ExpressionData::RecordEntries { record } => {
+                self.visit_expr(record);
+            }
             ExpressionData::RecordGetProp { record, property } => {
                 self.visit_expr(record);
                 self.visit_expr(property);
@@ -395,6 +616,15 @@ pub trait Visitor {
                 self.visit_expr(record);
                 self.visit_slot(slot);
             }
+            ExpressionData::RecordGetSlotOr {
+                record,
+                slot,
+                default,
+            } => {
+                self.visit_expr(record);
+                self.visit_slot(slot);
+                self.visit_expr(default);
+            }
             ExpressionData::RecordHasProp { record, property } => {
                 self.visit_expr(record);
                 self.visit_expr(property);
@@ -414,12 +644,33 @@ pub trait Visitor {
             ExpressionData::ListLen { list } => {
                 self.visit_expr(list);
             }
+            ExpressionData::ListPop { list } => {
+                self.visit_expr(list);
+            }
+            ExpressionData::ListMap {
+                list,
+                function,
+                args,
+            } => {
+                self.visit_expr(list);
+                self.visit_expr(function);
+                self.visit_exprs(args);
+            }
+            ExpressionData::GetFnPtrDynamic { name } => {
+                self.visit_expr(name);
+            }
             ExpressionData::CallStatic {
                 function_name: _,
                 args,
             } => {
                 self.visit_exprs(args);
             }
+            ExpressionData::CallExternal {
+                function_name: _,
+                args,
+            } => {
+                self.visit_exprs(args);
+            }
             ExpressionData::CallVirt { fn_ptr, args } => {
                 self.visit_expr(fn_ptr);
                 self.visit_exprs(args);
@@ -438,6 +689,21 @@ pub trait Visitor {
                 self.visit_expr(lhs);
                 self.visit_expr(rhs);
             }
+            ExpressionData::AssumeType { expr, kind: _ } => {
+                self.visit_expr(expr);
+            }
+            ExpressionData::TypeOf { expr } => {
+                self.visit_expr(expr);
+            }
+            ExpressionData::BytesConcat { lhs, rhs } => {
+                self.visit_expr(lhs);
+                self.visit_expr(rhs);
+            }
+            ExpressionData::BytesSlice { bytes, start, end } => {
+                self.visit_expr(bytes);
+                self.visit_expr(start);
+                self.visit_expr(end);
+            }
             ExpressionData::MakeAtom { atom } => {
                 self.visit_slot(atom);
             }
@@ -450,7 +716,7 @@ pub trait Visitor {
             | ExpressionData::Unreachable
             | ExpressionData::RecordNew
             | ExpressionData::ListNew => {}
-        }
+        })
     }
 
     fn visit_slot(&mut self, _slot: &mut String) {}
@@ -55,14 +55,17 @@ use std::{collections::HashMap, fmt::Display};
 
 use codegen::{Block, Field, Formatter, Function, Impl, Scope};
 use convert_case::{Case, Casing};
-use lexpr::{datum::Span, parse::Position};
+use lexpr::parse::Position;
 use rustc_hash::FxHashSet;
 
 use crate::{
-    Assign, Expression, ExpressionData, Section, SlotOrExpr, Statement, StatementData, Visitor, AST,
+    parser::grow_stack, Assign, BinOpKind, Expression, ExpressionData, FFIType, LowerError,
+    MatchCase, Section, SlotOrExpr, Span, Statement, StatementData, Visitor, AST,
 };
 
-pub fn gen(name: &str, mut ast: AST) -> String {
+pub fn gen(name: &str, mut ast: AST) -> Result<String, Vec<LowerError>> {
+    crate::lower_error::check(&mut ast)?;
+
     let mut scope = Scope::new();
 
     let r#struct = scope.new_struct(name);
@@ -71,6 +74,13 @@ pub fn gen(name: &str, mut ast: AST) -> String {
     r#struct.push_field(Field::new("pub atoms", format!("{}Atoms", name)));
     r#struct.push_field(Field::new("pub source_map", "SourceMap"));
 
+    for r#extern in ast.externs.iter() {
+        r#struct.push_field(Field::new(
+            &format!("pub {}", extern_name(&r#extern.name)),
+            "ExternalFunctionId",
+        ));
+    }
+
     for method in ast.sections.iter() {
         r#struct.push_field(Field::new(
             &format!("pub {}", method.header.method_name.replace(':', "_")),
@@ -113,14 +123,15 @@ pub fn gen(name: &str, mut ast: AST) -> String {
     let f = emit_method_new(name, &ast);
     r#impl.push_fn(f);
 
-    format!(
+    Ok(format!(
         "#![allow(non_snake_case)]
 #![allow(unused_variables)]
 
 use jssat_ir::{{
     frontend::{{
-        builder::{{FnSignature, RegisterId, ProgramBuilder}},
+        builder::{{ExternalFunctionId, FnSignature, RegisterId, ProgramBuilder}},
         emitter::{{ControlFlow, Emitter, LoopControlFlow}},
+        ir::{{FFIValueType, Returns}},
         source_map::{{SourceMap, SourceSpan, SourcePos}},
     }},
     isa::{{Atom, AtomDealer, ValueType}},
@@ -128,10 +139,22 @@ use jssat_ir::{{
 
 {}",
         scope.to_string()
-    )
+    ))
+}
+
+fn extern_name(name: &str) -> String {
+    format!("extern_{}", name.replace(['-', ':'], "_"))
 }
 
-fn atom_name(name: &str) -> String {
+fn ffi_type(r#type: FFIType) -> &'static str {
+    match r#type {
+        FFIType::Any => "FFIValueType::Any",
+        FFIType::Runtime => "FFIValueType::Runtime",
+        FFIType::String => "FFIValueType::String",
+    }
+}
+
+pub(crate) fn atom_name(name: &str) -> String {
     if name.contains('_') {
         return name
             .split('_')
@@ -160,6 +183,26 @@ fn emit_method_new(name: &str, ast: &AST) -> Function {
         ast.source
     ));
 
+    for r#extern in ast.externs.iter() {
+        let parameters = (r#extern.parameters.iter())
+            .map(|p| ffi_type(*p))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let return_type = match r#extern.return_type {
+            Some(r#type) => format!("Returns::Value({})", ffi_type(r#type)),
+            None => "Returns::Void".to_string(),
+        };
+
+        f.line(format!(
+            "let {} = program.external_function_dynargs({:?}, vec![{}], {});",
+            extern_name(&r#extern.name),
+            r#extern.name,
+            parameters,
+            return_type
+        ));
+    }
+
     for method in ast.sections.iter() {
         let fn_name = &method.header.method_name.replace(':', "_");
 
@@ -173,6 +216,14 @@ fn emit_method_new(name: &str, ast: &AST) -> Function {
             fn_name, method.header.method_name,
         ));
 
+        for name in method.header.specialize_on.iter() {
+            let idx = (method.header.parameters.iter())
+                .position(|p| p == name)
+                .unwrap();
+
+            f.line(format!("{}.specialize_on({});", fn_name, idx));
+        }
+
         f.line(format!("let signature_{0} = {0}.signature();", fn_name));
     }
 
@@ -180,6 +231,10 @@ fn emit_method_new(name: &str, ast: &AST) -> Function {
     fields.line(format!("atoms: {}Atoms::new(&mut program.dealer),", name));
     fields.line("source_map,");
 
+    for r#extern in ast.externs.iter() {
+        fields.line(format!("{},", extern_name(&r#extern.name)));
+    }
+
     for method in ast.sections.iter() {
         fields.line(format!(
             "{}: signature_{0},",
@@ -247,6 +302,10 @@ fn begin(span: Span) -> String {
 pub fn emit_method(scope: &mut Impl, section: &Section) {
     let mut f = Function::new(&section.header.method_name.replace(':', "_"));
 
+    if let Some(doc) = &section.header.doc {
+        f.doc(doc);
+    }
+
     f.arg_ref_self();
 
     let params = &section.header.parameters;
@@ -300,7 +359,7 @@ pub fn emit_method(scope: &mut Impl, section: &Section) {
         "source_map.begin({});",
         WriteSpan(section.body_span)
     ));
-    emit_stmts(&mut counter, &mut code, stmts_to_emit, false, false);
+    emit_stmts(&mut counter, &mut code, stmts_to_emit, false, false, None);
 
     match ret_expr {
         Some(Some(expr)) => {
@@ -328,6 +387,18 @@ fn emit_stmts(
     stmts: &[Statement],
     emit_fallthrough: bool,
     emit_loop: bool,
+    r#loop: Option<&LoopContext>,
+) -> bool {
+    grow_stack(|| emit_stmts_impl(counter, block, stmts, emit_fallthrough, emit_loop, r#loop))
+}
+
+fn emit_stmts_impl(
+    counter: &mut usize,
+    block: &mut Block,
+    stmts: &[Statement],
+    emit_fallthrough: bool,
+    emit_loop: bool,
+    r#loop: Option<&LoopContext>,
 ) -> bool {
     let varname = |x: &str| format!("r#var_{}", x.replace("-", "_"));
 
@@ -360,7 +431,7 @@ fn emit_stmts(
                 cond_expr.line(condition);
 
                 let mut then_blk = Block::new("");
-                emit_stmts(counter, &mut then_blk, then, true, false);
+                emit_stmts(counter, &mut then_blk, then, true, false, r#loop);
 
                 match r#else {
                     None => {
@@ -372,7 +443,7 @@ fn emit_stmts(
                     }
                     Some(stmts) => {
                         let mut else_blk = Block::new("");
-                        emit_stmts(counter, &mut else_blk, stmts, true, false);
+                        emit_stmts(counter, &mut else_blk, stmts, true, false, r#loop);
 
                         block.line(format!(
                             "e.if_then(|e| {}, |e| {})",
@@ -463,6 +534,22 @@ fn emit_stmts(
                     args
                 ));
             }
+            crate::StatementData::CallExternal {
+                function_name,
+                args,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|e| emit_expr(counter, block, e))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                block.line(format!(
+                    "e.call_external_function_dynargs(self.{}, vec![{}]);",
+                    extern_name(function_name),
+                    args
+                ));
+            }
             crate::StatementData::CallVirt { fn_ptr, args } => {
                 let fn_ptr = emit_expr(counter, block, fn_ptr);
                 let args = args
@@ -488,119 +575,282 @@ fn emit_stmts(
                     None => block.line(format!("e.list_del({}, {});", list, prop)),
                 };
             }
+            crate::StatementData::ListPush { list, value } => {
+                let list = emit_expr(counter, block, list);
+                let value = emit_expr(counter, block, value);
+                block.line(format!("e.list_push({}, {});", list, value));
+            }
+            crate::StatementData::ListForEach {
+                list,
+                function,
+                args,
+            } => {
+                let list = emit_expr(counter, block, list);
+                let function = emit_expr(counter, block, function);
+                let args = args
+                    .iter()
+                    .map(|e| emit_expr(counter, block, e))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                block.line(format!(
+                    "e.list_for_each_dynargs({}, {}, vec![{}]);",
+                    list, function, args
+                ));
+            }
             StatementData::Loop {
                 init,
                 cond,
                 body,
                 next,
             } => {
-                let mut vars = HashMap::new();
-                for i in init {
-                    vars.insert(i.variable.clone(), &i.value);
-                }
+                let LoopParts {
+                    init_exprs,
+                    names,
+                    cond_expr,
+                    body_stmts,
+                } = emit_loop_parts(counter, init, cond, next, body);
 
-                let mut vars2 = HashMap::new();
-                for i in next {
-                    let init = *vars
-                        .get(&i.variable)
-                        .expect("should be same assignments in next as init");
-                    vars2.insert(i.variable.clone(), (init, &i.value));
-                }
+                block.line(format!(
+                    "e.do_loop([{}], |e, [{}]| {}, |e, [{}]| {});",
+                    init_exprs, names, cond_expr, names, body_stmts,
+                ));
+            }
+            StatementData::Break { value } => {
+                returned = true;
 
-                // ensure we have same num of inits and nexts
-                assert_eq!(vars.len(), vars2.len());
+                let value = match value {
+                    Some(value) => format!("Some({})", emit_expr(counter, block, value)),
+                    None => "None".to_string(),
+                };
 
-                let mut vars = vars2
-                    .into_iter()
-                    .map(|(name, (init, next))| (name, init, next))
-                    .collect::<Vec<_>>();
+                let line = match emit_loop {
+                    true => format!("LoopControlFlow::Break({})", value),
+                    false => format!("ControlFlow::Break({})", value),
+                };
 
-                // UGLY CODE BEGIN
-                // we are going to sort `vars` based on the order of `init`
+                block.line("source_map.end();");
 
-                let mut new_vars = Vec::new();
+                block.line(line);
+                continue;
+            }
+            StatementData::Continue => {
+                returned = true;
 
-                for i in init {
-                    let mut tidx = None;
-                    for (idx, j) in vars.iter().enumerate() {
-                        if i.variable == j.0 {
-                            tidx = Some(idx);
-                            break;
-                        }
-                    }
+                let r#loop = r#loop.expect("`continue` used outside of a loop");
+                let names = (r#loop.next.iter())
+                    .map(|next| emit_expr(counter, block, *next))
+                    .collect::<Vec<_>>()
+                    .join(", ");
 
-                    let v = vars.remove(tidx.expect("expected to find var"));
-                    new_vars.push(v);
-                }
+                let line = match emit_loop {
+                    true => format!("LoopControlFlow::Next([{}])", names),
+                    false => format!("ControlFlow::Continue(vec![{}])", names),
+                };
 
-                let vars = new_vars;
+                block.line("source_map.end();");
 
-                // UGLY CODE END
+                block.line(line);
+                continue;
+            }
+            StatementData::Match {
+                discriminant,
+                cases,
+                r#else,
+            } => {
+                let desugared = desugar_match(counter, span, discriminant, cases, r#else);
+                emit_stmts(counter, block, &desugared, false, emit_loop, r#loop);
+            }
+        }
 
-                let init_exprs = vars
-                    .iter()
-                    .map(|(_, init, _)| {
-                        let mut block = Block::new("");
-                        let name = emit_expr(counter, &mut block, *init);
-                        block.line(name);
-                        blk_to_s(block)
-                    })
-                    .map(|s| format!("Box::new(move |e| {})", s))
-                    .collect::<Vec<_>>()
-                    .join(", ");
+        block.line("source_map.end();");
+    }
 
-                let names = vars
-                    .iter()
-                    .map(|(name, _, _)| varname(name.as_str()))
-                    .collect::<Vec<_>>()
-                    .join(", ");
+    if !returned && emit_fallthrough {
+        block.line("ControlFlow::Fallthrough");
+    }
 
-                let cond_expr = {
-                    let mut block = Block::new("");
-                    let expr_name = emit_expr(counter, &mut block, cond);
-                    block.line(expr_name);
-                    blk_to_s(block)
-                };
+    returned
+}
 
-                let body_stmts = {
-                    let mut block = Block::new("");
-                    let exited = emit_stmts(counter, &mut block, body, false, true);
+/// The loop that statements are being emitted within.
+struct LoopContext<'a> {
+    /// The expressions computing the values of the loop variables for the next
+    /// iteration, in the order the loop receives them.
+    next: Vec<&'a Expression>,
+}
 
-                    if !exited {
-                        let names = vars
-                            .iter()
-                            .map(|(_, _, next)| emit_expr(counter, &mut block, *next))
-                            .collect::<Vec<_>>()
-                            .join(", ");
+/// The pieces of code making up a call to `do_loop` or `do_loop_carry`.
+struct LoopParts {
+    init_exprs: String,
+    names: String,
+    cond_expr: String,
+    body_stmts: String,
+}
 
-                        block.line(format!("LoopControlFlow::Next([{}])", names));
-                    }
+fn emit_loop_parts(
+    counter: &mut usize,
+    init: &[Assign],
+    cond: &Expression,
+    next: &[Assign],
+    body: &[Statement],
+) -> LoopParts {
+    let varname = |x: &str| format!("r#var_{}", x.replace("-", "_"));
 
-                    blk_to_s(block)
-                };
+    let mut vars = HashMap::new();
+    for i in init {
+        vars.insert(i.variable.clone(), &i.value);
+    }
 
-                block.line(format!(
-                    "e.do_loop([{}], |e, [{}]| {}, |e, [{}]| {});",
-                    init_exprs, names, cond_expr, names, body_stmts,
-                ));
+    let mut vars2 = HashMap::new();
+    for i in next {
+        let init = *vars
+            .get(&i.variable)
+            .expect("should be same assignments in next as init");
+        vars2.insert(i.variable.clone(), (init, &i.value));
+    }
 
-                assert_eq!(init.len(), next.len());
+    // ensure we have same num of inits and nexts
+    assert_eq!(vars.len(), vars2.len());
+
+    let mut vars = vars2
+        .into_iter()
+        .map(|(name, (init, next))| (name, init, next))
+        .collect::<Vec<_>>();
+
+    // UGLY CODE BEGIN
+    // we are going to sort `vars` based on the order of `init`
+
+    let mut new_vars = Vec::new();
+
+    for i in init {
+        let mut tidx = None;
+        for (idx, j) in vars.iter().enumerate() {
+            if i.variable == j.0 {
+                tidx = Some(idx);
+                break;
             }
         }
 
-        block.line("source_map.end();");
+        let v = vars.remove(tidx.expect("expected to find var"));
+        new_vars.push(v);
     }
 
-    if !returned && emit_fallthrough {
-        block.line("ControlFlow::Fallthrough");
+    let vars = new_vars;
+
+    // UGLY CODE END
+
+    let init_exprs = vars
+        .iter()
+        .map(|(_, init, _)| {
+            let mut block = Block::new("");
+            let name = emit_expr(counter, &mut block, *init);
+            block.line(name);
+            blk_to_s(block)
+        })
+        .map(|s| format!("Box::new(move |e| {})", s))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let names = vars
+        .iter()
+        .map(|(name, _, _)| varname(name.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let cond_expr = {
+        let mut block = Block::new("");
+        let expr_name = emit_expr(counter, &mut block, cond);
+        block.line(expr_name);
+        blk_to_s(block)
+    };
+
+    let body_stmts = {
+        let r#loop = LoopContext {
+            next: vars.iter().map(|(_, _, next)| *next).collect(),
+        };
+
+        let mut block = Block::new("");
+        let exited = emit_stmts(counter, &mut block, body, false, true, Some(&r#loop));
+
+        if !exited {
+            let names = (r#loop.next.iter())
+                .map(|next| emit_expr(counter, &mut block, *next))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            block.line(format!("LoopControlFlow::Next([{}])", names));
+        }
+
+        blk_to_s(block)
+    };
+
+    assert_eq!(init.len(), next.len());
+
+    LoopParts {
+        init_exprs,
+        names,
+        cond_expr,
+        body_stmts,
     }
+}
 
-    returned
+/// Lowers a `match` statement into an assignment of the discriminant, followed
+/// by a chain of `if`/`else` statements comparing it against each case.
+pub(crate) fn desugar_match(
+    counter: &mut usize,
+    span: Span,
+    discriminant: &Expression,
+    cases: &[MatchCase],
+    r#else: &Option<Vec<Statement>>,
+) -> Vec<Statement> {
+    *counter += 1;
+    let variable = format!("match-discriminant-{}", counter);
+
+    let mut chain = r#else.clone();
+    for case in cases.iter().rev() {
+        let condition = Expression {
+            span: case.value.span,
+            data: ExpressionData::BinOp {
+                kind: BinOpKind::Eq,
+                lhs: Box::new(Expression {
+                    span: discriminant.span,
+                    data: ExpressionData::VarReference {
+                        variable: variable.clone(),
+                    },
+                }),
+                rhs: Box::new(case.value.clone()),
+            },
+        };
+
+        chain = Some(vec![Statement {
+            span,
+            data: StatementData::If {
+                condition,
+                then: case.body.clone(),
+                r#else: chain,
+            },
+        }]);
+    }
+
+    let mut stmts = vec![Statement {
+        span,
+        data: StatementData::Assign(Assign {
+            variable,
+            value: discriminant.clone(),
+        }),
+    }];
+    stmts.extend(chain.unwrap_or_default());
+    stmts
 }
 
 /// Emits an expression to the block, and a string identifier used to refer to
 /// the result of the computation.
 fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> String {
+    grow_stack(|| emit_expr_impl(counter, block, expr))
+}
+
+fn emit_expr_impl(counter: &mut usize, block: &mut Block, expr: &Expression) -> String {
     let mut has_basis = false;
     if let Some(span) = expr.span {
         block.line(begin(span));
@@ -632,14 +882,14 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
             cond_scope.line(condition);
 
             let mut then_scope = Block::new("");
-            let retd = emit_stmts(counter, &mut then_scope, then, false, false);
+            let retd = emit_stmts(counter, &mut then_scope, then, false, false, None);
             if !retd {
                 let then_expr = emit_expr(counter, &mut then_scope, thene);
                 then_scope.line(format!("ControlFlow::Carry({})", then_expr));
             }
 
             let mut else_scope = Block::new("");
-            let retd = emit_stmts(counter, &mut else_scope, els, false, false);
+            let retd = emit_stmts(counter, &mut else_scope, els, false, false, None);
             if !retd {
                 let else_expr = emit_expr(counter, &mut else_scope, els_e);
                 else_scope.line(format!("ControlFlow::Carry({})", else_expr));
@@ -670,7 +920,7 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
             let value = emit_expr(counter, block, be_bound_to);
             block.line(format!("let {} = {};", varname(variable), value));
 
-            emit_stmts(counter, block, stmts, false, false);
+            emit_stmts(counter, block, stmts, false, false, None);
 
             let e = emit_expr(counter, block, expr);
             if has_basis {
@@ -678,12 +928,52 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
             }
             return e;
         }
+        ExpressionData::Loop {
+            init,
+            cond,
+            next,
+            body,
+            r#else,
+        } => {
+            let LoopParts {
+                init_exprs,
+                names,
+                cond_expr,
+                body_stmts,
+            } = emit_loop_parts(counter, init, cond, next, body);
+
+            let else_expr = {
+                let mut block = Block::new("");
+                let expr_name = emit_expr(counter, &mut block, r#else);
+                block.line(expr_name);
+                blk_to_s(block)
+            };
+
+            block.line(format!(
+                "let {} = e.do_loop_carry([{}], |e, [{}]| {}, |e, [{}]| {}, |e, [{}]| {});",
+                result, init_exprs, names, cond_expr, names, else_expr, names, body_stmts,
+            ));
+        }
         ExpressionData::RecordNew => {
             block.line(format!("let {} = e.record_new();", result));
             if let Some(span) = expr.span {
                 block.line(sample(span));
             }
         }
+        ExpressionData::RecordClone { record } => {
+            let record = emit_expr(counter, block, record);
+            block.line(format!("let {} = e.record_clone({});", result, record));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
+        ExpressionData::RecordEntries { record } => {
+            let record = emit_expr(counter, block, record);
+            block.line(format!("let {} = e.record_entries({});", result, record));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
         ExpressionData::Unreachable => {
             block.line(format!("let {} = e.unreachable();", result));
             if let Some(span) = expr.span {
@@ -713,6 +1003,24 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
                 block.line(sample(span));
             }
         }
+        ExpressionData::RecordGetSlotOr {
+            record,
+            slot,
+            default,
+        } => {
+            let record = emit_expr(counter, block, record);
+            let default = emit_expr(counter, block, default);
+            block.line(format!(
+                "let {} = e.record_get_atom_or({}, self.atoms.{}, {});",
+                result,
+                record,
+                atom_name(slot),
+                default
+            ));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
         ExpressionData::RecordHasProp { record, property } => {
             let record = emit_expr(counter, block, record);
             let property = emit_expr(counter, block, property);
@@ -759,6 +1067,13 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
                 block.line(sample(span));
             }
         }
+        ExpressionData::GetFnPtrDynamic { name } => {
+            let name = emit_expr(counter, block, name);
+            block.line(format!("let {} = e.make_fnptr_dynamic({});", result, name));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
         ExpressionData::CallStatic {
             function_name,
             args,
@@ -779,6 +1094,26 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
                 block.line(sample(span));
             }
         }
+        ExpressionData::CallExternal {
+            function_name,
+            args,
+        } => {
+            let args = args
+                .iter()
+                .map(|e| emit_expr(counter, block, e))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            block.line(format!(
+                "let {} = e.call_external_function_dynargs_with_result(self.{}, vec![{}]);",
+                result,
+                extern_name(function_name),
+                args
+            ));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
         ExpressionData::CallVirt { fn_ptr, args } => {
             let fn_ptr = emit_expr(counter, block, fn_ptr);
 
@@ -891,6 +1226,16 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
                 block.line(sample(span));
             }
         }
+        ExpressionData::AssumeType { expr: texpr, kind } => {
+            let texpr = emit_expr(counter, block, texpr);
+            block.line(format!(
+                "let {} = e.assume_type({}, ValueType::{});",
+                result, texpr, kind
+            ));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
         ExpressionData::IsTypeAs { lhs, rhs } => {
             let lhs = emit_expr(counter, block, lhs);
             let rhs = emit_expr(counter, block, rhs);
@@ -899,6 +1244,36 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
                 block.line(sample(span));
             }
         }
+        ExpressionData::TypeOf { expr: texpr } => {
+            let texpr = emit_expr(counter, block, texpr);
+            block.line(format!("let {} = e.type_of({});", result, texpr));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
+        ExpressionData::BytesConcat { lhs, rhs } => {
+            let lhs = emit_expr(counter, block, lhs);
+            let rhs = emit_expr(counter, block, rhs);
+            block.line(format!(
+                "let {} = e.bytes_concat({}, {});",
+                result, lhs, rhs
+            ));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
+        ExpressionData::BytesSlice { bytes, start, end } => {
+            let bytes = emit_expr(counter, block, bytes);
+            let start = emit_expr(counter, block, start);
+            let end = emit_expr(counter, block, end);
+            block.line(format!(
+                "let {} = e.bytes_slice({}, {}, {});",
+                result, bytes, start, end
+            ));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
         ExpressionData::ListNew => {
             block.line(format!("let {} = e.list_new();", result));
             if let Some(span) = expr.span {
@@ -934,6 +1309,34 @@ fn emit_expr(counter: &mut usize, block: &mut Block, expr: &Expression) -> Strin
                 block.line(sample(span));
             }
         }
+        ExpressionData::ListPop { list } => {
+            let list = emit_expr(counter, block, list);
+            block.line(format!("let {} = e.list_pop({});", result, list));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
+        ExpressionData::ListMap {
+            list,
+            function,
+            args,
+        } => {
+            let list = emit_expr(counter, block, list);
+            let function = emit_expr(counter, block, function);
+            let args = args
+                .iter()
+                .map(|e| emit_expr(counter, block, e))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            block.line(format!(
+                "let {} = e.list_map_dynargs({}, {}, vec![{}]);",
+                result, list, function, args
+            ));
+            if let Some(span) = expr.span {
+                block.line(sample(span));
+            }
+        }
     };
 
     if has_basis {
@@ -967,3 +1370,79 @@ impl Visitor for AtomVisitor {
         self.atoms.insert(atom_name(slot));
     }
 }
+
+#[test]
+fn lowers_match_into_equality_chain() {
+    let ast = crate::parse(
+        "((:1.2 F (x)) ((match :x (1 ((return 10))) (2 ((return 20))) (else ((return 30))))))",
+        &[],
+    );
+
+    let code = gen("Test", ast).unwrap();
+
+    assert_eq!(code.matches("e.compare_equal(").count(), 2);
+    assert_eq!(code.matches(".else_then(").count(), 2);
+    assert!(code.contains("let r#var_match_discriminant_"));
+}
+
+#[test]
+fn lowers_extern_calls_against_declarations() {
+    let ast = crate::parse(
+        r#"
+(extern print (string) void)
+(extern read () any)
+
+((:1.1 F ()) ((call-extern print "hi") (return (call-extern read))))
+"#,
+        &[],
+    );
+
+    let code = gen("Test", ast).unwrap();
+
+    assert!(code.contains(
+        r#"program.external_function_dynargs("print", vec![FFIValueType::String], Returns::Void)"#
+    ));
+    assert!(code.contains("Returns::Value(FFIValueType::Any)"));
+    assert!(code.contains("e.call_external_function_dynargs(self.extern_print, vec!["));
+    assert!(code.contains("e.call_external_function_dynargs_with_result(self.extern_read, vec![])"));
+
+    let ast = crate::parse("((:1.1 F ()) ((call-extern missing) (return)))", &[]);
+    assert!(matches!(
+        &gen("Test", ast).unwrap_err()[..],
+        [LowerError::UnknownFunction { name, .. }] if name == "missing"
+    ));
+}
+
+#[test]
+fn lowers_specialize_on_into_parameter_indices() {
+    let ast = crate::parse(
+        "(((specialize-on count) :1.1 F (record count)) ((return)))",
+        &[],
+    );
+
+    let code = gen("Test", ast).unwrap();
+
+    assert!(code.contains("F.specialize_on(1);"));
+}
+
+#[test]
+fn lowers_loop_breaking_with_value() {
+    let ast = crate::parse(
+        r#"
+((:1.1 F (n))
+ ((x = (loop ((i = 0)) (:i < :n) ((i = (:i + 1)))
+        ((if (:i == 3) ((break :i)))
+         (if (:i == 1) ((continue))))
+        :n))
+  (return :x)))
+"#,
+        &[],
+    );
+
+    let code = gen("Test", ast).unwrap();
+
+    assert!(code.contains("= e.do_loop_carry([Box::new(move |e| "));
+    assert!(code.contains("ControlFlow::Break(Some(r#var_i))"));
+    assert!(code.contains("ControlFlow::Continue(vec!["));
+    assert!(code.contains("LoopControlFlow::Next(["));
+}
@@ -5,7 +5,8 @@
 //! any IR file within a [`ProgramBuilder`].
 //!
 //! This module provides a JSSAT IR parser, AST, and Rust code generator for
-//! JSSAT IR files.
+//! JSSAT IR files, along with a way to lower them into a [`ProgramBuilder`]
+//! while the program is running.
 
 mod ast;
 pub use ast::*;
@@ -13,13 +14,29 @@ pub use ast::*;
 mod codegen_rs;
 pub use codegen_rs::*;
 
+mod lower_ast;
+pub use lower_ast::{lower_ast, MAX_PARAMETERS};
+
+mod lower_error;
+pub use lower_error::LowerError;
+
 mod parser;
 pub use parser::*;
 
 mod threaded_state;
 
-pub fn generate(name: &str, code: &str) -> String {
-    let mut ast = parser::parse(code);
+/// Generates Rust code for an IR file, with the given features enabled. Any
+/// errors in parsing the file are reported as being in the file `filename`.
+pub fn generate(
+    name: &str,
+    filename: &str,
+    code: &str,
+    features: &[&str],
+) -> Result<String, Vec<LowerError>> {
+    let mut ast = match parser::parse_named(code, filename, features) {
+        Ok(ast) => ast,
+        Err(error) => panic!("{}", error),
+    };
     threaded_state::thread_state(&mut ast);
     codegen_rs::gen(name, ast)
 }
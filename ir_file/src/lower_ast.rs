@@ -0,0 +1,763 @@
+//! Lowers an AST straight into a [`ProgramBuilder`], rather than generating
+//! Rust code that builds it. This lets an IR file be compiled while the program
+//! is running, such as by tests that run IR files end to end.
+//!
+//! This builds the same code as the Rust code generated by `codegen_rs` does,
+//! so the two should be kept in sync.
+
+use std::cell::Cell;
+
+use rustc_hash::FxHashMap;
+
+use jssat_ir::{
+    frontend::{
+        builder::{ExternalFunctionId, FunctionId, ProgramBuilder, RegisterId},
+        emitter::{ControlFlow, Emitter},
+        ir::{FFIValueType, Returns},
+    },
+    isa::{Atom, AtomDealer, ValueType},
+};
+
+use crate::{
+    codegen_rs::{atom_name, desugar_match},
+    lower_error::check,
+    parser::grow_stack,
+    threaded_state::thread_state,
+    Assign, BinOpKind, Expression, ExpressionData, FFIType, LowerError, Section, SlotOrExpr,
+    Statement, StatementData, Visitor, AST,
+};
+
+/// The most parameters a function may take in order to be lowered. The amount
+/// of parameters a function takes is part of its type in the builder API, so
+/// one version of the lowering is instantiated for every amount up to this.
+pub const MAX_PARAMETERS: usize = 16;
+
+/// The register holding the value of every variable in scope.
+type Variables = FxHashMap<String, RegisterId>;
+
+/// Emits the body of a function that has been started, but not yet ended.
+type EmitBody<'a> = Box<dyn FnOnce(&Lowerer, &mut ProgramBuilder) + 'a>;
+
+/// Produces the initial value of a loop variable.
+type InitExpr<'b, const P: usize> = Box<dyn FnOnce(&mut Emitter<'b, P>) -> RegisterId>;
+
+/// Lowers every function and extern declared in an IR file into `program`,
+/// producing the id of every function by its name. Nothing is lowered if the
+/// file has any errors.
+pub fn lower_ast(
+    mut ast: AST,
+    program: &mut ProgramBuilder,
+) -> Result<FxHashMap<String, FunctionId>, Vec<LowerError>> {
+    thread_state(&mut ast);
+    check(&mut ast)?;
+
+    let too_many_parameters = (ast.sections.iter())
+        .filter(|section| section.header.parameters.len() > MAX_PARAMETERS)
+        .map(|section| LowerError::TooManyParameters {
+            function: section.header.method_name.clone(),
+            max: MAX_PARAMETERS,
+            span: section.header.span,
+        })
+        .collect::<Vec<_>>();
+
+    if !too_many_parameters.is_empty() {
+        return Err(too_many_parameters);
+    }
+
+    let mut dealing = AtomDealing {
+        dealer: &mut program.dealer,
+        atoms: FxHashMap::default(),
+    };
+    dealing.visit_ast(&mut ast);
+    let atoms = dealing.atoms;
+
+    let mut externs = FxHashMap::default();
+    for r#extern in ast.externs.iter() {
+        let parameters = (r#extern.parameters.iter())
+            .map(|p| ffi_type(*p))
+            .collect::<Vec<_>>();
+
+        let return_type = match r#extern.return_type {
+            Some(r#type) => Returns::Value(ffi_type(r#type)),
+            None => Returns::Void,
+        };
+
+        let id = program.external_function_dynargs(&r#extern.name, parameters, return_type);
+        externs.insert(r#extern.name.clone(), id);
+    }
+
+    // every function is started before any are emitted, so that a function can
+    // refer to the functions declared after it
+    let mut functions = FxHashMap::default();
+    let mut bodies = Vec::new();
+    for section in ast.sections.iter() {
+        let (id, body) = start_section(program, section);
+        functions.insert(section.header.method_name.clone(), id);
+        bodies.push(body);
+    }
+
+    let lowerer = Lowerer {
+        functions,
+        externs,
+        atoms,
+        counter: Cell::new(0),
+    };
+
+    for body in bodies {
+        body(&lowerer, program);
+    }
+
+    Ok(lowerer.functions)
+}
+
+fn ffi_type(r#type: FFIType) -> FFIValueType {
+    match r#type {
+        FFIType::Any => FFIValueType::Any,
+        FFIType::Runtime => FFIValueType::Runtime,
+        FFIType::String => FFIValueType::String,
+    }
+}
+
+/// The kind of value named by `kind`, which [`check`] ensures is the name of a
+/// variant of [`ValueType`].
+fn value_type(kind: &str) -> ValueType {
+    match kind {
+        "Atom" => ValueType::Atom,
+        "Bytes" => ValueType::Bytes,
+        "Number" => ValueType::Number,
+        "Boolean" => ValueType::Boolean,
+        "FnPtr" => ValueType::FnPtr,
+        "Record" => ValueType::Record,
+        "BigNumber" => ValueType::BigNumber,
+        "List" => ValueType::List,
+        "Runtime" => ValueType::Runtime,
+        _ => unreachable!("unknown kinds of values are reported when checking"),
+    }
+}
+
+fn start_section<'a>(program: &ProgramBuilder, section: &'a Section) -> (FunctionId, EmitBody<'a>) {
+    macro_rules! with_parameters {
+        ($($parameters:literal)*) => {
+            match section.header.parameters.len() {
+                $($parameters => start_function::<$parameters>(program, section),)*
+                _ => unreachable!("functions with too many parameters are reported first"),
+            }
+        };
+    }
+
+    with_parameters!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16)
+}
+
+fn start_function<'a, const P: usize>(
+    program: &ProgramBuilder,
+    section: &'a Section,
+) -> (FunctionId, EmitBody<'a>) {
+    let (mut function, parameters) = program.start_function::<P>();
+    function.with_name(section.header.method_name.clone());
+
+    for name in section.header.specialize_on.iter() {
+        let idx = (section.header.parameters.iter())
+            .position(|p| p == name)
+            .unwrap();
+
+        function.specialize_on(idx);
+    }
+
+    let id = function.id;
+    let body: EmitBody<'a> = Box::new(move |lowerer: &Lowerer, program: &mut ProgramBuilder| {
+        let mut variables = (section.header.parameters.iter().cloned())
+            .zip(parameters)
+            .collect::<Variables>();
+
+        let mut e = Emitter::new(program, function);
+        let result = lowerer.lower_section(&mut e, section, &mut variables);
+        e.finish(result);
+    });
+
+    (id, body)
+}
+
+/// Deals an atom for every atom and slot named in an AST.
+struct AtomDealing<'a> {
+    dealer: &'a mut AtomDealer,
+    atoms: FxHashMap<String, Atom>,
+}
+
+impl Visitor for AtomDealing<'_> {
+    fn visit_slot(&mut self, slot: &mut String) {
+        let name = atom_name(slot);
+        let atom = self.dealer.deal_dyn(&name);
+        self.atoms.insert(name, atom);
+    }
+}
+
+struct Lowerer {
+    functions: FxHashMap<String, FunctionId>,
+    externs: FxHashMap<String, ExternalFunctionId>,
+    atoms: FxHashMap<String, Atom>,
+    /// Used to name the variables introduced when lowering a `match`.
+    counter: Cell<usize>,
+}
+
+/// The loop that statements are being lowered within.
+struct LoopContext<'a> {
+    /// The expressions computing the values of the loop variables for the next
+    /// iteration, in the order the loop receives them.
+    next: Vec<&'a Expression>,
+}
+
+/// The variables of a loop in the order the loop receives them, along with the
+/// expressions computing their initial and next values.
+type LoopVariables<'a> = Vec<(&'a str, &'a Expression, &'a Expression)>;
+
+fn loop_variables<'a>(init: &'a [Assign], next: &'a [Assign]) -> LoopVariables<'a> {
+    assert_eq!(init.len(), next.len());
+
+    (init.iter())
+        .map(|init| {
+            let next = (next.iter())
+                .find(|next| next.variable == init.variable)
+                .expect("should be same assignments in next as init");
+
+            (init.variable.as_str(), &init.value, &next.value)
+        })
+        .collect()
+}
+
+impl Lowerer {
+    fn lower_section<const P: usize>(
+        &self,
+        e: &mut Emitter<P>,
+        section: &Section,
+        variables: &mut Variables,
+    ) -> Option<RegisterId> {
+        let header = &section.header;
+        e.comment(&format!(
+            "{} {} ( {} )",
+            header.document_index,
+            header.method_name,
+            header.parameters.join(", ")
+        ));
+
+        match self.lower_stmts(e, &section.body, variables, None) {
+            Some(ControlFlow::Return(value)) => value,
+            _ => panic!("expected return to finish off block"),
+        }
+    }
+
+    fn atom(&self, name: &str) -> Atom {
+        self.atoms[&atom_name(name)]
+    }
+
+    fn variable(&self, variables: &Variables, name: &str) -> RegisterId {
+        match variables.get(name) {
+            Some(register) => *register,
+            None => panic!("variable `{}` is used outside of where it's assigned", name),
+        }
+    }
+
+    /// Lowers statements, producing how control flow leaves them if one of
+    /// them exits the block, such as a `return`.
+    fn lower_stmts<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        stmts: &[Statement],
+        variables: &mut Variables,
+        r#loop: Option<&LoopContext>,
+    ) -> Option<ControlFlow> {
+        grow_stack(|| self.lower_stmts_impl(e, stmts, variables, r#loop))
+    }
+
+    fn lower_stmts_impl<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        stmts: &[Statement],
+        variables: &mut Variables,
+        r#loop: Option<&LoopContext>,
+    ) -> Option<ControlFlow> {
+        for (idx, stmt) in stmts.iter().enumerate() {
+            if let Some(exit) = self.lower_stmt(e, stmt, variables, r#loop) {
+                assert_eq!(
+                    idx,
+                    stmts.len() - 1,
+                    "already returned yet more instructions?, at {:#?}",
+                    stmt
+                );
+
+                return Some(exit);
+            }
+        }
+
+        None
+    }
+
+    fn lower_stmt<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        stmt: &Statement,
+        variables: &mut Variables,
+        r#loop: Option<&LoopContext>,
+    ) -> Option<ControlFlow> {
+        match &stmt.data {
+            StatementData::Assign(Assign { variable, value }) => {
+                let value = self.lower_expr(e, value, variables);
+                variables.insert(variable.clone(), value);
+            }
+            StatementData::If {
+                condition,
+                then,
+                r#else,
+            } => {
+                let condition = self.lower_expr(e, condition, variables);
+
+                let mut then_variables = variables.clone();
+                let then_branch = move |e: &mut Emitter<'b, P>| {
+                    (self.lower_stmts(e, then, &mut then_variables, r#loop))
+                        .unwrap_or(ControlFlow::Fallthrough)
+                };
+
+                match r#else {
+                    None => {
+                        e.if_then(|_| condition, then_branch);
+                    }
+                    Some(stmts) => {
+                        let mut else_variables = variables.clone();
+                        e.if_then(|_| condition, then_branch).else_then(move |e| {
+                            (self.lower_stmts(e, stmts, &mut else_variables, r#loop))
+                                .unwrap_or(ControlFlow::Fallthrough)
+                        });
+                    }
+                }
+            }
+            StatementData::RecordSetProp {
+                record,
+                prop,
+                value,
+            } => {
+                let record = self.lower_expr(e, record, variables);
+                let prop = self.lower_expr(e, prop, variables);
+
+                match value {
+                    Some(value) => {
+                        let value = self.lower_expr(e, value, variables);
+                        e.record_set_prop(record, prop, value);
+                    }
+                    None => e.record_del_prop(record, prop),
+                }
+            }
+            StatementData::RecordSetSlot {
+                record,
+                slot,
+                value,
+            } => {
+                let record = self.lower_expr(e, record, variables);
+
+                match value {
+                    Some(value) => {
+                        let value = self.lower_expr(e, value, variables);
+                        e.record_set_atom(record, self.atom(slot), value);
+                    }
+                    None => e.record_del_atom(record, self.atom(slot)),
+                }
+            }
+            StatementData::ListSet { list, prop, value } => {
+                let list = self.lower_expr(e, list, variables);
+                let prop = self.lower_expr(e, prop, variables);
+
+                match value {
+                    Some(value) => {
+                        let value = self.lower_expr(e, value, variables);
+                        e.list_set(list, prop, value);
+                    }
+                    None => e.list_del(list, prop),
+                }
+            }
+            StatementData::ListPush { list, value } => {
+                let list = self.lower_expr(e, list, variables);
+                let value = self.lower_expr(e, value, variables);
+                e.list_push(list, value);
+            }
+            StatementData::ListForEach {
+                list,
+                function,
+                args,
+            } => {
+                let list = self.lower_expr(e, list, variables);
+                let function = self.lower_expr(e, function, variables);
+                let args = self.lower_exprs(e, args, variables);
+                e.list_for_each_dynargs(list, function, args);
+            }
+            StatementData::Return { expr } => {
+                let value = expr
+                    .as_ref()
+                    .map(|expr| self.lower_expr(e, expr, variables));
+                return Some(ControlFlow::Return(value));
+            }
+            StatementData::CallStatic {
+                function_name,
+                args,
+            } => {
+                let args = self.lower_exprs(e, args, variables);
+                e.call_dynargs(self.functions[function_name], args);
+            }
+            StatementData::CallExternal {
+                function_name,
+                args,
+            } => {
+                let args = self.lower_exprs(e, args, variables);
+                e.call_external_function_dynargs(self.externs[function_name], args);
+            }
+            StatementData::CallVirt { fn_ptr, args } => {
+                let fn_ptr = self.lower_expr(e, fn_ptr, variables);
+                let args = self.lower_exprs(e, args, variables);
+                e.call_virt_dynargs(fn_ptr, args);
+            }
+            StatementData::Assert { expr, message } => {
+                let assertion = self.lower_expr(e, expr, variables);
+                // assertion messages are expected to be written into the source
+                // of the program, so they must outlive it
+                e.assert(assertion, Box::leak(message.clone().into_boxed_str()));
+            }
+            StatementData::Loop {
+                init,
+                cond,
+                next,
+                body,
+            } => {
+                let loop_variables = loop_variables(init, next);
+                let init_exprs = self.lower_loop_init(e, &loop_variables, variables);
+                let bind = |args| bind_loop_variables(variables, &loop_variables, args);
+
+                e.do_loop_dyn(
+                    init_exprs,
+                    |e, args| self.lower_expr(e, cond, &mut bind(args)),
+                    |e, args| self.lower_loop_body(e, body, &loop_variables, &mut bind(args)),
+                );
+            }
+            StatementData::Break { value } => {
+                let value = value
+                    .as_ref()
+                    .map(|value| self.lower_expr(e, value, variables));
+                return Some(ControlFlow::Break(value));
+            }
+            StatementData::Continue => {
+                let r#loop = r#loop.expect("`continue` used outside of a loop");
+                let values = (r#loop.next.iter())
+                    .map(|next| self.lower_expr(e, next, variables))
+                    .collect();
+
+                return Some(ControlFlow::Continue(values));
+            }
+            StatementData::Match {
+                discriminant,
+                cases,
+                r#else,
+            } => {
+                let mut counter = self.counter.get();
+                let desugared = desugar_match(&mut counter, stmt.span, discriminant, cases, r#else);
+                self.counter.set(counter);
+
+                return self.lower_stmts(e, &desugared, variables, r#loop);
+            }
+        }
+
+        None
+    }
+
+    /// Lowers the initial values of the variables of a loop.
+    fn lower_loop_init<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        loop_variables: &LoopVariables,
+        variables: &mut Variables,
+    ) -> Vec<InitExpr<'b, P>> {
+        (loop_variables.iter())
+            .map(|(_, init, _)| {
+                let value = self.lower_expr(e, init, variables);
+                Box::new(move |_: &mut Emitter<'b, P>| value) as InitExpr<'b, P>
+            })
+            .collect()
+    }
+
+    /// Lowers the body of a loop, moving on to the next iteration if the body
+    /// doesn't exit on its own.
+    fn lower_loop_body<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        body: &[Statement],
+        loop_variables: &LoopVariables,
+        variables: &mut Variables,
+    ) -> ControlFlow {
+        let r#loop = LoopContext {
+            next: (loop_variables.iter()).map(|(_, _, next)| *next).collect(),
+        };
+
+        match self.lower_stmts(e, body, variables, Some(&r#loop)) {
+            Some(exit) => exit,
+            None => ControlFlow::Next(
+                (r#loop.next.iter())
+                    .map(|next| self.lower_expr(e, next, variables))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn lower_exprs<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        exprs: &[Expression],
+        variables: &mut Variables,
+    ) -> Vec<RegisterId> {
+        (exprs.iter())
+            .map(|expr| self.lower_expr(e, expr, variables))
+            .collect()
+    }
+
+    fn lower_expr<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        expr: &Expression,
+        variables: &mut Variables,
+    ) -> RegisterId {
+        grow_stack(|| self.lower_expr_impl(e, expr, variables))
+    }
+
+    fn lower_expr_impl<'b, const P: usize>(
+        &self,
+        e: &mut Emitter<'b, P>,
+        expr: &Expression,
+        variables: &mut Variables,
+    ) -> RegisterId {
+        match &expr.data {
+            ExpressionData::GetGlobal => {
+                panic!(
+                    "get global instructions should automatically be replaced by threaded state"
+                );
+            }
+            ExpressionData::If {
+                condition,
+                then: (then, then_expr),
+                r#else: (r#else, else_expr),
+            } => {
+                let condition = self.lower_expr(e, condition, variables);
+
+                let mut then_variables = variables.clone();
+                let mut else_variables = variables.clone();
+
+                e.if_then(
+                    |_| condition,
+                    move |e| match self.lower_stmts(e, then, &mut then_variables, None) {
+                        Some(exit) => exit,
+                        None => {
+                            ControlFlow::Carry(self.lower_expr(e, then_expr, &mut then_variables))
+                        }
+                    },
+                )
+                .else_then(
+                    move |e| match self.lower_stmts(e, r#else, &mut else_variables, None) {
+                        Some(exit) => exit,
+                        None => {
+                            ControlFlow::Carry(self.lower_expr(e, else_expr, &mut else_variables))
+                        }
+                    },
+                )
+                .end()
+                .expect("an `if` expression must produce a value")
+            }
+            ExpressionData::VarReference { variable } => self.variable(variables, variable),
+            ExpressionData::LetIn {
+                variable,
+                be_bound_to,
+                r#in: (stmts, expr),
+            } => {
+                let value = self.lower_expr(e, be_bound_to, variables);
+                variables.insert(variable.clone(), value);
+
+                self.lower_stmts(e, stmts, variables, None);
+
+                self.lower_expr(e, expr, variables)
+            }
+            ExpressionData::Loop {
+                init,
+                cond,
+                next,
+                body,
+                r#else,
+            } => {
+                let loop_variables = loop_variables(init, next);
+                let init_exprs = self.lower_loop_init(e, &loop_variables, variables);
+                let bind = |args| bind_loop_variables(variables, &loop_variables, args);
+
+                e.do_loop_carry_dyn(
+                    init_exprs,
+                    |e, args| self.lower_expr(e, cond, &mut bind(args)),
+                    |e, args| self.lower_expr(e, r#else, &mut bind(args)),
+                    |e, args| self.lower_loop_body(e, body, &loop_variables, &mut bind(args)),
+                )
+            }
+            ExpressionData::RecordNew => e.record_new(),
+            ExpressionData::RecordClone { record } => {
+                let record = self.lower_expr(e, record, variables);
+                e.record_clone(record)
+            }
+            ExpressionData::RecordEntries { record } => {
+                let record = self.lower_expr(e, record, variables);
+                e.record_entries(record)
+            }
+            ExpressionData::Unreachable => e.unreachable(),
+            ExpressionData::RecordGetProp { record, property } => {
+                let record = self.lower_expr(e, record, variables);
+                let property = self.lower_expr(e, property, variables);
+                e.record_get_prop(record, property)
+            }
+            ExpressionData::RecordGetSlot { record, slot } => {
+                let record = self.lower_expr(e, record, variables);
+                e.record_get_atom(record, self.atom(slot))
+            }
+            ExpressionData::RecordGetSlotOr {
+                record,
+                slot,
+                default,
+            } => {
+                let record = self.lower_expr(e, record, variables);
+                let default = self.lower_expr(e, default, variables);
+                e.record_get_atom_or(record, self.atom(slot), default)
+            }
+            ExpressionData::RecordHasProp { record, property } => {
+                let record = self.lower_expr(e, record, variables);
+                let property = self.lower_expr(e, property, variables);
+                e.record_has_prop(record, property)
+            }
+            ExpressionData::RecordHasSlot { record, slot } => {
+                let record = self.lower_expr(e, record, variables);
+
+                match slot {
+                    SlotOrExpr::Slot(slot) => e.record_has_atom(record, self.atom(slot)),
+                    SlotOrExpr::Expr(slot) => {
+                        let slot = self.lower_expr(e, slot, variables);
+                        e.record_has_atom_dyn(record, slot)
+                    }
+                }
+            }
+            ExpressionData::ListNew => e.list_new(),
+            ExpressionData::ListGet { list, property } => {
+                let list = self.lower_expr(e, list, variables);
+                let property = self.lower_expr(e, property, variables);
+                e.list_get(list, property)
+            }
+            ExpressionData::ListHas { list, property } => {
+                let list = self.lower_expr(e, list, variables);
+                let property = self.lower_expr(e, property, variables);
+                e.list_has(list, property)
+            }
+            ExpressionData::ListLen { list } => {
+                let list = self.lower_expr(e, list, variables);
+                e.list_len(list)
+            }
+            ExpressionData::ListPop { list } => {
+                let list = self.lower_expr(e, list, variables);
+                e.list_pop(list)
+            }
+            ExpressionData::ListMap {
+                list,
+                function,
+                args,
+            } => {
+                let list = self.lower_expr(e, list, variables);
+                let function = self.lower_expr(e, function, variables);
+                let args = self.lower_exprs(e, args, variables);
+                e.list_map_dynargs(list, function, args)
+            }
+            ExpressionData::GetFnPtr { function_name } => {
+                e.make_fnptr(self.functions[function_name])
+            }
+            ExpressionData::GetFnPtrDynamic { name } => {
+                let name = self.lower_expr(e, name, variables);
+                e.make_fnptr_dynamic(name)
+            }
+            ExpressionData::CallStatic {
+                function_name,
+                args,
+            } => {
+                let args = self.lower_exprs(e, args, variables);
+                e.call_dynargs_with_result(self.functions[function_name], args)
+            }
+            ExpressionData::CallExternal {
+                function_name,
+                args,
+            } => {
+                let args = self.lower_exprs(e, args, variables);
+                e.call_external_function_dynargs_with_result(self.externs[function_name], args)
+            }
+            ExpressionData::CallVirt { fn_ptr, args } => {
+                let fn_ptr = self.lower_expr(e, fn_ptr, variables);
+                let args = self.lower_exprs(e, args, variables);
+                e.call_virt_dynargs_with_result(fn_ptr, args)
+            }
+            ExpressionData::MakeAtom { atom } => e.make_atom(self.atom(atom)),
+            ExpressionData::MakeBytes { bytes } => e.load_constant(bytes.clone()),
+            ExpressionData::MakeInteger { value } => e.make_number_decimal(*value),
+            ExpressionData::MakeBoolean { value } => e.make_bool(*value),
+            ExpressionData::BinOp { kind, lhs, rhs } => {
+                let lhs = self.lower_expr(e, lhs, variables);
+                let rhs = self.lower_expr(e, rhs, variables);
+
+                match kind {
+                    BinOpKind::Add => e.add(lhs, rhs),
+                    BinOpKind::And => e.and(lhs, rhs),
+                    BinOpKind::Or => e.or(lhs, rhs),
+                    BinOpKind::Eq => e.compare_equal(lhs, rhs),
+                    BinOpKind::Lt => e.compare_less_than(lhs, rhs),
+                }
+            }
+            ExpressionData::Negate { expr } => {
+                let value = self.lower_expr(e, expr, variables);
+                e.negate(value)
+            }
+            ExpressionData::IsTypeOf { expr, kind } => {
+                let value = self.lower_expr(e, expr, variables);
+                e.is_type_of(value, value_type(kind))
+            }
+            ExpressionData::AssumeType { expr, kind } => {
+                let value = self.lower_expr(e, expr, variables);
+                e.assume_type(value, value_type(kind))
+            }
+            ExpressionData::IsTypeAs { lhs, rhs } => {
+                let lhs = self.lower_expr(e, lhs, variables);
+                let rhs = self.lower_expr(e, rhs, variables);
+                e.is_type_as(lhs, rhs)
+            }
+            ExpressionData::TypeOf { expr } => {
+                let value = self.lower_expr(e, expr, variables);
+                e.type_of(value)
+            }
+            ExpressionData::BytesConcat { lhs, rhs } => {
+                let lhs = self.lower_expr(e, lhs, variables);
+                let rhs = self.lower_expr(e, rhs, variables);
+                e.bytes_concat(lhs, rhs)
+            }
+            ExpressionData::BytesSlice { bytes, start, end } => {
+                let bytes = self.lower_expr(e, bytes, variables);
+                let start = self.lower_expr(e, start, variables);
+                let end = self.lower_expr(e, end, variables);
+                e.bytes_slice(bytes, start, end)
+            }
+        }
+    }
+}
+
+/// The variables in scope within the body of a loop, where the variables of
+/// the loop are bound to the values the loop receives.
+fn bind_loop_variables(
+    variables: &Variables,
+    loop_variables: &LoopVariables,
+    args: Vec<RegisterId>,
+) -> Variables {
+    let mut variables = variables.clone();
+
+    for ((name, _, _), arg) in loop_variables.iter().zip(args) {
+        variables.insert(name.to_string(), arg);
+    }
+
+    variables
+}
@@ -0,0 +1,476 @@
+//! Checks an AST for errors that would prevent it from being lowered into
+//! Rust code, or into a program. All errors in a file are collected and
+//! reported at once, rather than bailing on the first one.
+
+use std::fmt::Display;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Assign, Expression, ExpressionData, Span, Statement, StatementData, Visitor, AST};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LowerError {
+    /// A call or function pointer refers to a function that isn't defined.
+    UnknownFunction { name: String, span: Option<Span> },
+    /// A function was called with a different amount of arguments than it
+    /// accepts.
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        got: usize,
+        span: Option<Span>,
+    },
+    /// A variable was used, but is never declared in the function.
+    UndefinedVariable { name: String, span: Option<Span> },
+    /// An assertion was made on an expression that can never be a boolean.
+    AssertNotBoolean { span: Option<Span> },
+    /// The body of a function does not end with a `return` statement.
+    MissingReturn { function: String, span: Span },
+    /// A `break` or `continue` was used outside of the body of a loop.
+    OutsideOfLoop { keyword: &'static str, span: Span },
+    /// A `break` carries a value out of a loop statement, or doesn't carry one
+    /// out of a loop expression.
+    BreakValue { loop_has_value: bool, span: Span },
+    /// A type check or assumption names a kind of value that doesn't exist.
+    UnknownValueType { kind: String, span: Option<Span> },
+    /// A function takes more parameters than can be lowered at runtime, which
+    /// is only reported by [`lower_ast`](crate::lower_ast).
+    TooManyParameters {
+        function: String,
+        max: usize,
+        span: Span,
+    },
+}
+
+/// The kinds of values that may be checked for or assumed, which are the
+/// variants of `jssat_ir::isa::ValueType`.
+const VALUE_TYPES: [&str; 9] = [
+    "Atom",
+    "Bytes",
+    "Number",
+    "Boolean",
+    "FnPtr",
+    "Record",
+    "BigNumber",
+    "List",
+    "Runtime",
+];
+
+impl LowerError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LowerError::UnknownFunction { span, .. }
+            | LowerError::ArgumentCount { span, .. }
+            | LowerError::UndefinedVariable { span, .. }
+            | LowerError::AssertNotBoolean { span }
+            | LowerError::UnknownValueType { span, .. } => *span,
+            LowerError::MissingReturn { span, .. }
+            | LowerError::OutsideOfLoop { span, .. }
+            | LowerError::BreakValue { span, .. }
+            | LowerError::TooManyParameters { span, .. } => Some(*span),
+        }
+    }
+}
+
+impl Display for LowerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LowerError::UnknownFunction { name, .. } => write!(f, "unknown function `{}`", name),
+            LowerError::ArgumentCount {
+                name,
+                expected,
+                got,
+                ..
+            } => write!(
+                f,
+                "function `{}` takes {} arguments, but {} were supplied",
+                name, expected, got
+            ),
+            LowerError::UndefinedVariable { name, .. } => {
+                write!(f, "undefined variable `{}`", name)
+            }
+            LowerError::AssertNotBoolean { .. } => {
+                write!(f, "assertion can never be given a boolean")
+            }
+            LowerError::MissingReturn { function, .. } => {
+                write!(f, "function `{}` must end with a `return`", function)
+            }
+            LowerError::OutsideOfLoop { keyword, .. } => {
+                write!(f, "`{}` used outside of a loop", keyword)
+            }
+            LowerError::BreakValue {
+                loop_has_value: true,
+                ..
+            } => write!(f, "`break` out of a loop expression must carry a value"),
+            LowerError::BreakValue {
+                loop_has_value: false,
+                ..
+            } => write!(f, "`break` out of a loop statement cannot carry a value"),
+            LowerError::UnknownValueType { kind, .. } => {
+                write!(f, "unknown kind of value `{}`", kind)
+            }
+            LowerError::TooManyParameters { function, max, .. } => {
+                write!(
+                    f,
+                    "function `{}` takes more than {} parameters",
+                    function, max
+                )
+            }
+        }?;
+
+        if let Some(span) = self.span() {
+            let start = span.start();
+            write!(
+                f,
+                " at {}:{}:{}",
+                span.filename(),
+                start.line(),
+                start.column()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn check(ast: &mut AST) -> Result<(), Vec<LowerError>> {
+    let functions = ast
+        .sections
+        .iter()
+        .map(|s| (s.header.method_name.clone(), s.header.parameters.len()))
+        .collect::<FxHashMap<_, _>>();
+
+    let externs = ast
+        .externs
+        .iter()
+        .map(|e| (e.name.clone(), e.parameters.len()))
+        .collect::<FxHashMap<_, _>>();
+
+    let mut errors = Vec::new();
+
+    for section in ast.sections.iter_mut() {
+        if !matches!(
+            section.body.last(),
+            Some(Statement {
+                data: StatementData::Return { .. },
+                ..
+            })
+        ) {
+            errors.push(LowerError::MissingReturn {
+                function: section.header.method_name.clone(),
+                span: section.body_span,
+            });
+        }
+
+        let mut declared = DeclaredVariables {
+            variables: section.header.parameters.iter().cloned().collect(),
+        };
+        declared.visit_section(section);
+
+        let mut checker = Checker {
+            functions: &functions,
+            externs: &externs,
+            variables: &declared.variables,
+            errors: &mut errors,
+            loops: Vec::new(),
+        };
+        checker.visit_section(section);
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+struct DeclaredVariables {
+    variables: FxHashSet<String>,
+}
+
+impl Visitor for DeclaredVariables {
+    fn visit_assign(&mut self, assign: &mut Assign) {
+        self.variables.insert(assign.variable.clone());
+        self.visit_assign_impl(assign);
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expression) {
+        if let ExpressionData::LetIn { variable, .. } = &expr.data {
+            self.variables.insert(variable.clone());
+        }
+
+        self.visit_expr_impl(expr);
+    }
+}
+
+struct Checker<'a> {
+    functions: &'a FxHashMap<String, usize>,
+    externs: &'a FxHashMap<String, usize>,
+    variables: &'a FxHashSet<String>,
+    errors: &'a mut Vec<LowerError>,
+    /// The kinds of bodies being visited that affect `break` and `continue`,
+    /// innermost last.
+    loops: Vec<LoopScope>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LoopScope {
+    Statement,
+    Expression,
+    /// The body of an `if` or `let` expression, which can't jump out to an
+    /// enclosing loop.
+    Barrier,
+}
+
+impl Checker<'_> {
+    fn check_fn(&mut self, name: &str, args: Option<usize>, span: Option<Span>) {
+        Self::check_call(self.functions, self.errors, name, args, span);
+    }
+
+    fn check_extern(&mut self, name: &str, args: usize, span: Option<Span>) {
+        Self::check_call(self.externs, self.errors, name, Some(args), span);
+    }
+
+    fn check_call(
+        functions: &FxHashMap<String, usize>,
+        errors: &mut Vec<LowerError>,
+        name: &str,
+        args: Option<usize>,
+        span: Option<Span>,
+    ) {
+        match (functions.get(name), args) {
+            (None, _) => errors.push(LowerError::UnknownFunction {
+                name: name.to_string(),
+                span,
+            }),
+            (Some(&expected), Some(got)) if expected != got => {
+                errors.push(LowerError::ArgumentCount {
+                    name: name.to_string(),
+                    expected,
+                    got,
+                    span,
+                })
+            }
+            (Some(_), _) => {}
+        }
+    }
+
+    fn check_loop_jump(&mut self, keyword: &'static str, value: Option<bool>, span: Span) {
+        let scope = match self.loops.last() {
+            Some(scope @ (LoopScope::Statement | LoopScope::Expression)) => *scope,
+            _ => {
+                self.errors
+                    .push(LowerError::OutsideOfLoop { keyword, span });
+                return;
+            }
+        };
+
+        let loop_has_value = scope == LoopScope::Expression;
+        if matches!(value, Some(has_value) if has_value != loop_has_value) {
+            self.errors.push(LowerError::BreakValue {
+                loop_has_value,
+                span,
+            });
+        }
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_stmt(&mut self, stmt: &mut Statement) {
+        match &stmt.data {
+            StatementData::CallStatic {
+                function_name,
+                args,
+            } => self.check_fn(function_name, Some(args.len()), Some(stmt.span)),
+            StatementData::CallExternal {
+                function_name,
+                args,
+            } => self.check_extern(function_name, args.len(), Some(stmt.span)),
+            StatementData::Assert { expr, message: _ } if !may_be_boolean(expr) => {
+                self.errors.push(LowerError::AssertNotBoolean {
+                    span: expr.span.or(Some(stmt.span)),
+                })
+            }
+            StatementData::Break { value } => {
+                self.check_loop_jump("break", Some(value.is_some()), stmt.span)
+            }
+            StatementData::Continue => self.check_loop_jump("continue", None, stmt.span),
+            _ => {}
+        }
+
+        match &stmt.data {
+            StatementData::Loop { .. } => {
+                self.loops.push(LoopScope::Statement);
+                self.visit_stmt_impl(stmt);
+                self.loops.pop();
+            }
+            _ => self.visit_stmt_impl(stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expression) {
+        match &expr.data {
+            ExpressionData::VarReference { variable } if !self.variables.contains(variable) => {
+                self.errors.push(LowerError::UndefinedVariable {
+                    name: variable.clone(),
+                    span: expr.span,
+                })
+            }
+            ExpressionData::CallStatic {
+                function_name,
+                args,
+            } => self.check_fn(function_name, Some(args.len()), expr.span),
+            ExpressionData::CallExternal {
+                function_name,
+                args,
+            } => self.check_extern(function_name, args.len(), expr.span),
+            ExpressionData::GetFnPtr { function_name } => {
+                self.check_fn(function_name, None, expr.span)
+            }
+            ExpressionData::IsTypeOf { kind, .. } | ExpressionData::AssumeType { kind, .. }
+                if !VALUE_TYPES.contains(&kind.as_str()) =>
+            {
+                self.errors.push(LowerError::UnknownValueType {
+                    kind: kind.clone(),
+                    span: expr.span,
+                })
+            }
+            _ => {}
+        }
+
+        let scope = match &expr.data {
+            ExpressionData::Loop { .. } => Some(LoopScope::Expression),
+            ExpressionData::If { .. } | ExpressionData::LetIn { .. } => Some(LoopScope::Barrier),
+            _ => None,
+        };
+
+        self.loops.extend(scope);
+        self.visit_expr_impl(expr);
+        if scope.is_some() {
+            self.loops.pop();
+        }
+    }
+}
+
+/// Determines if an expression could possibly evaluate to a boolean.
+fn may_be_boolean(expr: &Expression) -> bool {
+    !matches!(
+        expr.data,
+        ExpressionData::RecordNew
+            | ExpressionData::RecordClone { .. }
+            | ExpressionData::RecordEntries { .. }
+            | ExpressionData::ListNew
+            | ExpressionData::ListLen { .. }
+            | ExpressionData::ListMap { .. }
+            | ExpressionData::GetFnPtr { .. }
+            | ExpressionData::GetFnPtrDynamic { .. }
+            | ExpressionData::TypeOf { .. }
+            | ExpressionData::BytesConcat { .. }
+            | ExpressionData::BytesSlice { .. }
+            | ExpressionData::MakeBytes { .. }
+            | ExpressionData::MakeAtom { .. }
+            | ExpressionData::MakeInteger { .. }
+            | ExpressionData::BinOp {
+                kind: crate::BinOpKind::Add,
+                ..
+            }
+    )
+}
+
+#[test]
+fn reports_all_lowering_errors() {
+    let mut ast = crate::parse(
+        r#"
+((:1.1 F (x))
+ ((y = (call Missing :x))
+  (return :z)))
+"#,
+        &[],
+    );
+
+    let errors = check(&mut ast).unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(&errors[0], LowerError::UnknownFunction { name, .. } if name == "Missing"));
+    assert!(matches!(&errors[1], LowerError::UndefinedVariable { name, .. } if name == "z"));
+    assert!(errors.iter().all(|e| e.span().is_some()));
+}
+
+#[test]
+fn lowering_errors_name_the_file() {
+    let mut ast = crate::parse_named(
+        r#"((:1.1 F (x))
+ ((return :z)))"#,
+        "file.lisp",
+        &[],
+    )
+    .unwrap();
+
+    let errors = check(&mut ast).unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(
+        errors[0].to_string().contains(" at file.lisp:2:"),
+        "{} should name the file and line",
+        errors[0]
+    );
+}
+
+#[test]
+fn reports_break_outside_of_loop() {
+    let mut ast = crate::parse(
+        r#"
+((:1.1 F (x))
+ ((if :x
+   ((break)))
+  (loop () true ()
+   ((y = (if true ((break) 1) (1)))
+    (break 5)))
+  (return)))
+"#,
+        &[],
+    );
+
+    let errors = check(&mut ast).unwrap_err();
+
+    assert_eq!(errors.len(), 3);
+    assert!(matches!(
+        &errors[0],
+        LowerError::OutsideOfLoop {
+            keyword: "break",
+            ..
+        }
+    ));
+    assert!(matches!(
+        &errors[1],
+        LowerError::OutsideOfLoop {
+            keyword: "break",
+            ..
+        }
+    ));
+    assert!(matches!(
+        &errors[2],
+        LowerError::BreakValue {
+            loop_has_value: false,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn reports_unknown_value_types() {
+    let mut ast = crate::parse(
+        r#"
+((:1.1 F (x))
+ ((y = (is-type-of Number :x))
+  (z = (assume-type Strnig :x))
+  (return (is-type-of String :z))))
+"#,
+        &[],
+    );
+
+    let errors = check(&mut ast).unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(&errors[0], LowerError::UnknownValueType { kind, .. } if kind == "Strnig"));
+    assert!(matches!(&errors[1], LowerError::UnknownValueType { kind, .. } if kind == "String"));
+}
@@ -7,33 +7,328 @@ mod node;
 mod rules;
 use node::*;
 
-pub fn parse(code: &str) -> AST {
+/// The deepest nesting of S-expressions that [`parse_lenient`] accepts.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+/// The name given to code parsed without a filename.
+pub const UNNAMED_FILE: &str = "<unnamed>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The code is nested deeper than the maximum depth allowed.
+    TooDeep { filename: String, max_depth: usize },
+    /// The code is not a well formed S-expression, such as having unbalanced
+    /// parentheses or an unterminated string.
+    Syntax {
+        filename: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The code is not a well formed IR file.
+    Malformed { filename: String, message: String },
+    /// A rule never finishes expanding, such as one that refers to itself.
+    RecursiveRule { filename: String, rule: String },
+}
+
+impl ParseError {
+    fn malformed(message: String) -> Self {
+        ParseError::Malformed {
+            filename: UNNAMED_FILE.to_owned(),
+            message,
+        }
+    }
+
+    /// Names the file that the error occurred in.
+    fn in_file(self, name: &str) -> Self {
+        match self {
+            ParseError::Syntax {
+                line,
+                column,
+                message,
+                ..
+            } => ParseError::Syntax {
+                filename: name.to_owned(),
+                line,
+                column,
+                message,
+            },
+            ParseError::Malformed { message, .. } => ParseError::Malformed {
+                filename: name.to_owned(),
+                message,
+            },
+            ParseError::RecursiveRule { rule, .. } => ParseError::RecursiveRule {
+                filename: name.to_owned(),
+                rule,
+            },
+            ParseError::TooDeep { max_depth, .. } => ParseError::TooDeep {
+                filename: name.to_owned(),
+                max_depth,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::TooDeep {
+                filename,
+                max_depth,
+            } => write!(
+                f,
+                "{}: code is nested deeper than {} levels",
+                filename, max_depth
+            ),
+            ParseError::Syntax {
+                filename,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", filename, line, column, message),
+            ParseError::Malformed { filename, message } => {
+                write!(f, "{}: malformed code: {}", filename, message)
+            }
+            ParseError::RecursiveRule { filename, rule } => {
+                write!(f, "{}: rule {} never finishes expanding", filename, rule)
+            }
+        }
+    }
+}
+
+/// Produces a [`ParseError::Malformed`] with a formatted message.
+macro_rules! malformed {
+    ($($arg:tt)*) => {
+        ParseError::malformed(format!($($arg)*))
+    };
+}
+
+/// Parses an IR file, only including the sections gated behind a `when` form
+/// if their feature is one of the enabled `features`.
+pub fn parse(code: &str, features: &[&str]) -> AST {
+    match parse_named(code, UNNAMED_FILE, features) {
+        Ok(ast) => ast,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// Parses an IR file, producing an error rather than panicking on any input.
+/// This makes the parser safe to fuzz.
+///
+/// Code nested deeper than [`MAX_NESTING_DEPTH`] is rejected before parsing,
+/// as it could overflow the stack.
+pub fn parse_lenient(code: &str, features: &[&str]) -> Result<AST, ParseError> {
+    parse_with_max_depth(code, UNNAMED_FILE, features, MAX_NESTING_DEPTH)
+}
+
+/// Like [`parse`], but produces an error rather than panicking, and every span
+/// and error names the file `filename`, such as `file.lisp:3:12`.
+pub fn parse_named(code: &str, filename: &str, features: &[&str]) -> Result<AST, ParseError> {
+    parse_with_max_depth(code, filename, features, usize::MAX)
+}
+
+fn parse_with_max_depth(
+    code: &str,
+    filename: &str,
+    features: &[&str],
+    max_depth: usize,
+) -> Result<AST, ParseError> {
+    let nodes = try_parse_to_nodes(code, filename, max_depth).map_err(|e| e.in_file(filename))?;
+    parse_nodes(code, nodes, features).map_err(|e| e.in_file(filename))
+}
+
+/// Runs `f`, growing the stack if it is about to run out. The parser and the
+/// passes over the [`AST`] recurse over how deeply the code is nested, so they
+/// use this to handle deeply nested code without overflowing the stack.
+#[allow(non_upper_case_globals)]
+pub(crate) fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    const KiB: usize = 1024;
+    const MiB: usize = 1024 * KiB;
+    stacker::maybe_grow(32 * KiB, 4 * MiB, f)
+}
+
+fn parse_nodes(code: &str, nodes: Vec<Node>, features: &[&str]) -> Result<AST, ParseError> {
     let source = code.to_owned();
-    let nodes = parse_with_rule_application(parse_to_nodes(code));
+    let nodes = parse_with_feature_gates(nodes, features)?;
+    let nodes = parse_with_rule_application(nodes)?;
+
+    let (extern_nodes, nodes): (Vec<_>, Vec<_>) = nodes
+        .into_iter()
+        .partition(|node| header_word(node) == Some("extern"));
+
+    let externs = extern_nodes
+        .iter()
+        .map(parse_extern)
+        .collect::<Result<_, _>>()?;
 
     let sections = nodes
         .into_iter()
         .map(|node| {
             let span = node.span();
-            let mut children = node.expect_parent();
-            let body = children.pop().unwrap();
+            let mut children = node.into_parent()?;
+            let (body, header) = match (children.pop(), children.pop()) {
+                (Some(body), Some(header)) => (body, header),
+                _ => {
+                    return Err(malformed!(
+                        "expected a header and body on {}",
+                        DisplaySpan(span)
+                    ))
+                }
+            };
             let body_span = body.span();
-            let header = parse_header(children.pop().unwrap());
+            let header = parse_header(header)?;
+
+            let body_nodes = body.into_parent()?;
+            let body = parse_body(&body_nodes);
+            drop_nodes(body_nodes);
 
-            let body = parse_body(body.expect_parent());
-            Section {
+            Ok(Section {
                 header,
-                body,
+                body: body?,
                 span,
                 body_span,
-            }
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(AST {
+        source,
+        externs,
+        sections,
+    })
+}
+
+/// Parses the signature of a function provided by the runtime.
+///
+/// ```text
+/// (extern print (runtime string) void)
+/// ```
+fn parse_extern(node: &Node) -> Result<ExternDecl, ParseError> {
+    let (name, parameters, return_type) = match node.as_ref() {
+        Node::Parent([_, Node::Word(name, _), parameters, Node::Word(return_type, _)], _) => {
+            (name, parameters, return_type)
+        }
+        _ => {
+            return Err(malformed!(
+                "malformed extern declaration {}",
+                node.to_lisp()
+            ))
+        }
+    };
+
+    let parameters = match parameters {
+        Node::Parent(parameters, _) => parameters
+            .iter()
+            .map(parse_ffi_type)
+            .collect::<Result<_, _>>()?,
+        Node::Null(_) => Vec::new(),
+        _ => {
+            return Err(malformed!(
+                "expected extern parameter list on {}",
+                node.to_lisp()
+            ))
+        }
+    };
+
+    let return_type = match return_type.as_str() {
+        "void" => None,
+        _ => Some(parse_ffi_type_name(return_type)?),
+    };
+
+    Ok(ExternDecl {
+        name: name.to_owned(),
+        parameters,
+        return_type,
+        span: node.span(),
+    })
+}
+
+fn parse_ffi_type(node: &Node) -> Result<FFIType, ParseError> {
+    match node.as_ref() {
+        Node::Word(name, _) => parse_ffi_type_name(name),
+        _ => Err(malformed!("expected extern type, got {}", node.to_lisp())),
+    }
+}
+
+fn parse_ffi_type_name(name: &str) -> Result<FFIType, ParseError> {
+    match name {
+        "any" => Ok(FFIType::Any),
+        "runtime" => Ok(FFIType::Runtime),
+        "string" => Ok(FFIType::String),
+        other => Err(malformed!("unknown extern type `{}`", other)),
+    }
+}
+
+/// Removes every `(feature name)` declaration, and replaces every
+/// `(when name ...nodes)` with its nodes if the feature is enabled, or nothing
+/// if it isn't.
+///
+/// ```text
+/// (feature annex-b)
+///
+/// (when annex-b
+///     ((:B.3.3 Example ()) ((return))))
+/// ```
+///
+/// A `when` may only refer to features declared in the file.
+fn parse_with_feature_gates(nodes: Vec<Node>, features: &[&str]) -> Result<Vec<Node>, ParseError> {
+    let declared = (nodes.iter())
+        .filter(|node| header_word(node) == Some("feature"))
+        .map(|node| match node.as_ref() {
+            Node::Parent([_, Node::Word(name, _)], _) => Ok(name.clone()),
+            _ => Err(malformed!(
+                "malformed feature declaration {}",
+                node.to_lisp()
+            )),
         })
-        .collect();
+        .collect::<Result<Vec<_>, _>>()?;
+
+    apply_feature_gates(nodes, features, &declared)
+}
+
+fn apply_feature_gates(
+    nodes: Vec<Node>,
+    features: &[&str],
+    declared: &[String],
+) -> Result<Vec<Node>, ParseError> {
+    let mut new_nodes = Vec::new();
+
+    for node in nodes {
+        match header_word(&node) {
+            Some("feature") => {}
+            Some("when") => {
+                let feature = match node.as_ref() {
+                    Node::Parent([_, Node::Word(feature, _), ..], _) => feature.clone(),
+                    _ => return Err(malformed!("malformed feature gate {}", node.to_lisp())),
+                };
+
+                if !declared.contains(&feature) {
+                    return Err(malformed!("unknown feature `{}` in `when`", feature));
+                }
+
+                if features.contains(&feature.as_str()) {
+                    let gated = node.into_parent()?.into_iter().skip(2).collect();
+                    new_nodes.extend(apply_feature_gates(gated, features, declared)?);
+                }
+            }
+            _ => new_nodes.push(node),
+        }
+    }
 
-    AST { source, sections }
+    Ok(new_nodes)
 }
 
-fn parse_with_rule_application(nodes: Vec<Node>) -> Vec<Node> {
+fn header_word(node: &Node) -> Option<&str> {
+    match node {
+        Node::Parent(children, _) => match children.get(0) {
+            Some(Node::Word(header_word, _)) => Some(header_word.as_str()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn parse_with_rule_application(nodes: Vec<Node>) -> Result<Vec<Node>, ParseError> {
     let mut new_nodes = Vec::new();
     let mut custom_rules = Vec::new();
 
@@ -45,7 +340,7 @@ fn parse_with_rule_application(nodes: Vec<Node>) -> Vec<Node> {
                 if header_word == "def" {
                     let (rule, generate) = match (children.get(1), children.get(2)) {
                         (Some(n), Some(m)) => (n.clone(), m.clone()),
-                        _ => panic!("malformed rule {}", node.to_lisp()),
+                        _ => return Err(malformed!("malformed rule {}", node.to_lisp())),
                     };
 
                     custom_rules.push((rule, generate));
@@ -59,11 +354,17 @@ fn parse_with_rule_application(nodes: Vec<Node>) -> Vec<Node> {
     // this will mean that once we match a rule, it will already be fully expanded
     // which is useful for both performance and for ease of use (as we now mostly
     // don't have to care about the order things are defined in)
+    // every pass fully expands at least one more level of rules that refer to
+    // other rules, so rules that don't refer back to themselves are expanded
+    // within a pass per rule
+    let max_passes = custom_rules.len() + 1;
+
     custom_rules = custom_rules
         .iter()
         // for every rewrite rule
         .map(|(rule, generate)| {
             let mut new_generate = generate.clone();
+            let mut passes = 0;
 
             // keep rewriting the rule using the existing rules until it is fully rewritten
             // (i.e. expanded)
@@ -85,12 +386,21 @@ fn parse_with_rule_application(nodes: Vec<Node>) -> Vec<Node> {
                         });
 
                 did_change
-            }) {}
+            }) {
+                passes += 1;
+
+                if passes == max_passes {
+                    return Err(ParseError::RecursiveRule {
+                        filename: UNNAMED_FILE.to_owned(),
+                        rule: rule.to_lisp(),
+                    });
+                }
+            }
 
             // use the fully rewritten rule as the new rule
-            (rule.clone(), new_generate)
+            Ok((rule.clone(), new_generate))
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     for node in nodes {
         if let Node::Parent(children, _) = &node {
@@ -108,7 +418,7 @@ fn parse_with_rule_application(nodes: Vec<Node>) -> Vec<Node> {
         new_nodes.push(node);
     }
 
-    new_nodes
+    Ok(new_nodes)
 }
 
 #[cfg(test)]
@@ -118,7 +428,7 @@ mod parse_with_rule_application_tests {
     macro_rules! parse {
         ($code: expr, $yields: expr) => {
             assert_eq!(
-                parse_with_rule_application(parse_to_nodes($code)),
+                parse_with_rule_application(parse_to_nodes($code)).unwrap(),
                 parse_to_nodes($yields)
             )
         };
@@ -214,235 +524,400 @@ mod parse_with_rule_application_tests {
     }
 }
 
-fn parse_header(header: Node) -> Header {
+fn parse_header(header: Node) -> Result<Header, ParseError> {
     let span = header.span();
-    let mut header = header.expect_parent();
-    let parameters = header.pop().unwrap().expect_parent();
-    let method_name = header.pop().unwrap().expect_word();
-    let document_index = header.pop().unwrap().expect_atom();
-    assert!(header.is_empty());
+    let mut header = header.into_parent()?;
+    let mut next =
+        || (header.pop()).ok_or_else(|| malformed!("incomplete header on {}", DisplaySpan(span)));
+    let parameters = next()?.into_parent()?;
+    let method_name = next()?.into_word()?;
+    let document_index = next()?.into_atom()?;
 
     let parameters = parameters
         .into_iter()
-        .map(|p| p.expect_word().trim().trim_matches(',').to_string())
-        .collect();
+        .map(|p| Ok(p.into_word()?.trim().trim_matches(',').to_string()))
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    let mut doc = None;
+    let mut specialize_on = Vec::new();
+    for annotation in header {
+        match parse_specialize_on(&annotation)? {
+            Some(names) => specialize_on.extend(names),
+            None if doc.is_none() => doc = Some(parse_doc(annotation)?),
+            None => {
+                return Err(malformed!(
+                    "unrecognized header annotation {}",
+                    annotation.to_lisp()
+                ))
+            }
+        }
+    }
+
+    for name in specialize_on.iter() {
+        if !parameters.contains(name) {
+            return Err(malformed!(
+                "`specialize-on` names `{}`, which is not a parameter of {}",
+                name,
+                method_name
+            ));
+        }
+    }
 
-    Header {
+    Ok(Header {
         document_index,
         method_name,
         parameters,
+        doc,
+        specialize_on,
         span,
+    })
+}
+
+/// Parses a `(specialize-on ...names)` header annotation into the names of the
+/// parameters it lists, or `None` if `annotation` is something else.
+fn parse_specialize_on(annotation: &Node) -> Result<Option<Vec<String>>, ParseError> {
+    match annotation {
+        Node::Parent(children, _) => match children.split_first() {
+            Some((Node::Word(word, _), names)) if word == "specialize-on" => names
+                .iter()
+                .map(|name| name.clone().into_word())
+                .collect::<Result<_, _>>()
+                .map(Some),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
     }
 }
 
-fn parse_body(body: Vec<Node>) -> Vec<Statement> {
-    body.into_iter()
-        .map(|node| {
-            let node_span = node.span();
-            let children = match node {
-                Node::Parent(children, _) => children,
-                other => panic!("expected parent node, got {:?}", other),
-            };
+fn parse_doc(doc: Node) -> Result<String, ParseError> {
+    if let Node::Parent(children, _) = &doc {
+        if let [Node::Word(word, _), Node::String(doc, _)] = children.as_slice() {
+            if word == "doc" {
+                return Ok(doc.clone());
+            }
+        }
+    }
 
-            let get = |x| children.get(x).map(Node::as_ref);
-            let getp = |x| children.get(x);
-
-            if let (
-                Some(Node::Word("loop", _)),
-                Some(assigns),
-                Some(cond),
-                Some(next),
-                Some(body),
-            ) = (get(0), getp(1), get(2), getp(3), getp(4))
-            {
-                let assigns = parse_body(assigns.clone().expect_parent())
-                    .into_iter()
-                    .map(|s| match s.data {
-                        StatementData::Assign(a) => a,
-                        _ => panic!("expected list of assignments"),
-                    })
-                    .collect();
-
-                let nexts = parse_body(next.clone().expect_parent())
-                    .into_iter()
-                    .map(|s| match s.data {
-                        StatementData::Assign(a) => a,
-                        _ => panic!("expected list of assignments"),
-                    })
-                    .collect();
-
-                let expr = parse_expression(cond);
-
-                let body = parse_body(body.clone().expect_parent());
-
-                return Statement {
-                    span: node_span,
-                    data: StatementData::Loop {
-                        init: assigns,
-                        next: nexts,
-                        cond: expr,
-                        body,
-                    },
-                };
+    match doc {
+        Node::String(doc, _) => Ok(doc),
+        other => Err(malformed!(
+            "unrecognized header documentation {}",
+            other.to_lisp()
+        )),
+    }
+}
+
+fn parse_body(body: &[Node]) -> Result<Vec<Statement>, ParseError> {
+    grow_stack(|| parse_body_impl(body))
+}
+
+fn parse_body_impl(body: &[Node]) -> Result<Vec<Statement>, ParseError> {
+    body.iter().map(parse_statement).collect()
+}
+
+fn parse_statement(node: &Node) -> Result<Statement, ParseError> {
+    let node_span = node.span();
+    let children = match node.as_ref() {
+        Node::Parent(children, _) => children,
+        other => return Err(malformed!("expected parent node, got {}", other.to_lisp())),
+    };
+
+    let get = |x| children.get(x).map(Node::as_ref);
+    let getp = |x| children.get(x);
+
+    if let (Some(Node::Word("loop", _)), Some(assigns), Some(cond), Some(next), Some(body)) =
+        (get(0), getp(1), get(2), getp(3), getp(4))
+    {
+        if getp(5).is_some() {
+            return Err(malformed!(
+                "a loop statement cannot have an `else` value on {}",
+                DisplaySpan(node_span)
+            ));
+        }
+
+        let assigns = parse_assigns(assigns)?;
+        let nexts = parse_assigns(next)?;
+
+        let expr = parse_expression(cond)?;
+
+        let body = parse_body(body.as_ref().into_parent()?)?;
+
+        return Ok(Statement {
+            span: node_span,
+            data: StatementData::Loop {
+                init: assigns,
+                next: nexts,
+                cond: expr,
+                body,
+            },
+        });
+    }
+
+    if let (Some(Node::Word("match", _)), Some(discriminant)) = (get(0), get(1)) {
+        let discriminant = parse_expression(discriminant)?;
+
+        let mut cases = Vec::new();
+        let mut r#else = None;
+
+        for case in children.iter().skip(2) {
+            let case_span = case.span();
+            let case = case.as_ref().into_parent()?;
+            if case.len() != 2 {
+                return Err(malformed!(
+                    "expected match case `(value (body...))` on {}",
+                    DisplaySpan(case_span)
+                ));
             }
 
-            match (get(0), get(1), get(2), get(3)) {
-                (Some(Node::Word("assert", _)), Some(expr), Some(Node::String(msg, _)), None) => {
-                    Statement {
-                        span: node_span,
-                        data: StatementData::Assert {
-                            expr: parse_expression(expr),
-                            message: msg.to_owned(),
-                        },
-                    }
-                }
-                // (Some(Node::Word("comment", _)), Some(Node::String(msg, _)), None, None) => {
-                //     Statement::Comment {
-                //         message: msg.to_string(),
-                //         location: node_span,
-                //     }
-                // }
-                (Some(Node::Word(identifier, _)), Some(Node::Word("=", _)), Some(expr), None) => {
-                    Statement {
-                        span: node_span,
-                        data: StatementData::Assign(Assign {
-                            variable: identifier.to_string(),
-                            value: parse_expression(expr),
-                        }),
-                    }
-                }
-                (
-                    Some(Node::Word("record-set-slot", _)),
-                    Some(record),
-                    Some(Node::Word(slot, _)),
-                    Some(value),
-                ) => Statement {
-                    span: node_span,
-                    data: StatementData::RecordSetSlot {
-                        record: parse_expression(record),
-                        slot: slot.to_owned(),
-                        value: Some(parse_expression(value)),
-                    },
-                },
-                (
-                    Some(Node::Word("record-del-slot", _)),
-                    Some(record),
-                    Some(Node::Word(slot, _)),
-                    None,
-                ) => Statement {
-                    span: node_span,
-                    data: StatementData::RecordSetSlot {
-                        record: parse_expression(record),
-                        slot: slot.to_owned(),
-                        value: None,
-                    },
-                },
-                (Some(Node::Word("record-set-prop", _)), Some(record), Some(prop), Some(value)) => {
-                    Statement {
-                        span: node_span,
-                        data: StatementData::RecordSetProp {
-                            record: parse_expression(record),
-                            prop: parse_expression(prop),
-                            value: Some(parse_expression(value)),
-                        },
-                    }
-                }
-                (Some(Node::Word("record-del-prop", _)), Some(record), Some(prop), None) => {
-                    Statement {
-                        span: node_span,
-                        data: StatementData::RecordSetProp {
-                            record: parse_expression(record),
-                            prop: parse_expression(prop),
-                            value: None,
-                        },
-                    }
-                }
-                (Some(Node::Word("list-set", _)), Some(list), Some(prop), Some(value)) => {
-                    Statement {
-                        span: node_span,
-                        data: StatementData::ListSet {
-                            list: parse_expression(list),
-                            prop: parse_expression(prop),
-                            value: Some(parse_expression(value)),
-                        },
-                    }
-                }
-                (Some(Node::Word("list-del", _)), Some(list), Some(prop), None) => Statement {
-                    span: node_span,
-                    data: StatementData::ListSet {
-                        list: parse_expression(list),
-                        prop: parse_expression(prop),
-                        value: None,
-                    },
-                },
-                (Some(Node::Word("call", _)), Some(Node::Word(fn_name, _)), _, _) => Statement {
-                    span: node_span,
-                    data: StatementData::CallStatic {
-                        function_name: fn_name.to_owned(),
-                        args: children
-                            .iter()
-                            .skip(2)
-                            .map(|node| parse_expression(node.as_ref()))
-                            .collect(),
-                    },
+            let body = parse_body(case[1].as_ref().into_parent()?)?;
+
+            if r#else.is_some() {
+                return Err(malformed!(
+                    "`else` must be the last match case on {}",
+                    DisplaySpan(case_span)
+                ));
+            }
+            match case[0].as_ref() {
+                Node::Word("else", _) => r#else = Some(body),
+                value => cases.push(MatchCase {
+                    value: parse_expression(value)?,
+                    body,
+                }),
+            }
+        }
+
+        return Ok(Statement {
+            span: node_span,
+            data: StatementData::Match {
+                discriminant,
+                cases,
+                r#else,
+            },
+        });
+    }
+
+    Ok(match (get(0), get(1), get(2), get(3)) {
+        (Some(Node::Word("assert", _)), Some(expr), Some(Node::String(msg, _)), None) => {
+            Statement {
+                span: node_span,
+                data: StatementData::Assert {
+                    expr: parse_expression(expr)?,
+                    message: msg.to_owned(),
                 },
-                (Some(Node::Word("call-virt", _)), Some(expr), _, _) => Statement {
-                    span: node_span,
-                    data: StatementData::CallVirt {
-                        fn_ptr: parse_expression(expr),
-                        args: children
-                            .iter()
-                            .skip(2)
-                            .map(|node| parse_expression(node.as_ref()))
-                            .collect(),
-                    },
+            }
+        }
+        // (Some(Node::Word("comment", _)), Some(Node::String(msg, _)), None, None) => {
+        //     Statement::Comment {
+        //         message: msg.to_string(),
+        //         location: node_span,
+        //     }
+        // }
+        (Some(Node::Word(identifier, _)), Some(Node::Word("=", _)), Some(expr), None) => {
+            Statement {
+                span: node_span,
+                data: StatementData::Assign(Assign {
+                    variable: identifier.to_string(),
+                    value: parse_expression(expr)?,
+                }),
+            }
+        }
+        (
+            Some(Node::Word("record-set-slot", _)),
+            Some(record),
+            Some(Node::Word(slot, _)),
+            Some(value),
+        ) => Statement {
+            span: node_span,
+            data: StatementData::RecordSetSlot {
+                record: parse_expression(record)?,
+                slot: slot.to_owned(),
+                value: Some(parse_expression(value)?),
+            },
+        },
+        // `Prototype` is dealt the same atom as `InternalSlot::Prototype`,
+        // which is the slot used for inherited lookups
+        (Some(Node::Word("record-set-prototype", _)), Some(record), Some(prototype), None) => {
+            Statement {
+                span: node_span,
+                data: StatementData::RecordSetSlot {
+                    record: parse_expression(record)?,
+                    slot: "Prototype".to_owned(),
+                    value: Some(parse_expression(prototype)?),
                 },
-                (Some(Node::Word("if", _)), Some(condition), Some(Node::Parent(then, _)), None) => {
-                    Statement {
-                        span: node_span,
-                        data: StatementData::If {
-                            condition: parse_expression(condition),
-                            then: parse_body(then),
-                            r#else: None,
-                        },
-                    }
-                }
-                (
-                    Some(Node::Word("if", _)),
-                    Some(condition),
-                    Some(Node::Parent(then, _)),
-                    Some(Node::Parent(r#else, _)),
-                ) => Statement {
-                    span: node_span,
-                    data: StatementData::If {
-                        condition: parse_expression(condition),
-                        then: parse_body(then),
-                        r#else: Some(parse_body(r#else)),
-                    },
+            }
+        }
+        (Some(Node::Word("record-del-slot", _)), Some(record), Some(Node::Word(slot, _)), None) => {
+            Statement {
+                span: node_span,
+                data: StatementData::RecordSetSlot {
+                    record: parse_expression(record)?,
+                    slot: slot.to_owned(),
+                    value: None,
                 },
-                (Some(Node::Word("return", _)), Some(expr), None, None) => Statement {
-                    span: node_span,
-                    data: StatementData::Return {
-                        expr: Some(parse_expression(expr)),
-                    },
+            }
+        }
+        (Some(Node::Word("record-set-prop", _)), Some(record), Some(prop), Some(value)) => {
+            Statement {
+                span: node_span,
+                data: StatementData::RecordSetProp {
+                    record: parse_expression(record)?,
+                    prop: parse_expression(prop)?,
+                    value: Some(parse_expression(value)?),
                 },
-                (Some(Node::Word("return", _)), None, None, None) => Statement {
-                    span: node_span,
-                    data: StatementData::Return { expr: None },
+            }
+        }
+        (Some(Node::Word("record-del-prop", _)), Some(record), Some(prop), None) => Statement {
+            span: node_span,
+            data: StatementData::RecordSetProp {
+                record: parse_expression(record)?,
+                prop: parse_expression(prop)?,
+                value: None,
+            },
+        },
+        (Some(Node::Word("list-set", _)), Some(list), Some(prop), Some(value)) => Statement {
+            span: node_span,
+            data: StatementData::ListSet {
+                list: parse_expression(list)?,
+                prop: parse_expression(prop)?,
+                value: Some(parse_expression(value)?),
+            },
+        },
+        (Some(Node::Word("list-del", _)), Some(list), Some(prop), None) => Statement {
+            span: node_span,
+            data: StatementData::ListSet {
+                list: parse_expression(list)?,
+                prop: parse_expression(prop)?,
+                value: None,
+            },
+        },
+        (Some(Node::Word("list-push", _)), Some(list), Some(value), None) => Statement {
+            span: node_span,
+            data: StatementData::ListPush {
+                list: parse_expression(list)?,
+                value: parse_expression(value)?,
+            },
+        },
+        (Some(Node::Word("list-for-each", _)), Some(list), Some(function), None) => Statement {
+            span: node_span,
+            data: StatementData::ListForEach {
+                list: parse_expression(list)?,
+                function: parse_expression(function)?,
+                args: Vec::new(),
+            },
+        },
+        (Some(Node::Word("call", _)), Some(Node::Word(fn_name, _)), _, _) => Statement {
+            span: node_span,
+            data: StatementData::CallStatic {
+                function_name: fn_name.to_owned(),
+                args: children
+                    .iter()
+                    .skip(2)
+                    .map(|node| parse_expression(node.as_ref()))
+                    .collect::<Result<_, _>>()?,
+            },
+        },
+        (Some(Node::Word("call-extern", _)), Some(Node::Word(fn_name, _)), _, _) => Statement {
+            span: node_span,
+            data: StatementData::CallExternal {
+                function_name: fn_name.to_owned(),
+                args: children
+                    .iter()
+                    .skip(2)
+                    .map(|node| parse_expression(node.as_ref()))
+                    .collect::<Result<_, _>>()?,
+            },
+        },
+        (Some(Node::Word("call-virt", _)), Some(expr), _, _) => Statement {
+            span: node_span,
+            data: StatementData::CallVirt {
+                fn_ptr: parse_expression(expr)?,
+                args: children
+                    .iter()
+                    .skip(2)
+                    .map(|node| parse_expression(node.as_ref()))
+                    .collect::<Result<_, _>>()?,
+            },
+        },
+        (Some(Node::Word("if", _)), Some(condition), Some(Node::Parent(then, _)), None) => {
+            Statement {
+                span: node_span,
+                data: StatementData::If {
+                    condition: parse_expression(condition)?,
+                    then: parse_body(then)?,
+                    r#else: None,
                 },
-                _ => panic!(
-                    "unrecognized statement {}",
-                    // TODO(maybe-rustc-bug): why can't rustc infer the type here?
-                    Node::<String>::Parent(children, node_span).to_lisp()
-                ),
+            }
+        }
+        (
+            Some(Node::Word("if", _)),
+            Some(condition),
+            Some(Node::Parent(then, _)),
+            Some(Node::Parent(r#else, _)),
+        ) => Statement {
+            span: node_span,
+            data: StatementData::If {
+                condition: parse_expression(condition)?,
+                then: parse_body(then)?,
+                r#else: Some(parse_body(r#else)?),
+            },
+        },
+        (Some(Node::Word("return", _)), Some(expr), None, None) => Statement {
+            span: node_span,
+            data: StatementData::Return {
+                expr: Some(parse_expression(expr)?),
+            },
+        },
+        (Some(Node::Word("return", _)), None, None, None) => Statement {
+            span: node_span,
+            data: StatementData::Return { expr: None },
+        },
+        (Some(Node::Word("break", _)), Some(expr), None, None) => Statement {
+            span: node_span,
+            data: StatementData::Break {
+                value: Some(parse_expression(expr)?),
+            },
+        },
+        (Some(Node::Word("break", _)), None, None, None) => Statement {
+            span: node_span,
+            data: StatementData::Break { value: None },
+        },
+        (Some(Node::Word("continue", _)), None, None, None) => Statement {
+            span: node_span,
+            data: StatementData::Continue,
+        },
+        _ => {
+            return Err(malformed!(
+                "unrecognized statement {}",
+                // TODO(maybe-rustc-bug): why can't rustc infer the type here?
+                Node::<String>::Parent(children.to_vec(), node_span).to_lisp()
+            ));
+        }
+    })
+}
+
+fn parse_assigns(assigns: &Node) -> Result<Vec<Assign>, ParseError> {
+    parse_body(assigns.as_ref().into_parent()?)?
+        .into_iter()
+        .map(|mut s| {
+            // statements can't be moved out of, as they're dropped specially
+            match std::mem::replace(&mut s.data, StatementData::Continue) {
+                StatementData::Assign(a) => Ok(a),
+                _ => Err(malformed!(
+                    "expected list of assignments on {}",
+                    DisplaySpan(s.span)
+                )),
             }
         })
         .collect()
 }
 
-fn parse_expression(node: Node<&str>) -> Expression {
+fn parse_expression(node: Node<&str, &[Node]>) -> Result<Expression, ParseError> {
+    grow_stack(|| parse_expression_impl(node))
+}
+
+fn parse_expression_impl(node: Node<&str, &[Node]>) -> Result<Expression, ParseError> {
     let node_span = Some(node.span());
-    match node {
+    Ok(match node {
         Node::Word("get-global", _) => Expression {
             span: node_span,
             data: ExpressionData::GetGlobal,
@@ -479,13 +954,17 @@ fn parse_expression(node: Node<&str>) -> Expression {
                 bytes: str.as_bytes().to_owned(),
             },
         },
-        Node::Number(num, _) => Expression {
+        Node::Number(num, span) => Expression {
             span: node_span,
             data: ExpressionData::MakeInteger {
-                value: if let Some(v) = num.as_i64() {
-                    v
-                } else {
-                    panic!("cannot do fp at this time")
+                value: match num.as_i64() {
+                    Some(value) => value,
+                    None => {
+                        return Err(malformed!(
+                            "cannot do fp at this time on {}",
+                            DisplaySpan(span)
+                        ))
+                    }
                 },
             },
         },
@@ -502,49 +981,108 @@ fn parse_expression(node: Node<&str>) -> Expression {
                     Some(expr2),
                 ) => {
                     let r#in = match expr2 {
-                        Node::Parent(mut children, _) => {
-                            let result = children.pop().unwrap();
+                        Node::Parent(children, span) => {
+                            let (result, children) = children.split_last().ok_or_else(|| {
+                                malformed!("expected a result for `in` on {}", DisplaySpan(span))
+                            })?;
                             (
-                                parse_body(children),
-                                Box::new(parse_expression(result.as_ref())),
+                                parse_body(children)?,
+                                Box::new(parse_expression(result.as_ref())?),
                             )
                         }
-                        expr => (Vec::new(), Box::new(parse_expression(expr))),
+                        expr => (Vec::new(), Box::new(parse_expression(expr)?)),
                     };
 
-                    return Expression {
+                    return Ok(Expression {
                         span: node_span,
                         data: ExpressionData::LetIn {
                             variable: identifier.into(),
-                            be_bound_to: Box::new(parse_expression(expr)),
+                            be_bound_to: Box::new(parse_expression(expr)?),
                             r#in,
                         },
-                    };
+                    });
                 }
                 (
                     Some(Node::Word("if", _)),
                     Some(condition),
-                    Some(Node::Parent(mut then, _)),
-                    Some(Node::Parent(mut r#else, _)),
+                    Some(Node::Parent(then, _)),
+                    Some(Node::Parent(r#else, _)),
                     None,
                     None,
                 ) => {
-                    let condition = parse_expression(condition);
+                    let condition = parse_expression(condition)?;
 
-                    let then_expr = parse_expression(then.pop().unwrap().as_ref());
-                    let then_stmts = parse_body(then);
+                    let empty_arm =
+                        || malformed!("expected a result for `if` on {}", DisplaySpan(parent_span));
+                    let (then, then_stmts) = then.split_last().ok_or_else(empty_arm)?;
+                    let then_expr = parse_expression(then.as_ref())?;
+                    let then_stmts = parse_body(then_stmts)?;
 
-                    let else_expr = parse_expression(r#else.pop().unwrap().as_ref());
-                    let else_stmts = parse_body(r#else);
+                    let (r#else, else_stmts) = r#else.split_last().ok_or_else(empty_arm)?;
+                    let else_expr = parse_expression(r#else.as_ref())?;
+                    let else_stmts = parse_body(else_stmts)?;
 
-                    return Expression {
+                    return Ok(Expression {
                         span: node_span,
                         data: ExpressionData::If {
                             condition: Box::new(condition),
                             then: (then_stmts, Box::new(then_expr)),
                             r#else: (else_stmts, Box::new(else_expr)),
                         },
-                    };
+                    });
+                }
+                (
+                    Some(Node::Word("loop", _)),
+                    Some(Node::Parent(..)),
+                    Some(cond),
+                    Some(Node::Parent(..)),
+                    Some(Node::Parent(body, _)),
+                    Some(r#else),
+                ) if children.len() == 6 => {
+                    return Ok(Expression {
+                        span: node_span,
+                        data: ExpressionData::Loop {
+                            init: parse_assigns(&children[1])?,
+                            cond: Box::new(parse_expression(cond)?),
+                            next: parse_assigns(&children[3])?,
+                            body: parse_body(body)?,
+                            r#else: Box::new(parse_expression(r#else)?),
+                        },
+                    });
+                }
+                (
+                    Some(Node::Word("record-get-slot-or", _)),
+                    Some(record),
+                    Some(Node::Word(slot, _)),
+                    Some(default),
+                    None,
+                    None,
+                ) => {
+                    return Ok(Expression {
+                        span: node_span,
+                        data: ExpressionData::RecordGetSlotOr {
+                            record: Box::new(parse_expression(record)?),
+                            slot: slot.to_string(),
+                            default: Box::new(parse_expression(default)?),
+                        },
+                    });
+                }
+                (
+                    Some(Node::Word("bytes-slice", _)),
+                    Some(bytes),
+                    Some(start),
+                    Some(end),
+                    None,
+                    None,
+                ) => {
+                    return Ok(Expression {
+                        span: node_span,
+                        data: ExpressionData::BytesSlice {
+                            bytes: Box::new(parse_expression(bytes)?),
+                            start: Box::new(parse_expression(start)?),
+                            end: Box::new(parse_expression(end)?),
+                        },
+                    });
                 }
                 _ => {}
             };
@@ -553,8 +1091,8 @@ fn parse_expression(node: Node<&str>) -> Expression {
                 (Some(Node::Word("record-get-prop", _)), Some(record), Some(expr)) => Expression {
                     span: node_span,
                     data: ExpressionData::RecordGetProp {
-                        record: Box::new(parse_expression(record)),
-                        property: Box::new(parse_expression(expr)),
+                        record: Box::new(parse_expression(record)?),
+                        property: Box::new(parse_expression(expr)?),
                     },
                 },
                 (
@@ -564,15 +1102,15 @@ fn parse_expression(node: Node<&str>) -> Expression {
                 ) => Expression {
                     span: node_span,
                     data: ExpressionData::RecordGetSlot {
-                        record: Box::new(parse_expression(record)),
+                        record: Box::new(parse_expression(record)?),
                         slot: slot.to_owned(),
                     },
                 },
                 (Some(Node::Word("record-has-prop", _)), Some(record), Some(expr)) => Expression {
                     span: node_span,
                     data: ExpressionData::RecordHasProp {
-                        record: Box::new(parse_expression(record)),
-                        property: Box::new(parse_expression(expr)),
+                        record: Box::new(parse_expression(record)?),
+                        property: Box::new(parse_expression(expr)?),
                     },
                 },
                 (
@@ -582,35 +1120,61 @@ fn parse_expression(node: Node<&str>) -> Expression {
                 ) => Expression {
                     span: node_span,
                     data: ExpressionData::RecordHasSlot {
-                        record: Box::new(parse_expression(record)),
+                        record: Box::new(parse_expression(record)?),
                         slot: SlotOrExpr::Slot(slot.to_owned()),
                     },
                 },
                 (Some(Node::Word("record-has-slot", _)), Some(record), Some(expr)) => Expression {
                     span: node_span,
                     data: ExpressionData::RecordHasSlot {
-                        record: Box::new(parse_expression(record)),
-                        slot: SlotOrExpr::Expr(Box::new(parse_expression(expr))),
+                        record: Box::new(parse_expression(record)?),
+                        slot: SlotOrExpr::Expr(Box::new(parse_expression(expr)?)),
                     },
                 },
                 (Some(Node::Word("list-get", _)), Some(list), Some(expr)) => Expression {
                     span: node_span,
                     data: ExpressionData::ListGet {
-                        list: Box::new(parse_expression(list)),
-                        property: Box::new(parse_expression(expr)),
+                        list: Box::new(parse_expression(list)?),
+                        property: Box::new(parse_expression(expr)?),
                     },
                 },
                 (Some(Node::Word("list-has", _)), Some(list), Some(expr)) => Expression {
                     span: node_span,
                     data: ExpressionData::ListHas {
-                        list: Box::new(parse_expression(list)),
-                        property: Box::new(parse_expression(expr)),
+                        list: Box::new(parse_expression(list)?),
+                        property: Box::new(parse_expression(expr)?),
+                    },
+                },
+                (Some(Node::Word("record-clone", _)), Some(record), None) => Expression {
+                    span: node_span,
+                    data: ExpressionData::RecordClone {
+                        record: Box::new(parse_expression(record)?),
+                    },
+                },
+                (Some(Node::Word("record-entries", _)), Some(record), None) => Expression {
+                    span: node_span,
+                    data: ExpressionData::RecordEntries {
+                        record: Box::new(parse_expression(record)?),
                     },
                 },
                 (Some(Node::Word("list-len", _)), Some(list), None) => Expression {
                     span: node_span,
                     data: ExpressionData::ListLen {
-                        list: Box::new(parse_expression(list)),
+                        list: Box::new(parse_expression(list)?),
+                    },
+                },
+                (Some(Node::Word("list-pop", _)), Some(list), None) => Expression {
+                    span: node_span,
+                    data: ExpressionData::ListPop {
+                        list: Box::new(parse_expression(list)?),
+                    },
+                },
+                (Some(Node::Word("list-map", _)), Some(list), Some(function)) => Expression {
+                    span: node_span,
+                    data: ExpressionData::ListMap {
+                        list: Box::new(parse_expression(list)?),
+                        function: Box::new(parse_expression(function)?),
+                        args: Vec::new(),
                     },
                 },
                 (Some(Node::Word("get-fn-ptr", _)), Some(Node::Word(fn_name, _)), None) => {
@@ -621,6 +1185,12 @@ fn parse_expression(node: Node<&str>) -> Expression {
                         },
                     }
                 }
+                (Some(Node::Word("get-fn-ptr-dynamic", _)), Some(name), None) => Expression {
+                    span: node_span,
+                    data: ExpressionData::GetFnPtrDynamic {
+                        name: Box::new(parse_expression(name)?),
+                    },
+                },
                 (Some(Node::Word("call", _)), Some(Node::Word(fn_name, _)), _) => Expression {
                     span: node_span,
                     data: ExpressionData::CallStatic {
@@ -629,18 +1199,31 @@ fn parse_expression(node: Node<&str>) -> Expression {
                             .iter()
                             .skip(2)
                             .map(|node| parse_expression(node.as_ref()))
-                            .collect(),
+                            .collect::<Result<_, _>>()?,
                     },
                 },
+                (Some(Node::Word("call-extern", _)), Some(Node::Word(fn_name, _)), _) => {
+                    Expression {
+                        span: node_span,
+                        data: ExpressionData::CallExternal {
+                            function_name: fn_name.to_owned(),
+                            args: children
+                                .iter()
+                                .skip(2)
+                                .map(|node| parse_expression(node.as_ref()))
+                                .collect::<Result<_, _>>()?,
+                        },
+                    }
+                }
                 (Some(Node::Word("call-virt", _)), Some(expr), _) => Expression {
                     span: node_span,
                     data: ExpressionData::CallVirt {
-                        fn_ptr: Box::new(parse_expression(expr)),
+                        fn_ptr: Box::new(parse_expression(expr)?),
                         args: children
                             .iter()
                             .skip(2)
                             .map(|node| parse_expression(node.as_ref()))
-                            .collect(),
+                            .collect::<Result<_, _>>()?,
                     },
                 },
                 (Some(Node::Word("atom", _)), Some(Node::Word(atom, _)), None) => Expression {
@@ -666,21 +1249,30 @@ fn parse_expression(node: Node<&str>) -> Expression {
                             "or" => BinOpKind::Or,
                             _ => unreachable!("what"),
                         },
-                        lhs: Box::new(parse_expression(lhs)),
-                        rhs: Box::new(parse_expression(rhs)),
+                        lhs: Box::new(parse_expression(lhs)?),
+                        rhs: Box::new(parse_expression(rhs)?),
                     },
                 },
                 (Some(Node::Word("not", _)), Some(expr), None) => Expression {
                     span: node_span,
                     data: ExpressionData::Negate {
-                        expr: Box::new(parse_expression(expr)),
+                        expr: Box::new(parse_expression(expr)?),
                     },
                 },
                 (Some(Node::Word("is-type-of", _)), Some(Node::Word(kind, _)), Some(expr)) => {
                     Expression {
                         span: node_span,
                         data: ExpressionData::IsTypeOf {
-                            expr: Box::new(parse_expression(expr)),
+                            expr: Box::new(parse_expression(expr)?),
+                            kind: kind.to_owned(),
+                        },
+                    }
+                }
+                (Some(Node::Word("assume-type", _)), Some(Node::Word(kind, _)), Some(expr)) => {
+                    Expression {
+                        span: node_span,
+                        data: ExpressionData::AssumeType {
+                            expr: Box::new(parse_expression(expr)?),
                             kind: kind.to_owned(),
                         },
                     }
@@ -688,24 +1280,41 @@ fn parse_expression(node: Node<&str>) -> Expression {
                 (Some(Node::Word("is-type-as", _)), Some(lhs), Some(rhs)) => Expression {
                     span: node_span,
                     data: ExpressionData::IsTypeAs {
-                        lhs: Box::new(parse_expression(lhs)),
-                        rhs: Box::new(parse_expression(rhs)),
+                        lhs: Box::new(parse_expression(lhs)?),
+                        rhs: Box::new(parse_expression(rhs)?),
+                    },
+                },
+                (Some(Node::Word("type-of", _)), Some(expr), None) => Expression {
+                    span: node_span,
+                    data: ExpressionData::TypeOf {
+                        expr: Box::new(parse_expression(expr)?),
+                    },
+                },
+                (Some(Node::Word("bytes-concat", _)), Some(lhs), Some(rhs)) => Expression {
+                    span: node_span,
+                    data: ExpressionData::BytesConcat {
+                        lhs: Box::new(parse_expression(lhs)?),
+                        rhs: Box::new(parse_expression(rhs)?),
                     },
                 },
-                (Some(parenthetical), None, None) => parse_expression(parenthetical),
-                _ => panic!(
-                    "unrecognized expression {:?} at {:?}",
-                    Node::<String>::Parent(children, parent_span).to_lisp(),
-                    parent_span
-                ),
+                (Some(parenthetical), None, None) => parse_expression(parenthetical)?,
+                _ => {
+                    return Err(malformed!(
+                        "unrecognized expression {:?} at {}",
+                        Node::<String>::Parent(children.to_vec(), parent_span).to_lisp(),
+                        DisplaySpan(parent_span)
+                    ))
+                }
             }
         }
-        other => panic!(
-            "unrecognized expression {:?} at {:?}",
-            other.to_lisp(),
-            other.span()
-        ),
-    }
+        other => {
+            return Err(malformed!(
+                "unrecognized expression {:?} at {}",
+                other.to_lisp(),
+                DisplaySpan(other.span())
+            ))
+        }
+    })
 }
 
 #[test]
@@ -722,11 +1331,183 @@ fn parses_header() {
     //         document_index: "6.9.4.2".into(),
     //         method_name: "TheFunctionName".into(),
     //         parameters: vec!["a".into(), "b".into(), "c".into()],
+    //         doc: None,
     //         span: Span::,
     //     }
     // );
 }
 
+#[test]
+fn lenient_parse_rejects_malformed_code() {
+    let nested = format!("{}{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert_eq!(
+        parse_lenient(&nested, &[]),
+        Err(ParseError::TooDeep {
+            filename: UNNAMED_FILE.to_owned(),
+            max_depth: MAX_NESTING_DEPTH
+        })
+    );
+
+    let unbalanced = "(".repeat(100_000);
+    assert!(matches!(
+        parse_lenient(&unbalanced, &[]),
+        Err(ParseError::TooDeep { .. })
+    ));
+
+    assert!(matches!(
+        parse_lenient("((:1.1 F (x)) ((return :x))", &[]),
+        Err(ParseError::Syntax { .. })
+    ));
+    assert!(matches!(
+        parse_lenient("((:1.1 F) ((return :x)))", &[]),
+        Err(ParseError::Malformed { .. })
+    ));
+
+    assert!(matches!(
+        parse_lenient("(def x (x)) (x)", &[]),
+        Err(ParseError::RecursiveRule { .. })
+    ));
+
+    assert!(parse_lenient("((:1.1 F (x)) ((return :x)))", &[]).is_ok());
+}
+
+#[test]
+fn named_parse_errors_include_the_filename() {
+    let error = parse_named("((:1.1 F) ((return :x)))", "file.lisp", &[]).unwrap_err();
+    match &error {
+        ParseError::Malformed { filename, .. } => assert_eq!(filename, "file.lisp"),
+        other => panic!("expected malformed code, got {}", other),
+    }
+    assert!(
+        error.to_string().starts_with("file.lisp: "),
+        "{} should name the file",
+        error
+    );
+    assert!(
+        error.to_string().contains("file.lisp:1:"),
+        "{} should say where the error is",
+        error
+    );
+
+    let error = parse_named("((:1.1 F (x)) ((return :x))", "file.lisp", &[]).unwrap_err();
+    assert!(error.to_string().starts_with("file.lisp:"), "{}", error);
+
+    let error = parse_lenient("((:1.1 F) ((return :x)))", &[]).unwrap_err();
+    assert!(error.to_string().starts_with(UNNAMED_FILE), "{}", error);
+}
+
+#[test]
+fn reports_where_unbalanced_code_fails_to_parse() {
+    let code = "((:1.1 F (x))\n  ((return :x)";
+
+    let error = parse_named(code, "file.lisp", &[]).unwrap_err();
+    match &error {
+        ParseError::Syntax { line, .. } => assert_eq!(*line, 2, "{}", error),
+        other => panic!("expected a syntax error, got {}", other),
+    }
+
+    assert!(error.to_string().starts_with("file.lisp:2:"));
+}
+
+#[test]
+fn parses_deeply_nested_expression() {
+    const DEPTH: usize = 100_000;
+    let code = format!(
+        "((:1.1 F ()) ((return {}true{})))",
+        "(not ".repeat(DEPTH),
+        ")".repeat(DEPTH)
+    );
+
+    let ast = parse(&code, &[]);
+
+    let mut expr = match &ast.sections[0].body[..] {
+        [Statement {
+            data: StatementData::Return { expr: Some(expr) },
+            ..
+        }] => expr,
+        other => panic!("expected a single return, got {} statements", other.len()),
+    };
+
+    let mut depth = 0;
+    while let ExpressionData::Negate { expr: inner } = &expr.data {
+        expr = inner;
+        depth += 1;
+    }
+
+    assert_eq!(depth, DEPTH);
+    assert!(matches!(
+        expr.data,
+        ExpressionData::MakeBoolean { value: true }
+    ));
+}
+
+#[test]
+fn parses_header_doc() {
+    let parse_doc = |code| {
+        parse_header(parse_to_nodes(code).pop().unwrap())
+            .unwrap()
+            .doc
+    };
+
+    assert_eq!(parse_doc("(:1.1 F (x))"), None);
+    assert_eq!(
+        parse_doc(r#"("Does a thing." :1.1 F (x))"#),
+        Some("Does a thing.".into())
+    );
+    assert_eq!(
+        parse_doc(r#"((doc "Does a thing.") :1.1 F (x))"#),
+        Some("Does a thing.".into())
+    );
+}
+
+#[test]
+fn parses_header_specialize_on() {
+    let parse_header = |code| parse_header(parse_to_nodes(code).pop().unwrap()).unwrap();
+
+    assert!(parse_header("(:1.1 F (x y))").specialize_on.is_empty());
+
+    let header = parse_header(r#"("Does a thing." (specialize-on y) :1.1 F (x y))"#);
+    assert_eq!(header.doc, Some("Does a thing.".into()));
+    assert_eq!(header.specialize_on, vec!["y".to_string()]);
+}
+
+#[test]
+fn rejects_specializing_on_unknown_parameters() {
+    let header = parse_to_nodes("((specialize-on z) :1.1 F (x y))").pop();
+    let error = parse_header(header.unwrap()).unwrap_err();
+    assert!(error.to_string().contains("not a parameter of F"));
+}
+
+#[test]
+fn parses_match() {
+    let stmts = parse_body(&parse_to_nodes(
+        "(match :x (1 ((return 10))) (2 ((return 20))) (else ((return 30))))",
+    ))
+    .unwrap();
+
+    assert_eq!(stmts.len(), 1);
+    match &stmts[0].data {
+        StatementData::Match {
+            discriminant,
+            cases,
+            r#else,
+        } => {
+            assert!(matches!(
+                discriminant.data,
+                ExpressionData::VarReference { .. }
+            ));
+            assert_eq!(cases.len(), 2);
+            assert!(matches!(
+                cases[1].value.data,
+                ExpressionData::MakeInteger { value: 2 }
+            ));
+            assert_eq!(cases[1].body.len(), 1);
+            assert_eq!(r#else.as_ref().map(Vec::len), Some(1));
+        }
+        other => panic!("expected match statement, got {:?}", other),
+    }
+}
+
 // #[test]
 #[cfg(disabled_for_now)]
 fn parses_statement() {
@@ -742,7 +1523,7 @@ fn parses_statement() {
         variable: "z".into(),
     };
 
-    let check = |code, stmt| assert_eq!(parse_body(parse_to_nodes(code)), vec![stmt]);
+    let check = |code, stmt| assert_eq!(parse_body(&parse_to_nodes(code)), vec![stmt]);
 
     check(
         "(y = :x)",
@@ -835,7 +1616,7 @@ fn parses_statement() {
 #[cfg(test)]
 macro_rules! expr {
     ($x: expr) => {
-        parse_expression(parse_to_nodes($x)[0].as_ref())
+        parse_expression(parse_to_nodes($x)[0].as_ref()).unwrap()
     };
 }
 
@@ -1072,3 +1853,66 @@ fn parses_expression() {
         }
     );
 }
+
+#[test]
+fn feature_gated_sections_depend_on_enabled_features() {
+    let code = r#"
+(feature annex-b)
+
+((:1.1 F ()) ((return)))
+
+(when annex-b
+    ((:B.3.3 G ()) ((return))))
+"#;
+
+    assert_eq!(parse(code, &["annex-b"]).sections.len(), 2);
+
+    let ast = parse(code, &[]);
+    assert_eq!(ast.sections.len(), 1);
+    assert_eq!(ast.sections[0].header.method_name, "F");
+}
+
+#[test]
+fn feature_gate_on_unknown_feature_errors() {
+    let code = "(when annex-b ((:B.3.3 G ()) ((return))))";
+
+    assert!(matches!(
+        parse_lenient(code, &["annex-b"]),
+        Err(ParseError::Malformed { .. })
+    ));
+}
+
+#[test]
+fn parses_extern_declarations() {
+    let ast = parse(
+        r#"
+(extern print (runtime string) void)
+(extern read () any)
+
+((:1.1 F ()) ((call-extern print "hi") (return (call-extern read))))
+"#,
+        &[],
+    );
+
+    assert_eq!(ast.sections.len(), 1);
+    assert_eq!(ast.externs.len(), 2);
+    assert_eq!(ast.externs[0].name, "print");
+    assert_eq!(
+        ast.externs[0].parameters,
+        vec![FFIType::Runtime, FFIType::String]
+    );
+    assert_eq!(ast.externs[0].return_type, None);
+    assert_eq!(ast.externs[1].parameters, vec![]);
+    assert_eq!(ast.externs[1].return_type, Some(FFIType::Any));
+
+    match &ast.sections[0].body[0].data {
+        StatementData::CallExternal {
+            function_name,
+            args,
+        } => {
+            assert_eq!(function_name, "print");
+            assert_eq!(args.len(), 1);
+        }
+        other => panic!("expected extern call, got {:?}", other),
+    }
+}
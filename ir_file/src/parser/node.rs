@@ -1,19 +1,84 @@
-use std::fmt::Display;
+use std::{cell::RefCell, fmt::Display};
 
-use lexpr::{
-    datum::{Ref, Span},
-    Number, Parser, Value,
-};
+use lexpr::{datum::Ref, Number, Parser, Value};
+use rustc_hash::FxHashSet;
+
+use super::{grow_stack, ParseError, UNNAMED_FILE};
+use crate::Span;
 
 pub fn parse_to_nodes(code: &str) -> Vec<Node> {
+    match try_parse_to_nodes(code, UNNAMED_FILE, usize::MAX) {
+        Ok(nodes) => nodes,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// Like [`parse_to_nodes`], but produces an error rather than panicking if the
+/// code is malformed or is nested deeper than `max_depth`. The spans of the
+/// nodes name the file `filename`.
+pub fn try_parse_to_nodes(
+    code: &str,
+    filename: &str,
+    max_depth: usize,
+) -> Result<Vec<Node>, ParseError> {
+    // the nesting is checked before parsing, as `lexpr` could overflow the
+    // stack before we ever see the nodes
+    let depth = nesting_depth(code);
+    if depth > max_depth {
+        return Err(ParseError::TooDeep {
+            filename: filename.to_owned(),
+            max_depth,
+        });
+    }
+
+    let filename = intern_filename(filename);
+
+    // `lexpr` parses and drops its values recursively, so make sure there is
+    // enough stack for how deeply the code is nested
+    #[allow(non_upper_case_globals)]
+    const KiB: usize = 1024;
+    let stack_needed = depth.saturating_mul(KiB);
+    stacker::maybe_grow(stack_needed, stack_needed.saturating_add(64 * KiB), || {
+        parse_datums(code, filename)
+    })
+}
+
+/// Spans are copied freely, so rather than every span owning the name of its
+/// file, the name is leaked once and shared by every span in the file.
+fn intern_filename(filename: &str) -> &'static str {
+    thread_local! {
+        static FILENAMES: RefCell<FxHashSet<&'static str>> = Default::default();
+    }
+
+    FILENAMES.with(|filenames| {
+        let mut filenames = filenames.borrow_mut();
+
+        match filenames.get(filename) {
+            Some(filename) => *filename,
+            None => {
+                let filename: &'static str = Box::leak(filename.into());
+                filenames.insert(filename);
+                filename
+            }
+        }
+    })
+}
+
+fn parse_datums(code: &str, filename: &'static str) -> Result<Vec<Node>, ParseError> {
     let mut nodes = Vec::new();
     let mut parser = Parser::from_str(code);
 
-    fn to_node(datum: Ref) -> Node {
-        match datum.value() {
+    fn to_node(datum: Ref, filename: &'static str) -> Result<Node, ParseError> {
+        grow_stack(|| to_node_impl(datum, filename))
+    }
+
+    fn to_node_impl(datum: Ref, filename: &'static str) -> Result<Node, ParseError> {
+        let span = || Span::new(filename, datum.span());
+
+        Ok(match datum.value() {
             Value::String(value) => {
                 let string = (&**value).into();
-                Node::String(string, datum.span())
+                Node::String(string, span())
             }
             Value::Symbol(value) => {
                 let mut string = (&**value).to_string();
@@ -21,43 +86,132 @@ pub fn parse_to_nodes(code: &str) -> Vec<Node> {
                 match string.starts_with(':') {
                     true => {
                         string.remove(0);
-                        Node::Atom(string, datum.span())
+                        Node::Atom(string, span())
                     }
-                    false => Node::Word(string, datum.span()),
+                    false => Node::Word(string, span()),
                 }
             }
             Value::Cons(_) => {
                 let mut children = Vec::new();
 
-                for datum in datum.list_iter().unwrap() {
-                    children.push(to_node(datum));
+                let list = datum.list_iter().ok_or_else(|| {
+                    ParseError::malformed(format!("cannot handle improper list {}", datum.value()))
+                })?;
+
+                for datum in list {
+                    children.push(to_node(datum, filename)?);
                 }
 
-                Node::Parent(children, datum.span())
+                Node::Parent(children, span())
             }
-            Value::Number(n) => Node::Number(n.clone(), datum.span()),
-            Value::Null => Node::Parent(Vec::new(), datum.span()),
-            Value::Nil
-            | Value::Bytes(_)
+            Value::Number(n) => Node::Number(n.clone(), span()),
+            // `lexpr` represents the empty list `()` as `Null`, which is distinct
+            // from an explicit `#nil`
+            Value::Null => Node::Parent(Vec::new(), span()),
+            Value::Nil => Node::Null(span()),
+            Value::Bytes(_)
             | Value::Bool(_)
             | Value::Char(_)
             | Value::Keyword(_)
-            | Value::Vector(_) => panic!("cannot handle {:?}", datum.value()),
+            | Value::Vector(_) => {
+                return Err(ParseError::malformed(format!(
+                    "cannot handle {:?}",
+                    datum.value()
+                )))
+            }
+        })
+    }
+
+    for datum in parser.datum_iter() {
+        let datum = datum.map_err(|error| syntax_error(code, error))?;
+        nodes.push(to_node(datum.as_ref(), filename)?);
+    }
+
+    Ok(nodes)
+}
+
+/// Converts an error from `lexpr` into one pointing at where in `code` it
+/// happened. Errors `lexpr` doesn't give a location for are placed at the end
+/// of the code, as they come from running out of input.
+fn syntax_error(code: &str, error: lexpr::parse::Error) -> ParseError {
+    let (line, column) = match error.location() {
+        Some(location) => (location.line(), location.column()),
+        None => (
+            code.lines().count().max(1),
+            code.lines().last().map_or(0, str::len),
+        ),
+    };
+
+    ParseError::Syntax {
+        filename: UNNAMED_FILE.to_owned(),
+        line,
+        column,
+        message: error.to_string(),
+    }
+}
+
+/// Drops nodes without recursing over how deeply they are nested, as the
+/// derived drop glue would overflow the stack on deeply nested code.
+pub fn drop_nodes(mut nodes: Vec<Node>) {
+    while let Some(node) = nodes.pop() {
+        if let Node::Parent(children, _) = node {
+            nodes.extend(children);
         }
     }
+}
+
+/// Computes how deeply parentheses are nested in the code, without parsing it.
+/// Parentheses within strings and comments are ignored.
+fn nesting_depth(code: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0;
+    let mut chars = code.chars();
 
-    for datum in parser.datum_iter().map(Result::unwrap) {
-        nodes.push(to_node(datum.as_ref()));
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            // character literals, such as `#\(`
+            '#' if chars.clone().next() == Some('\\') => {
+                chars.next();
+                chars.next();
+            }
+            _ => {}
+        }
     }
 
-    nodes
+    max_depth
 }
 
 /// A [`Node`] is the representation of S-expressions to what a JSSAT IR file
 /// understands. The existence of this simplifies parsing and rules for a JSSAT
 /// IR file, as [`lexpr`] provides far more utilities that we do not need.
+///
+/// The children of a parent are `C`, so that [`Node::as_ref`] can borrow them
+/// rather than clone the entire tree.
 #[derive(Debug, Clone)]
-pub enum Node<S = String> {
+pub enum Node<S = String, C = Vec<Node>> {
     /// ```text
     /// (hello)
     ///  ^^^^^ is a word
@@ -98,18 +252,28 @@ pub enum Node<S = String> {
     ///         ^^^^^^^ parent
     /// ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ also a parent
     /// ```
-    Parent(Vec<Node>, Span),
+    Parent(C, Span),
+    /// An explicit null value. This is not the same as an empty parent.
+    ///
+    /// ```text
+    /// (#nil)
+    ///  ^^^^ is null
+    ///
+    /// (())
+    ///  ^^ is an empty parent, not null
+    /// ```
+    Null(Span),
 }
 
 impl Node {
-    /// NOTE: this may be expensive
-    pub fn as_ref(&self) -> Node<&str> {
+    pub fn as_ref(&self) -> Node<&str, &[Node]> {
         match self {
             Node::Word(data, span) => Node::Word(data.as_str(), *span),
             Node::Atom(data, span) => Node::Atom(data.as_str(), *span),
             Node::String(data, span) => Node::String(data.as_str(), *span),
             Node::Number(data, span) => Node::Number(data.clone(), *span),
-            Node::Parent(data, span) => Node::Parent(data.clone(), *span),
+            Node::Parent(data, span) => Node::Parent(data.as_slice(), *span),
+            Node::Null(span) => Node::Null(*span),
         }
     }
 }
@@ -122,45 +286,57 @@ impl<S: PartialEq> PartialEq for Node<S> {
             (Self::String(l0, _), Self::String(r0, _)) => l0 == r0,
             (Self::Number(l0, _), Self::Number(r0, _)) => l0 == r0,
             (Self::Parent(l0, _), Self::Parent(r0, _)) => l0 == r0,
+            (Self::Null(_), Self::Null(_)) => true,
             _ => false,
         }
     }
 }
 
 impl Node {
-    #[track_caller]
-    pub fn expect_word(self) -> String {
+    pub fn into_word(self) -> Result<String, ParseError> {
         match self {
-            Node::Word(value, _) => value,
-            other => panic!("expected word node on {}", DisplaySpan(other.span())),
+            Node::Word(value, _) => Ok(value),
+            other => Err(ParseError::malformed(format!(
+                "expected word node on {}",
+                DisplaySpan(other.span())
+            ))),
         }
     }
 
-    #[track_caller]
-    pub fn expect_atom(self) -> String {
+    pub fn into_atom(self) -> Result<String, ParseError> {
         match self {
-            Node::Atom(value, _) => value,
-            other => panic!("expected atom node on {}", DisplaySpan(other.span())),
+            Node::Atom(value, _) => Ok(value),
+            other => Err(ParseError::malformed(format!(
+                "expected atom node on {}",
+                DisplaySpan(other.span())
+            ))),
         }
     }
+}
 
-    #[track_caller]
-    pub fn expect_parent(self) -> Vec<Node> {
+impl<S, C> Node<S, C> {
+    pub fn into_parent(self) -> Result<C, ParseError> {
         match self {
-            Node::Parent(value, _) => value,
-            other => panic!("expected parent node on {}", DisplaySpan(other.span())),
+            Node::Parent(value, _) => Ok(value),
+            Node::Null(span) => Err(ParseError::malformed(format!(
+                "expected parent node on {}, but got null (use `()` for an empty parent)",
+                DisplaySpan(span)
+            ))),
+            other => Err(ParseError::malformed(format!(
+                "expected parent node on {}",
+                DisplaySpan(other.span())
+            ))),
         }
     }
-}
 
-impl<S> Node<S> {
     pub fn span(&self) -> Span {
         match self {
             Node::Word(_, span)
             | Node::Atom(_, span)
             | Node::String(_, span)
             | Node::Number(_, span)
-            | Node::Parent(_, span) => *span,
+            | Node::Parent(_, span)
+            | Node::Null(span) => *span,
         }
     }
 }
@@ -181,11 +357,12 @@ impl<S: ToString> Node<S> {
                     .reduce(|a, b| format!("{} {}", a, b))
                     .unwrap_or_else(|| "".into())
             ),
+            Node::Null(_) => "#nil".into(),
         }
     }
 }
 
-struct DisplaySpan(Span);
+pub struct DisplaySpan(pub Span);
 
 impl Display for DisplaySpan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -194,7 +371,8 @@ impl Display for DisplaySpan {
 
         write!(
             f,
-            "line {} column {} to line {} column {}",
+            "{}:{}:{} to {}:{}",
+            self.0.filename(),
             start.line(),
             start.column(),
             end.line(),
@@ -243,6 +421,15 @@ mod node_tests {
         assert!(matches!(&nodes[0], Parent(a, _) if a.is_empty()));
     }
 
+    #[test]
+    pub fn parses_null_distinct_from_empty_parent() {
+        let nodes = parse_to_nodes("() #nil");
+        assert_eq!(nodes.len(), 2);
+        assert!(matches!(&nodes[0], Parent(a, _) if a.is_empty()));
+        assert!(matches!(&nodes[1], Null(_)));
+        assert_ne!(nodes[0], nodes[1]);
+    }
+
     #[test]
     #[allow(clippy::float_cmp)]
     pub fn parses_number() {
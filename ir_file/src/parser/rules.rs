@@ -48,6 +48,10 @@ pub fn apply_rule_recursively(rule: &Node, generate: &Node, node: Node) -> Node
 }
 
 pub fn apply_rule_recursively_inner(rule: &Node, generate: &Node, node: Node) -> (bool, Node) {
+    grow_stack(|| apply_rule_recursively_inner_impl(rule, generate, node))
+}
+
+fn apply_rule_recursively_inner_impl(rule: &Node, generate: &Node, node: Node) -> (bool, Node) {
     let (changed, node) = match node {
         Node::Parent(children, span) => {
             let mut changed = false;
@@ -128,6 +132,7 @@ fn matches_rule(rule: &Node, node: &Node) -> bool {
         (Node::Atom(_, _), _) => true,
         (Node::Word(a, _), Node::Word(b, _)) | (Node::String(a, _), Node::String(b, _)) => a == b,
         (Node::Number(a, _), Node::Number(b, _)) => a == b,
+        (Node::Null(_), Node::Null(_)) => true,
         (Node::Parent(a, _), Node::Parent(b, _)) if a.len() == b.len() => {
             a.iter().zip(b).all(|(node, rule)| matches_rule(node, rule))
         }
@@ -2,7 +2,9 @@
 //! entirety of an AST into carrying a piece of "threaded state" throughout all
 //! functions in the application.
 
-use crate::{Assign, Expression, ExpressionData, Statement, StatementData, AST};
+use crate::{
+    parser::grow_stack, Assign, Expression, ExpressionData, Statement, StatementData, AST,
+};
 
 pub fn thread_state(ast: &mut AST) -> bool {
     if has_global_expr(ast) {
@@ -30,6 +32,10 @@ fn has_global_expr(ast: &AST) -> bool {
 }
 
 fn expr_is_global(expression: &Expression) -> bool {
+    grow_stack(|| expr_is_global_impl(expression))
+}
+
+fn expr_is_global_impl(expression: &Expression) -> bool {
     match &expression.data {
         ExpressionData::GetGlobal => true,
         ExpressionData::If {
@@ -52,10 +58,31 @@ fn expr_is_global(expression: &Expression) -> bool {
                 || r#in.0.iter().any(statement_has_global)
                 || expr_is_global(&r#in.1)
         }
+        ExpressionData::Loop {
+            init,
+            cond,
+            next,
+            body,
+            r#else,
+        } => {
+            init.iter().any(|a| expr_is_global(&a.value))
+                || expr_is_global(cond)
+                || next.iter().any(|a| expr_is_global(&a.value))
+                || body.iter().any(statement_has_global)
+                || expr_is_global(r#else)
+        }
+        ExpressionData::RecordClone { record } | ExpressionData::RecordEntries { record } => {
+            expr_is_global(record)
+        }
         ExpressionData::RecordGetProp { record, property } => {
             expr_is_global(record) || expr_is_global(property)
         }
         ExpressionData::RecordGetSlot { record, slot: _ } => expr_is_global(record),
+        ExpressionData::RecordGetSlotOr {
+            record,
+            slot: _,
+            default,
+        } => expr_is_global(record) || expr_is_global(default),
         ExpressionData::RecordHasProp { record, property } => {
             expr_is_global(record) || expr_is_global(property)
         }
@@ -67,10 +94,21 @@ fn expr_is_global(expression: &Expression) -> bool {
             expr_is_global(list) || expr_is_global(property)
         }
         ExpressionData::ListLen { list } => expr_is_global(list),
+        ExpressionData::ListPop { list } => expr_is_global(list),
+        ExpressionData::ListMap {
+            list,
+            function,
+            args,
+        } => expr_is_global(list) || expr_is_global(function) || args.iter().any(expr_is_global),
+        ExpressionData::GetFnPtrDynamic { name } => expr_is_global(name),
         ExpressionData::CallStatic {
             function_name: _,
             args,
         } => args.iter().any(expr_is_global),
+        ExpressionData::CallExternal {
+            function_name: _,
+            args,
+        } => args.iter().any(expr_is_global),
         ExpressionData::CallVirt { fn_ptr, args } => {
             expr_is_global(fn_ptr) || args.iter().any(expr_is_global)
         }
@@ -78,6 +116,12 @@ fn expr_is_global(expression: &Expression) -> bool {
         ExpressionData::Negate { expr } => expr_is_global(expr),
         ExpressionData::IsTypeOf { expr, kind: _ } => expr_is_global(expr),
         ExpressionData::IsTypeAs { lhs, rhs } => expr_is_global(lhs) || expr_is_global(rhs),
+        ExpressionData::AssumeType { expr, kind: _ } => expr_is_global(expr),
+        ExpressionData::TypeOf { expr } => expr_is_global(expr),
+        ExpressionData::BytesConcat { lhs, rhs } => expr_is_global(lhs) || expr_is_global(rhs),
+        ExpressionData::BytesSlice { bytes, start, end } => {
+            expr_is_global(bytes) || expr_is_global(start) || expr_is_global(end)
+        }
         ExpressionData::GetFnPtr { function_name: _ }
         | ExpressionData::Unreachable
         | ExpressionData::RecordNew
@@ -91,6 +135,10 @@ fn expr_is_global(expression: &Expression) -> bool {
 }
 
 fn statement_has_global(statement: &Statement) -> bool {
+    grow_stack(|| statement_has_global_impl(statement))
+}
+
+fn statement_has_global_impl(statement: &Statement) -> bool {
     match &statement.data {
         crate::StatementData::Assign(a) => expr_is_global(&a.value),
         crate::StatementData::If {
@@ -124,6 +172,14 @@ fn statement_has_global(statement: &Statement) -> bool {
                 || expr_is_global(prop)
                 || value.as_ref().map(|x| expr_is_global(x)).unwrap_or(false)
         }
+        crate::StatementData::ListPush { list, value } => {
+            expr_is_global(list) || expr_is_global(value)
+        }
+        crate::StatementData::ListForEach {
+            list,
+            function,
+            args,
+        } => expr_is_global(list) || expr_is_global(function) || args.iter().any(expr_is_global),
         crate::StatementData::Return { expr } => {
             expr.as_ref().map(|x| expr_is_global(x)).unwrap_or(false)
         }
@@ -131,6 +187,10 @@ fn statement_has_global(statement: &Statement) -> bool {
             function_name: _,
             args,
         } => args.iter().any(expr_is_global),
+        crate::StatementData::CallExternal {
+            function_name: _,
+            args,
+        } => args.iter().any(expr_is_global),
         crate::StatementData::CallVirt { fn_ptr, args } => {
             expr_is_global(fn_ptr) || args.iter().any(expr_is_global)
         }
@@ -146,6 +206,24 @@ fn statement_has_global(statement: &Statement) -> bool {
                 || next.iter().any(|a| expr_is_global(&a.value))
                 || body.iter().any(statement_has_global)
         }
+        crate::StatementData::Match {
+            discriminant,
+            cases,
+            r#else,
+        } => {
+            expr_is_global(discriminant)
+                || cases
+                    .iter()
+                    .any(|c| expr_is_global(&c.value) || c.body.iter().any(statement_has_global))
+                || r#else
+                    .as_ref()
+                    .map(|x| x.iter().any(statement_has_global))
+                    .unwrap_or(false)
+        }
+        crate::StatementData::Break { value } => {
+            value.as_ref().map(|x| expr_is_global(x)).unwrap_or(false)
+        }
+        crate::StatementData::Continue => false,
     }
 }
 
@@ -166,6 +244,10 @@ fn thread_statements(statements: &mut Vec<Statement>) {
 }
 
 fn thread_statement(statement: &mut Statement) {
+    grow_stack(|| thread_statement_impl(statement))
+}
+
+fn thread_statement_impl(statement: &mut Statement) {
     match &mut statement.data {
         StatementData::Assign(assign) => thread_assign(assign),
         StatementData::If {
@@ -199,6 +281,20 @@ fn thread_statement(statement: &mut Statement) {
             thread_expression(prop);
             optional_do(value, thread_expression);
         }
+        StatementData::ListPush { list, value } => {
+            thread_expression(list);
+            thread_expression(value);
+        }
+        StatementData::ListForEach {
+            list,
+            function,
+            args,
+        } => {
+            thread_expression(list);
+            thread_expression(function);
+            thread_expressions(args);
+            args.insert(0, threaded_global());
+        }
         StatementData::Return { expr } => {
             optional_do(expr, thread_expression);
         }
@@ -209,6 +305,13 @@ fn thread_statement(statement: &mut Statement) {
             thread_expressions(args);
             args.insert(0, threaded_global());
         }
+        // external functions know nothing of the threaded state
+        StatementData::CallExternal {
+            function_name: _,
+            args,
+        } => {
+            thread_expressions(args);
+        }
         StatementData::CallVirt { fn_ptr, args } => {
             thread_expression(fn_ptr);
             thread_expressions(args);
@@ -228,6 +331,22 @@ fn thread_statement(statement: &mut Statement) {
             thread_assigns(next);
             thread_statements(body);
         }
+        StatementData::Match {
+            discriminant,
+            cases,
+            r#else,
+        } => {
+            thread_expression(discriminant);
+            for case in cases.iter_mut() {
+                thread_expression(&mut case.value);
+                thread_statements(&mut case.body);
+            }
+            optional_do(r#else, thread_statements);
+        }
+        StatementData::Break { value } => {
+            optional_do(value, thread_expression);
+        }
+        StatementData::Continue => {}
     }
 }
 
@@ -244,6 +363,10 @@ fn thread_expressions(expressions: &mut Vec<Expression>) {
 }
 
 fn thread_expression(expression: &mut Expression) {
+    grow_stack(|| thread_expression_impl(expression))
+}
+
+fn thread_expression_impl(expression: &mut Expression) {
     match &mut expression.data {
         ExpressionData::GetGlobal => {
             // replace the `GetGlobal` expression as we're threading it through all state
@@ -276,8 +399,24 @@ fn thread_expression(expression: &mut Expression) {
             thread_statements(statements);
             thread_expression(expression);
         }
+        ExpressionData::Loop {
+            init,
+            cond,
+            next,
+            body,
+            r#else,
+        } => {
+            thread_assigns(init);
+            thread_expression(cond);
+            thread_assigns(next);
+            thread_statements(body);
+            thread_expression(r#else);
+        }
         ExpressionData::Unreachable => {}
         ExpressionData::RecordNew => {}
+        ExpressionData::RecordClone { record } | ExpressionData::RecordEntries { record } => {
+            thread_expression(record);
+        }
         ExpressionData::RecordGetProp { record, property } => {
             thread_expression(record);
             thread_expression(property);
@@ -285,6 +424,14 @@ fn thread_expression(expression: &mut Expression) {
         ExpressionData::RecordGetSlot { record, slot: _ } => {
             thread_expression(record);
         }
+        ExpressionData::RecordGetSlotOr {
+            record,
+            slot: _,
+            default,
+        } => {
+            thread_expression(record);
+            thread_expression(default);
+        }
         ExpressionData::RecordHasProp { record, property } => {
             thread_expression(record);
             thread_expression(property);
@@ -304,7 +451,23 @@ fn thread_expression(expression: &mut Expression) {
         ExpressionData::ListLen { list } => {
             thread_expression(list);
         }
+        ExpressionData::ListPop { list } => {
+            thread_expression(list);
+        }
+        ExpressionData::ListMap {
+            list,
+            function,
+            args,
+        } => {
+            thread_expression(list);
+            thread_expression(function);
+            thread_expressions(args);
+            args.insert(0, threaded_global());
+        }
         ExpressionData::GetFnPtr { function_name: _ } => {}
+        ExpressionData::GetFnPtrDynamic { name } => {
+            thread_expression(name);
+        }
         ExpressionData::CallStatic {
             function_name: _,
             args,
@@ -312,6 +475,13 @@ fn thread_expression(expression: &mut Expression) {
             thread_expressions(args);
             args.insert(0, threaded_global());
         }
+        // external functions know nothing of the threaded state
+        ExpressionData::CallExternal {
+            function_name: _,
+            args,
+        } => {
+            thread_expressions(args);
+        }
         ExpressionData::CallVirt { fn_ptr, args } => {
             thread_expression(fn_ptr);
             thread_expressions(args);
@@ -335,5 +505,20 @@ fn thread_expression(expression: &mut Expression) {
             thread_expression(lhs);
             thread_expression(rhs);
         }
+        ExpressionData::AssumeType { expr, kind: _ } => {
+            thread_expression(expr);
+        }
+        ExpressionData::TypeOf { expr } => {
+            thread_expression(expr);
+        }
+        ExpressionData::BytesConcat { lhs, rhs } => {
+            thread_expression(lhs);
+            thread_expression(rhs);
+        }
+        ExpressionData::BytesSlice { bytes, start, end } => {
+            thread_expression(bytes);
+            thread_expression(start);
+            thread_expression(end);
+        }
     }
 }
@@ -42,6 +42,16 @@ fn emit_virt_overrides(
     true
 }
 
+/// Emits a kind of parse node in place of the default emission, once all of its
+/// children have been visited. This allows experimenting with alternative
+/// semantics, or covering productions the default emission doesn't handle.
+///
+/// The hook is given the parse node with its children, and produces the
+/// register that the parent of the parse node refers to instead.
+pub type LoweringHook = Box<dyn Fn(&mut NodeEmitter, &ParseNode) -> RegisterId>;
+
+pub type LoweringHooks = FxHashMap<js::ParseNodeKind, LoweringHook>;
+
 pub struct NodeEmitter<'scope> {
     block: &'scope mut DynBlockBuilder,
     program: &'scope mut ProgramBuilder,
@@ -53,6 +63,7 @@ pub struct NodeEmitter<'scope> {
     slots: [Atom; 3],
     identifier_name_data: Atom,
     string_literal_data: Atom,
+    lowering_hooks: &'scope LoweringHooks,
 }
 
 impl<'s> NodeEmitter<'s> {
@@ -61,6 +72,7 @@ impl<'s> NodeEmitter<'s> {
         program: &'s mut ProgramBuilder,
         ecma_methods: &'s ECMA262Methods,
         dealer: &'s js::Dealer,
+        lowering_hooks: &'s LoweringHooks,
     ) -> Self {
         let simple_fns = Self::generate_simple_fns(
             ecma_methods.atoms.JSSATParseNodeSlot1,
@@ -85,9 +97,22 @@ impl<'s> NodeEmitter<'s> {
             slots,
             identifier_name_data: ecma_methods.atoms.JSSATParseNode_Identifier_StringValue,
             string_literal_data: ecma_methods.atoms.JSSATParseNode_StringLiteral_StringValue,
+            lowering_hooks,
         }
     }
 
+    pub fn block(&mut self) -> &mut DynBlockBuilder {
+        self.block
+    }
+
+    pub fn program(&mut self) -> &mut ProgramBuilder {
+        self.program
+    }
+
+    pub fn ecma_methods(&self) -> &ECMA262Methods {
+        self.ecma_methods
+    }
+
     /// Generates the "simple" ECMAScript functions - the functions that are
     /// defined by the following catch-most clause:
     ///
@@ -125,7 +150,7 @@ impl<'s> NodeEmitter<'s> {
         let next = e.record_get_atom(x, node_slot);
         let fn_ptr = e.record_get_atom(next, slot);
         let result = e.call_virt_with_result(fn_ptr, [threaded_global, next]);
-        f.end_block(e.ret(Some(result)));
+        f.end_block(e.ret(&[result]));
 
         let signature = program.end_function(f);
 
@@ -143,6 +168,14 @@ pub struct ParseNode {
 }
 
 impl ParseNode {
+    pub fn kind(&self) -> js::ParseNodeKind {
+        self.kind
+    }
+
+    pub fn variant_idx(&self) -> usize {
+        self.variant_idx
+    }
+
     fn new(emitter: &mut NodeEmitter, kind: js::ParseNodeKind, variant_idx: usize) -> Self {
         let block = &mut emitter.block;
         let parse_node = block.record_new();
@@ -211,7 +244,15 @@ impl<'b> Visitor for NodeEmitter<'b> {
         let node = (self.stack.pop())
             .expect("post_visit should be called exactly same amount as pre_visit");
 
-        let node = node.finish(self.block, &self.simple_fns, self.ecma_methods);
+        // a lowering hook takes the place of the default emission
+        let lowering_hooks = self.lowering_hooks;
+        let node = match lowering_hooks.get(&node.kind) {
+            Some(hook) => ParseNode {
+                parse_node: hook(self, &node),
+                ..node
+            },
+            None => node.finish(self.block, &self.simple_fns, self.ecma_methods),
+        };
 
         if let Some(parent) = self.stack.last_mut() {
             parent.on_child_created(self.block, &self.slots, &node);
@@ -301,3 +342,51 @@ impl<'b> Visitor for NodeEmitter<'b> {
         self.post_visit();
     }
 }
+
+/// Ensures that a lowering hook registered for a kind of parse node is run in
+/// place of the default emission for that kind, and that the parent of the
+/// parse node refers to what the hook emitted instead
+#[test]
+pub fn lowering_hook_replaces_default_emission() {
+    use crate::{hosts::JSSATHostEnvironment, JavaScriptFrontend};
+    use jssat_ir::frontend::ir::InstructionData;
+    use std::{cell::Cell, rc::Rc};
+
+    let mut program = ProgramBuilder::new();
+    let mut main = program.start_function_main();
+    let mut block = main.start_block_main();
+
+    let lowered = Rc::new(Cell::new(None));
+
+    let mut frontend = JavaScriptFrontend::new(&mut program);
+    let hook_lowered = lowered.clone();
+    frontend.lowering_hooks.insert(
+        js::ParseNodeKind::ScriptBody,
+        Box::new(move |emitter, node| {
+            let replacement = emitter.block().record_new();
+            hook_lowered.set(Some((node.parse_node, replacement)));
+            replacement
+        }),
+    );
+
+    let result = frontend
+        .parse("1;", &mut block, &mut JSSATHostEnvironment::new())
+        .expect("should parse js");
+
+    main.end_block(block.ret(&[result]));
+    program.end_function(main);
+
+    let (default, replacement) = lowered.get().expect("the hook should have run");
+
+    let ir = program.finish();
+    let main = ir.functions.get(&ir.entrypoint).unwrap();
+    let set_values = (main.iter_instructions())
+        .filter_map(|(_, _, inst)| match &inst.data {
+            InstructionData::RecordSet(set) => set.value,
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert!(set_values.contains(&replacement));
+    assert!(!set_values.contains(&default));
+}
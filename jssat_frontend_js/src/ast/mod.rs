@@ -22,18 +22,35 @@ pub fn parse_script(script: &str) -> PResult<parse_nodes::Script> {
     parser::parse_script(script)
 }
 
+/// The top-level parse node produced by [`emit_nodes`].
+pub struct EmittedNode {
+    /// The register holding the record of the parse node.
+    pub register: RegisterId,
+    /// The kind of parse node that the source text reduced to.
+    pub kind: parse_nodes::ParseNodeKind,
+    /// Which production of `kind` the source text matched.
+    pub variant_idx: usize,
+}
+
 pub fn emit_nodes(
     program: &mut ProgramBuilder,
     block: &mut DynBlockBuilder,
     ecma_methods: &ECMA262Methods,
     dealer: &Dealer,
+    lowering_hooks: &emit_nodes::LoweringHooks,
     visit_initial_node: impl FnOnce(&mut emit_nodes::NodeEmitter),
-) -> RegisterId {
-    let mut node_emitter = emit_nodes::NodeEmitter::new(block, program, ecma_methods, dealer);
+) -> EmittedNode {
+    let mut node_emitter =
+        emit_nodes::NodeEmitter::new(block, program, ecma_methods, dealer, lowering_hooks);
     visit_initial_node(&mut node_emitter);
 
     let last_visited = node_emitter
         .last_completed
         .expect("expected the node emitter to visit a node");
-    last_visited.parse_node
+
+    EmittedNode {
+        register: last_visited.parse_node,
+        kind: last_visited.kind(),
+        variant_idx: last_visited.variant_idx(),
+    }
 }
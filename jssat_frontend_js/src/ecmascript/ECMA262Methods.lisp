@@ -215,11 +215,11 @@
 (def (is-unresolvable :x) (:x == unresolvable))
 (def (is-empty :x) (:x == empty))
 (def (isnt-empty :x) (not (is-empty :x)))
-(def (is-string :x) (is-type-of String :x))
+(def (is-string :x) (is-type-of Bytes :x))
 ; (def (is-symbol :x) (is-type-of Symbol :x))
 (def (is-symbol :x) (false))
 (def (is-number :x) (is-type-of Number :x))
-(def (is-bigint :x) (is-type-of BigInt :x))
+(def (is-bigint :x) (is-type-of BigNumber :x))
 (def (is-bool :x) (is-type-of Boolean :x))
 (def (is-record :x) (is-type-of Record :x))
 (def (isnt-record :x) (not (is-record :x)))
@@ -20,7 +20,7 @@ pub fn traverse(_source: String) -> IR {
         let mut block = print_stub.start_block_main();
         let runtime = block.get_runtime();
         block.call_external_function(print, [runtime, any]);
-        print_stub.end_block(block.ret(None));
+        print_stub.end_block(block.ret(&[]));
 
         builder.end_function(print_stub)
     };
@@ -84,7 +84,7 @@ pub fn traverse(_source: String) -> IR {
             sum.end_block(bloop.jmp(check_sig, [i2, total2]));
         }
         {
-            sum.end_block(end.ret(Some(etotal)));
+            sum.end_block(end.ret(&[etotal]));
         }
 
         builder.end_function(sum)
@@ -103,7 +103,7 @@ pub fn traverse(_source: String) -> IR {
         block.call(print_stub, [prop_at_slot]);
         block.call_virt(call_it, [key]);
 
-        func.end_block(block.ret(None));
+        func.end_block(block.ret(&[]));
         builder.end_function(func)
     };
 
@@ -130,7 +130,7 @@ pub fn traverse(_source: String) -> IR {
 
         block.call(print_is_cool_and_key, [obj]);
 
-        main.end_block(block.ret(None));
+        main.end_block(block.ret(&[]));
 
         builder.end_function(main)
     };
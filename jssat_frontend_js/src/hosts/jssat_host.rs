@@ -36,7 +36,7 @@ impl HostEnvironment for JSSATHostEnvironment {
 
             let undef = program.dealer.deal("Undefined");
             let undef = main.make_atom(undef);
-            print_fn.end_block(main.ret(Some(undef)));
+            print_fn.end_block(main.ret(&[undef]));
 
             program.end_function(print_fn)
         };
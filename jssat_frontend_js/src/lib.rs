@@ -7,7 +7,10 @@ use swc_ecmascript::parser::PResult;
 use self::ast::parse_nodes::Dealer;
 use jssat_ir::isa::Atom;
 
-use self::{ast::parse_nodes::Visitor, ecmascript::ECMA262Methods};
+use self::{
+    ast::{emit_nodes::LoweringHooks, parse_nodes::Visitor},
+    ecmascript::ECMA262Methods,
+};
 
 use jssat_ir::frontend::builder::{DynBlockBuilder, ProgramBuilder, RegisterId};
 
@@ -18,6 +21,9 @@ pub mod hosts;
 pub struct JavaScriptFrontend<'program> {
     program: &'program mut ProgramBuilder,
     pub ecma_methods: ECMA262Methods,
+    /// Overrides how specific kinds of parse nodes are emitted. See
+    /// [`ast::emit_nodes::LoweringHook`].
+    pub lowering_hooks: LoweringHooks,
 }
 
 impl<'p> JavaScriptFrontend<'p> {
@@ -29,6 +35,7 @@ impl<'p> JavaScriptFrontend<'p> {
         Self {
             program,
             ecma_methods,
+            lowering_hooks: Default::default(),
         }
     }
 
@@ -55,10 +62,14 @@ impl<'p> JavaScriptFrontend<'p> {
 
         let dealer = Dealer::new(self.program);
 
-        let entry_parse_node =
-            ast::emit_nodes(self.program, block, &self.ecma_methods, &dealer, |v| {
-                v.visit_script(&script)
-            });
+        let entry_parse_node = ast::emit_nodes(
+            self.program,
+            block,
+            &self.ecma_methods,
+            &dealer,
+            &self.lowering_hooks,
+            |v| v.visit_script(&script),
+        );
 
         let threaded_global = block.record_new();
         block.call(
@@ -101,7 +112,7 @@ impl<'p> JavaScriptFrontend<'p> {
 
         let script_context = block.call_with_result(
             self.ecma_methods.ParseScript,
-            [threaded_global, null, realm, null, entry_parse_node],
+            [threaded_global, null, realm, null, entry_parse_node.register],
         );
 
         let result = block.call_with_result(
@@ -0,0 +1,52 @@
+//! Provides the implementations of the external functions a program calls, for
+//! tests that run whole programs rather than building them by hand.
+
+use rustc_hash::FxHashMap;
+
+use jssat_ir::{
+    lifted::{ExternalFunctionId, LiftedProgram},
+    UnwrapNone,
+};
+
+use super::{ExtFnImpl, InstResult, Value};
+
+/// The implementations of the external functions a program may call, looked
+/// up by the name the program declares them with.
+#[derive(Default)]
+pub struct ExternRegistry {
+    fns: FxHashMap<String, fn(Vec<Value>) -> InstResult<Option<Value>>>,
+}
+
+impl ExternRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<N: ToString>(
+        mut self,
+        name: N,
+        function: fn(Vec<Value>) -> InstResult<Option<Value>>,
+    ) -> Self {
+        self.fns.insert(name.to_string(), function).expect_free();
+        self
+    }
+
+    /// Looks up the implementation of every external function `program`
+    /// declares, in the form the [`Interpreter`](super::Interpreter) takes.
+    ///
+    /// Panics if `program` declares an external function missing from the
+    /// registry.
+    pub fn bind(&self, program: &LiftedProgram) -> FxHashMap<ExternalFunctionId, ExtFnImpl> {
+        let mut ext_fns = FxHashMap::default();
+
+        for (id, ext_fn) in program.external_functions.iter() {
+            let function = *(self.fns.get(&ext_fn.name)).unwrap_or_else(|| {
+                panic!("no implementation of external function {}", ext_fn.name)
+            });
+
+            ext_fns.insert(*id, ExtFnImpl { function }).expect_free();
+        }
+
+        ext_fns
+    }
+}
@@ -11,6 +11,8 @@
 
 mod build;
 pub use build::*;
+mod harness;
+pub use harness::*;
 use jssat_ir::UnwrapNone;
 
 #[cfg(test)]
@@ -26,7 +28,7 @@ use gc::{custom_trace, BorrowError, BorrowMutError, Finalize, Gc, GcCell, Trace}
 use rustc_hash::FxHashMap;
 use thiserror::Error;
 
-use jssat_ir::isa::{Atom, CompareType, ValueType};
+use jssat_ir::isa::{Atom, CompareType, InternalSlot, ValueType};
 use jssat_ir::lifted::Function;
 use jssat_ir::{collections::StrictZip, isa::BinaryOperator};
 
@@ -42,6 +44,8 @@ pub struct Interpreter<'parent> {
     alloc_id: usize,
     code: &'parent LiftedProgram,
     external_fns: &'parent FxHashMap<ExternalFunctionId, ExtFnImpl>,
+    /// The amount of instructions left to execute, if execution is limited.
+    fuel: Option<usize>,
     pub moment: MomentApi,
 }
 
@@ -66,6 +70,19 @@ pub enum RecordKey {
     FnPtr(FunctionId),
 }
 
+impl RecordKey {
+    /// The value that would be used to refer to this key.
+    pub fn to_value(&self) -> Value {
+        match self {
+            RecordKey::Atom(atom) => Value::Atom(*atom),
+            RecordKey::Bytes(bytes) => Value::Bytes(bytes.clone()),
+            RecordKey::Number(number) => Value::Number(*number),
+            RecordKey::Boolean(boolean) => Value::Boolean(*boolean),
+            RecordKey::FnPtr(fn_id) => Value::FnPtr(*fn_id),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ListKey {
     Index(usize),
@@ -188,6 +205,8 @@ pub struct Record {
     #[deref]
     #[deref_mut]
     values: FxHashMap<RecordKey, Value>,
+    /// The keys set with [`Record::set`], in the order they were inserted.
+    order: Vec<RecordKey>,
 }
 
 impl Record {
@@ -195,9 +214,30 @@ impl Record {
         Self {
             alloc_id,
             values: Default::default(),
+            order: Default::default(),
+        }
+    }
+
+    /// Sets the field `key` to `value`. A key that isn't already in the record
+    /// is placed after every other key in [`Record::entries`].
+    pub fn set(&mut self, key: RecordKey, value: Value) {
+        if self.values.insert(key.clone(), value).is_none() {
+            self.order.push(key);
+        }
+    }
+
+    /// Removes the field `key`, if the record has it.
+    pub fn delete(&mut self, key: &RecordKey) {
+        if self.values.remove(key).is_some() {
+            self.order.retain(|k| k != key);
         }
     }
 
+    /// The fields set with [`Record::set`], in the order they were inserted.
+    pub fn entries(&self) -> impl Iterator<Item = (&RecordKey, &Value)> {
+        (self.order.iter()).filter_map(|key| self.values.get_key_value(key))
+    }
+
     pub fn new_gc(alloc_id: usize) -> Gc<GcCell<Self>> {
         let me = Self::new(alloc_id);
         Gc::new(GcCell::new(me))
@@ -354,10 +394,29 @@ impl<'p> Interpreter<'p> {
             alloc_id: 0,
             code,
             external_fns: ext_fns,
+            fuel: None,
             moment: MomentApi::new(code),
         }
     }
 
+    /// Limits the interpreter to executing at most `fuel` instructions, after
+    /// which execution fails with [`InstErr::OutOfFuel`].
+    pub fn with_fuel(mut self, fuel: usize) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    fn consume_fuel(&mut self) -> InstResult<()> {
+        match &mut self.fuel {
+            Some(0) => Err(InstErr::OutOfFuel(Location::caller())),
+            Some(fuel) => {
+                *fuel -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
     fn next_alloc_id(&mut self) -> usize {
         let id = self.alloc_id;
         self.alloc_id += 1;
@@ -401,12 +460,17 @@ impl<'p> Interpreter<'p> {
         };
 
         for (idx, inst) in function.instructions.iter().enumerate() {
+            inst_exec.interpreter.consume_fuel()?;
+
             // only take a pre snapshot if it's a non-trivial instruction that
             // could mutate args, like call
             use jssat_ir::frontend::ir::InstructionData;
             if matches!(
                 inst.data,
-                InstructionData::CallVirt(_) | InstructionData::CallStatic(_)
+                InstructionData::CallVirt(_)
+                    | InstructionData::CallStatic(_)
+                    | InstructionData::CallSpread(_)
+                    | InstructionData::ListMap(_)
             ) {
                 let mut pre = ValueSnapshotArena::new();
 
@@ -443,6 +507,8 @@ impl<'p> Interpreter<'p> {
             result?;
         }
 
+        inst_exec.interpreter.consume_fuel()?;
+
         let inst_idx = function.instructions.len();
         let mut pre = ValueSnapshotArena::new();
 
@@ -475,12 +541,23 @@ impl<'p> Interpreter<'p> {
                 let args = inst_exec.load_args(&jump.1)?;
                 (inst_exec.registers, self.execute_fn_id(jump.0, args))
             }
-            EndInstruction::Return(i) => match i.0 {
-                Some(register) => {
-                    let value = inst_exec.get(register)?.clone();
+            EndInstruction::Return(i) => match i.0.as_slice() {
+                [] => (inst_exec.registers, Ok(None)),
+                [register] => {
+                    let value = inst_exec.get(*register)?.clone();
                     (inst_exec.registers, Ok(Some(value)))
                 }
-                None => (inst_exec.registers, Ok(None)),
+                // multiple values are handed back to the caller as a list
+                registers => {
+                    let list = List::new_gc(inst_exec.interpreter.next_alloc_id());
+
+                    for register in registers {
+                        let value = inst_exec.get(*register)?.clone();
+                        list.borrow_mut().push(value);
+                    }
+
+                    (inst_exec.registers, Ok(Some(Value::List(list))))
+                }
             },
         };
 
@@ -523,6 +600,10 @@ pub enum InstErr {
     ListInvalidIndex(i64, PanicLocation),
     #[error("Invalid index value for list: {:?}", .0)]
     ListInvalidIndexRegister(Value, PanicLocation),
+    #[error("Cannot pop from an empty list")]
+    ListEmpty(PanicLocation),
+    #[error("Slice {}..{} is out of range for bytes of length {}", .0, .1, .2)]
+    BytesSliceOutOfRange(i64, i64, usize, PanicLocation),
     #[error("Constant does not exist: {}", .0)]
     ConstantDNE(ConstantId, PanicLocation),
     #[error("Unable to perform binary operation: {:?} `{}` {:?}", .0, .2, .1)]
@@ -539,6 +620,8 @@ pub enum InstErr {
     NotEnoughArgs(usize, usize, PanicLocation),
     #[error("External function does not exist: {}", .0)]
     ExtFnDNE(ExternalFunctionId, PanicLocation),
+    #[error("Function does not exist: {}", .0)]
+    FnDNE(String, PanicLocation),
     // TODO: supply more information here?
     #[error("Invalid type of argument")]
     InvalidType(PanicLocation),
@@ -546,6 +629,8 @@ pub enum InstErr {
     BorrowError(#[from] BorrowErrorWrapper),
     #[error("An assertion failed: {}", .0)]
     AssertionFailed(&'static str, PanicLocation),
+    #[error("Ran out of fuel before execution finished")]
+    OutOfFuel(PanicLocation),
 
     // TODO: support external function calls
     // they can be implemented by having some kind of rust function be paired
@@ -587,6 +672,38 @@ impl<'i, 'c> InstExec<'i, 'c> {
                     Value::Record(Record::new_gc(self.interpreter.next_alloc_id())),
                 );
             }
+            RecordClone(i) => {
+                let source = self.get_record(i.record)?;
+                let (values, order) = (source.values.clone(), source.order.clone());
+                drop(source);
+
+                let mut record = Record::new(self.interpreter.next_alloc_id());
+                record.values = values;
+                record.order = order;
+
+                self.registers
+                    .insert(i.result, Value::Record(Gc::new(GcCell::new(record))));
+            }
+            RecordEntries(i) => {
+                let entries = (self.get_record(i.record)?.entries())
+                    // internal slots aren't part of a record's entries
+                    .filter(|(key, _)| !matches!(key, RecordKey::Atom(_)))
+                    .map(|(key, value)| (key.to_value(), value.clone()))
+                    .collect::<Vec<_>>();
+
+                let list = List::new_gc(self.interpreter.next_alloc_id());
+
+                for (key, value) in entries {
+                    let mut pair = Record::new(self.interpreter.next_alloc_id());
+                    pair.set(RecordKey::Number(0), key);
+                    pair.set(RecordKey::Number(1), value);
+
+                    let pair = Value::Record(Gc::new(GcCell::new(pair)));
+                    list.borrow_mut().push(pair);
+                }
+
+                self.registers.insert(i.result, Value::List(list));
+            }
             RecordGet(i) => {
                 let key = self.to_key(i.key)?;
                 let record = self.get_record(i.record)?;
@@ -594,6 +711,43 @@ impl<'i, 'c> InstExec<'i, 'c> {
                 drop(record);
                 self.registers.insert(i.result, value);
             }
+            RecordGetOr(i) => {
+                let key = self.to_key(i.key)?;
+                let record = self.get_record(i.record)?;
+                let value = record.get(&key).cloned();
+                drop(record);
+
+                let value = match value {
+                    Some(value) => value,
+                    None => self.get(i.default)?.clone(),
+                };
+                self.registers.insert(i.result, value);
+            }
+            RecordGetInherited(i) => {
+                let key = self.to_key(i.key)?;
+                let prototype = RecordKey::Atom(InternalSlot::Prototype.atom());
+                let mut record = self.get(i.record)?.clone();
+
+                let value = loop {
+                    let next = {
+                        let current = record.try_into_record()?;
+
+                        if let Ok(value) = current.try_get(&key) {
+                            break value.clone();
+                        }
+
+                        match current.try_get(&prototype) {
+                            // a non-record prototype, such as `null`, is the end of the chain
+                            Ok(parent @ Value::Record(_)) => parent.clone(),
+                            _ => break current.try_get(&key)?.clone(),
+                        }
+                    };
+
+                    record = next;
+                };
+
+                self.registers.insert(i.result, value);
+            }
             RecordSet(i) => {
                 let key = self.to_key(i.key)?;
 
@@ -601,11 +755,11 @@ impl<'i, 'c> InstExec<'i, 'c> {
                     Some(register) => {
                         let value = self.get(register)?.clone();
                         let mut record = self.get_record_mut(i.record)?;
-                        record.insert(key, value);
+                        record.set(key, value);
                     }
                     None => {
                         let mut record = self.get_record_mut(i.record)?;
-                        record.remove(&key);
+                        record.delete(&key);
                     }
                 };
             }
@@ -619,9 +773,25 @@ impl<'i, 'c> InstExec<'i, 'c> {
             GetFnPtr(i) => {
                 self.registers.insert(i.result, Value::FnPtr(i.item));
             }
+            GetFnPtrDynamic(i) => {
+                let name = self.get(i.name)?.try_into_bytes()?;
+                let name = String::from_utf8_lossy(name).into_owned();
+
+                let fn_id = (self.interpreter.code.function_names)
+                    .get(&name)
+                    .copied()
+                    .ok_or_else(|| FnDNE(name, Location::caller()))?;
+
+                self.registers.insert(i.result, Value::FnPtr(fn_id));
+            }
             CallStatic(i) => {
                 self.call_fn(&i.args, i.calling, i.result)?;
             }
+            CallSpread(i) => {
+                let args = self.get_list(i.args)?.to_vec();
+                let result = self.interpreter.execute_fn_id(i.calling, args)?;
+                self.store_fn_result(result, i.result)?;
+            }
             CallExtern(i) => {
                 let ext_fn = self.get_ext_fn(i.calling)?;
                 let args = self.load_args(&i.args)?;
@@ -675,7 +845,17 @@ impl<'i, 'c> InstExec<'i, 'c> {
                             combined.extend(rhs);
                             Bytes(combined)
                         }
-                        (Number(lhs), Number(rhs)) => Number(*lhs + *rhs),
+                        (Number(lhs), Number(rhs)) => match lhs.checked_add(*rhs) {
+                            Some(sum) => Number(sum),
+                            None => return fail(),
+                        },
+                        _ => return fail(),
+                    },
+                    Multiply => match (lhs, rhs) {
+                        (Number(lhs), Number(rhs)) => match lhs.checked_mul(*rhs) {
+                            Some(product) => Number(product),
+                            None => return fail(),
+                        },
                         _ => return fail(),
                     },
                     And => match (lhs, rhs) {
@@ -752,6 +932,39 @@ impl<'i, 'c> InstExec<'i, 'c> {
 
                 self.registers.insert(i.result, is_type);
             }
+            AssumeType(i) => {
+                // the frontend is trusted to be correct, so there's no check
+                let value = self.get(i.value)?.clone();
+                self.registers.insert(i.result, value);
+            }
+            TypeOf(i) => {
+                let name = self.get(i.value)?.kind().type_name();
+                self.registers
+                    .insert(i.result, Value::Bytes(name.as_bytes().to_vec()));
+            }
+            BytesConcat(i) => {
+                let mut bytes = self.get(i.lhs)?.try_into_bytes()?.clone();
+                bytes.extend(self.get(i.rhs)?.try_into_bytes()?);
+                self.registers.insert(i.result, Value::Bytes(bytes));
+            }
+            BytesSlice(i) => {
+                let bytes = self.get(i.bytes)?.try_into_bytes()?;
+                let start = self.get(i.start)?.try_into_number()?;
+                let end = self.get(i.end)?.try_into_number()?;
+
+                let range: Option<(usize, usize)> = start.try_into().ok().zip(end.try_into().ok());
+                let slice = range
+                    .and_then(|(start, end)| bytes.get(start..end))
+                    .ok_or(BytesSliceOutOfRange(
+                        start,
+                        end,
+                        bytes.len(),
+                        Location::caller(),
+                    ))?
+                    .to_vec();
+
+                self.registers.insert(i.result, Value::Bytes(slice));
+            }
             NewList(i) => {
                 self.registers.insert(
                     i.result,
@@ -793,6 +1006,45 @@ impl<'i, 'c> InstExec<'i, 'c> {
                 drop(list);
                 self.registers.insert(i.result, Value::Number(len as i64));
             }
+            ListPush(i) => {
+                let value = self.get(i.value)?.clone();
+                let mut list = self.get_list_mut(i.list)?;
+                list.push(value);
+            }
+            ListPop(i) => {
+                let mut list = self.get_list_mut(i.list)?;
+                let value = list.pop().ok_or(ListEmpty(Location::caller()))?;
+                drop(list);
+                self.registers.insert(i.result, value);
+            }
+            ListMap(i) => {
+                let fn_id = match self.get(i.function)? {
+                    Value::FnPtr(fn_id) => *fn_id,
+                    value => return Err(NonVirt(value.clone(), Location::caller())),
+                };
+
+                // the function may modify the list, so we only visit the
+                // elements that were in it to begin with
+                let elements = self.get_list(i.list)?.to_vec();
+                let mapped = List::new_gc(self.interpreter.next_alloc_id());
+
+                let args = self.load_args(&i.args)?;
+
+                for element in elements {
+                    let mut args = args.clone();
+                    args.push(element);
+                    let result = self.interpreter.execute_fn_id(fn_id, args)?;
+
+                    if i.result.is_some() {
+                        let value = result.ok_or(ExpectedNonVoid(Location::caller()))?;
+                        mapped.borrow_mut().push(value);
+                    }
+                }
+
+                if let Some(result) = i.result {
+                    self.registers.insert(result, Value::List(mapped));
+                }
+            }
             GetRuntime(i) => {
                 self.registers.insert(i.result, Value::Runtime);
             }
@@ -1,4 +1,7 @@
-use jssat_ir::{frontend::builder::ProgramBuilder, lifted::lift};
+use jssat_ir::{
+    frontend::{builder::ProgramBuilder, parse_jssatir},
+    lifted::lift,
+};
 
 use super::*;
 use Value::*;
@@ -66,3 +69,45 @@ fn can_get() {
         .unwrap();
     assert!(matches!(results, Some(Number(2))));
 }
+
+/// Interprets the entrypoint of a program written in the textual JSSAT IR
+/// format, producing the value it returns.
+fn interpret_jssatir(source: &str) -> Value {
+    let program = lift(parse_jssatir::parse(source));
+    let ext_fns = FxHashMap::default();
+
+    let mut interpreter = Interpreter::new(&program, &ext_fns);
+    let result = interpreter
+        .execute_fn_id(program.entrypoint, vec![])
+        .unwrap_or_else(|err| panic!("failed to run the program: {:?}", err));
+
+    result.expect("the entrypoint should return a value")
+}
+
+#[test]
+fn list_map_passes_args_before_elements() {
+    let source = r#"; jssat-ir v1
+entrypoint @0;
+
+fn @0() entry $0 {
+  @0.$0():
+    %0 = NewList;
+    %1 = MakeInteger 2;
+    ListPush %0 <- %1;
+    %2 = MakeInteger 10;
+    %3 = GetFnPtr @1;
+    %4 = ListMap %0, %3(%2);
+    %5 = ListPop %4;
+    Return %5;
+}
+
+fn @1(%0, %1, ) entry $0 {
+  @1.$0():
+    %2 = BinOp Add %0, %1
+    Return %2;
+}
+"#;
+
+    let result = interpret_jssatir(source);
+    assert!(matches!(result, Number(12)));
+}
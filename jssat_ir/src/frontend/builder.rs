@@ -31,6 +31,151 @@ pub fn panics_on_drop_with_function_start_without_function_end() {
     // builder.end_function(my_fn);
 }
 
+#[test]
+#[should_panic(expected = "jmpif condition")]
+pub fn panics_on_jmpif_with_arithmetic_condition() {
+    let mut builder = ProgramBuilder::new();
+    let mut main = builder.start_function_main();
+
+    let mut block = main.start_block_main();
+    let one = block.make_number_decimal(1);
+    let two = block.add(one, one);
+
+    let (exit, []) = main.start_block();
+    let exit_sig = exit.signature();
+    main.end_block(exit.ret(&[]));
+
+    // Oops! `two` is a number, not a boolean
+    main.end_block(block.jmpif(two, exit_sig, [], exit_sig, []));
+    builder.end_function(main);
+}
+
+#[test]
+#[should_panic(expected = "was never ended")]
+pub fn panics_on_jmp_to_block_without_block_end() {
+    let mut builder = ProgramBuilder::new();
+    let mut main = builder.start_function_main();
+
+    let block = main.start_block_main();
+    let (exit, []) = main.start_block();
+
+    // Oops! `exit` is never ended with `main.end_block(exit.ret(&[]))`
+    main.end_block(block.jmp(exit.signature(), []));
+    builder.end_function(main);
+}
+
+#[test]
+#[should_panic(expected = "cannot be returned as")]
+pub fn panics_on_ret_of_incompatible_value_in_typed_function() {
+    let mut builder = ProgramBuilder::new();
+    let (mut f, []) = builder.start_function_typed("f", Returns::Value(FFIValueType::String));
+
+    let mut block = f.start_block_main();
+    // Oops! `f` is declared to return a string, not a number
+    let one = block.make_number_decimal(1);
+    f.end_block(block.ret(&[one]));
+    builder.end_function(f);
+}
+
+#[test]
+pub fn with_function_abandons_function_on_error() {
+    let mut builder = ProgramBuilder::new();
+
+    let result = builder.with_function("fails", |_, f, [x]| {
+        f.with_block_main(|block| Ok(block.ret(&[x])))?;
+        // Oops! Building the block failed partway through
+        f.with_block::<0, _>(|_, []| Err("oops"))?;
+        Ok(())
+    });
+    assert_eq!(result, Err("oops"));
+
+    let ok = builder.with_function::<0, ()>("succeeds", |_, f, []| {
+        f.with_block_main(|block| Ok(block.ret(&[])))?;
+        Ok(())
+    });
+    assert!(ok.is_ok());
+
+    builder.create_blank_entrypoint();
+    let ir = builder.finish();
+    assert_eq!(ir.functions.len(), 2);
+    assert!((ir.functions.values()).all(|f| f.name.as_deref() != Some("fails")));
+}
+
+#[test]
+pub fn sequentially_built_functions_have_ids_in_insertion_order() {
+    fn build() -> Vec<FunctionId> {
+        let mut builder = ProgramBuilder::new();
+
+        (0..3)
+            .map(|_| {
+                let (mut f, []) = builder.start_function_seq();
+                let block = f.start_block_main();
+                f.end_block(block.ret(&[]));
+                builder.end_function(f).id
+            })
+            .collect()
+    }
+
+    let ids = build();
+    let values = ids.iter().map(|id| id.value()).collect::<Vec<_>>();
+    assert_eq!(values, [0, 1, 2]);
+    assert_eq!(ids, build());
+}
+
+#[test]
+#[should_panic(expected = "is still being built")]
+pub fn panics_on_start_function_seq_while_building() {
+    let mut builder = ProgramBuilder::new();
+    let (_first, []) = builder.start_function_seq::<0>();
+
+    // Oops! `first` was never ended, so it is still being built
+    let (second, []) = builder.start_function_seq::<0>();
+    builder.abandon_function(second);
+}
+
+#[test]
+pub fn start_function_seq_after_abandoning() {
+    let mut builder = ProgramBuilder::new();
+    let (first, []) = builder.start_function_seq::<0>();
+    builder.abandon_function(first);
+
+    let (mut second, []) = builder.start_function_seq();
+    let block = second.start_block_main();
+    second.end_block(block.ret(&[]));
+    builder.end_function(second);
+}
+
+#[test]
+pub fn signatures_can_be_stored_in_sets() {
+    let mut builder = ProgramBuilder::new();
+
+    let (mut a, []) = builder.start_function();
+    let block = a.start_block_main();
+    let (b_blk, []) = a.start_block();
+    let b_blk_sig = b_blk.signature();
+    a.end_block(b_blk.ret(&[]));
+    let a_blk_sig = block.signature();
+    a.end_block(block.jmp(b_blk_sig, []));
+    let a = builder.end_function(a);
+
+    let (mut b, []) = builder.start_function();
+    let block = b.start_block_main();
+    b.end_block(block.ret(&[]));
+    let b = builder.end_function(b);
+
+    let mut fns = std::collections::HashSet::new();
+    fns.insert(a);
+    fns.insert(b);
+    fns.insert(a);
+    assert_eq!(fns.len(), 2);
+    assert!(fns.contains(&a) && fns.contains(&b));
+
+    let mut blks = std::collections::HashSet::new();
+    blks.insert(a_blk_sig);
+    assert!(blks.contains(&a_blk_sig));
+    assert!(!blks.contains(&b_blk_sig));
+}
+
 #[derive(Deref, DerefMut)]
 pub struct ProgramBuilder {
     #[deref]
@@ -41,6 +186,9 @@ pub struct ProgramBuilder {
     external_functions: Vec<ExternalFunction>,
     functions: FxHashMap<FunctionId, Function>,
     gen_function_id: Counter<FunctionId>,
+    /// The function started with `start_function_seq` that has yet to be
+    /// ended, if any.
+    building_seq: Option<FunctionId>,
 }
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
@@ -55,6 +203,7 @@ impl ProgramBuilder {
             external_functions: vec![],
             functions: FxHashMap::default(),
             gen_function_id: Counter::new(),
+            building_seq: None,
         }
     }
 
@@ -66,7 +215,7 @@ impl ProgramBuilder {
     pub fn create_blank_entrypoint(&mut self) -> FnSignature<0> {
         let mut f = self.start_function_main();
         let b = f.start_block_main();
-        f.end_block(b.ret(None));
+        f.end_block(b.ret(&[]));
         self.end_function(f)
     }
 
@@ -152,6 +301,28 @@ impl ProgramBuilder {
         builder
     }
 
+    /// Starts a function with a declared return type. Every `ret` in the
+    /// function is checked against it when the function is ended.
+    pub fn start_function_typed<const PARAMETERS: usize>(
+        &self,
+        name: &str,
+        return_type: FFIReturnType,
+    ) -> (FunctionBuilder<PARAMETERS>, [RegisterId; PARAMETERS]) {
+        let (mut builder, parameters) = self.start_function();
+        builder.with_name(name.to_string());
+        builder.return_type = Some(return_type);
+        (builder, parameters)
+    }
+
+    /// Starts building a function, which must later be passed to
+    /// [`ProgramBuilder::end_function`].
+    ///
+    /// The id of the function is handed out here, while the function is only
+    /// inserted into the program once it is ended. As this only needs `&self`,
+    /// several functions may be built at once, so the order of the ids follows
+    /// the order functions were started in rather than the order they were
+    /// ended in. Use [`ProgramBuilder::start_function_seq`] when functions are
+    /// built one after another and that order matters.
     pub fn start_function<const PARAMETERS: usize>(
         &self,
     ) -> (FunctionBuilder<PARAMETERS>, [RegisterId; PARAMETERS]) {
@@ -168,12 +339,36 @@ impl ProgramBuilder {
         (FunctionBuilder::new(id), parameters)
     }
 
+    /// Starts building a function, like [`ProgramBuilder::start_function`],
+    /// but panics if another function started with this method hasn't been
+    /// ended yet. As long as every function is started this way, the ids of
+    /// functions are in the same order as they are inserted into the program.
+    #[track_caller]
+    pub fn start_function_seq<const PARAMETERS: usize>(
+        &mut self,
+    ) -> (FunctionBuilder<PARAMETERS>, [RegisterId; PARAMETERS]) {
+        if let Some(id) = self.building_seq {
+            panic!(
+                "cannot start a function while function {:?} is still being built",
+                id
+            );
+        }
+
+        let (builder, parameters) = self.start_function();
+        self.building_seq = Some(builder.id);
+        (builder, parameters)
+    }
+
     pub fn end_function<const PARAMETERS: usize>(
         &mut self,
         mut builder: FunctionBuilder<PARAMETERS>,
     ) -> FnSignature<PARAMETERS> {
         let signature = builder.signature();
 
+        if self.building_seq == Some(signature.id) {
+            self.building_seq = None;
+        }
+
         builder.is_ok_to_drop = true;
         let function = builder.finish();
 
@@ -181,6 +376,42 @@ impl ProgramBuilder {
 
         signature
     }
+
+    /// Builds a named function within `build`. The function is ended if
+    /// `build` succeeds, and abandoned if it fails, so that the builder is
+    /// never dropped without being ended.
+    pub fn with_function<const PARAMETERS: usize, E>(
+        &mut self,
+        name: &str,
+        build: impl FnOnce(
+            &mut Self,
+            &mut FunctionBuilder<PARAMETERS>,
+            [RegisterId; PARAMETERS],
+        ) -> Result<(), E>,
+    ) -> Result<FnSignature<PARAMETERS>, E> {
+        let (mut builder, parameters) = self.start_function();
+        builder.with_name(name.to_string());
+
+        match build(self, &mut builder, parameters) {
+            Ok(()) => Ok(self.end_function(builder)),
+            Err(error) => {
+                self.abandon_function(builder);
+                Err(error)
+            }
+        }
+    }
+
+    /// Discards a function without adding it to the program.
+    pub fn abandon_function<const PARAMETERS: usize>(
+        &mut self,
+        builder: FunctionBuilder<PARAMETERS>,
+    ) {
+        if self.building_seq == Some(builder.id) {
+            self.building_seq = None;
+        }
+
+        builder.abandon();
+    }
 }
 
 impl Default for ProgramBuilder {
@@ -196,6 +427,10 @@ pub struct FunctionBuilder<const PARAMETERS: usize> {
     gen_register_id: Arc<Counter<RegisterId>>,
     entrypoint: Option<BlockId>,
     blocks: FxHashMap<BlockId, FunctionBlock>,
+    /// The return type declared with `start_function_typed`, if any.
+    return_type: Option<FFIReturnType>,
+    /// The parameters marked with `specialize_on`.
+    exact: [bool; PARAMETERS],
     /// To prevent mistakes, if the user does not call `end_function` after
     /// having declared a builder with `start_function`, then this field will
     /// remain `false`. If, when being dropped, this field is `false`, a panic
@@ -216,6 +451,8 @@ impl<const P: usize> FunctionBuilder<P> {
             gen_register_id: Arc::new(Counter::new_with_value(P)),
             entrypoint: None,
             blocks: FxHashMap::default(),
+            return_type: None,
+            exact: [false; P],
             is_ok_to_drop: false,
         }
     }
@@ -224,13 +461,41 @@ impl<const P: usize> FunctionBuilder<P> {
         self.name = Some(name);
     }
 
+    /// Exempts the parameter at `parameter` from generalization, so that
+    /// symbolic execution keeps a separate specialization for every exact
+    /// value passed to it. Useful for arguments that later code is expected to
+    /// fold over, such as a field count used for unrolling.
+    #[track_caller]
+    pub fn specialize_on(&mut self, parameter: usize) {
+        assert!(
+            parameter < P,
+            "cannot specialize on parameter {} of a function with {} parameters",
+            parameter,
+            P
+        );
+
+        self.exact[parameter] = true;
+    }
+
+    /// Discards the function without adding it to the program. Functions are
+    /// abandoned through [`ProgramBuilder::abandon_function`], so that the
+    /// program builder knows the function is no longer being built.
+    fn abandon(mut self) {
+        self.is_ok_to_drop = true;
+    }
+
     fn finish(self) -> Function {
+        self.check_jump_targets();
+        self.check_jmpif_conditions();
+        self.check_returns();
+
         Function {
             name: self.name.clone(),
             parameters: (0..P)
                 .into_iter()
                 .map(|p| Parameter {
                     register: RegisterId::new_with_value_const(p),
+                    exact: self.exact[p],
                 })
                 .collect(),
             entry_block: self.entrypoint.expect("expected entry block"),
@@ -239,6 +504,122 @@ impl<const P: usize> FunctionBuilder<P> {
         }
     }
 
+    /// Ensures that every block jumped to in this function was ended with
+    /// `end_block`. Otherwise, jumping to a block that doesn't exist would
+    /// only be noticed much later in symbolic execution.
+    #[track_caller]
+    fn check_jump_targets(&self) {
+        for (block_id, block) in self.blocks.iter() {
+            let targets = match &block.end {
+                ControlFlowInstruction::Jmp(Jump(target)) => vec![target.0],
+                ControlFlowInstruction::JmpIf(JumpIf { if_so, other, .. }) => {
+                    vec![if_so.0, other.0]
+                }
+                ControlFlowInstruction::Ret(_) => continue,
+            };
+
+            for target in targets {
+                if !self.blocks.contains_key(&target) {
+                    panic!(
+                        "${} jumps to ${}, but ${} was never ended with `end_block`",
+                        block_id, target, target
+                    );
+                }
+            }
+        }
+    }
+
+    /// Ensures that every `jmpif` condition in this function could be a
+    /// boolean. Registers which come from an instruction that is known to
+    /// produce a non-boolean (e.g. `add`, `make_number_decimal`) are rejected
+    /// here, rather than much later in symbolic execution.
+    ///
+    /// Registers that we cannot reason about structurally (block parameters,
+    /// call results, record/list reads) are let through.
+    #[track_caller]
+    fn check_jmpif_conditions(&self) {
+        let producers = self.producers();
+
+        for (block_id, block) in self.blocks.iter() {
+            let condition = match &block.end {
+                ControlFlowInstruction::JmpIf(JumpIf { condition, .. }) => *condition,
+                _ => continue,
+            };
+
+            if let Some(producer) = producers.get(&condition) {
+                if !may_produce_boolean(producer) {
+                    panic!(
+                        "jmpif condition %{} in ${} is not a boolean, it is produced by `{}`",
+                        condition,
+                        block_id,
+                        producer.as_display()
+                    );
+                }
+            }
+        }
+    }
+
+    /// Ensures that every `ret` in a function with a declared return type
+    /// returns as many values as declared, and that the value returned could
+    /// be of the declared type. As with `jmpif` conditions, registers whose
+    /// kind can't be known until symbolic execution are let through.
+    #[track_caller]
+    fn check_returns(&self) {
+        let return_type = match &self.return_type {
+            Some(return_type) => return_type,
+            None => return,
+        };
+
+        let producers = self.producers();
+
+        for (block_id, block) in self.blocks.iter() {
+            let values = match &block.end {
+                ControlFlowInstruction::Ret(Return(values)) => values,
+                _ => continue,
+            };
+
+            let (value, ffi_type) = match (values.as_slice(), return_type) {
+                ([], Returns::Void) => continue,
+                ([value], Returns::Value(ffi_type)) => (value, ffi_type),
+                _ => panic!(
+                    "${} returns {} values, but the function is declared to return {:?}",
+                    block_id,
+                    values.len(),
+                    return_type
+                ),
+            };
+
+            if let Some(producer) = producers.get(value) {
+                if let Some(kind) = produced_value_type(producer) {
+                    if !is_ffi_compatible(kind, ffi_type) {
+                        panic!(
+                            "${} returns %{}, produced by `{}`, which cannot be returned as {:?}",
+                            block_id,
+                            value,
+                            producer.as_display(),
+                            ffi_type
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Maps every register assigned to in this function to the instruction
+    /// that assigns it.
+    fn producers(&self) -> FxHashMap<RegisterId, &InstructionData> {
+        let mut producers = FxHashMap::default();
+        for block in self.blocks.values() {
+            for inst in block.instructions.iter() {
+                if let Some(register) = inst.assigned_to() {
+                    producers.insert(register, &inst.data).expect_free();
+                }
+            }
+        }
+
+        producers
+    }
+
     pub const fn parameter_const<const PARAMETER: usize>(&self) -> RegisterId {
         // TODO: make this a constant assertion
         // this is currently not possible because use of generic parameters from
@@ -315,6 +696,28 @@ impl<const P: usize> FunctionBuilder<P> {
         BlkSignature(self.end_block_dyn(builder.0))
     }
 
+    /// Builds a block within `build`, ending it if `build` succeeds. Should
+    /// `build` fail, the block is discarded.
+    pub fn with_block<const PARAMETERS: usize, E>(
+        &mut self,
+        build: impl FnOnce(
+            BlockBuilder<PARAMETERS>,
+            [RegisterId; PARAMETERS],
+        ) -> Result<FinalizedBlockBuilder<PARAMETERS>, E>,
+    ) -> Result<BlkSignature<PARAMETERS>, E> {
+        let (builder, parameters) = self.start_block();
+        build(builder, parameters).map(|block| self.end_block(block))
+    }
+
+    /// Like [`FunctionBuilder::with_block`], but for the entrypoint block.
+    pub fn with_block_main<E>(
+        &mut self,
+        build: impl FnOnce(BlockBuilder<0>) -> Result<FinalizedBlockBuilder<0>, E>,
+    ) -> Result<BlkSignature<0>, E> {
+        let builder = self.start_block_main();
+        build(builder).map(|block| self.end_block(block))
+    }
+
     pub fn end_block_dyn(&mut self, mut builder: DynFinalizedBlockBuilder) -> DynBlkSignature {
         let signature = builder.builder.signature();
 
@@ -329,23 +732,90 @@ impl<const P: usize> FunctionBuilder<P> {
 
 impl<const P: usize> Drop for FunctionBuilder<P> {
     fn drop(&mut self) {
-        if !self.is_ok_to_drop {
+        // panicking again while unwinding would abort the process
+        if !self.is_ok_to_drop && !std::thread::panicking() {
             panic!("A `FunctionBuilder` (created with `start_function`) was dropped without `end_function` being called.");
         }
     }
 }
 
-#[derive(Clone, Copy)]
+/// Determines if the result of an instruction could possibly be a boolean.
+fn may_produce_boolean(inst: &InstructionData) -> bool {
+    produced_value_type(inst).map_or(true, |kind| kind == ValueType::Boolean)
+}
+
+/// The kind of value an instruction produces, if it can be known without
+/// symbolic execution.
+fn produced_value_type(inst: &InstructionData) -> Option<ValueType> {
+    match inst {
+        InstructionData::MakeBoolean(_)
+        | InstructionData::Negate(_)
+        | InstructionData::IsType(_)
+        | InstructionData::RecordHasKey(_)
+        | InstructionData::ListHasKey(_) => Some(ValueType::Boolean),
+        InstructionData::BinOp(BinOp { op, .. }) => match op {
+            BinaryOperator::Add | BinaryOperator::Multiply => Some(ValueType::Number),
+            _ => Some(ValueType::Boolean),
+        },
+        InstructionData::AssumeType(AssumeType { kind, .. }) => Some(*kind),
+        InstructionData::MakeInteger(_) | InstructionData::ListLen(_) => Some(ValueType::Number),
+        InstructionData::MakeBytes(_)
+        | InstructionData::TypeOf(_)
+        | InstructionData::BytesConcat(_)
+        | InstructionData::BytesSlice(_) => Some(ValueType::Bytes),
+        InstructionData::MakeAtom(_) => Some(ValueType::Atom),
+        InstructionData::NewRecord(_) | InstructionData::RecordClone(_) => Some(ValueType::Record),
+        InstructionData::NewList(_)
+        | InstructionData::RecordEntries(_)
+        | InstructionData::ListMap(ListMap {
+            result: Some(_), ..
+        }) => Some(ValueType::List),
+        InstructionData::GetFnPtr(_) | InstructionData::GetFnPtrDynamic(_) => {
+            Some(ValueType::FnPtr)
+        }
+        InstructionData::GetRuntime(_) => Some(ValueType::Runtime),
+        // we can't know what these produce until symbolic execution
+        InstructionData::RecordGet(_)
+        | InstructionData::RecordGetInherited(_)
+        | InstructionData::RecordGetOr(_)
+        | InstructionData::ListGet(_)
+        | InstructionData::ListPop(_)
+        | InstructionData::CallStatic(_)
+        | InstructionData::CallSpread(_)
+        | InstructionData::CallExtern(_)
+        | InstructionData::CallVirt(_)
+        | InstructionData::Generalize(_)
+        | InstructionData::Unreachable(_) => None,
+        // these don't produce anything
+        InstructionData::Comment(_)
+        | InstructionData::RecordSet(_)
+        | InstructionData::ListSet(_)
+        | InstructionData::ListPush(_)
+        | InstructionData::ListMap(_)
+        | InstructionData::Assert(_) => None,
+    }
+}
+
+/// Determines if a value of the given kind can be passed as an [`FFIValueType`].
+fn is_ffi_compatible(kind: ValueType, ffi_type: &FFIValueType) -> bool {
+    match ffi_type {
+        FFIValueType::Any => true,
+        FFIValueType::Runtime => kind == ValueType::Runtime,
+        FFIValueType::String => kind == ValueType::Bytes,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct FnSignature<const PARAMETERS: usize> {
     pub id: FunctionId,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct DynBlkSignature {
     pub id: BlockId,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct BlkSignature<const PARAMETERS: usize>(DynBlkSignature);
 
 impl<const P: usize> Deref for BlkSignature<P> {
@@ -402,8 +872,8 @@ impl<const P: usize> BlockBuilder<P> {
         FinalizedBlockBuilder(self.0.jmp_dynargs(block, values))
     }
 
-    pub fn ret(self, value: Option<RegisterId>) -> FinalizedBlockBuilder<P> {
-        FinalizedBlockBuilder(self.0.ret(value))
+    pub fn ret(self, values: &[RegisterId]) -> FinalizedBlockBuilder<P> {
+        FinalizedBlockBuilder(self.0.ret(values))
     }
 
     pub fn jmpif<const PARAMS_TRUE: usize, const PARAMS_FALSE: usize>(
@@ -508,10 +978,12 @@ impl DynBlockBuilder {
         self.push_inst(|result| Instruction::Unreachable(Unreachable { result }))
     }
 
+    /// Emits a comment, which has no effect other than showing up when the IR
+    /// is displayed. Useful for annotating generated IR when debugging.
     #[track_caller]
-    pub fn comment(&mut self, message: &'static str) {
+    pub fn comment(&mut self, message: &str) {
         self.instructions.push(Instruction::Comment(Comment {
-            message,
+            message: message.to_owned(),
             location: std::panic::Location::caller(),
         }));
     }
@@ -544,6 +1016,57 @@ impl DynBlockBuilder {
         result
     }
 
+    /// Produces `value` re-typed as `kind`, without checking at runtime that
+    /// `value` is actually of that type. Prefer [`DynBlockBuilder::assert`]
+    /// with [`DynBlockBuilder::is_type_of`] unless the type is guaranteed.
+    #[track_caller]
+    pub fn assume_type(&mut self, value: RegisterId, kind: ValueType) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions.push(Instruction::AssumeType(AssumeType {
+            result,
+            value,
+            kind,
+        }));
+        result
+    }
+
+    /// Produces a string with the name of the type of `value`.
+    #[track_caller]
+    pub fn type_of(&mut self, value: RegisterId) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions
+            .push(Instruction::TypeOf(TypeOf { result, value }));
+        result
+    }
+
+    /// Produces a new string with the contents of `rhs` appended to `lhs`.
+    #[track_caller]
+    pub fn bytes_concat(&mut self, lhs: RegisterId, rhs: RegisterId) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions
+            .push(Instruction::BytesConcat(BytesConcat { result, lhs, rhs }));
+        result
+    }
+
+    /// Produces a new string with the contents of `bytes` from `start`
+    /// (inclusive) up until `end` (exclusive).
+    #[track_caller]
+    pub fn bytes_slice(
+        &mut self,
+        bytes: RegisterId,
+        start: RegisterId,
+        end: RegisterId,
+    ) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions.push(Instruction::BytesSlice(BytesSlice {
+            result,
+            bytes,
+            start,
+            end,
+        }));
+        result
+    }
+
     #[track_caller]
     pub fn get_runtime(&mut self) -> RegisterId {
         let result = self.gen_register_id.next();
@@ -599,6 +1122,19 @@ impl DynBlockBuilder {
         result
     }
 
+    /// Makes a pointer to the function whose name is the runtime value of
+    /// `name`. The function pointed to is not statically known.
+    #[track_caller]
+    pub fn make_fnptr_dynamic(&mut self, name: RegisterId) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions
+            .push(Instruction::GetFnPtrDynamic(GetFnPtrDynamic {
+                result,
+                name,
+            }));
+        result
+    }
+
     #[track_caller]
     pub fn generalize(&mut self, value: RegisterId) -> RegisterId {
         let result = self.gen_register_id.next();
@@ -615,6 +1151,20 @@ impl DynBlockBuilder {
         result
     }
 
+    /// Makes a shallow copy of `record`, which has a different identity than
+    /// the original record.
+    #[track_caller]
+    pub fn record_clone(&mut self, record: RegisterId) -> RegisterId {
+        self.push_inst(|result| Instruction::RecordClone(RecordClone { result, record }))
+    }
+
+    /// Produces a list of `[key, value]` pairs for every field of `record`, in
+    /// the order the fields were inserted.
+    #[track_caller]
+    pub fn record_entries(&mut self, record: RegisterId) -> RegisterId {
+        self.push_inst(|result| Instruction::RecordEntries(RecordEntries { result, record }))
+    }
+
     #[track_caller]
     pub fn record_get_prop(&mut self, record: RegisterId, property: RegisterId) -> RegisterId {
         let result = self.gen_register_id.next();
@@ -637,6 +1187,43 @@ impl DynBlockBuilder {
         })
     }
 
+    /// Gets a property of a record, walking up the chain of
+    /// [`InternalSlot::Prototype`]s if the record does not have the property.
+    #[track_caller]
+    pub fn record_get_inherited(&mut self, record: RegisterId, property: RegisterId) -> RegisterId {
+        self.push_inst(|result| {
+            Instruction::RecordGetInherited(RecordGetInherited {
+                result,
+                record,
+                key: RecordKey::Prop(property),
+            })
+        })
+    }
+
+    /// Gets an internal slot of a record, or `default` if the record does not
+    /// have the slot.
+    #[track_caller]
+    pub fn record_get_atom_or(
+        &mut self,
+        record: RegisterId,
+        atom: Atom,
+        default: RegisterId,
+    ) -> RegisterId {
+        self.push_inst(|result| {
+            Instruction::RecordGetOr(RecordGetOr {
+                result,
+                record,
+                key: RecordKey::Atom(atom),
+                default,
+            })
+        })
+    }
+
+    #[track_caller]
+    pub fn record_set_prototype(&mut self, record: RegisterId, prototype: RegisterId) {
+        self.record_set_atom(record, InternalSlot::Prototype.atom(), prototype);
+    }
+
     #[track_caller]
     pub fn record_set_prop(&mut self, record: RegisterId, property: RegisterId, value: RegisterId) {
         self.instructions.push(Instruction::RecordSet(RecordSet {
@@ -763,6 +1350,69 @@ impl DynBlockBuilder {
         result
     }
 
+    #[track_caller]
+    pub fn list_push(&mut self, list: RegisterId, value: RegisterId) {
+        self.instructions
+            .push(Instruction::ListPush(ListPush { list, value }));
+    }
+
+    #[track_caller]
+    pub fn list_pop(&mut self, list: RegisterId) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions
+            .push(Instruction::ListPop(ListPop { result, list }));
+        result
+    }
+
+    /// Produces a new list made of the result of calling `function` with
+    /// every element of `list`.
+    #[track_caller]
+    pub fn list_map(&mut self, list: RegisterId, function: RegisterId) -> RegisterId {
+        self.list_map_dynargs(list, function, Vec::new())
+    }
+
+    /// Like [`list_map`](Self::list_map), but `args` are passed to `function`
+    /// before every element.
+    #[track_caller]
+    pub fn list_map_dynargs(
+        &mut self,
+        list: RegisterId,
+        function: RegisterId,
+        args: Vec<RegisterId>,
+    ) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions.push(Instruction::ListMap(ListMap {
+            result: Some(result),
+            list,
+            function,
+            args,
+        }));
+        result
+    }
+
+    /// Calls `function` with every element of `list`.
+    #[track_caller]
+    pub fn list_for_each(&mut self, list: RegisterId, function: RegisterId) {
+        self.list_for_each_dynargs(list, function, Vec::new())
+    }
+
+    /// Like [`list_for_each`](Self::list_for_each), but `args` are passed to
+    /// `function` before every element.
+    #[track_caller]
+    pub fn list_for_each_dynargs(
+        &mut self,
+        list: RegisterId,
+        function: RegisterId,
+        args: Vec<RegisterId>,
+    ) {
+        self.instructions.push(Instruction::ListMap(ListMap {
+            result: None,
+            list,
+            function,
+            args,
+        }));
+    }
+
     #[track_caller]
     fn binop(
         result: RegisterId,
@@ -786,6 +1436,14 @@ impl DynBlockBuilder {
         result
     }
 
+    #[track_caller]
+    pub fn multiply(&mut self, lhs: RegisterId, rhs: RegisterId) -> RegisterId {
+        let result = self.gen_register_id.next();
+        self.instructions
+            .push(Self::binop(result, lhs, rhs, BinaryOperator::Multiply));
+        result
+    }
+
     #[track_caller]
     pub fn compare_equal(&mut self, lhs: RegisterId, rhs: RegisterId) -> RegisterId {
         let result = self.gen_register_id.next();
@@ -802,6 +1460,30 @@ impl DynBlockBuilder {
         result
     }
 
+    /// Compares `lhs > rhs`, which is performed as `rhs < lhs`.
+    #[track_caller]
+    pub fn compare_greater_than(&mut self, lhs: RegisterId, rhs: RegisterId) -> RegisterId {
+        self.compare_less_than(rhs, lhs)
+    }
+
+    /// Compares `lhs <= rhs`, which is performed as `!(rhs < lhs)`.
+    #[track_caller]
+    pub fn compare_less_than_or_equal(&mut self, lhs: RegisterId, rhs: RegisterId) -> RegisterId {
+        let greater_than = self.compare_less_than(rhs, lhs);
+        self.negate(greater_than)
+    }
+
+    /// Compares `lhs >= rhs`, which is performed as `!(lhs < rhs)`.
+    #[track_caller]
+    pub fn compare_greater_than_or_equal(
+        &mut self,
+        lhs: RegisterId,
+        rhs: RegisterId,
+    ) -> RegisterId {
+        let less_than = self.compare_less_than(lhs, rhs);
+        self.negate(less_than)
+    }
+
     #[track_caller]
     pub fn or(&mut self, lhs: RegisterId, rhs: RegisterId) -> RegisterId {
         let result = self.gen_register_id.next();
@@ -912,6 +1594,30 @@ impl DynBlockBuilder {
         result
     }
 
+    /// Calls a function, using every element of the list `args` as an
+    /// argument.
+    #[track_caller]
+    pub fn call_spread(&mut self, fn_id: FunctionId, args: RegisterId) {
+        self.instructions.push(Instruction::CallSpread(CallSpread {
+            result: None,
+            calling: fn_id,
+            args,
+        }));
+    }
+
+    #[track_caller]
+    pub fn call_spread_with_result(&mut self, fn_id: FunctionId, args: RegisterId) -> RegisterId {
+        let result = self.gen_register_id.next();
+
+        self.instructions.push(Instruction::CallSpread(CallSpread {
+            result: Some(result),
+            calling: fn_id,
+            args,
+        }));
+
+        result
+    }
+
     #[track_caller]
     pub fn call_external_function<const PARAMETERS: usize>(
         &mut self,
@@ -973,12 +1679,14 @@ impl DynBlockBuilder {
         }
     }
 
-    pub fn ret(mut self, value: Option<RegisterId>) -> DynFinalizedBlockBuilder {
+    /// Returns from the function with the specified values. An empty slice
+    /// returns nothing, and more than one value performs a multi-value return.
+    pub fn ret(mut self, values: &[RegisterId]) -> DynFinalizedBlockBuilder {
         self.is_ok_to_drop = true;
         DynFinalizedBlockBuilder {
             builder: self,
             is_ok_to_drop: false,
-            end_control_flow: ControlFlowInstruction::Ret(Return(value)),
+            end_control_flow: ControlFlowInstruction::Ret(Return(values.to_vec())),
         }
     }
 
@@ -1047,7 +1755,8 @@ impl DynFinalizedBlockBuilder {
 
 impl Drop for DynFinalizedBlockBuilder {
     fn drop(&mut self) {
-        if !self.is_ok_to_drop {
+        // panicking again while unwinding would abort the process
+        if !self.is_ok_to_drop && !std::thread::panicking() {
             panic!("A `DynFinalizedBlockBuilder` (created with a finalizing method) was dropped without `end_block` being called.");
         }
     }
@@ -1,8 +1,22 @@
-use crate::{frontend::ir::ControlFlowInstruction, isa::ISAInstruction};
+//! Writes the IR as text, in a format which can be read back with
+//! [`parse_jssatir::parse`](super::parse_jssatir::parse).
+//!
+//! The output begins with a header naming the version of the format, such as
+//! `; jssat-ir v1`. Within a version the format is stable, so tests and other
+//! tools may depend on it. Any change to what is written must bump
+//! [`FORMAT_VERSION`].
+
+use crate::{
+    frontend::ir::{ControlFlowInstruction, InstructionData, Returns},
+    isa::{ISAInstruction, InternalSlot},
+};
 
 use super::ir::{FFIValueType, IR};
 use std::fmt::Write;
 
+/// The version of the textual format, written in the header of every dump.
+pub const FORMAT_VERSION: u32 = 1;
+
 /// Infallible write
 macro_rules! iw {
     ($($e:tt)+) => {
@@ -19,27 +33,73 @@ macro_rules! iwl {
 pub fn display(program: &IR) -> String {
     let mut text = String::new();
 
-    for (id, ext_fn) in program.external_functions.iter() {
-        iw!(text, "ext fn @@{}(", id);
+    iwl!(text, "; jssat-ir v{}", FORMAT_VERSION);
+    iw!(text, "entrypoint @{};\n\n", program.entrypoint);
+
+    // the dealer interns atoms in order, so dealing these names in the order
+    // they're written reproduces the same atoms. internal slots are always
+    // dealt first, so they aren't written
+    let mut atoms = String::new();
+    for (atom, name) in program.dealer.dealt().skip(InternalSlot::ALL.len()) {
+        iwl!(atoms, "atom {} = {:?};", atom, name);
+    }
+
+    if !atoms.is_empty() {
+        iwl!(text, "{}", atoms);
+    }
+
+    let mut constants = program.constants.iter().collect::<Vec<_>>();
+    constants.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (id, constant) in constants {
+        iw!(text, "const #{} = <", id);
+
+        for byte in constant.payload.iter() {
+            iw!(text, "{:02x}", byte);
+        }
+
+        iw!(text, ">;\n\n");
+    }
+
+    let mut ext_fns = program.external_functions.iter().collect::<Vec<_>>();
+    ext_fns.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (id, ext_fn) in ext_fns {
+        iw!(text, "ext fn @@{} {:?}(", id, ext_fn.name);
 
         for arg in ext_fn.parameters.iter() {
             iw!(text, "{}, ", display_norecord(arg));
         }
 
-        iw!(text, ");\n\n");
+        let return_type = match &ext_fn.return_type {
+            Returns::Value(typ) => display_norecord(typ),
+            Returns::Void => "Void".to_string(),
+        };
+
+        iw!(text, ") -> {};\n\n", return_type);
     }
 
     let mut fns = program.functions.iter().collect::<Vec<_>>();
     fns.sort_by(|(a, _), (b, _)| a.cmp(b));
 
     for (fn_id, f) in fns {
-        iw!(text, "fn @{}(", fn_id);
+        iw!(text, "fn @{}", fn_id);
+
+        if let Some(name) = &f.name {
+            iw!(text, " {:?}", name);
+        }
+
+        iw!(text, "(");
 
         for p in f.parameters.iter() {
+            if p.exact {
+                iw!(text, "exact ");
+            }
+
             iw!(text, "%{}, ", p.register);
         }
 
-        iw!(text, ") {{\n");
+        iw!(text, ") entry ${} {{\n", f.entry_block);
 
         let mut blocks = std::iter::once((&f.entry_block, f.blocks.get(&f.entry_block).unwrap()))
             .chain(f.blocks.iter().filter(|(id, _)| **id != f.entry_block))
@@ -54,7 +114,9 @@ pub fn display(program: &IR) -> String {
             iw!(text, "):\n");
 
             for inst in block.instructions.iter() {
-                iwl!(text, "    {}", inst.as_display());
+                iw!(text, "    ");
+                display_inst(&mut text, &inst.data);
+                iwl!(text);
             }
 
             iw!(text, "    ");
@@ -73,6 +135,20 @@ pub fn display(program: &IR) -> String {
     text
 }
 
+/// Writes an instruction. The `Make` family of instructions display their
+/// payload alone, which is ambiguous, so they're written with the kind of value
+/// they make.
+pub(crate) fn display_inst(text: &mut String, inst: &InstructionData) {
+    match inst {
+        InstructionData::MakeInteger(i) => iw!(text, "%{} = MakeInteger {};", i.result, i.item),
+        InstructionData::MakeBoolean(i) => iw!(text, "%{} = MakeBoolean {};", i.result, i.item),
+        InstructionData::MakeBytes(i) => iw!(text, "%{} = MakeBytes #{};", i.result, i.item),
+        InstructionData::MakeAtom(i) => iw!(text, "%{} = MakeAtom {};", i.result, i.item),
+        InstructionData::GetFnPtr(i) => iw!(text, "%{} = GetFnPtr @{};", i.result, i.item),
+        _ => inst.display(text).unwrap(),
+    }
+}
+
 fn display_norecord(t: &FFIValueType) -> String {
     match t {
         // ValueType::Bool(b) => format!("{}", b),
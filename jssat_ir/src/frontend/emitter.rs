@@ -23,6 +23,17 @@ pub struct Emitter<'builder, const P: usize> {
     #[deref]
     #[deref_mut]
     block_builder: DynBlockBuilder,
+    /// The loops currently being emitted, innermost last.
+    loops: Vec<LoopLabels>,
+}
+
+/// Where to jump to in order to `break` out of or `continue` a loop.
+#[derive(Clone, Copy)]
+struct LoopLabels {
+    next: BlockId,
+    exit: BlockId,
+    /// Whether breaking out of the loop must carry a value.
+    carries: bool,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -36,12 +47,18 @@ pub enum ControlFlow {
     Jump(BlockId, Vec<RegisterId>),
     /// Not intended for general use
     Next(Vec<RegisterId>),
+    /// Exits the innermost loop, carrying a value if the loop produces one.
+    Break(Option<RegisterId>),
+    /// Starts the next iteration of the innermost loop with the given values
+    /// for its loop variables.
+    Continue(Vec<RegisterId>),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LoopControlFlow<const L: usize> {
     Return(Option<RegisterId>),
     Next([RegisterId; L]),
+    Break(Option<RegisterId>),
 }
 
 impl<const L: usize> LoopControlFlow<L> {
@@ -51,6 +68,7 @@ impl<const L: usize> LoopControlFlow<L> {
         match self {
             Return(value) => ControlFlow::Return(value),
             Next(value) => ControlFlow::Next(value.to_vec()),
+            Break(value) => ControlFlow::Break(value),
         }
     }
 }
@@ -61,6 +79,15 @@ impl ControlFlow {
     }
 }
 
+/// The emitter only ever returns zero or one values, whereas the builder
+/// supports returning any amount of values.
+fn values_of(value: &Option<RegisterId>) -> &[RegisterId] {
+    match value {
+        Some(value) => std::slice::from_ref(value),
+        None => &[],
+    }
+}
+
 impl<'b, const P: usize> Emitter<'b, P> {
     pub fn new(
         program_builder: &'b mut ProgramBuilder,
@@ -72,6 +99,7 @@ impl<'b, const P: usize> Emitter<'b, P> {
             program_builder,
             function_builder,
             block_builder,
+            loops: Vec::new(),
         }
     }
 
@@ -92,7 +120,7 @@ impl<'b, const P: usize> Emitter<'b, P> {
 
     pub fn finish(mut self, return_value: Option<RegisterId>) -> FnSignature<P> {
         self.function_builder
-            .end_block_dyn(self.block_builder.ret(return_value));
+            .end_block_dyn(self.block_builder.ret(values_of(&return_value)));
 
         self.program_builder.end_function(self.function_builder)
     }
@@ -132,7 +160,52 @@ impl<'b, const P: usize> Emitter<'b, P> {
         cond_expr: impl FnOnce(&mut Self, Vec<RegisterId>) -> RegisterId,
         body: impl FnOnce(&mut Self, Vec<RegisterId>) -> ControlFlow,
     ) {
+        let no_value = None::<fn(&mut Self, Vec<RegisterId>) -> RegisterId>;
+        self.emit_loop(init_exprs, cond_expr, no_value, body);
+    }
+
+    /// Creates a loop which produces a value. The value is either the one
+    /// carried by a [`LoopControlFlow::Break`] out of the loop, or the result
+    /// of `else_expr` if the loop ends because its condition is false.
+    pub fn do_loop_carry<'closure, const R: usize>(
+        &mut self,
+        init_exprs: [Box<dyn FnOnce(&mut Self) -> RegisterId + 'closure>; R],
+        cond_expr: impl FnOnce(&mut Self, [RegisterId; R]) -> RegisterId,
+        else_expr: impl FnOnce(&mut Self, [RegisterId; R]) -> RegisterId,
+        body: impl FnOnce(&mut Self, [RegisterId; R]) -> LoopControlFlow<R>,
+    ) -> RegisterId {
+        self.do_loop_carry_dyn(
+            std::array::IntoIter::new(init_exprs).into_iter().collect(),
+            |me, args| cond_expr(me, args.try_into().unwrap()),
+            |me, args| else_expr(me, args.try_into().unwrap()),
+            |me, args| body(me, args.try_into().unwrap()).into_dyn(),
+        )
+    }
+
+    /// See [`do_loop_carry`] for more documentation
+    pub fn do_loop_carry_dyn<'closure>(
+        &mut self,
+        init_exprs: Vec<Box<dyn FnOnce(&mut Self) -> RegisterId + 'closure>>,
+        cond_expr: impl FnOnce(&mut Self, Vec<RegisterId>) -> RegisterId,
+        else_expr: impl FnOnce(&mut Self, Vec<RegisterId>) -> RegisterId,
+        body: impl FnOnce(&mut Self, Vec<RegisterId>) -> ControlFlow,
+    ) -> RegisterId {
+        (self.emit_loop(init_exprs, cond_expr, Some(else_expr), body))
+            .expect("loop carrying a value has a result")
+    }
+
+    fn emit_loop<'closure, E>(
+        &mut self,
+        init_exprs: Vec<Box<dyn FnOnce(&mut Self) -> RegisterId + 'closure>>,
+        cond_expr: impl FnOnce(&mut Self, Vec<RegisterId>) -> RegisterId,
+        else_expr: Option<E>,
+        body: impl FnOnce(&mut Self, Vec<RegisterId>) -> ControlFlow,
+    ) -> Option<RegisterId>
+    where
+        E: FnOnce(&mut Self, Vec<RegisterId>) -> RegisterId,
+    {
         let arg_count = init_exprs.len();
+        let carries = else_expr.is_some();
 
         // get the initial values
         let initial_values = init_exprs.into_iter().map(|f| f(self)).collect::<Vec<_>>();
@@ -141,8 +214,10 @@ impl<'b, const P: usize> Emitter<'b, P> {
         let (mut loop_iter, args) = self.function_builder.start_block_dynargs(arg_count);
         let loop_iter_id = loop_iter.id;
 
-        // set up a block to return to once the
-        let (mut final_block, _) = self.function_builder.start_block_dynargs(0);
+        // set up a block to return to once the loop is done, which receives the
+        // value of the loop if it has one
+        let result_count = carries as usize;
+        let (mut final_block, result) = self.function_builder.start_block_dynargs(result_count);
         let final_block_id = final_block.id;
 
         // jump to the loop block with the initial values
@@ -160,12 +235,21 @@ impl<'b, const P: usize> Emitter<'b, P> {
         let condition = cond_expr(self, args.clone());
 
         // jump out of the loop if the condition is false
+        let else_args = args.clone();
         self.if_then(
             |e| e.negate(condition),
-            |_| ControlFlow::Jump(final_block_id, vec![]),
+            |e| {
+                let value = else_expr.map(|else_expr| else_expr(e, else_args));
+                ControlFlow::Jump(final_block_id, value.into_iter().collect())
+            },
         );
 
-        // emit the body of the loop
+        // emit the body of the loop, which may `break` or `continue`
+        self.loops.push(LoopLabels {
+            next: loop_iter_id,
+            exit: final_block_id,
+            carries,
+        });
         let new_values = body(self, args);
 
         // to finish off the loop, we must set our current control flow to after the
@@ -175,15 +259,19 @@ impl<'b, const P: usize> Emitter<'b, P> {
         let _real_final_block = real_loop_iter;
         let mut real_loop_iter = final_block;
 
-        self.function_builder.end_block_dyn(match new_values {
-            ControlFlow::Return(value) => real_loop_iter.ret(value),
+        let end = match new_values {
+            ControlFlow::Return(value) => real_loop_iter.ret(values_of(&value)),
             ControlFlow::Next(new_values) => {
                 debug_assert_eq!(arg_count, new_values.len());
                 real_loop_iter.jmp_dynargs(loop_iter_id, new_values)
             }
             ControlFlow::Unreachable => {
                 real_loop_iter.unreachable();
-                real_loop_iter.ret(None)
+                real_loop_iter.ret(&[])
+            }
+            control_flow @ (ControlFlow::Break(_) | ControlFlow::Continue(_)) => {
+                let (block, args) = self.loop_jump(control_flow);
+                real_loop_iter.jmp_dynargs(block, args)
             }
             _ => panic!("should not be using that control flow methtod here"),
             /* // we shouldn't be pattern matching on any of these ever, but there is a sensible
@@ -197,9 +285,32 @@ impl<'b, const P: usize> Emitter<'b, P> {
              *     real_loop_iter.jmp_dynargs(real_final_block.id, vec![value])
              * }
              * ControlFlow::Jump(block, args) => real_loop_iter.jmp_dynargs(block, args), */
-        });
+        };
+        self.function_builder.end_block_dyn(end);
+
+        self.loops.pop();
 
         // now we're done
+        result.first().copied()
+    }
+
+    /// Finds where a `break` or `continue` within the innermost loop jumps to,
+    /// along with the arguments to jump with.
+    fn loop_jump(&self, control_flow: ControlFlow) -> (BlockId, Vec<RegisterId>) {
+        let labels = (self.loops.last()).expect("`break` or `continue` used outside of a loop");
+
+        match control_flow {
+            ControlFlow::Break(value) => {
+                assert_eq!(
+                    value.is_some(),
+                    labels.carries,
+                    "`break` must carry a value if and only if the loop produces one"
+                );
+                (labels.exit, value.into_iter().collect())
+            }
+            ControlFlow::Continue(values) => (labels.next, values),
+            _ => unreachable!("only `break` and `continue` jump within a loop"),
+        }
     }
 }
 
@@ -313,11 +424,15 @@ impl<'bo, 'bu, const P: usize> EmitterIf<'bo, 'bu, P> {
         let termination = match self.control_flow.clone() {
             ControlFlow::Fallthrough => current_path.jmp_dynargs(fallthrough_id, vec![]),
             ControlFlow::Carry(value) => current_path.jmp_dynargs(fallthrough_id, vec![value]),
-            ControlFlow::Return(value) => current_path.ret(value),
+            ControlFlow::Return(value) => current_path.ret(values_of(&value)),
             ControlFlow::Jump(block, args) => current_path.jmp_dynargs(block, args),
             ControlFlow::Unreachable => {
                 current_path.unreachable();
-                current_path.ret(None)
+                current_path.ret(&[])
+            }
+            control_flow @ (ControlFlow::Break(_) | ControlFlow::Continue(_)) => {
+                let (block, args) = self.emitter.loop_jump(control_flow);
+                current_path.jmp_dynargs(block, args)
             }
             ControlFlow::Next(_) => panic!("improper control flow in if"),
         };
@@ -408,11 +523,15 @@ where
         let finalized = match control_flow {
             ControlFlow::Fallthrough => false_clause.jmp_dynargs(end_clause_id, vec![]),
             ControlFlow::Carry(value) => false_clause.jmp_dynargs(end_clause_id, vec![value]),
-            ControlFlow::Return(value) => false_clause.ret(value),
+            ControlFlow::Return(value) => false_clause.ret(values_of(&value)),
             ControlFlow::Jump(block, args) => false_clause.jmp_dynargs(block, args),
             ControlFlow::Unreachable => {
                 false_clause.unreachable();
-                false_clause.ret(None)
+                false_clause.ret(&[])
+            }
+            control_flow @ (ControlFlow::Break(_) | ControlFlow::Continue(_)) => {
+                let (block, args) = emitter.loop_jump(control_flow);
+                false_clause.jmp_dynargs(block, args)
             }
             ControlFlow::Next(_) => panic!("improper control flow in if"),
         };
@@ -3,7 +3,7 @@ use std::hash::Hash;
 use std::panic::Location;
 use std::sync::Arc;
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use tinyvec::TinyVec;
 
 use crate::id::{IrCtx, Tag};
@@ -13,7 +13,12 @@ type ConstantId = crate::id::ConstantId<IrCtx>;
 use crate::id::RegisterId;
 
 use crate::isa::*;
-use crate::retag::{BlkRetagger, CnstRetagger, ExtFnRetagger, FnRetagger, RegRetagger};
+use crate::retag::{
+    BlkMapRetagger, BlkRetagger, CnstPassRetagger, CnstRetagger, ExtFnPassRetagger, ExtFnRetagger,
+    FnPassRetagger, FnRetagger, RegMapRetagger, RegRetagger,
+};
+
+use super::display_jssatir::display_inst;
 
 use super::source_map::SourceMapIdx;
 type PlainRegisterId = RegisterId<IrCtx>;
@@ -34,6 +39,157 @@ impl IR {
     }
 }
 
+/// Computes the set of functions which may be executed by the program, by
+/// walking the call graph from the entrypoint. Functions which have their
+/// pointer taken are considered reachable, as they may be called virtually.
+/// Looking up a function pointer by name at runtime could reach any function
+/// with a name, so all of them are considered reachable when that happens.
+pub fn reachable_functions(ir: &IR) -> FxHashSet<FunctionId> {
+    let mut visited = FxHashSet::default();
+    let mut queue = vec![ir.entrypoint];
+    let mut looked_up_by_name = false;
+
+    while let Some(fn_id) = queue.pop() {
+        if !visited.insert(fn_id) {
+            continue;
+        }
+
+        let function = ir.functions.get(&fn_id).unwrap();
+        for block in function.blocks.values() {
+            for inst in block.instructions.iter() {
+                match &inst.data {
+                    InstructionData::CallStatic(call) => queue.push(call.calling),
+                    InstructionData::CallSpread(call) => queue.push(call.calling),
+                    InstructionData::GetFnPtr(make) => queue.push(make.item),
+                    InstructionData::GetFnPtrDynamic(_) if !looked_up_by_name => {
+                        looked_up_by_name = true;
+                        queue.extend(
+                            (ir.functions.iter())
+                                .filter(|(_, f)| f.name.is_some())
+                                .map(|(id, _)| *id),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    visited
+}
+
+/// Removes every function that can't be reached from the entrypoint, as
+/// determined by [`reachable_functions`].
+pub fn dead_code_elimination(ir: &mut IR) {
+    let reachable = reachable_functions(ir);
+    ir.functions.retain(|id, _| reachable.contains(id));
+}
+
+/// Computes the set of external functions which may be called by the program,
+/// by looking at every function in [`reachable_functions`].
+pub fn used_external_functions(ir: &IR) -> FxHashSet<ExternalFunctionId> {
+    let mut used = FxHashSet::default();
+
+    for fn_id in reachable_functions(ir) {
+        let function = ir.functions.get(&fn_id).unwrap();
+        for block in function.blocks.values() {
+            for inst in block.instructions.iter() {
+                if let InstructionData::CallExtern(call) = &inst.data {
+                    used.insert(call.calling);
+                }
+            }
+        }
+    }
+
+    used
+}
+
+/// Determines if `a` and `b` do the same thing, differing only in the ids given
+/// to their registers and blocks. Blocks are matched up by walking both
+/// functions from their entry blocks, so blocks that can't be reached aren't
+/// compared. Comments are skipped, and constants and functions are compared by
+/// their ids.
+pub fn functions_equivalent(a: &Function, b: &Function) -> bool {
+    canonical_form(a) == canonical_form(b)
+}
+
+/// Writes out each block of `function`, with its registers and blocks
+/// renumbered in the order they're declared. Blocks are written in the order
+/// they're jumped to from the entry block.
+fn canonical_form(function: &Function) -> Vec<String> {
+    let mut blk_retagger = BlkMapRetagger::<IrCtx, IrCtx>::default();
+    let mut visited = FxHashSet::default();
+    let mut order = vec![function.entry_block];
+    visited.insert(function.entry_block);
+
+    let mut idx = 0;
+    while let Some(id) = order.get(idx).copied() {
+        blk_retagger.retag_new(id);
+        idx += 1;
+
+        let block = function.blocks.get(&id).unwrap();
+        for BlockJump(target, _) in block.end.children() {
+            if visited.insert(*target) {
+                order.push(*target);
+            }
+        }
+    }
+
+    let mut reg_retagger = RegMapRetagger::<IrCtx, IrCtx>::default();
+    let mut ext_fn_retagger = ExtFnPassRetagger::default();
+    ext_fn_retagger.ignore_checks();
+    let mut fn_retagger = FnPassRetagger::default();
+    fn_retagger.ignore_checks();
+    let mut const_retagger = CnstPassRetagger::default();
+    const_retagger.ignore_checks();
+
+    let mut text = String::new();
+    for parameter in function.parameters.iter() {
+        let exact = if parameter.exact { "exact " } else { "" };
+        text += &format!("{}%{}, ", exact, reg_retagger.retag_new(parameter.register));
+    }
+
+    let mut lines = vec![text];
+    for id in order {
+        let block = function.blocks.get(&id).unwrap();
+
+        let mut text = format!("${}(", blk_retagger.retag_old(id));
+        for register in block.parameters.iter() {
+            text += &format!("%{}, ", reg_retagger.retag_new(*register));
+        }
+        text += "):";
+        lines.push(text);
+
+        for inst in block.instructions.iter() {
+            if let InstructionData::Comment(_) = inst.data {
+                continue;
+            }
+
+            let inst = inst.data.clone().retag(
+                &mut reg_retagger,
+                &ext_fn_retagger,
+                &fn_retagger,
+                &const_retagger,
+            );
+
+            let mut text = String::new();
+            display_inst(&mut text, &inst);
+            lines.push(text);
+        }
+
+        let mut text = String::new();
+        match block.end.clone().retag(&reg_retagger, &blk_retagger) {
+            ControlFlowInstruction::Jmp(inst) => inst.display(&mut text),
+            ControlFlowInstruction::JmpIf(inst) => inst.display(&mut text),
+            ControlFlowInstruction::Ret(inst) => inst.display(&mut text),
+        }
+        .unwrap();
+        lines.push(text);
+    }
+
+    lines
+}
+
 #[derive(Debug, Clone)]
 pub struct Constant {
     pub payload: Vec<u8>,
@@ -46,7 +202,7 @@ pub struct ExternalFunction {
     pub return_type: FFIReturnType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FFIValueType {
     /// Useful to box a value into the largest possible idea of what it may be.
     /// Primarily used during prototyping, and is only really useful if our
@@ -59,6 +215,36 @@ pub enum FFIValueType {
     String,
 }
 
+/// The machine a program is compiled for, as far as laying out values goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    /// The size of a pointer or a native integer, in bytes.
+    pub word_size: usize,
+}
+
+impl Target {
+    /// The machine the compiler itself is running on.
+    pub fn host() -> Self {
+        Target {
+            word_size: std::mem::size_of::<usize>(),
+        }
+    }
+}
+
+impl FFIValueType {
+    /// The number of bytes a value of this type occupies when passed to an
+    /// external function on the given target.
+    pub fn size_of(&self, target: Target) -> usize {
+        match self {
+            // a tag for the kind of value, followed by either the value itself
+            // or a pointer to it if it doesn't fit in a word
+            FFIValueType::Any => 2 * target.word_size,
+            // both of these are passed behind a pointer
+            FFIValueType::Runtime | FFIValueType::String => target.word_size,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Returns<T> {
     Value(T),
@@ -78,9 +264,39 @@ pub struct Function {
      * pub register_flow: ValueFlowGraph, */
 }
 
+impl Function {
+    /// Iterates over every instruction of the function, alongside the block it
+    /// is in and its index within that block. Blocks are visited in the order
+    /// of their ids, so that the order is the same across runs.
+    pub fn iter_instructions(&self) -> impl Iterator<Item = (BlockId, usize, &Instruction)> {
+        let mut blocks = self.blocks.iter().collect::<Vec<_>>();
+        blocks.sort_unstable_by_key(|(id, _)| **id);
+
+        blocks.into_iter().flat_map(|(id, block)| {
+            (block.instructions.iter().enumerate()).map(move |(idx, inst)| (*id, idx, inst))
+        })
+    }
+
+    /// Mutable counterpart of [`Function::iter_instructions`], visiting
+    /// instructions in the same order.
+    pub fn iter_instructions_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (BlockId, usize, &mut Instruction)> {
+        let mut blocks = self.blocks.iter_mut().collect::<Vec<_>>();
+        blocks.sort_unstable_by_key(|(id, _)| **id);
+
+        blocks.into_iter().flat_map(|(id, block)| {
+            (block.instructions.iter_mut().enumerate()).map(move |(idx, inst)| (*id, idx, inst))
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Parameter {
     pub register: PlainRegisterId,
+    /// Whether symbolic execution should keep a specialization for every exact
+    /// value passed to this parameter, rather than generalizing it.
+    pub exact: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -120,15 +336,24 @@ impl<C: Tag, F: Tag> Instruction<C, F> {
     enum_bridge!(Comment, Comment);
     enum_bridge!(NewRecord, NewRecord<C>);
     enum_bridge!(RecordGet, RecordGet<C>);
+    enum_bridge!(RecordGetInherited, RecordGetInherited<C>);
+    enum_bridge!(RecordGetOr, RecordGetOr<C>);
     enum_bridge!(RecordSet, RecordSet<C>);
     enum_bridge!(RecordHasKey, RecordHasKey<C>);
+    enum_bridge!(RecordClone, RecordClone<C>);
+    enum_bridge!(RecordEntries, RecordEntries<C>);
     enum_bridge!(NewList, NewList<C>);
     enum_bridge!(ListGet, ListGet<C>);
     enum_bridge!(ListSet, ListSet<C>);
     enum_bridge!(ListHasKey, ListHasKey<C>);
     enum_bridge!(ListLen, ListLen<C>);
+    enum_bridge!(ListPush, ListPush<C>);
+    enum_bridge!(ListPop, ListPop<C>);
+    enum_bridge!(ListMap, ListMap<C>);
     enum_bridge!(GetFnPtr, Make<C, crate::id::FunctionId<F>>);
+    enum_bridge!(GetFnPtrDynamic, GetFnPtrDynamic<C>);
     enum_bridge!(CallStatic, Call<C, crate::id::FunctionId<F>>);
+    enum_bridge!(CallSpread, CallSpread<C, F>);
     enum_bridge!(CallExtern, Call<C, crate::id::ExternalFunctionId<F>>);
     enum_bridge!(CallVirt, Call<C, crate::id::RegisterId<C>>);
     enum_bridge!(MakeAtom, Make<C, Atom>);
@@ -140,6 +365,10 @@ impl<C: Tag, F: Tag> Instruction<C, F> {
     enum_bridge!(Generalize, Generalize<C>);
     enum_bridge!(Assert, Assert<C>);
     enum_bridge!(IsType, IsType<C>);
+    enum_bridge!(AssumeType, AssumeType<C>);
+    enum_bridge!(TypeOf, TypeOf<C>);
+    enum_bridge!(BytesConcat, BytesConcat<C>);
+    enum_bridge!(BytesSlice, BytesSlice<C>);
     enum_bridge!(GetRuntime, GetRuntime<C>);
     enum_bridge!(Unreachable, Unreachable<C>);
 }
@@ -149,15 +378,24 @@ pub enum InstructionData<C: Tag = crate::id::IrCtx, F: Tag = crate::id::IrCtx> {
     Comment(Comment),
     NewRecord(NewRecord<C>),
     RecordGet(RecordGet<C>),
+    RecordGetInherited(RecordGetInherited<C>),
+    RecordGetOr(RecordGetOr<C>),
     RecordSet(RecordSet<C>),
     RecordHasKey(RecordHasKey<C>),
+    RecordClone(RecordClone<C>),
+    RecordEntries(RecordEntries<C>),
     NewList(NewList<C>),
     ListGet(ListGet<C>),
     ListSet(ListSet<C>),
     ListHasKey(ListHasKey<C>),
     ListLen(ListLen<C>),
+    ListPush(ListPush<C>),
+    ListPop(ListPop<C>),
+    ListMap(ListMap<C>),
     GetFnPtr(Make<C, crate::id::FunctionId<F>>),
+    GetFnPtrDynamic(GetFnPtrDynamic<C>),
     CallStatic(Call<C, crate::id::FunctionId<F>>),
+    CallSpread(CallSpread<C, F>),
     CallExtern(Call<C, crate::id::ExternalFunctionId<F>>),
     CallVirt(Call<C, crate::id::RegisterId<C>>),
     MakeAtom(Make<C, Atom>),
@@ -169,6 +407,10 @@ pub enum InstructionData<C: Tag = crate::id::IrCtx, F: Tag = crate::id::IrCtx> {
     Generalize(Generalize<C>),
     Assert(Assert<C>),
     IsType(IsType<C>),
+    AssumeType(AssumeType<C>),
+    TypeOf(TypeOf<C>),
+    BytesConcat(BytesConcat<C>),
+    BytesSlice(BytesSlice<C>),
     GetRuntime(GetRuntime<C>),
     Unreachable(Unreachable<C>),
     /* RefIsEmpty(RegisterId /*=*/, RegisterId),
@@ -265,21 +507,42 @@ impl<C: Tag, F: Tag> InstructionData<C, F> {
             InstructionData::BinOp(inst) => InstructionData::BinOp(inst.retag(retagger)),
             InstructionData::NewRecord(inst) => InstructionData::NewRecord(inst.retag(retagger)),
             InstructionData::RecordGet(inst) => InstructionData::RecordGet(inst.retag(retagger)),
+            InstructionData::RecordGetInherited(inst) => {
+                InstructionData::RecordGetInherited(inst.retag(retagger))
+            }
+            InstructionData::RecordGetOr(inst) => {
+                InstructionData::RecordGetOr(inst.retag(retagger))
+            }
             InstructionData::RecordSet(inst) => InstructionData::RecordSet(inst.retag(retagger)),
             InstructionData::RecordHasKey(inst) => {
                 InstructionData::RecordHasKey(inst.retag(retagger))
             }
+            InstructionData::RecordClone(inst) => {
+                InstructionData::RecordClone(inst.retag(retagger))
+            }
+            InstructionData::RecordEntries(inst) => {
+                InstructionData::RecordEntries(inst.retag(retagger))
+            }
             InstructionData::NewList(inst) => InstructionData::NewList(inst.retag(retagger)),
             InstructionData::ListGet(inst) => InstructionData::ListGet(inst.retag(retagger)),
             InstructionData::ListSet(inst) => InstructionData::ListSet(inst.retag(retagger)),
             InstructionData::ListHasKey(inst) => InstructionData::ListHasKey(inst.retag(retagger)),
             InstructionData::ListLen(inst) => InstructionData::ListLen(inst.retag(retagger)),
+            InstructionData::ListPush(inst) => InstructionData::ListPush(inst.retag(retagger)),
+            InstructionData::ListPop(inst) => InstructionData::ListPop(inst.retag(retagger)),
+            InstructionData::ListMap(inst) => InstructionData::ListMap(inst.retag(retagger)),
             InstructionData::GetFnPtr(inst) => {
                 InstructionData::GetFnPtr(inst.retag(retagger, fn_retagger))
             }
+            InstructionData::GetFnPtrDynamic(inst) => {
+                InstructionData::GetFnPtrDynamic(inst.retag(retagger))
+            }
             InstructionData::CallStatic(inst) => {
                 InstructionData::CallStatic(inst.retag(retagger, fn_retagger))
             }
+            InstructionData::CallSpread(inst) => {
+                InstructionData::CallSpread(inst.retag(retagger, fn_retagger))
+            }
             InstructionData::CallExtern(inst) => {
                 InstructionData::CallExtern(inst.retag(retagger, ext_fn_retagger))
             }
@@ -295,6 +558,12 @@ impl<C: Tag, F: Tag> InstructionData<C, F> {
             InstructionData::Generalize(inst) => InstructionData::Generalize(inst.retag(retagger)),
             InstructionData::Assert(inst) => InstructionData::Assert(inst.retag(retagger)),
             InstructionData::IsType(inst) => InstructionData::IsType(inst.retag(retagger)),
+            InstructionData::AssumeType(inst) => InstructionData::AssumeType(inst.retag(retagger)),
+            InstructionData::TypeOf(inst) => InstructionData::TypeOf(inst.retag(retagger)),
+            InstructionData::BytesConcat(inst) => {
+                InstructionData::BytesConcat(inst.retag(retagger))
+            }
+            InstructionData::BytesSlice(inst) => InstructionData::BytesSlice(inst.retag(retagger)),
             InstructionData::GetRuntime(inst) => InstructionData::GetRuntime(inst.retag(retagger)),
             InstructionData::Unreachable(inst) => {
                 InstructionData::Unreachable(inst.retag(retagger))
@@ -328,27 +597,40 @@ impl<C: Tag, F: Tag> InstructionData<C, F> {
         match self {
             InstructionData::Comment(inst) => inst.declared_register(),
             InstructionData::GetFnPtr(inst) => inst.declared_register(),
+            InstructionData::GetFnPtrDynamic(inst) => inst.declared_register(),
             InstructionData::MakeBytes(inst) => inst.declared_register(),
             InstructionData::NewRecord(isa) => isa.declared_register(),
             InstructionData::BinOp(inst) => inst.declared_register(),
             InstructionData::CallStatic(inst) => inst.declared_register(),
+            InstructionData::CallSpread(inst) => inst.declared_register(),
             InstructionData::CallExtern(inst) => inst.declared_register(),
             InstructionData::CallVirt(inst) => inst.declared_register(),
             InstructionData::MakeAtom(inst) => inst.declared_register(),
             InstructionData::RecordGet(inst) => inst.declared_register(),
+            InstructionData::RecordGetInherited(inst) => inst.declared_register(),
+            InstructionData::RecordGetOr(inst) => inst.declared_register(),
             InstructionData::RecordSet(inst) => inst.declared_register(),
             InstructionData::RecordHasKey(inst) => inst.declared_register(),
+            InstructionData::RecordClone(inst) => inst.declared_register(),
+            InstructionData::RecordEntries(inst) => inst.declared_register(),
             InstructionData::MakeInteger(inst) => inst.declared_register(),
             InstructionData::MakeBoolean(inst) => inst.declared_register(),
             InstructionData::Negate(inst) => inst.declared_register(),
             InstructionData::Generalize(inst) => inst.declared_register(),
             InstructionData::Assert(inst) => inst.declared_register(),
             InstructionData::IsType(inst) => inst.declared_register(),
+            InstructionData::AssumeType(inst) => inst.declared_register(),
+            InstructionData::TypeOf(inst) => inst.declared_register(),
+            InstructionData::BytesConcat(inst) => inst.declared_register(),
+            InstructionData::BytesSlice(inst) => inst.declared_register(),
             InstructionData::NewList(inst) => inst.declared_register(),
             InstructionData::ListGet(inst) => inst.declared_register(),
             InstructionData::ListSet(inst) => inst.declared_register(),
             InstructionData::ListHasKey(inst) => inst.declared_register(),
             InstructionData::ListLen(inst) => inst.declared_register(),
+            InstructionData::ListPush(inst) => inst.declared_register(),
+            InstructionData::ListPop(inst) => inst.declared_register(),
+            InstructionData::ListMap(inst) => inst.declared_register(),
             InstructionData::GetRuntime(inst) => inst.declared_register(),
             InstructionData::Unreachable(inst) => inst.declared_register(),
         }
@@ -358,27 +640,40 @@ impl<C: Tag, F: Tag> InstructionData<C, F> {
         match self {
             InstructionData::Comment(inst) => inst.used_registers(),
             InstructionData::GetFnPtr(inst) => inst.used_registers(),
+            InstructionData::GetFnPtrDynamic(inst) => inst.used_registers(),
             InstructionData::MakeBytes(inst) => inst.used_registers(),
             InstructionData::NewRecord(inst) => inst.used_registers(),
             InstructionData::BinOp(inst) => inst.used_registers(),
             InstructionData::CallStatic(inst) => inst.used_registers(),
+            InstructionData::CallSpread(inst) => inst.used_registers(),
             InstructionData::CallExtern(inst) => inst.used_registers(),
             InstructionData::CallVirt(inst) => inst.used_registers(),
             InstructionData::MakeAtom(inst) => inst.used_registers(),
             InstructionData::RecordGet(inst) => inst.used_registers(),
+            InstructionData::RecordGetInherited(inst) => inst.used_registers(),
+            InstructionData::RecordGetOr(inst) => inst.used_registers(),
             InstructionData::RecordSet(inst) => inst.used_registers(),
             InstructionData::RecordHasKey(inst) => inst.used_registers(),
+            InstructionData::RecordClone(inst) => inst.used_registers(),
+            InstructionData::RecordEntries(inst) => inst.used_registers(),
             InstructionData::MakeInteger(inst) => inst.used_registers(),
             InstructionData::MakeBoolean(inst) => inst.used_registers(),
             InstructionData::Negate(inst) => inst.used_registers(),
             InstructionData::Generalize(inst) => inst.used_registers(),
             InstructionData::Assert(inst) => inst.used_registers(),
             InstructionData::IsType(inst) => inst.used_registers(),
+            InstructionData::AssumeType(inst) => inst.used_registers(),
+            InstructionData::TypeOf(inst) => inst.used_registers(),
+            InstructionData::BytesConcat(inst) => inst.used_registers(),
+            InstructionData::BytesSlice(inst) => inst.used_registers(),
             InstructionData::NewList(inst) => inst.used_registers(),
             InstructionData::ListGet(inst) => inst.used_registers(),
             InstructionData::ListSet(inst) => inst.used_registers(),
             InstructionData::ListHasKey(inst) => inst.used_registers(),
             InstructionData::ListLen(inst) => inst.used_registers(),
+            InstructionData::ListPush(inst) => inst.used_registers(),
+            InstructionData::ListPop(inst) => inst.used_registers(),
+            InstructionData::ListMap(inst) => inst.used_registers(),
             InstructionData::GetRuntime(inst) => inst.used_registers(),
             InstructionData::Unreachable(inst) => inst.used_registers(),
         }
@@ -388,27 +683,40 @@ impl<C: Tag, F: Tag> InstructionData<C, F> {
         match self {
             InstructionData::Comment(inst) => inst.used_registers_mut(),
             InstructionData::GetFnPtr(inst) => inst.used_registers_mut(),
+            InstructionData::GetFnPtrDynamic(inst) => inst.used_registers_mut(),
             InstructionData::MakeBytes(inst) => inst.used_registers_mut(),
             InstructionData::NewRecord(inst) => inst.used_registers_mut(),
             InstructionData::BinOp(inst) => inst.used_registers_mut(),
             InstructionData::CallStatic(inst) => inst.used_registers_mut(),
+            InstructionData::CallSpread(inst) => inst.used_registers_mut(),
             InstructionData::CallExtern(inst) => inst.used_registers_mut(),
             InstructionData::CallVirt(inst) => inst.used_registers_mut(),
             InstructionData::MakeAtom(inst) => inst.used_registers_mut(),
             InstructionData::RecordGet(inst) => inst.used_registers_mut(),
+            InstructionData::RecordGetInherited(inst) => inst.used_registers_mut(),
+            InstructionData::RecordGetOr(inst) => inst.used_registers_mut(),
             InstructionData::RecordSet(inst) => inst.used_registers_mut(),
             InstructionData::RecordHasKey(inst) => inst.used_registers_mut(),
+            InstructionData::RecordClone(inst) => inst.used_registers_mut(),
+            InstructionData::RecordEntries(inst) => inst.used_registers_mut(),
             InstructionData::MakeInteger(inst) => inst.used_registers_mut(),
             InstructionData::MakeBoolean(inst) => inst.used_registers_mut(),
             InstructionData::Negate(inst) => inst.used_registers_mut(),
             InstructionData::Generalize(inst) => inst.used_registers_mut(),
             InstructionData::Assert(inst) => inst.used_registers_mut(),
             InstructionData::IsType(inst) => inst.used_registers_mut(),
+            InstructionData::AssumeType(inst) => inst.used_registers_mut(),
+            InstructionData::TypeOf(inst) => inst.used_registers_mut(),
+            InstructionData::BytesConcat(inst) => inst.used_registers_mut(),
+            InstructionData::BytesSlice(inst) => inst.used_registers_mut(),
             InstructionData::NewList(inst) => inst.used_registers_mut(),
             InstructionData::ListGet(inst) => inst.used_registers_mut(),
             InstructionData::ListSet(inst) => inst.used_registers_mut(),
             InstructionData::ListHasKey(inst) => inst.used_registers_mut(),
             InstructionData::ListLen(inst) => inst.used_registers_mut(),
+            InstructionData::ListPush(inst) => inst.used_registers_mut(),
+            InstructionData::ListPop(inst) => inst.used_registers_mut(),
+            InstructionData::ListMap(inst) => inst.used_registers_mut(),
             InstructionData::GetRuntime(inst) => inst.used_registers_mut(),
             InstructionData::Unreachable(inst) => inst.used_registers_mut(),
         }
@@ -424,27 +732,40 @@ impl<C: Tag, F: Tag> InstructionData<C, F> {
                 ISAInstruction::<crate::id::NoContext>::display(inst, w)
             }
             InstructionData::GetFnPtr(inst) => inst.display(w),
+            InstructionData::GetFnPtrDynamic(inst) => inst.display(w),
             InstructionData::MakeBytes(inst) => inst.display(w),
             InstructionData::NewRecord(inst) => inst.display(w),
             InstructionData::BinOp(inst) => inst.display(w),
             InstructionData::CallStatic(inst) => inst.display(w),
+            InstructionData::CallSpread(inst) => inst.display(w),
             InstructionData::CallExtern(inst) => inst.display(w),
             InstructionData::CallVirt(inst) => inst.display(w),
             InstructionData::MakeAtom(inst) => inst.display(w),
             InstructionData::RecordGet(inst) => inst.display(w),
+            InstructionData::RecordGetInherited(inst) => inst.display(w),
+            InstructionData::RecordGetOr(inst) => inst.display(w),
             InstructionData::RecordSet(inst) => inst.display(w),
             InstructionData::RecordHasKey(inst) => inst.display(w),
+            InstructionData::RecordClone(inst) => inst.display(w),
+            InstructionData::RecordEntries(inst) => inst.display(w),
             InstructionData::MakeInteger(inst) => inst.display(w),
             InstructionData::MakeBoolean(inst) => inst.display(w),
             InstructionData::Negate(inst) => inst.display(w),
             InstructionData::Generalize(inst) => inst.display(w),
             InstructionData::Assert(inst) => inst.display(w),
             InstructionData::IsType(inst) => inst.display(w),
+            InstructionData::AssumeType(inst) => inst.display(w),
+            InstructionData::TypeOf(inst) => inst.display(w),
+            InstructionData::BytesConcat(inst) => inst.display(w),
+            InstructionData::BytesSlice(inst) => inst.display(w),
             InstructionData::NewList(inst) => inst.display(w),
             InstructionData::ListGet(inst) => inst.display(w),
             InstructionData::ListSet(inst) => inst.display(w),
             InstructionData::ListHasKey(inst) => inst.display(w),
             InstructionData::ListLen(inst) => inst.display(w),
+            InstructionData::ListPush(inst) => inst.display(w),
+            InstructionData::ListPop(inst) => inst.display(w),
+            InstructionData::ListMap(inst) => inst.display(w),
             InstructionData::GetRuntime(inst) => inst.display(w),
             InstructionData::Unreachable(inst) => inst.display(w),
         }
@@ -484,3 +805,426 @@ impl<C: Tag, P: Tag> ControlFlowInstruction<C, P> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::StrictZip;
+    use crate::frontend::{builder::ProgramBuilder, display_jssatir, parse_jssatir};
+
+    /// If a new instruction is added, it must be added here too.
+    fn every_instruction() -> Vec<InstructionData> {
+        let mut registers = 0;
+        let mut r = || {
+            registers += 1;
+            PlainRegisterId::new_with_value_const(registers)
+        };
+        let atom = AtomDealer::new().deal("Atom");
+
+        vec![
+            InstructionData::Comment(Comment {
+                message: "comment".into(),
+                location: Location::caller(),
+            }),
+            InstructionData::NewRecord(NewRecord { result: r() }),
+            InstructionData::RecordGet(RecordGet {
+                result: r(),
+                record: r(),
+                key: RecordKey::Prop(r()),
+            }),
+            InstructionData::RecordGetInherited(RecordGetInherited {
+                result: r(),
+                record: r(),
+                key: RecordKey::Atom(atom),
+            }),
+            InstructionData::RecordGetOr(RecordGetOr {
+                result: r(),
+                record: r(),
+                key: RecordKey::Atom(atom),
+                default: r(),
+            }),
+            InstructionData::RecordSet(RecordSet {
+                record: r(),
+                key: RecordKey::DynAtom(r()),
+                value: None,
+            }),
+            InstructionData::RecordHasKey(RecordHasKey {
+                result: r(),
+                record: r(),
+                key: RecordKey::Prop(r()),
+            }),
+            InstructionData::RecordClone(RecordClone {
+                result: r(),
+                record: r(),
+            }),
+            InstructionData::RecordEntries(RecordEntries {
+                result: r(),
+                record: r(),
+            }),
+            InstructionData::NewList(NewList { result: r() }),
+            InstructionData::ListGet(ListGet {
+                result: r(),
+                list: r(),
+                key: ListKey::Index(r()),
+            }),
+            InstructionData::ListSet(ListSet {
+                list: r(),
+                key: ListKey::Index(r()),
+                value: Some(r()),
+            }),
+            InstructionData::ListHasKey(ListHasKey {
+                result: r(),
+                list: r(),
+                key: ListKey::Index(r()),
+            }),
+            InstructionData::ListLen(ListLen {
+                result: r(),
+                list: r(),
+            }),
+            InstructionData::ListPush(ListPush {
+                list: r(),
+                value: r(),
+            }),
+            InstructionData::ListPop(ListPop {
+                result: r(),
+                list: r(),
+            }),
+            InstructionData::ListMap(ListMap {
+                result: Some(r()),
+                list: r(),
+                function: r(),
+                args: vec![r()],
+            }),
+            InstructionData::ListMap(ListMap {
+                result: None,
+                list: r(),
+                function: r(),
+                args: Vec::new(),
+            }),
+            InstructionData::GetFnPtr(Make {
+                result: r(),
+                item: FunctionId::new(),
+            }),
+            InstructionData::GetFnPtrDynamic(GetFnPtrDynamic {
+                result: r(),
+                name: r(),
+            }),
+            InstructionData::CallStatic(Call {
+                result: None,
+                calling: FunctionId::new(),
+                args: vec![r()],
+            }),
+            InstructionData::CallSpread(CallSpread {
+                result: Some(r()),
+                calling: FunctionId::new(),
+                args: r(),
+            }),
+            InstructionData::CallExtern(Call {
+                result: Some(r()),
+                calling: ExternalFunctionId::new(),
+                args: vec![],
+            }),
+            InstructionData::CallVirt(Call {
+                result: None,
+                calling: r(),
+                args: vec![],
+            }),
+            InstructionData::MakeAtom(Make {
+                result: r(),
+                item: atom,
+            }),
+            InstructionData::MakeBytes(Make {
+                result: r(),
+                item: ConstantId::new(),
+            }),
+            InstructionData::MakeInteger(Make {
+                result: r(),
+                item: 1,
+            }),
+            InstructionData::MakeBoolean(Make {
+                result: r(),
+                item: true,
+            }),
+            InstructionData::BinOp(BinOp {
+                result: r(),
+                op: BinaryOperator::Add,
+                lhs: r(),
+                rhs: r(),
+            }),
+            InstructionData::Negate(Negate {
+                result: r(),
+                operand: r(),
+            }),
+            InstructionData::Generalize(Generalize {
+                result: r(),
+                value: r(),
+            }),
+            InstructionData::Assert(Assert {
+                condition: r(),
+                message: "assertion",
+            }),
+            InstructionData::IsType(IsType {
+                result: r(),
+                value: r(),
+                kind: CompareType::Kind(ValueType::Number),
+            }),
+            InstructionData::AssumeType(AssumeType {
+                result: r(),
+                value: r(),
+                kind: ValueType::Number,
+            }),
+            InstructionData::TypeOf(TypeOf {
+                result: r(),
+                value: r(),
+            }),
+            InstructionData::BytesConcat(BytesConcat {
+                result: r(),
+                lhs: r(),
+                rhs: r(),
+            }),
+            InstructionData::BytesSlice(BytesSlice {
+                result: r(),
+                bytes: r(),
+                start: r(),
+                end: r(),
+            }),
+            InstructionData::GetRuntime(GetRuntime { result: r() }),
+            InstructionData::Unreachable(Unreachable { result: r() }),
+        ]
+    }
+
+    #[test]
+    pub fn every_instruction_displays() {
+        for inst in every_instruction() {
+            let mut text = String::new();
+            inst.display(&mut text).unwrap();
+
+            assert!(!text.is_empty(), "{:?} displayed nothing", inst);
+            assert!(
+                !text.to_lowercase().contains("todo"),
+                "{:?} displayed {}",
+                inst,
+                text
+            );
+        }
+    }
+
+    #[test]
+    pub fn every_instruction_round_trips_through_dump() {
+        let entry_block = BlockId::new_with_value_const(0);
+        let instructions = (every_instruction().into_iter())
+            .map(Instruction::make)
+            .collect::<Vec<_>>();
+
+        // every register an instruction reads is passed into the block, so that
+        // the function is well formed enough to be compared structurally
+        let block = FunctionBlock {
+            parameters: (instructions.iter())
+                .flat_map(|inst| inst.used_registers())
+                .collect(),
+            instructions,
+            end: ControlFlowInstruction::Ret(Return(Vec::new())),
+        };
+
+        let function = Function {
+            name: None,
+            parameters: vec![
+                Parameter {
+                    register: RegisterId::new_with_value_const(1000),
+                    exact: false,
+                },
+                Parameter {
+                    register: RegisterId::new_with_value_const(1001),
+                    exact: true,
+                },
+            ],
+            entry_block,
+            blocks: vec![(entry_block, block)].into_iter().collect(),
+        };
+
+        // deal the same atom `every_instruction` uses, and another after it
+        let mut dealer = AtomDealer::new();
+        dealer.deal("Atom");
+        dealer.deal("Other");
+
+        let constant = Constant {
+            payload: b"bytes".to_vec(),
+        };
+        let external_function = ExternalFunction {
+            name: "external".into(),
+            parameters: vec![FFIValueType::Runtime, FFIValueType::Any],
+            return_type: Returns::Value(FFIValueType::String),
+        };
+
+        let entrypoint = FunctionId::new_with_value_const(0);
+        let ir = IR {
+            dealer: Arc::new(dealer),
+            entrypoint,
+            constants: vec![(ConstantId::new(), constant)].into_iter().collect(),
+            external_functions: vec![(ExternalFunctionId::new(), external_function)]
+                .into_iter()
+                .collect(),
+            functions: vec![(entrypoint, function)].into_iter().collect(),
+        };
+
+        let parsed = parse_jssatir::parse(&display_jssatir::display(&ir));
+
+        assert_eq!(parsed.entrypoint, ir.entrypoint);
+        assert_eq!(
+            parsed.dealer.dealt().collect::<Vec<_>>(),
+            ir.dealer.dealt().collect::<Vec<_>>()
+        );
+
+        assert_eq!(parsed.constants.len(), ir.constants.len());
+        for (id, constant) in ir.constants.iter() {
+            assert_eq!(parsed.constants.get(id).unwrap().payload, constant.payload);
+        }
+
+        assert_eq!(parsed.external_functions.len(), ir.external_functions.len());
+        for (id, ext_fn) in ir.external_functions.iter() {
+            let parsed = parsed.external_functions.get(id).unwrap();
+            assert_eq!(parsed.name, ext_fn.name);
+            assert_eq!(parsed.parameters, ext_fn.parameters);
+            assert_eq!(parsed.return_type, ext_fn.return_type);
+        }
+
+        assert_eq!(parsed.functions.len(), ir.functions.len());
+        for (id, function) in ir.functions.iter() {
+            let parsed = parsed.functions.get(id).unwrap();
+            assert_eq!(parsed.name, function.name);
+            assert_eq!(parsed.entry_block, function.entry_block);
+            assert!(functions_equivalent(parsed, function));
+        }
+    }
+
+    #[test]
+    pub fn iter_instructions_visits_blocks_in_order() {
+        let r = PlainRegisterId::new;
+        let (entry_block, next_block) = (
+            BlockId::new_with_value_const(0),
+            BlockId::new_with_value_const(1),
+        );
+
+        let make_block = |instructions: Vec<InstructionData>| FunctionBlock {
+            parameters: Vec::new(),
+            instructions: instructions.into_iter().map(Instruction::make).collect(),
+            end: ControlFlowInstruction::Ret(Return(Vec::new())),
+        };
+
+        // blocks are inserted out of order, to make sure they're sorted
+        let blocks = vec![
+            (
+                next_block,
+                make_block(vec![InstructionData::NewList(NewList { result: r() })]),
+            ),
+            (
+                entry_block,
+                make_block(vec![
+                    InstructionData::NewRecord(NewRecord { result: r() }),
+                    InstructionData::GetRuntime(GetRuntime { result: r() }),
+                ]),
+            ),
+        ];
+
+        let mut function = Function {
+            name: None,
+            parameters: Vec::new(),
+            entry_block,
+            blocks: blocks.into_iter().collect(),
+        };
+
+        let visited = (function.iter_instructions())
+            .map(|(block, idx, inst)| (block, idx, inst.assigned_to().unwrap()))
+            .collect::<Vec<_>>();
+
+        let expected = [(entry_block, 0), (entry_block, 1), (next_block, 0)];
+        for ((block, idx, _), expected) in visited.iter().strict_zip(expected) {
+            assert_eq!((*block, *idx), expected);
+        }
+
+        let visited_mut = (function.iter_instructions_mut())
+            .map(|(block, idx, inst)| (block, idx, inst.assigned_to().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(visited_mut, visited);
+    }
+
+    #[test]
+    pub fn dead_code_elimination_keeps_functions_with_pointers_taken() {
+        let mut program = ProgramBuilder::new();
+
+        let function = |program: &mut ProgramBuilder| {
+            let (mut f, []) = program.start_function();
+            let block = f.start_block_main();
+            f.end_block(block.ret(&[]));
+            program.end_function(f)
+        };
+
+        let called = function(&mut program);
+        let pointed_to = function(&mut program);
+        let dead = function(&mut program);
+
+        let mut main = program.start_function_main();
+        let mut block = main.start_block_main();
+        block.call(called, []);
+        block.make_fnptr(pointed_to.id);
+        main.end_block(block.ret(&[]));
+        let main = program.end_function(main);
+
+        let mut ir = program.finish();
+        dead_code_elimination(&mut ir);
+
+        assert_eq!(ir.functions.len(), 3);
+        for live in [main.id, called.id, pointed_to.id] {
+            assert!(ir.functions.contains_key(&live));
+        }
+        assert!(!ir.functions.contains_key(&dead.id));
+    }
+
+    #[test]
+    pub fn functions_equivalent_up_to_renaming() {
+        // each function is given ids starting from `first_id`, so that copies of
+        // it don't share any registers or blocks
+        let make_function = |first_id: usize, value: i64| {
+            let r = |offset| PlainRegisterId::new_with_value_const(first_id + offset);
+            let b = |offset| BlockId::new_with_value_const(first_id + offset);
+            let (entry_block, next_block) = (b(0), b(1));
+
+            let entry = FunctionBlock {
+                parameters: Vec::new(),
+                instructions: vec![Instruction::make(InstructionData::MakeInteger(Make {
+                    result: r(1),
+                    item: value,
+                }))],
+                end: ControlFlowInstruction::Jmp(Jump(BlockJump(next_block, vec![r(1)]))),
+            };
+
+            let next = FunctionBlock {
+                parameters: vec![r(2)],
+                instructions: vec![Instruction::make(InstructionData::BinOp(BinOp {
+                    result: r(3),
+                    op: BinaryOperator::Add,
+                    lhs: r(0),
+                    rhs: r(2),
+                }))],
+                end: ControlFlowInstruction::Ret(Return(vec![r(3)])),
+            };
+
+            Function {
+                name: None,
+                parameters: vec![Parameter {
+                    register: r(0),
+                    exact: false,
+                }],
+                entry_block,
+                blocks: vec![(entry_block, entry), (next_block, next)]
+                    .into_iter()
+                    .collect(),
+            }
+        };
+
+        let function = make_function(0, 1);
+        assert!(functions_equivalent(&function, &function));
+        assert!(functions_equivalent(&function, &make_function(10, 1)));
+        assert!(!functions_equivalent(&function, &make_function(10, 2)));
+    }
+}
@@ -0,0 +1,774 @@
+//! Reads the textual format written by
+//! [`display_jssatir::display`](super::display_jssatir::display) back into an
+//! [`IR`]. Source map indices aren't part of the format, so they're lost.
+
+use std::num::NonZeroU16;
+use std::panic::Location;
+use std::str::Lines;
+use std::sync::Arc;
+
+use rustc_hash::FxHashMap;
+
+use crate::id::{IdCompat, IrCtx};
+use crate::isa::*;
+
+use super::display_jssatir::FORMAT_VERSION;
+use super::ir::{
+    Constant, ControlFlowInstruction, ExternalFunction, FFIValueType, Function, FunctionBlock,
+    Instruction, Parameter, Returns, IR,
+};
+
+type BlockId = crate::id::BlockId<IrCtx>;
+type FunctionId = crate::id::FunctionId<IrCtx>;
+type ConstantId = crate::id::ConstantId<IrCtx>;
+type RegisterId = crate::id::RegisterId<IrCtx>;
+type ExternalFunctionId = crate::id::ExternalFunctionId<IrCtx>;
+
+/// Parses a dump of the IR. Panics if the text isn't of the version of the
+/// format this reader understands, or is malformed.
+pub fn parse(text: &str) -> IR {
+    let mut lines = text.lines();
+
+    let header = lines.next().unwrap_or_default();
+    let expected = format!("; jssat-ir v{}", FORMAT_VERSION);
+    if header != expected {
+        panic!("expected the header `{}`, found `{}`", expected, header);
+    }
+
+    let mut dealer = AtomDealer::new();
+    let mut entrypoint = None;
+    let mut constants = FxHashMap::default();
+    let mut external_functions = FxHashMap::default();
+    let mut functions = FxHashMap::default();
+
+    while let Some(line) = lines.next() {
+        let mut cursor = Cursor(line);
+
+        if cursor.is_empty() {
+            continue;
+        } else if cursor.eat("entrypoint") {
+            entrypoint = Some(cursor.function_id());
+        } else if cursor.eat("atom") {
+            let atom = cursor.atom();
+            cursor.expect("=");
+
+            // atoms are given names with a `'static` lifetime
+            let name = Box::leak(cursor.string().into_boxed_str());
+            let dealt = dealer.deal(name);
+            assert_eq!(
+                dealt, atom,
+                "atoms must be written in the order they were dealt"
+            );
+        } else if cursor.eat("const") {
+            cursor.expect("#");
+            let id = ConstantId::new_with_value(cursor.number());
+            cursor.expect("=");
+            constants.insert(
+                id,
+                Constant {
+                    payload: cursor.bytes(),
+                },
+            );
+        } else if cursor.eat("ext fn") {
+            cursor.expect("@@");
+            let id = ExternalFunctionId::new_with_value(cursor.number());
+            external_functions.insert(id, parse_external_function(&mut cursor));
+        } else if cursor.eat("fn") {
+            let (id, function) = parse_function(&mut cursor, &mut lines);
+            functions.insert(id, function);
+            continue;
+        } else {
+            panic!("unexpected line `{}`", line);
+        }
+
+        cursor.expect(";");
+        cursor.expect_end();
+    }
+
+    IR {
+        dealer: Arc::new(dealer),
+        entrypoint: entrypoint.expect("expected an `entrypoint` line"),
+        constants,
+        external_functions,
+        functions,
+    }
+}
+
+fn parse_external_function(cursor: &mut Cursor) -> ExternalFunction {
+    let name = cursor.string();
+
+    cursor.expect("(");
+    let mut parameters = Vec::new();
+    while !cursor.eat(")") {
+        parameters.push(parse_ffi_value_type(cursor.word()));
+        cursor.eat(",");
+    }
+
+    cursor.expect("->");
+    let return_type = match cursor.word() {
+        "Void" => Returns::Void,
+        typ => Returns::Value(parse_ffi_value_type(typ)),
+    };
+
+    ExternalFunction {
+        name,
+        parameters,
+        return_type,
+    }
+}
+
+fn parse_ffi_value_type(word: &str) -> FFIValueType {
+    match word {
+        "Any" => FFIValueType::Any,
+        "Runtime" => FFIValueType::Runtime,
+        "String" => FFIValueType::String,
+        _ => panic!("unknown ffi value type `{}`", word),
+    }
+}
+
+fn parse_function(cursor: &mut Cursor, lines: &mut Lines) -> (FunctionId, Function) {
+    let id = cursor.function_id();
+    let name = cursor.peek("\"").then(|| cursor.string());
+
+    cursor.expect("(");
+    let mut parameters = Vec::new();
+    while !cursor.eat(")") {
+        let exact = cursor.eat("exact");
+        let register = cursor.register();
+        parameters.push(Parameter { register, exact });
+        cursor.eat(",");
+    }
+
+    cursor.expect("entry");
+    let entry_block = cursor.block_id();
+    cursor.expect("{");
+    cursor.expect_end();
+
+    let mut blocks = FxHashMap::default();
+    loop {
+        let line = lines.next().expect("expected the end of the function");
+        let mut cursor = Cursor(line);
+
+        if cursor.eat("}") {
+            cursor.expect_end();
+            break;
+        }
+
+        cursor.expect("@");
+        cursor.number();
+        cursor.expect(".");
+        let block_id = cursor.block_id();
+        cursor.expect("(");
+        let parameters = cursor.registers(")");
+        cursor.expect(":");
+        cursor.expect_end();
+
+        let mut instructions = Vec::new();
+        let end = loop {
+            let line = lines.next().expect("expected the end of the block");
+            let mut cursor = Cursor(line);
+
+            if let Some(end) = parse_end(&mut cursor, lines) {
+                break end;
+            }
+
+            instructions.push(parse_instruction(&mut cursor));
+        };
+
+        blocks.insert(
+            block_id,
+            FunctionBlock {
+                parameters,
+                instructions,
+                end,
+            },
+        );
+    }
+
+    let function = Function {
+        name,
+        parameters,
+        entry_block,
+        blocks,
+    };
+
+    (id, function)
+}
+
+fn parse_end(cursor: &mut Cursor, lines: &mut Lines) -> Option<ControlFlowInstruction> {
+    let end = if cursor.eat("Jump") {
+        ControlFlowInstruction::Jmp(Jump(cursor.block_jump()))
+    } else if cursor.eat("If") {
+        let condition = cursor.register();
+        cursor.expect(":");
+        cursor.expect_end();
+
+        let mut cursor = Cursor(lines.next().expect("expected the path taken if so"));
+        let if_so = cursor.block_jump();
+        cursor.expect(";");
+        cursor.expect_end();
+
+        let mut cursor = Cursor(lines.next().expect("expected the path taken otherwise"));
+        cursor.expect("else");
+        let other = cursor.block_jump();
+        cursor.expect(";");
+        cursor.expect_end();
+
+        return Some(ControlFlowInstruction::JmpIf(JumpIf {
+            condition,
+            if_so,
+            other,
+        }));
+    } else if cursor.eat("Return") {
+        ControlFlowInstruction::Ret(Return(cursor.registers(";")))
+    } else {
+        return None;
+    };
+
+    cursor.eat(";");
+    cursor.expect_end();
+    Some(end)
+}
+
+fn parse_instruction(cursor: &mut Cursor) -> Instruction {
+    if cursor.eat(";") {
+        let message = cursor.0.strip_prefix(' ').unwrap_or(cursor.0);
+        return Instruction::Comment(Comment {
+            message: message.to_string(),
+            location: Location::caller(),
+        });
+    }
+
+    let assigned = cursor.peek("%").then(|| {
+        let register = cursor.register();
+        cursor.expect("=");
+        register
+    });
+
+    let op = cursor.word();
+    let result = || assigned.unwrap_or_else(|| panic!("expected `{}` to have a result", op));
+
+    let inst = match op {
+        "NewRecord" => Instruction::NewRecord(NewRecord { result: result() }),
+        "NewList" => Instruction::NewList(NewList { result: result() }),
+        "GetRuntime" => Instruction::GetRuntime(GetRuntime { result: result() }),
+        "Unreachable" => Instruction::Unreachable(Unreachable { result: result() }),
+        "RecordClone" => Instruction::RecordClone(RecordClone {
+            result: result(),
+            record: cursor.register(),
+        }),
+        "RecordEntries" => Instruction::RecordEntries(RecordEntries {
+            result: result(),
+            record: cursor.register(),
+        }),
+        "Generalize" => Instruction::Generalize(Generalize {
+            result: result(),
+            value: cursor.register(),
+        }),
+        "GetFnPtrDynamic" => Instruction::GetFnPtrDynamic(GetFnPtrDynamic {
+            result: result(),
+            name: cursor.register(),
+        }),
+        "TypeOf" => Instruction::TypeOf(TypeOf {
+            result: result(),
+            value: cursor.register(),
+        }),
+        "ListLen" => Instruction::ListLen(ListLen {
+            result: result(),
+            list: cursor.register(),
+        }),
+        "ListPop" => Instruction::ListPop(ListPop {
+            result: result(),
+            list: cursor.register(),
+        }),
+        "Negate" => Instruction::Negate(Negate {
+            result: result(),
+            operand: cursor.register(),
+        }),
+        "RecordGet" => {
+            let (record, key) = cursor.record_access();
+            Instruction::RecordGet(RecordGet {
+                result: result(),
+                record,
+                key,
+            })
+        }
+        "RecordGetInherited" => {
+            let (record, key) = cursor.record_access();
+            Instruction::RecordGetInherited(RecordGetInherited {
+                result: result(),
+                record,
+                key,
+            })
+        }
+        "RecordGetOr" => {
+            let (record, key) = cursor.record_access();
+            cursor.expect(",");
+            Instruction::RecordGetOr(RecordGetOr {
+                result: result(),
+                record,
+                key,
+                default: cursor.register(),
+            })
+        }
+        "RecordHasKey" => {
+            let (record, key) = cursor.record_access();
+            Instruction::RecordHasKey(RecordHasKey {
+                result: result(),
+                record,
+                key,
+            })
+        }
+        "RecordSet" => {
+            let (record, key) = cursor.record_access();
+            cursor.expect("=");
+            Instruction::RecordSet(RecordSet {
+                record,
+                key,
+                value: Some(cursor.register()),
+            })
+        }
+        "RecordDelProp" | "RecordDelSlot" => {
+            let record = cursor.register();
+            cursor.expect(",");
+            Instruction::RecordSet(RecordSet {
+                record,
+                key: cursor.record_key(),
+                value: None,
+            })
+        }
+        "ListGet" => {
+            let (list, key) = cursor.list_access();
+            Instruction::ListGet(ListGet {
+                result: result(),
+                list,
+                key,
+            })
+        }
+        "ListHasKey" => {
+            let (list, key) = cursor.list_access();
+            Instruction::ListHasKey(ListHasKey {
+                result: result(),
+                list,
+                key,
+            })
+        }
+        "ListSet" => {
+            let (list, key) = cursor.list_access();
+            cursor.expect("=");
+            let value = (!cursor.eat("<remove>")).then(|| cursor.register());
+            Instruction::ListSet(ListSet { list, key, value })
+        }
+        "ListPush" => {
+            let list = cursor.register();
+            cursor.expect("<-");
+            Instruction::ListPush(ListPush {
+                list,
+                value: cursor.register(),
+            })
+        }
+        "ListMap" | "ListForEach" => {
+            let list = cursor.register();
+            cursor.expect(",");
+            let function = cursor.register();
+            let args = match cursor.eat("(") {
+                true => cursor.registers(")"),
+                false => Vec::new(),
+            };
+            Instruction::ListMap(ListMap {
+                result: (op == "ListMap").then(result),
+                list,
+                function,
+                args,
+            })
+        }
+        "IsType" => {
+            let value = cursor.register();
+            cursor.expect("=");
+            let kind = match cursor.peek("%") {
+                true => CompareType::Register(cursor.register()),
+                false => CompareType::Kind(parse_value_type(cursor.word())),
+            };
+            Instruction::IsType(IsType {
+                result: result(),
+                value,
+                kind,
+            })
+        }
+        "AssumeType" => {
+            let value = cursor.register();
+            cursor.expect("=");
+            Instruction::AssumeType(AssumeType {
+                result: result(),
+                value,
+                kind: parse_value_type(cursor.word()),
+            })
+        }
+        "BinOp" => {
+            let op = parse_binary_operator(cursor.word());
+            let lhs = cursor.register();
+            cursor.expect(",");
+            Instruction::BinOp(BinOp {
+                result: result(),
+                op,
+                lhs,
+                rhs: cursor.register(),
+            })
+        }
+        "BytesConcat" => {
+            let lhs = cursor.register();
+            cursor.expect(",");
+            Instruction::BytesConcat(BytesConcat {
+                result: result(),
+                lhs,
+                rhs: cursor.register(),
+            })
+        }
+        "BytesSlice" => {
+            let bytes = cursor.register();
+            cursor.expect(",");
+            let start = cursor.register();
+            cursor.expect("..");
+            Instruction::BytesSlice(BytesSlice {
+                result: result(),
+                bytes,
+                start,
+                end: cursor.register(),
+            })
+        }
+        "Assert" => {
+            let condition = cursor.register();
+            cursor.expect(";");
+            cursor.expect("--");
+
+            // assertion messages have a `'static` lifetime
+            let message = cursor.0.strip_prefix(' ').unwrap_or(cursor.0);
+            let message = Box::leak(message.to_string().into_boxed_str());
+            cursor.0 = "";
+
+            return Instruction::Assert(Assert { condition, message });
+        }
+        "CallStatic" => {
+            let calling = cursor.function_id();
+            cursor.expect("(");
+            Instruction::CallStatic(Call {
+                result: assigned,
+                calling,
+                args: cursor.registers(")"),
+            })
+        }
+        "CallExtern" => {
+            cursor.expect("@@");
+            let calling = ExternalFunctionId::new_with_value(cursor.number());
+            cursor.expect("(");
+            Instruction::CallExtern(Call {
+                result: assigned,
+                calling,
+                args: cursor.registers(")"),
+            })
+        }
+        "CallVirt" => {
+            let calling = cursor.register();
+            cursor.expect("(");
+            Instruction::CallVirt(Call {
+                result: assigned,
+                calling,
+                args: cursor.registers(")"),
+            })
+        }
+        "CallSpread" => {
+            let calling = cursor.function_id();
+            cursor.expect("(");
+            cursor.expect("...");
+            let args = cursor.register();
+            cursor.expect(")");
+            Instruction::CallSpread(CallSpread {
+                result: assigned,
+                calling,
+                args,
+            })
+        }
+        "MakeInteger" => Instruction::MakeInteger(Make {
+            result: result(),
+            item: cursor.integer(),
+        }),
+        "MakeBoolean" => Instruction::MakeBoolean(Make {
+            result: result(),
+            item: match cursor.word() {
+                "true" => true,
+                "false" => false,
+                word => panic!("expected a boolean, found `{}`", word),
+            },
+        }),
+        "MakeBytes" => {
+            cursor.expect("#");
+            Instruction::MakeBytes(Make {
+                result: result(),
+                item: ConstantId::new_with_value(cursor.number()),
+            })
+        }
+        "MakeAtom" => Instruction::MakeAtom(Make {
+            result: result(),
+            item: cursor.atom(),
+        }),
+        "GetFnPtr" => Instruction::GetFnPtr(Make {
+            result: result(),
+            item: cursor.function_id(),
+        }),
+        _ => panic!("unknown instruction `{}`", op),
+    };
+
+    cursor.eat(";");
+    cursor.expect_end();
+    inst
+}
+
+fn parse_binary_operator(word: &str) -> BinaryOperator {
+    match word {
+        "Add" => BinaryOperator::Add,
+        "Multiply" => BinaryOperator::Multiply,
+        "And" => BinaryOperator::And,
+        "Or" => BinaryOperator::Or,
+        "Equals" => BinaryOperator::Equals,
+        "LessThan" => BinaryOperator::LessThan,
+        _ => panic!("unknown binary operator `{}`", word),
+    }
+}
+
+fn parse_value_type(word: &str) -> ValueType {
+    match word {
+        "Atom" => ValueType::Atom,
+        "Bytes" => ValueType::Bytes,
+        "Number" => ValueType::Number,
+        "Boolean" => ValueType::Boolean,
+        "FnPtr" => ValueType::FnPtr,
+        "Record" => ValueType::Record,
+        "BigNumber" => ValueType::BigNumber,
+        "List" => ValueType::List,
+        "Runtime" => ValueType::Runtime,
+        _ => panic!("unknown value type `{}`", word),
+    }
+}
+
+/// The unparsed remainder of a line. Every method skips whitespace before the
+/// token it reads.
+struct Cursor<'t>(&'t str);
+
+impl<'t> Cursor<'t> {
+    fn skip_whitespace(&mut self) {
+        self.0 = self.0.trim_start();
+    }
+
+    fn is_empty(&mut self) -> bool {
+        self.skip_whitespace();
+        self.0.is_empty()
+    }
+
+    fn peek(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        self.0.starts_with(token)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+
+        match self.0.strip_prefix(token) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[track_caller]
+    fn expect(&mut self, token: &str) {
+        if !self.eat(token) {
+            panic!("expected `{}`, found `{}`", token, self.0);
+        }
+    }
+
+    #[track_caller]
+    fn expect_end(&mut self) {
+        if !self.is_empty() {
+            panic!("expected the end of the line, found `{}`", self.0);
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'t str {
+        self.skip_whitespace();
+
+        let end = self.0.find(|c| !predicate(c)).unwrap_or(self.0.len());
+        let (taken, rest) = self.0.split_at(end);
+        self.0 = rest;
+        taken
+    }
+
+    #[track_caller]
+    fn word(&mut self) -> &'t str {
+        let word = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if word.is_empty() {
+            panic!("expected a word, found `{}`", self.0);
+        }
+
+        word
+    }
+
+    #[track_caller]
+    fn number(&mut self) -> usize {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits
+            .parse()
+            .unwrap_or_else(|_| panic!("expected a number, found `{}`", self.0))
+    }
+
+    #[track_caller]
+    fn integer(&mut self) -> i64 {
+        let negative = self.eat("-");
+        let digits = self.take_while(|c| c.is_ascii_digit());
+
+        let integer = match negative {
+            true => format!("-{}", digits).parse(),
+            false => digits.parse(),
+        };
+
+        integer.unwrap_or_else(|_| panic!("expected an integer, found `{}`", self.0))
+    }
+
+    #[track_caller]
+    fn atom(&mut self) -> Atom {
+        let value = NonZeroU16::new(self.number() as u16).expect("atoms are never zero");
+        Atom(value)
+    }
+
+    #[track_caller]
+    fn register(&mut self) -> RegisterId {
+        self.expect("%");
+        RegisterId::new_with_value(self.number())
+    }
+
+    /// Reads a list of registers, separated by commas and ended by `close`.
+    #[track_caller]
+    fn registers(&mut self, close: &str) -> Vec<RegisterId> {
+        let mut registers = Vec::new();
+
+        while !self.eat(close) {
+            registers.push(self.register());
+            self.eat(",");
+        }
+
+        registers
+    }
+
+    #[track_caller]
+    fn function_id(&mut self) -> FunctionId {
+        self.expect("@");
+        FunctionId::new_with_value(self.number())
+    }
+
+    #[track_caller]
+    fn block_id(&mut self) -> BlockId {
+        self.expect("$");
+        BlockId::new_with_value(self.number())
+    }
+
+    #[track_caller]
+    fn block_jump(&mut self) -> BlockJump<BlockId, IrCtx> {
+        let block = self.block_id();
+        self.expect("(");
+        BlockJump(block, self.registers(")"))
+    }
+
+    #[track_caller]
+    fn record_key(&mut self) -> RecordKey<IrCtx> {
+        if !self.eat("[[") {
+            return RecordKey::Prop(self.register());
+        }
+
+        let key = match self.peek("%") {
+            true => RecordKey::DynAtom(self.register()),
+            false => RecordKey::Atom(self.atom()),
+        };
+
+        self.expect("]]");
+        key
+    }
+
+    #[track_caller]
+    fn record_access(&mut self) -> (RegisterId, RecordKey<IrCtx>) {
+        let record = self.register();
+        self.expect(".");
+        (record, self.record_key())
+    }
+
+    #[track_caller]
+    fn list_access(&mut self) -> (RegisterId, ListKey<IrCtx>) {
+        let list = self.register();
+        self.expect(".");
+        self.expect("[");
+        let key = ListKey::Index(RegisterId::new_with_value(self.number()));
+        self.expect("]");
+        (list, key)
+    }
+
+    /// Reads a string quoted and escaped as by `{:?}`.
+    #[track_caller]
+    fn string(&mut self) -> String {
+        self.expect("\"");
+
+        let mut string = String::new();
+        let mut chars = self.0.char_indices();
+
+        while let Some((idx, c)) = chars.next() {
+            let c = match c {
+                '"' => {
+                    self.0 = &self.0[idx + 1..];
+                    return string;
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some('u') => {
+                        let digits = (chars.by_ref())
+                            .map(|(_, c)| c)
+                            .skip_while(|c| *c == '{')
+                            .take_while(|c| *c != '}')
+                            .collect::<String>();
+
+                        u32::from_str_radix(&digits, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .unwrap_or_else(|| panic!("invalid unicode escape `{}`", digits))
+                    }
+                    Some(c) => c,
+                    None => break,
+                },
+                c => c,
+            };
+
+            string.push(c);
+        }
+
+        panic!("unterminated string");
+    }
+
+    /// Reads bytes written in hexadecimal between `<` and `>`.
+    #[track_caller]
+    fn bytes(&mut self) -> Vec<u8> {
+        self.expect("<");
+        let digits = self.take_while(|c| c.is_ascii_hexdigit());
+        self.expect(">");
+
+        if digits.len() % 2 != 0 {
+            panic!("expected an even amount of hex digits, found `{}`", digits);
+        }
+
+        (0..digits.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&digits[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+}
@@ -86,6 +86,21 @@ macro_rules! gen_id {
             }
         }
 
+        unsafe impl<C: Tag> ::lasso::Key for $name<C> {
+            fn into_usize(self) -> usize {
+                crate::id::IdCompat::value(&self)
+            }
+
+            fn try_from_usize(int: usize) -> Option<Self> {
+                // prevent `usize::MAX + 1` from being an issue
+                if int == usize::MAX {
+                    return None;
+                }
+
+                Some(Self::new_with_value_const(int))
+            }
+        }
+
         impl<C: Tag> ::std::fmt::Display for $name<C> {
             fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
                 write!(f, "{}", self.value())
@@ -47,6 +47,30 @@ unsafe impl Key for Atom {
     }
 }
 
+/// Internal slots which have a special meaning to JSSAT. Every [`AtomDealer`]
+/// deals these before any other atom, so the [`Atom`] of an internal slot is
+/// the same as the one produced by [`AtomDealer::deal`] with its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InternalSlot {
+    /// The `[[Prototype]]` of a record. Inherited lookups walk the chain of
+    /// prototypes until a prototype that isn't a record is reached.
+    Prototype,
+}
+
+impl InternalSlot {
+    pub const ALL: [InternalSlot; 1] = [InternalSlot::Prototype];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InternalSlot::Prototype => "Prototype",
+        }
+    }
+
+    pub fn atom(&self) -> Atom {
+        Atom::try_from_usize(*self as usize).expect("internal slots are always dealt")
+    }
+}
+
 /// Assists in ensuring unique atoms are given on request. If all requests for
 /// new atoms go through an [`AtomDealer`], the API will prevent duplicate atoms
 /// from being issued.
@@ -71,11 +95,23 @@ impl AtomDealer {
         self.rodeo.try_resolve(&atom)
     }
 
+    /// Every [`Atom`] issued by [`deal`](Self::deal), along with its name, in
+    /// the order they were dealt. Internal slots are dealt first.
+    pub fn dealt(&self) -> impl Iterator<Item = (Atom, &str)> {
+        self.rodeo.iter()
+    }
+
     /// Issues a unique [`Atom`] that corresponds to only the string given.
     pub fn deal(&mut self, name: &'static str) -> Atom {
         self.rodeo.get_or_intern_static(name)
     }
 
+    /// Like [`deal`](Self::deal), but for names that aren't known until
+    /// runtime.
+    pub fn deal_dyn(&mut self, name: &str) -> Atom {
+        self.rodeo.get_or_intern(name)
+    }
+
     /// Generates a unique [`Atom`] that has never been generated before.
     pub fn gen(&mut self) -> Atom {
         let return_result = self.gen_unused;
@@ -93,8 +129,15 @@ impl AtomDealer {
 
 impl Default for AtomDealer {
     fn default() -> Self {
+        let mut rodeo = Rodeo::new();
+
+        for slot in InternalSlot::ALL {
+            let atom = rodeo.get_or_intern_static(slot.name());
+            debug_assert_eq!(atom, slot.atom());
+        }
+
         Self {
-            rodeo: Rodeo::new(),
+            rodeo,
             gen_unused: Atom(NonZeroU16::new(MAX_INTERNED_ATOM).expect("`MAX_INTERNED_ATOM` != 0")),
         }
     }
@@ -8,6 +8,7 @@ use crate::{id::*, retag::RegRetagger};
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
 pub enum BinaryOperator {
     Add,
+    Multiply,
     And,
     Or,
     Equals,
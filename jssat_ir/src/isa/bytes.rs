@@ -0,0 +1,91 @@
+use std::fmt::Write;
+use tinyvec::{tiny_vec, TinyVec};
+
+use super::ISAInstruction;
+use crate::{id::*, retag::RegRetagger};
+
+/// [`BytesConcat`] produces a new byte buffer with the contents of `rhs`
+/// appended to the contents of `lhs`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BytesConcat<C: Tag> {
+    pub result: RegisterId<C>,
+    pub lhs: RegisterId<C>,
+    pub rhs: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for BytesConcat<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.lhs, self.rhs]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.lhs, &mut self.rhs]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(
+            w,
+            "%{} = BytesConcat %{}, %{};",
+            self.result, self.lhs, self.rhs
+        )
+    }
+}
+
+impl<C: Tag> BytesConcat<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> BytesConcat<C2> {
+        BytesConcat {
+            result: retagger.retag_new(self.result),
+            lhs: retagger.retag_old(self.lhs),
+            rhs: retagger.retag_old(self.rhs),
+        }
+    }
+}
+
+/// [`BytesSlice`] produces a new byte buffer with the contents of `bytes` from
+/// the index `start` (inclusive) up until the index `end` (exclusive).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BytesSlice<C: Tag> {
+    pub result: RegisterId<C>,
+    pub bytes: RegisterId<C>,
+    pub start: RegisterId<C>,
+    pub end: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for BytesSlice<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.bytes, self.start, self.end]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.bytes, &mut self.start, &mut self.end]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(
+            w,
+            "%{} = BytesSlice %{}, %{}..%{};",
+            self.result, self.bytes, self.start, self.end
+        )
+    }
+}
+
+impl<C: Tag> BytesSlice<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> BytesSlice<C2> {
+        BytesSlice {
+            result: retagger.retag_new(self.result),
+            bytes: retagger.retag_old(self.bytes),
+            start: retagger.retag_old(self.start),
+            end: retagger.retag_old(self.end),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use std::fmt::Write;
-use tinyvec::TinyVec;
+use tinyvec::{tiny_vec, TinyVec};
 
 use super::{ISAInstruction, Registers};
 use crate::{
@@ -146,3 +146,53 @@ impl<C: Tag> Call<C, RegisterId<C>> {
         }
     }
 }
+
+/// [`CallSpread`] calls a function, passing every element of the list `args`
+/// as an argument to the function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallSpread<C: Tag, F: Tag> {
+    pub result: Option<RegisterId<C>>,
+    pub calling: FunctionId<F>,
+    pub args: RegisterId<C>,
+}
+
+impl<C: Tag, F: Tag> ISAInstruction<C> for CallSpread<C, F> {
+    fn is_pure() -> bool {
+        // inside of the function may be calls to external functions
+        false
+    }
+
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        self.result
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.args]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.args]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        if let Some(r) = self.result {
+            write!(w, "%{} = ", r)?;
+        }
+        write!(w, "CallSpread @{}(...%{})", self.calling, self.args)
+    }
+}
+
+impl<C: Tag, F: Tag> CallSpread<C, F> {
+    #[track_caller]
+    pub fn retag<C2: Tag, F2: Tag>(
+        self,
+        retagger: &mut impl RegRetagger<C, C2>,
+        fn_retagger: &impl FnRetagger<F, F2>,
+    ) -> CallSpread<C2, F2> {
+        CallSpread {
+            result: self.result.map(|r| retagger.retag_new(r)),
+            calling: fn_retagger.retag_old(self.calling),
+            args: retagger.retag_old(self.args),
+        }
+    }
+}
@@ -4,9 +4,9 @@ use tinyvec::TinyVec;
 use super::ISAInstruction;
 use crate::id::*;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Comment {
-    pub message: &'static str,
+    pub message: String,
     pub location: &'static Location<'static>,
 }
 
@@ -24,7 +24,7 @@ impl<T: Tag> ISAInstruction<T> for Comment {
     }
 
     fn display(&self, w: &mut impl Write) -> std::fmt::Result {
-        write!(w, "-- {}, {}", self.message, self.location)
+        write!(w, "; {}", self.message)
     }
 }
 
@@ -54,8 +54,10 @@ impl<F: Tag, R: Tag> Display for BlockJump<FunctionId<F>, R> {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
-pub struct Return<C: Tag>(pub Option<RegisterId<C>>);
+/// Returns from the current function. A function may return no values, a
+/// single value, or multiple values at once.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Return<C: Tag>(pub Vec<RegisterId<C>>);
 
 impl<C: Tag> ISAInstruction<C> for Return<C> {
     fn is_pure() -> bool {
@@ -69,38 +71,31 @@ impl<C: Tag> ISAInstruction<C> for Return<C> {
     }
 
     fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
-        let mut used_registers = TinyVec::new();
-
-        if let Some(r) = self.0 {
-            used_registers.push(r)
-        }
-
-        used_registers
+        self.0.iter().copied().collect()
     }
 
     fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
-        match &mut self.0 {
-            Some(r) => vec![r],
-            None => Vec::new(),
-        }
+        self.0.iter_mut().collect()
     }
 
     fn display(&self, w: &mut impl Write) -> std::fmt::Result {
-        match self.0 {
-            Some(r) => write!(w, "Return %{};", r),
-            None => write!(w, "Return;"),
+        match self.0.is_empty() {
+            true => write!(w, "Return;"),
+            false => write!(w, "Return {};", Registers(&self.0)),
         }
     }
 }
 
 impl<C: Tag> Return<C> {
     #[track_caller]
-    #[allow(clippy::manual_map)] // can't use closures because then `track_caller` doesn't work
     pub fn retag<C2: Tag>(self, retagger: &impl RegRetagger<C, C2>) -> Return<C2> {
-        Return(match self.0 {
-            Some(r) => Some(retagger.retag_old(r)),
-            None => None,
-        })
+        // can't use closures because then `track_caller` doesn't work
+        let mut values = Vec::with_capacity(self.0.len());
+        for r in self.0 {
+            values.push(retagger.retag_old(r));
+        }
+
+        Return(values)
     }
 }
 
@@ -0,0 +1,42 @@
+use std::fmt::Write;
+use tinyvec::{tiny_vec, TinyVec};
+
+use super::ISAInstruction;
+use crate::{id::*, retag::RegRetagger};
+
+/// [`GetFnPtrDynamic`] obtains a pointer to a function by looking up the
+/// function with the name held in the `name` register at runtime. As the target
+/// of the pointer is not statically known, calls through it remain virtual.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GetFnPtrDynamic<C: Tag> {
+    pub result: RegisterId<C>,
+    pub name: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for GetFnPtrDynamic<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.name]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.name]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "%{} = GetFnPtrDynamic %{};", self.result, self.name)
+    }
+}
+
+impl<C: Tag> GetFnPtrDynamic<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> GetFnPtrDynamic<C2> {
+        GetFnPtrDynamic {
+            result: retagger.retag_new(self.result),
+            name: retagger.retag_old(self.name),
+        }
+    }
+}
@@ -18,6 +18,22 @@ pub enum ValueType {
     Runtime,
 }
 
+impl ValueType {
+    /// The name of the type, as produced by the [`TypeOf`] instruction.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueType::Atom => "atom",
+            ValueType::Bytes => "string",
+            ValueType::Number => "number",
+            ValueType::Boolean => "boolean",
+            ValueType::FnPtr => "function",
+            ValueType::Record | ValueType::List => "object",
+            ValueType::BigNumber => "bigint",
+            ValueType::Runtime => "runtime",
+        }
+    }
+}
+
 // TODO(isa/specification): add a new 'type' value to allow carrying around and
 //     passing types as values? not sure if that should be done or not
 /// Allows checking if the type is equal to the type of another register, or a
@@ -79,3 +95,83 @@ impl<C: Tag> IsType<C> {
         }
     }
 }
+
+/// [`AssumeType`] produces `value`, re-typed as `kind`. Unlike [`IsType`],
+/// no check is performed at runtime. The frontend is trusted to only assume
+/// types that are correct, which is useful when a value's type is known for
+/// reasons the analyzer can't see.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssumeType<C: Tag> {
+    pub result: RegisterId<C>,
+    pub value: RegisterId<C>,
+    pub kind: ValueType,
+}
+
+impl<C: Tag> ISAInstruction<C> for AssumeType<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.value]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.value]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(
+            w,
+            "%{} = AssumeType %{} = {};",
+            self.result, self.value, self.kind
+        )
+    }
+}
+
+impl<C: Tag> AssumeType<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> AssumeType<C2> {
+        AssumeType {
+            result: retagger.retag_new(self.result),
+            value: retagger.retag_old(self.value),
+            kind: self.kind,
+        }
+    }
+}
+
+/// [`TypeOf`] produces a string with the name of the type of `value`, as
+/// specified by [`ValueType::type_name`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeOf<C: Tag> {
+    pub result: RegisterId<C>,
+    pub value: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for TypeOf<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.value]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.value]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "%{} = TypeOf %{};", self.result, self.value)
+    }
+}
+
+impl<C: Tag> TypeOf<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> TypeOf<C2> {
+        TypeOf {
+            result: retagger.retag_new(self.result),
+            value: retagger.retag_old(self.value),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use derive_more::Display;
 use std::fmt::Write;
 use tinyvec::{tiny_vec, TinyVec};
 
-use super::ISAInstruction;
+use super::{ISAInstruction, Registers};
 use crate::{id::*, retag::RegRetagger};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
@@ -232,3 +232,140 @@ impl<C: Tag> ListLen<C> {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListPush<C: Tag> {
+    pub list: RegisterId<C>,
+    pub value: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for ListPush<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        None
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.list, self.value]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.list, &mut self.value]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "ListPush %{} <- %{};", self.list, self.value)
+    }
+}
+
+impl<C: Tag> ListPush<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> ListPush<C2> {
+        ListPush {
+            list: retagger.retag_old(self.list),
+            value: retagger.retag_old(self.value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListPop<C: Tag> {
+    pub result: RegisterId<C>,
+    pub list: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for ListPop<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.list]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.list]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "%{} = ListPop %{};", self.result, self.list)
+    }
+}
+
+impl<C: Tag> ListPop<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> ListPop<C2> {
+        ListPop {
+            result: retagger.retag_new(self.result),
+            list: retagger.retag_old(self.list),
+        }
+    }
+}
+
+/// [`ListMap`] calls the function pointer `function` once for every element of
+/// `list`, in order, passing `args` followed by the element as its arguments.
+/// If there is a `result`, it is a new list of the values each call returned.
+/// Otherwise, the values returned are discarded, which is how `list-for-each`
+/// is represented.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ListMap<C: Tag> {
+    pub result: Option<RegisterId<C>>,
+    pub list: RegisterId<C>,
+    pub function: RegisterId<C>,
+    /// Passed to every call before the element, such as state threaded
+    /// through every function.
+    pub args: Vec<RegisterId<C>>,
+}
+
+impl<C: Tag> ISAInstruction<C> for ListMap<C> {
+    fn is_pure() -> bool {
+        // the function being called may be side-effectful
+        false
+    }
+
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        self.result
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        let mut used_registers = tiny_vec![self.list, self.function];
+        used_registers.extend(self.args.iter().copied());
+        used_registers
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        let mut used_registers = vec![&mut self.list, &mut self.function];
+        used_registers.extend(self.args.iter_mut());
+        used_registers
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        match self.result {
+            Some(result) => write!(
+                w,
+                "%{} = ListMap %{}, %{}",
+                result, self.list, self.function
+            )?,
+            None => write!(w, "ListForEach %{}, %{}", self.list, self.function)?,
+        };
+
+        if !self.args.is_empty() {
+            write!(w, "({})", Registers(&self.args))?;
+        }
+
+        write!(w, ";")
+    }
+}
+
+impl<C: Tag> ListMap<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> ListMap<C2> {
+        ListMap {
+            result: self.result.map(|r| retagger.retag_new(r)),
+            list: retagger.retag_old(self.list),
+            function: retagger.retag_old(self.function),
+            args: (self.args.into_iter())
+                .map(|r| retagger.retag_old(r))
+                .collect(),
+        }
+    }
+}
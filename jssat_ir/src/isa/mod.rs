@@ -51,7 +51,7 @@ pub trait ISAInstruction<C: Tag> {
 }
 
 mod atom;
-pub use atom::{Atom, AtomDealer};
+pub use atom::{Atom, AtomDealer, InternalSlot};
 
 mod noop;
 pub use noop::Noop;
@@ -75,13 +75,24 @@ mod binop;
 pub use binop::{BinOp, BinaryOperator};
 
 mod records;
-pub use records::{NewRecord, RecordGet, RecordHasKey, RecordKey, RecordSet};
+pub use records::{
+    NewRecord, RecordClone, RecordEntries, RecordGet, RecordGetInherited, RecordGetOr,
+    RecordHasKey, RecordKey, RecordSet,
+};
 
 mod lists;
-pub use lists::{ListGet, ListHasKey, ListKey, ListLen, ListSet, NewList};
+pub use lists::{
+    ListGet, ListHasKey, ListKey, ListLen, ListMap, ListPop, ListPush, ListSet, NewList,
+};
 
 mod call;
-pub use call::Call;
+pub use call::{Call, CallSpread};
+
+mod bytes;
+pub use bytes::{BytesConcat, BytesSlice};
+
+mod get_fn_ptr_dynamic;
+pub use get_fn_ptr_dynamic::GetFnPtrDynamic;
 
 // TODO: widen/narrow instructions that operate based on a type
 mod widen;
@@ -97,7 +108,7 @@ mod assert;
 pub use assert::Assert;
 
 mod is_type;
-pub use is_type::{CompareType, IsType, ValueType};
+pub use is_type::{AssumeType, CompareType, IsType, TypeOf, ValueType};
 
 mod get_runtime;
 pub use get_runtime::GetRuntime;
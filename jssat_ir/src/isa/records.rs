@@ -60,6 +60,80 @@ impl<C: Tag> NewRecord<C> {
     }
 }
 
+/// [`RecordClone`] makes a shallow copy of a record. The new record has a fresh
+/// identity, but its fields hold the same values as the original record.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RecordClone<C: Tag> {
+    pub result: RegisterId<C>,
+    pub record: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for RecordClone<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.record]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.record]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "%{} = RecordClone %{};", self.result, self.record)
+    }
+}
+
+impl<C: Tag> RecordClone<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> RecordClone<C2> {
+        RecordClone {
+            result: retagger.retag_new(self.result),
+            record: retagger.retag_old(self.record),
+        }
+    }
+}
+
+/// [`RecordEntries`] produces a list with a `[key, value]` pair for every field
+/// of a record, in the order the fields were inserted. Each pair is a new
+/// record, with the key at `0` and the value at `1`. Internal slots are not
+/// included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RecordEntries<C: Tag> {
+    pub result: RegisterId<C>,
+    pub record: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for RecordEntries<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        tiny_vec![self.record]
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        vec![&mut self.record]
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "%{} = RecordEntries %{};", self.result, self.record)
+    }
+}
+
+impl<C: Tag> RecordEntries<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> RecordEntries<C2> {
+        RecordEntries {
+            result: retagger.retag_new(self.result),
+            record: retagger.retag_old(self.record),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct RecordHasKey<C: Tag> {
     pub result: RegisterId<C>,
@@ -168,6 +242,122 @@ impl<C: Tag> RecordGet<C> {
     }
 }
 
+/// [`RecordGetInherited`] gets the value of a key on a record. If the record
+/// does not have the key, the lookup continues on the record's
+/// [`InternalSlot::Prototype`](super::InternalSlot::Prototype), until a
+/// prototype that isn't a record terminates the chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RecordGetInherited<C: Tag> {
+    pub result: RegisterId<C>,
+    pub record: RegisterId<C>,
+    pub key: RecordKey<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for RecordGetInherited<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        let mut used_registers = tiny_vec![self.record];
+        match self.key {
+            RecordKey::Prop(register) | RecordKey::DynAtom(register) => {
+                used_registers.push(register)
+            }
+            _ => {}
+        }
+        used_registers
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        let mut used_registers = vec![&mut self.record];
+        match &mut self.key {
+            RecordKey::Prop(register) | RecordKey::DynAtom(register) => {
+                used_registers.push(register)
+            }
+            _ => {}
+        }
+        used_registers
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(
+            w,
+            "%{} = RecordGetInherited %{}.{};",
+            self.result, self.record, self.key
+        )
+    }
+}
+
+impl<C: Tag> RecordGetInherited<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> RecordGetInherited<C2> {
+        RecordGetInherited {
+            result: retagger.retag_new(self.result),
+            record: retagger.retag_old(self.record),
+            key: self.key.retag(retagger),
+        }
+    }
+}
+
+/// [`RecordGetOr`] gets the value of a key on a record, or the value of
+/// `default` if the record does not have the key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RecordGetOr<C: Tag> {
+    pub result: RegisterId<C>,
+    pub record: RegisterId<C>,
+    pub key: RecordKey<C>,
+    pub default: RegisterId<C>,
+}
+
+impl<C: Tag> ISAInstruction<C> for RecordGetOr<C> {
+    fn declared_register(&self) -> Option<RegisterId<C>> {
+        Some(self.result)
+    }
+
+    fn used_registers(&self) -> TinyVec<[RegisterId<C>; 3]> {
+        let mut used_registers = tiny_vec![self.record, self.default];
+        match self.key {
+            RecordKey::Prop(register) | RecordKey::DynAtom(register) => {
+                used_registers.push(register)
+            }
+            _ => {}
+        }
+        used_registers
+    }
+
+    fn used_registers_mut(&mut self) -> Vec<&mut RegisterId<C>> {
+        let mut used_registers = vec![&mut self.record, &mut self.default];
+        match &mut self.key {
+            RecordKey::Prop(register) | RecordKey::DynAtom(register) => {
+                used_registers.push(register)
+            }
+            _ => {}
+        }
+        used_registers
+    }
+
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(
+            w,
+            "%{} = RecordGetOr %{}.{}, %{};",
+            self.result, self.record, self.key, self.default
+        )
+    }
+}
+
+impl<C: Tag> RecordGetOr<C> {
+    #[track_caller]
+    pub fn retag<C2: Tag>(self, retagger: &mut impl RegRetagger<C, C2>) -> RecordGetOr<C2> {
+        RecordGetOr {
+            result: retagger.retag_new(self.result),
+            record: retagger.retag_old(self.record),
+            key: self.key.retag(retagger),
+            default: retagger.retag_old(self.default),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct RecordSet<C: Tag> {
     pub record: RegisterId<C>,
@@ -209,16 +399,18 @@ impl<C: Tag> ISAInstruction<C> for RecordSet<C> {
     }
 
     fn display(&self, w: &mut impl Write) -> std::fmt::Result {
-        write!(
-            w,
-            "RecordSet %{}.{} = {}",
-            self.record,
-            self.key,
-            match self.value {
-                Some(r) => format!("%{}", r),
-                None => "<remove>".to_string(),
+        match self.value {
+            Some(value) => write!(w, "RecordSet %{}.{} = %{}", self.record, self.key, value),
+            // deletions are displayed distinctly so they aren't mistaken for a set
+            None => {
+                let kind = match self.key {
+                    RecordKey::Prop(_) => "Prop",
+                    RecordKey::Atom(_) | RecordKey::DynAtom(_) => "Slot",
+                };
+
+                write!(w, "RecordDel{} %{}, {}", kind, self.record, self.key)
             }
-        )
+        }
     }
 }
 
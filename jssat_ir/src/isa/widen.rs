@@ -23,7 +23,7 @@ impl<C: Tag> ISAInstruction<C> for Widen<C> {
         vec![&mut self.input]
     }
 
-    fn display(&self, _w: &mut impl Write) -> std::fmt::Result {
-        todo!()
+    fn display(&self, w: &mut impl Write) -> std::fmt::Result {
+        write!(w, "%{} = Widen %{};", self.result, self.input)
     }
 }
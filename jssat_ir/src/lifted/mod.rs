@@ -14,6 +14,9 @@ use crate::{
     UnwrapNone,
 };
 
+mod simplify_cfg;
+pub use simplify_cfg::simplify_cfg;
+
 pub type ExternalFunctionId = crate::id::ExternalFunctionId<LiftedCtx>;
 pub type FunctionId = crate::id::FunctionId<LiftedCtx>;
 pub type ConstantId = crate::id::ConstantId<LiftedCtx>;
@@ -26,6 +29,9 @@ pub struct LiftedProgram {
     pub constants: FxHashMap<ConstantId, Constant>,
     pub external_functions: FxHashMap<ExternalFunctionId, ExternalFunction>,
     pub functions: FxHashMap<FunctionId, Function>,
+    /// Maps the name of a function to the lifted function for its entry block,
+    /// so that functions may be looked up by name at runtime.
+    pub function_names: FxHashMap<String, FunctionId>,
 }
 
 pub type Constant = crate::frontend::ir::Constant;
@@ -38,6 +44,10 @@ pub struct Function {
     pub ir_fn_id: crate::id::FunctionId<IrCtx>,
     pub ir_blk_id: crate::id::BlockId<IrCtx>,
     pub parameters: Vec<RegisterId>,
+    /// The parameters of the original function which were marked as exact.
+    /// Every block of a function shares these, as a block which needs one of
+    /// the function's parameters receives it under the same register.
+    pub exact_parameters: FxHashSet<RegisterId>,
     pub instructions: Vec<Instruction<LiftedCtx, LiftedCtx>>,
     pub end: EndInstruction,
 }
@@ -147,7 +157,12 @@ pub fn lift(ir: IR) -> LiftedProgram {
 
     // lift functions
     let mut functions = FxHashMap::default();
+    let mut function_names = FxHashMap::default();
     for (id, func) in ir.functions.into_iter() {
+        if let Some(name) = &func.name {
+            function_names.insert(name.clone(), fn_retagger.retag_old(id));
+        }
+
         let lifted_blocks = lift_function(
             id,
             func,
@@ -170,6 +185,7 @@ pub fn lift(ir: IR) -> LiftedProgram {
         constants,
         external_functions,
         functions,
+        function_names,
     }
 }
 
@@ -200,6 +216,11 @@ fn lift_function(
     // must ignore those
     reg_retagger.ignore_checks();
 
+    let exact_parameters = (function.parameters.iter())
+        .filter(|p| p.exact)
+        .map(|p| reg_retagger.retag_new(p.register))
+        .collect::<FxHashSet<_>>();
+
     let mut lifted = FxHashMap::default();
 
     for (id, mut blk) in function.blocks.into_iter() {
@@ -218,6 +239,7 @@ fn lift_function(
             ir_fn_id,
             id,
             blk,
+            exact_parameters.clone(),
             &lifted_ids,
             &mut reg_retagger,
             fn_retagger,
@@ -252,6 +274,7 @@ fn fn_block_to_fn(
     ir_fn_id: crate::id::FunctionId<IrCtx>,
     ir_blk_id: crate::id::BlockId<IrCtx>,
     fn_blk: ir::FunctionBlock,
+    exact_parameters: FxHashSet<RegisterId>,
     blk_to_fn: &impl BlkToFn<IrCtx, LiftedCtx>,
     retagger: &mut impl RegRetagger<IrCtx, LiftedCtx>,
     fn_retagger: &impl FnGenRetagger<IrCtx, LiftedCtx>,
@@ -281,6 +304,7 @@ fn fn_block_to_fn(
         ir_fn_id,
         ir_blk_id,
         parameters,
+        exact_parameters,
         instructions,
         end,
     }
@@ -486,8 +510,8 @@ mod tests {
 
         f.end_block(main.jmpif(cond1, check.signature(), [], check.signature(), []));
         f.end_block(check.jmpif(cond2, if_true.signature(), [], if_false.signature(), []));
-        f.end_block(if_true.ret(Some(reg_true)));
-        f.end_block(if_false.ret(Some(reg_false)));
+        f.end_block(if_true.ret(&[reg_true]));
+        f.end_block(if_false.ret(&[reg_false]));
 
         let f = program.end_function(f);
 
@@ -0,0 +1,114 @@
+//! Removes blocks that do nothing but jump to another block. Lowering tends to
+//! produce a lot of these, and every one of them is an extra function that has
+//! to be analyzed.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{frontend::ir::InstructionData, id::LiftedCtx, isa::BlockJump};
+
+use super::{EndInstruction, Function, FunctionId, LiftedProgram};
+
+/// A block with no instructions, which only jumps to `target`. Each parameter
+/// of `target` is given one of the parameters of the forwarding block, by
+/// index.
+struct Forward {
+    target: FunctionId,
+    args: Vec<usize>,
+}
+
+/// Redirects every jump to a forwarding block to the block it ultimately jumps
+/// to, and then removes the forwarding blocks that are no longer referenced.
+pub fn simplify_cfg(program: &mut LiftedProgram) {
+    let forwards = program
+        .functions
+        .iter()
+        .filter_map(|(id, function)| Some((*id, as_forward(function)?)))
+        .collect::<FxHashMap<_, _>>();
+
+    for function in program.functions.values_mut() {
+        for path in function.end.paths_mut() {
+            redirect(path, &forwards);
+        }
+    }
+
+    let referenced = referenced_functions(program);
+    program
+        .functions
+        .retain(|id, _| !forwards.contains_key(id) || referenced.contains(id));
+}
+
+fn as_forward(function: &Function) -> Option<Forward> {
+    let path = match &function.end {
+        EndInstruction::Jump(jump) if function.instructions.is_empty() => &jump.0,
+        _ => return None,
+    };
+
+    // with no instructions, every register passed along must be a parameter
+    let args = (path.1.iter())
+        .map(|arg| function.parameters.iter().position(|p| p == arg))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Forward {
+        target: path.0,
+        args,
+    })
+}
+
+/// Follows the chain of forwarding blocks that `path` jumps to, rewriting the
+/// registers passed along at every step so that the arguments given to the
+/// final block are preserved.
+fn redirect(
+    path: &mut BlockJump<FunctionId, LiftedCtx>,
+    forwards: &FxHashMap<FunctionId, Forward>,
+) {
+    let mut visited = FxHashSet::default();
+
+    while let Some(forward) = forwards.get(&path.0) {
+        // a cycle of forwarding blocks is an infinite loop, which we leave be
+        if !visited.insert(path.0) {
+            break;
+        }
+
+        let args = (forward.args.iter())
+            .map(|idx| path.1.get(*idx).copied())
+            .collect::<Option<Vec<_>>>();
+
+        // a jump that doesn't pass every parameter yet can't be redirected
+        match args {
+            Some(args) => {
+                path.0 = forward.target;
+                path.1 = args;
+            }
+            None => break,
+        }
+    }
+}
+
+/// Collects every function that can be reached by some means other than being
+/// removed: jumps, calls, function pointers, names, and the entrypoint.
+fn referenced_functions(program: &LiftedProgram) -> FxHashSet<FunctionId> {
+    let mut referenced = FxHashSet::default();
+    referenced.insert(program.entrypoint);
+    referenced.extend(program.function_names.values().copied());
+
+    for function in program.functions.values() {
+        referenced.extend(function.end.paths().into_iter().map(|path| path.0));
+
+        for inst in function.instructions.iter() {
+            match &inst.data {
+                InstructionData::CallStatic(call) => {
+                    referenced.insert(call.calling);
+                }
+                InstructionData::CallSpread(call) => {
+                    referenced.insert(call.calling);
+                }
+                InstructionData::GetFnPtr(make) => {
+                    referenced.insert(make.item);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    referenced
+}